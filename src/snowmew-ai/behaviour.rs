@@ -0,0 +1,209 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+
+use cgmath::Vector3;
+use snowmew::common::Entity;
+
+/// The result of running a node of a behaviour tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq, RustcEncodable, RustcDecodable)]
+pub enum Status {
+    Success,
+    Failure,
+    Running
+}
+
+/// A value that can be stored in a `Blackboard`
+#[derive(Clone, Copy, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Value {
+    Bool(bool),
+    Number(f32),
+    Vector(Vector3<f32>),
+    Entity(Entity)
+}
+
+/// Every agent has its own blackboard, this is where the actions of
+/// the behaviour tree can store any information about the world
+/// they have discovered or need to remember between steps.
+#[derive(Clone, Default, RustcEncodable, RustcDecodable)]
+pub struct Blackboard {
+    values: BTreeMap<String, Value>
+}
+
+impl Blackboard {
+    pub fn new() -> Blackboard {
+        Blackboard { values: BTreeMap::new() }
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.values.get(key).map(|&v| v)
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.values.remove(key).is_some()
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key) { Some(Value::Bool(x)) => Some(x), _ => None }
+    }
+
+    pub fn get_number(&self, key: &str) -> Option<f32> {
+        match self.get(key) { Some(Value::Number(x)) => Some(x), _ => None }
+    }
+
+    pub fn get_vector(&self, key: &str) -> Option<Vector3<f32>> {
+        match self.get(key) { Some(Value::Vector(x)) => Some(x), _ => None }
+    }
+
+    pub fn get_entity(&self, key: &str) -> Option<Entity> {
+        match self.get(key) { Some(Value::Entity(x)) => Some(x), _ => None }
+    }
+}
+
+/// A node in a behaviour tree. Trees only refer to actions by name
+/// so that they can be serialized with the rest of the game.
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub enum Node {
+    /// Run each child in order until one does not succeed
+    Sequence(Vec<Node>),
+    /// Run each child in order until one does not fail
+    Selector(Vec<Node>),
+    /// Swap `Success` and `Failure` of the child
+    Inverter(Box<Node>),
+    /// Always succeeds once the child has finished
+    Succeeder(Box<Node>),
+    /// Always fails once the child has finished
+    Failer(Box<Node>),
+    /// Run the child up to `n` times, stopping early if it fails
+    Repeat(u32, Box<Node>),
+    /// Succeeds if the blackboard contains `true` for the key
+    Condition(String),
+    /// Run the action registered with this name
+    Action(String)
+}
+
+/// An action is supplied by the game, it is given the game data, the
+/// agent that is running the tree and the agent's blackboard. The
+/// blackboard is taken out of the agent while the tree runs, so the
+/// one passed in is the only one an action can change.
+pub type Action<GD> = fn(&mut GD, Entity, &mut Blackboard) -> Status;
+
+/// A registry of the actions a behaviour tree can call by name.
+pub struct Actions<GD> {
+    actions: HashMap<String, Action<GD>>
+}
+
+impl<GD> Actions<GD> {
+    pub fn new() -> Actions<GD> {
+        Actions { actions: HashMap::new() }
+    }
+
+    /// register an action under `name`
+    pub fn register(&mut self, name: &str, action: Action<GD>) {
+        self.actions.insert(name.to_string(), action);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Action<GD>> {
+        self.actions.get(name).map(|&a| a)
+    }
+}
+
+impl Node {
+    pub fn sequence(children: Vec<Node>) -> Node { Node::Sequence(children) }
+    pub fn selector(children: Vec<Node>) -> Node { Node::Selector(children) }
+    pub fn inverter(child: Node) -> Node { Node::Inverter(Box::new(child)) }
+    pub fn succeeder(child: Node) -> Node { Node::Succeeder(Box::new(child)) }
+    pub fn failer(child: Node) -> Node { Node::Failer(Box::new(child)) }
+    pub fn repeat(n: u32, child: Node) -> Node { Node::Repeat(n, Box::new(child)) }
+    pub fn condition(key: &str) -> Node { Node::Condition(key.to_string()) }
+    pub fn action(name: &str) -> Node { Node::Action(name.to_string()) }
+
+    /// Evaluate the tree for `agent`. The tree is evaluated from the root
+    /// every step, an action that returns `Running` will simply be reached
+    /// again on the next step if nothing with a higher priority succeeds.
+    ///
+    /// An action that is not registered fails.
+    pub fn evaluate<GD>(&self,
+                        gd: &mut GD,
+                        agent: Entity,
+                        blackboard: &mut Blackboard,
+                        actions: &Actions<GD>) -> Status {
+        match *self {
+            Node::Sequence(ref children) => {
+                for child in children.iter() {
+                    match child.evaluate(gd, agent, blackboard, actions) {
+                        Status::Success => (),
+                        other => return other
+                    }
+                }
+                Status::Success
+            }
+            Node::Selector(ref children) => {
+                for child in children.iter() {
+                    match child.evaluate(gd, agent, blackboard, actions) {
+                        Status::Failure => (),
+                        other => return other
+                    }
+                }
+                Status::Failure
+            }
+            Node::Inverter(ref child) => {
+                match child.evaluate(gd, agent, blackboard, actions) {
+                    Status::Success => Status::Failure,
+                    Status::Failure => Status::Success,
+                    Status::Running => Status::Running
+                }
+            }
+            Node::Succeeder(ref child) => {
+                match child.evaluate(gd, agent, blackboard, actions) {
+                    Status::Running => Status::Running,
+                    _ => Status::Success
+                }
+            }
+            Node::Failer(ref child) => {
+                match child.evaluate(gd, agent, blackboard, actions) {
+                    Status::Running => Status::Running,
+                    _ => Status::Failure
+                }
+            }
+            Node::Repeat(n, ref child) => {
+                for _ in (0..n) {
+                    match child.evaluate(gd, agent, blackboard, actions) {
+                        Status::Success => (),
+                        other => return other
+                    }
+                }
+                Status::Success
+            }
+            Node::Condition(ref key) => {
+                if blackboard.get_bool(key) == Some(true) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            }
+            Node::Action(ref name) => {
+                match actions.get(name) {
+                    Some(action) => action(gd, agent, blackboard),
+                    None => Status::Failure
+                }
+            }
+        }
+    }
+}
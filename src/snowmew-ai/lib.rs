@@ -0,0 +1,272 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![crate_name = "snowmew-ai"]
+#![crate_type = "lib"]
+#![feature(core, collections, std_misc)]
+
+extern crate cgmath;
+extern crate rand;
+extern crate "rustc-serialize" as rustc_serialize;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-random" as random;
+
+use std::mem;
+use std::num::Float;
+
+use cgmath::{Vector, Vector3, Vector4, Rotation3, rad};
+
+use snowmew::common::{Common, Entity, Duplicate, Delete};
+use snowmew::game::Game;
+use snowmew::table::{Static, StaticIterator};
+use position::Positions;
+use random::Random;
+
+pub use behaviour::{Status, Value, Blackboard, Node, Action, Actions};
+pub use steering::{Steering, Behaviour, Target, Obstacle};
+
+/// contains the behaviour tree
+pub mod behaviour;
+/// contains the steering behaviours
+pub mod steering;
+
+/// An agent is an entity that is controlled by a behaviour tree
+/// and/or steering behaviours.
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Agent {
+    /// The behaviour tree entity that is evaluated for this agent
+    pub tree: Option<Entity>,
+    pub blackboard: Blackboard,
+    pub steering: Option<Steering>
+}
+
+impl Agent {
+    pub fn new(tree: Option<Entity>) -> Agent {
+        Agent {
+            tree: tree,
+            blackboard: Blackboard::new(),
+            steering: None
+        }
+    }
+}
+
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct AiData {
+    trees:     Static<Node>,
+    agents:    Static<Agent>,
+    obstacles: Static<Obstacle>
+}
+
+impl AiData {
+    pub fn new() -> AiData {
+        AiData {
+            trees: Static::new(),
+            agents: Static::new(),
+            obstacles: Static::new()
+        }
+    }
+}
+
+fn world_position<P: Positions>(p: &P, oid: Entity) -> Vector3<f32> {
+    p.position(oid).mul_v(&Vector4::new(0., 0., 0., 1.)).truncate()
+}
+
+pub trait Ai: Common + Positions + Random + Sized {
+    fn get_ai<'a>(&'a self) -> &'a AiData;
+    fn get_ai_mut<'a>(&'a mut self) -> &'a mut AiData;
+
+    /// create a new behaviour tree, the tree can be shared by any
+    /// number of agents.
+    fn new_behaviour(&mut self, tree: Node) -> Entity {
+        let oid = self.new_object(None);
+        self.get_ai_mut().trees.insert(oid, tree);
+        oid
+    }
+
+    fn behaviour<'a>(&'a self, oid: Entity) -> Option<&'a Node> {
+        self.get_ai().trees.get(oid)
+    }
+
+    /// make an existing entity into an agent
+    fn set_agent(&mut self, oid: Entity, agent: Agent) {
+        self.get_ai_mut().agents.insert(oid, agent);
+    }
+
+    fn agent<'a>(&'a self, oid: Entity) -> Option<&'a Agent> {
+        self.get_ai().agents.get(oid)
+    }
+
+    fn agent_mut<'a>(&'a mut self, oid: Entity) -> Option<&'a mut Agent> {
+        self.get_ai_mut().agents.get_mut(oid)
+    }
+
+    fn agent_iter<'a>(&'a self) -> StaticIterator<'a, Agent> {
+        self.get_ai().agents.iter()
+    }
+
+    fn steering_mut<'a>(&'a mut self, oid: Entity) -> Option<&'a mut Steering> {
+        self.agent_mut(oid).and_then(|a| a.steering.as_mut())
+    }
+
+    /// mark an entity as something agents should steer around
+    fn set_obstacle(&mut self, oid: Entity, radius: f32) {
+        self.get_ai_mut().obstacles.insert(oid, Obstacle { radius: radius });
+    }
+
+    /// Evaluate the behaviour tree of every agent once. Agents are
+    /// visited in entity order so the result is deterministic. The
+    /// agent's blackboard is handed to the actions and put back once
+    /// the tree is done.
+    fn think(&mut self, actions: &Actions<Self>) {
+        let agents: Vec<(Entity, Entity)> = self.agent_iter()
+            .filter_map(|(oid, a)| a.tree.map(|t| (oid, t)))
+            .collect();
+
+        for &(oid, tree) in agents.iter() {
+            let tree = match self.behaviour(tree) {
+                Some(t) => t.clone(),
+                None => continue
+            };
+            let mut blackboard = mem::replace(&mut self.agent_mut(oid).unwrap().blackboard,
+                                              Blackboard::new());
+            tree.evaluate(self, oid, &mut blackboard, actions);
+            self.agent_mut(oid).map(|a| a.blackboard = blackboard);
+        }
+    }
+
+    /// Move every agent with steering behaviours forward by `dt` seconds.
+    /// The agent's displacement is updated and it is rotated about the y
+    /// axis to face the direction it is moving.
+    fn steer(&mut self, dt: f32) {
+        let obstacles: Vec<(Vector3<f32>, f32)> = self.get_ai().obstacles.iter()
+            .map(|(oid, o)| (world_position(self, oid), o.radius))
+            .collect();
+        let agents: Vec<Entity> = self.agent_iter()
+            .filter(|&(_, a)| a.steering.is_some())
+            .map(|(oid, _)| oid)
+            .collect();
+
+        // copy the random state out so it can be used while the agent's
+        // steering is borrowed, and written back when done
+        let mut rng = *self.rng();
+
+        for &oid in agents.iter() {
+            let position = world_position(self, oid);
+            let mut steering = self.agent(oid).unwrap().steering.clone().unwrap();
+
+            let mut force = Vector3::new(0f32, 0., 0.);
+            for &(behaviour, weight) in steering.behaviours.clone().iter() {
+                let target = |t: Target| match t {
+                    Target::Point(p) => p,
+                    Target::Entity(e) => world_position(self, e)
+                };
+                let f = match behaviour {
+                    Behaviour::Seek(t) => {
+                        steering::seek(&position, &steering.velocity,
+                                       &target(t), steering.max_speed)
+                    }
+                    Behaviour::Flee(t) => {
+                        steering::flee(&position, &steering.velocity,
+                                       &target(t), steering.max_speed)
+                    }
+                    Behaviour::Arrive(t, slowing) => {
+                        steering::arrive(&position, &steering.velocity,
+                                         &target(t), steering.max_speed, slowing)
+                    }
+                    Behaviour::Wander { radius, distance, jitter } => {
+                        steering::wander(&steering.velocity, &mut steering.wander_angle,
+                                         radius, distance, jitter, &mut rng)
+                    }
+                    Behaviour::AvoidObstacles(look_ahead) => {
+                        let others: Vec<(Vector3<f32>, f32)> = obstacles.iter()
+                            .filter(|&&(p, _)| p != position)
+                            .map(|&x| x)
+                            .collect();
+                        steering::avoid(&position, &steering.velocity, &others,
+                                        look_ahead, steering.max_force)
+                    }
+                };
+                force = force.add_v(&f.mul_s(weight));
+            }
+
+            let moved = steering::integrate(&mut steering, force, dt);
+            let velocity = steering.velocity;
+            self.agent_mut(oid).map(|a| a.steering = Some(steering));
+
+            let disp = self.get_displacement(oid).map(|&d| d)
+                           .unwrap_or(Vector3::new(0., 0., 0.));
+            self.set_displacement(oid, disp.add_v(&moved));
+            if velocity.x != 0. || velocity.z != 0. {
+                let yaw = (-velocity.x).atan2(-velocity.z);
+                self.set_rotation(oid, Rotation3::from_euler(rad(0f32), rad(yaw), rad(0f32)));
+            }
+        }
+
+        *self.rng() = rng;
+    }
+}
+
+impl Duplicate for AiData {
+    fn duplicate(&mut self, src: Entity, dst: Entity) {
+        let x = self.trees.get(src).map(|x| x.clone());
+        x.map(|x| self.trees.insert(dst, x));
+        let x = self.agents.get(src).map(|x| x.clone());
+        x.map(|x| self.agents.insert(dst, x));
+        let x = self.obstacles.get(src).map(|x| x.clone());
+        x.map(|x| self.obstacles.insert(dst, x));
+    }
+}
+
+impl Delete for AiData {
+    fn delete(&mut self, oid: Entity) -> bool {
+        self.trees.remove(oid)  |
+        self.agents.remove(oid) |
+        self.obstacles.remove(oid)
+    }
+}
+
+/// A `Brain` wraps a game, after each step of the wrapped game every
+/// agent thinks and then steers. The brain expects to be stepped once
+/// per cadence, so it should sit inside of the input integrator.
+pub struct Brain<G, GD> {
+    game: G,
+    actions: Actions<GD>,
+    cadance: f32
+}
+
+impl<G, GD> Brain<G, GD> {
+    /// `cadance` is the time in seconds that passes every step
+    pub fn new(game: G, actions: Actions<GD>, cadance: f32) -> Brain<G, GD> {
+        Brain {
+            game: game,
+            actions: actions,
+            cadance: cadance
+        }
+    }
+
+    pub fn actions_mut<'a>(&'a mut self) -> &'a mut Actions<GD> {
+        &mut self.actions
+    }
+}
+
+impl<E, GD: Ai, G: Game<GD, E>> Game<GD, E> for Brain<G, GD> {
+    fn step(&mut self, event: E, gd: GD) -> GD {
+        let mut gd = self.game.step(event, gd);
+        gd.think(&self.actions);
+        gd.steer(self.cadance);
+        gd
+    }
+}
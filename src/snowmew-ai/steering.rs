@@ -0,0 +1,216 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::f32::consts::PI;
+use std::num::Float;
+
+use cgmath::{Vector, Vector3, EuclideanVector};
+use rand::Rng;
+
+use snowmew::common::Entity;
+
+/// Where a steering behaviour is heading
+#[derive(Clone, Copy, RustcEncodable, RustcDecodable)]
+pub enum Target {
+    /// a fixed point in world space
+    Point(Vector3<f32>),
+    /// follow the world position of an entity
+    Entity(Entity)
+}
+
+/// The steering behaviours an agent can be driven by. Each
+/// produces a steering force that is blended with the others
+/// by weight.
+#[derive(Clone, Copy, RustcEncodable, RustcDecodable)]
+pub enum Behaviour {
+    Seek(Target),
+    Flee(Target),
+    /// Seek the target, slowing down once inside `slowing_radius`
+    Arrive(Target, f32),
+    /// Random, smooth, wandering
+    Wander {
+        radius: f32,
+        distance: f32,
+        jitter: f32
+    },
+    /// Steer away from any `Obstacle`s within `look_ahead` units
+    AvoidObstacles(f32)
+}
+
+/// The movement state of an agent
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Steering {
+    pub velocity: Vector3<f32>,
+    pub max_speed: f32,
+    pub max_force: f32,
+    pub behaviours: Vec<(Behaviour, f32)>,
+    pub wander_angle: f32
+}
+
+impl Steering {
+    pub fn new(max_speed: f32, max_force: f32) -> Steering {
+        Steering {
+            velocity: Vector3::new(0., 0., 0.),
+            max_speed: max_speed,
+            max_force: max_force,
+            behaviours: Vec::new(),
+            wander_angle: 0.
+        }
+    }
+
+    /// add a behaviour with a weight
+    pub fn add(&mut self, behaviour: Behaviour, weight: f32) {
+        self.behaviours.push((behaviour, weight));
+    }
+
+    pub fn clear(&mut self) {
+        self.behaviours.clear();
+    }
+}
+
+/// A sphere that agents using `AvoidObstacles` will steer around
+#[derive(Clone, Copy, RustcEncodable, RustcDecodable)]
+pub struct Obstacle {
+    pub radius: f32
+}
+
+fn truncate(v: Vector3<f32>, max: f32) -> Vector3<f32> {
+    let len = v.length();
+    if len > max && len > 0. {
+        v.mul_s(max / len)
+    } else {
+        v
+    }
+}
+
+/// the force needed to head to the target at full speed
+pub fn seek(position: &Vector3<f32>,
+            velocity: &Vector3<f32>,
+            target: &Vector3<f32>,
+            max_speed: f32) -> Vector3<f32> {
+    let desired = target.sub_v(position);
+    if desired.length() == 0. {
+        return velocity.mul_s(-1.);
+    }
+    desired.normalize().mul_s(max_speed).sub_v(velocity)
+}
+
+/// the force needed to head away from the target at full speed
+pub fn flee(position: &Vector3<f32>,
+            velocity: &Vector3<f32>,
+            target: &Vector3<f32>,
+            max_speed: f32) -> Vector3<f32> {
+    let desired = position.sub_v(target);
+    if desired.length() == 0. {
+        return Vector3::new(0., 0., 0.);
+    }
+    desired.normalize().mul_s(max_speed).sub_v(velocity)
+}
+
+/// like seek but will come to a stop at the target
+pub fn arrive(position: &Vector3<f32>,
+              velocity: &Vector3<f32>,
+              target: &Vector3<f32>,
+              max_speed: f32,
+              slowing_radius: f32) -> Vector3<f32> {
+    let offset = target.sub_v(position);
+    let distance = offset.length();
+    if distance == 0. {
+        return velocity.mul_s(-1.);
+    }
+    let speed = if distance < slowing_radius {
+        max_speed * (distance / slowing_radius)
+    } else {
+        max_speed
+    };
+    offset.mul_s(speed / distance).sub_v(velocity)
+}
+
+/// Wander around the XZ plane. A point on a circle projected in front
+/// of the agent is jittered a small amount each step, and the agent
+/// seeks it. `angle` is the state of the wander and must be kept
+/// between steps.
+pub fn wander<R: Rng>(velocity: &Vector3<f32>,
+                      angle: &mut f32,
+                      radius: f32,
+                      distance: f32,
+                      jitter: f32,
+                      rng: &mut R) -> Vector3<f32> {
+    *angle += (rng.next_f32() * 2. - 1.) * jitter;
+    if *angle > PI { *angle -= 2. * PI; }
+    if *angle < -PI { *angle += 2. * PI; }
+
+    let heading = if velocity.length() > 0. {
+        velocity.normalize()
+    } else {
+        Vector3::new(0., 0., -1.)
+    };
+    let center = heading.mul_s(distance);
+    let offset = Vector3::new(angle.cos() * radius, 0., angle.sin() * radius);
+    center.add_v(&offset)
+}
+
+/// Steer away from the closest obstacle that is in the path of the
+/// agent. `obstacles` is a list of sphere centers and radii.
+pub fn avoid(position: &Vector3<f32>,
+             velocity: &Vector3<f32>,
+             obstacles: &[(Vector3<f32>, f32)],
+             look_ahead: f32,
+             max_force: f32) -> Vector3<f32> {
+    if velocity.length() == 0. {
+        return Vector3::new(0., 0., 0.);
+    }
+    let heading = velocity.normalize();
+
+    let mut closest: Option<(f32, Vector3<f32>)> = None;
+    for &(center, radius) in obstacles.iter() {
+        let to = center.sub_v(position);
+        let along = to.dot(&heading);
+        if along < 0. || along > look_ahead + radius {
+            continue;
+        }
+        let lateral = to.sub_v(&heading.mul_s(along));
+        if lateral.length() > radius {
+            continue;
+        }
+        if closest.map(|(d, _)| along < d).unwrap_or(true) {
+            closest = Some((along, lateral));
+        }
+    }
+
+    match closest {
+        Some((_, lateral)) => {
+            if lateral.length() == 0. {
+                // dead ahead, pick a side
+                let side = heading.cross(&Vector3::new(0., 1., 0.));
+                if side.length() == 0. {
+                    Vector3::new(max_force, 0., 0.)
+                } else {
+                    side.normalize().mul_s(max_force)
+                }
+            } else {
+                lateral.normalize().mul_s(-max_force)
+            }
+        }
+        None => Vector3::new(0., 0., 0.)
+    }
+}
+
+/// Move the agent forward in time by `dt` given a steering force.
+/// Returns the displacement the agent moved.
+pub fn integrate(steering: &mut Steering, force: Vector3<f32>, dt: f32) -> Vector3<f32> {
+    let force = truncate(force, steering.max_force);
+    steering.velocity = truncate(steering.velocity.add_v(&force.mul_s(dt)), steering.max_speed);
+    steering.velocity.mul_s(dt)
+}
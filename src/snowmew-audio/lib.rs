@@ -0,0 +1,235 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![crate_name = "snowmew-audio"]
+#![crate_type = "lib"]
+#![feature(old_io, old_path, collections, std_misc, core)]
+
+extern crate cgmath;
+extern crate "rustc-serialize" as rustc_serialize;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+
+use std::default::Default;
+
+use snowmew::common::{Common, Entity, Duplicate, Delete};
+use snowmew::table::{Static, StaticIterator};
+
+pub use mixer::{Mixer, AudioManager, attenuation, pan};
+pub use output::{Output, NullOutput, BufferOutput, WavOutput};
+
+/// contains the software mixer
+pub mod mixer;
+/// contains the places the mixer can write its samples to
+pub mod output;
+
+/// A clip is a chunk of decoded audio. The samples are stored
+/// interleaved if there is more then one channel.
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Clip {
+    sample_rate: u32,
+    channels: u32,
+    data: Vec<f32>
+}
+
+impl Default for Clip {
+    fn default() -> Clip {
+        Clip::new(44100, 1, Vec::new())
+    }
+}
+
+impl Clip {
+    pub fn new(sample_rate: u32, channels: u32, data: Vec<f32>) -> Clip {
+        assert!(channels > 0, "a clip needs at least one channel");
+        Clip {
+            sample_rate: sample_rate,
+            channels: channels,
+            data: data
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 { self.sample_rate }
+    pub fn channels(&self) -> u32 { self.channels }
+    pub fn data<'a>(&'a self) -> &'a [f32] { &self.data }
+
+    /// the number of frames (one sample per channel) in the clip
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.channels as usize
+    }
+
+    /// get a frame as a (left, right) pair, mono clips are
+    /// duplicated to both channels.
+    pub fn stereo(&self, frame: usize) -> (f32, f32) {
+        let base = frame * self.channels as usize;
+        if self.channels == 1 {
+            (self.data[base], self.data[base])
+        } else {
+            (self.data[base], self.data[base+1])
+        }
+    }
+
+    /// get a frame mixed down to a single channel
+    pub fn mono(&self, frame: usize) -> f32 {
+        let base = frame * self.channels as usize;
+        let mut sum = 0.;
+        for c in (0..self.channels as usize) {
+            sum += self.data[base+c];
+        }
+        sum / self.channels as f32
+    }
+}
+
+/// A source is attached to an `Entity` and plays a `Clip` from the
+/// position of the entity.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
+pub struct Source {
+    /// The `Clip` entity that is played by this source
+    pub clip: Entity,
+    /// Linear volume of the source
+    pub gain: f32,
+    /// Restart the clip when it reaches its end
+    pub looping: bool,
+    /// Is the source currently audible
+    pub playing: bool,
+    /// If false the source ignores the listener and is played
+    /// as is, this is useful for music and ui sounds.
+    pub spatial: bool,
+    /// The distance at which the source is heard at full volume
+    pub reference_distance: f32,
+    /// Past this distance the source stops getting quieter
+    pub max_distance: f32,
+    /// How quickly the source falls off with distance
+    pub rolloff: f32,
+    /// Incremented each time the source is (re)started, the mixer
+    /// uses this to know when to rewind the clip.
+    pub generation: u32
+}
+
+impl Source {
+    pub fn new(clip: Entity) -> Source {
+        Source {
+            clip: clip,
+            gain: 1.,
+            looping: false,
+            playing: true,
+            spatial: true,
+            reference_distance: 1.,
+            max_distance: 1000.,
+            rolloff: 1.,
+            generation: 0
+        }
+    }
+}
+
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct AudioData {
+    clips:    Static<Clip>,
+    sources:  Static<Source>,
+    listener: Option<Entity>,
+    gain:     f32
+}
+
+impl AudioData {
+    pub fn new() -> AudioData {
+        AudioData {
+            clips: Static::new(),
+            sources: Static::new(),
+            listener: None,
+            gain: 1.
+        }
+    }
+}
+
+pub trait Audio: Common + Sized {
+    fn get_audio<'a>(&'a self) -> &'a AudioData;
+    fn get_audio_mut<'a>(&'a mut self) -> &'a mut AudioData;
+
+    fn new_clip(&mut self, clip: Clip) -> Entity {
+        let oid = self.new_object(None);
+        self.get_audio_mut().clips.insert(oid, clip);
+        oid
+    }
+
+    fn clip<'a>(&'a self, oid: Entity) -> Option<&'a Clip> {
+        self.get_audio().clips.get(oid)
+    }
+
+    /// attach a sound source to an existing entity
+    fn set_source(&mut self, oid: Entity, source: Source) {
+        self.get_audio_mut().sources.insert(oid, source);
+    }
+
+    fn source<'a>(&'a self, oid: Entity) -> Option<&'a Source> {
+        self.get_audio().sources.get(oid)
+    }
+
+    fn source_mut<'a>(&'a mut self, oid: Entity) -> Option<&'a mut Source> {
+        self.get_audio_mut().sources.get_mut(oid)
+    }
+
+    fn source_iter<'a>(&'a self) -> StaticIterator<'a, Source> {
+        self.get_audio().sources.iter()
+    }
+
+    /// start the source from the beginning of its clip
+    fn play(&mut self, oid: Entity) {
+        self.source_mut(oid).map(|s| {
+            s.playing = true;
+            s.generation += 1;
+        });
+    }
+
+    fn stop(&mut self, oid: Entity) {
+        self.source_mut(oid).map(|s| s.playing = false);
+    }
+
+    /// set the entity that the sound is heard from, this is
+    /// normally the same entity as the camera
+    fn set_listener(&mut self, oid: Entity) {
+        self.get_audio_mut().listener = Some(oid);
+    }
+
+    fn listener(&self) -> Option<Entity> {
+        self.get_audio().listener
+    }
+
+    /// set the master volume
+    fn set_master_gain(&mut self, gain: f32) {
+        self.get_audio_mut().gain = gain;
+    }
+
+    fn master_gain(&self) -> f32 {
+        self.get_audio().gain
+    }
+}
+
+impl Duplicate for AudioData {
+    fn duplicate(&mut self, src: Entity, dst: Entity) {
+        let x = self.clips.get(src).map(|x| x.clone());
+        x.map(|x| self.clips.insert(dst, x));
+        let x = self.sources.get(src).map(|x| x.clone());
+        x.map(|x| self.sources.insert(dst, x));
+    }
+}
+
+impl Delete for AudioData {
+    fn delete(&mut self, oid: Entity) -> bool {
+        if self.listener == Some(oid) {
+            self.listener = None;
+        }
+        self.clips.remove(oid)   |
+        self.sources.remove(oid)
+    }
+}
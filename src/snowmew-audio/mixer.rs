@@ -0,0 +1,207 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_4;
+use std::iter::repeat;
+use std::num::Float;
+
+use cgmath::{Vector3, Vector4, Matrix, EuclideanVector, Vector};
+
+use snowmew::common::Entity;
+use position::Positions;
+
+use {Audio, Source};
+use output::Output;
+
+/// AudioManager is the audio equivalent of `Render`, it is a passive
+/// manager that is handed a copy of the game each cadence pulse.
+pub trait AudioManager<T> {
+    fn update(&mut self, db: T);
+}
+
+/// Calculate how loud a source is at `distance` from the listener.
+/// This is the inverse distance clamped model used by OpenAL.
+pub fn attenuation(source: &Source, distance: f32) -> f32 {
+    let reference = source.reference_distance;
+    let distance = distance.max(reference).min(source.max_distance);
+    if reference + source.rolloff * (distance - reference) <= 0. {
+        return 1.;
+    }
+    reference / (reference + source.rolloff * (distance - reference))
+}
+
+/// Calculate the (left, right) gain of a source in the direction `dir`
+/// relative to a listener whose right hand points along `right`.
+/// This uses a constant power pan law so the source does not get
+/// quieter as it moves across the listener.
+pub fn pan(dir: &Vector3<f32>, right: &Vector3<f32>) -> (f32, f32) {
+    let p = if dir.length() < 1e-6 {
+        0.
+    } else {
+        dir.normalize().dot(&right.normalize()).max(-1.).min(1.)
+    };
+    let angle = (p + 1.) * FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+#[derive(Copy)]
+struct Voice {
+    generation: u32,
+    cursor: f64,
+    finished: bool
+}
+
+/// A software mixer, it mixes all the playing sources into a stereo
+/// stream of samples and hands them to an `Output`.
+pub struct Mixer<O> {
+    output: O,
+    sample_rate: u32,
+    cadance: f64,
+    remainder: f64,
+    voices: HashMap<Entity, Voice>,
+    buffer: Vec<f32>
+}
+
+impl<O: Output> Mixer<O> {
+    /// Create a mixer that writes `sample_rate` frames per second to `output`.
+    /// `cadance` is the number of seconds between each call to `update`.
+    pub fn new(output: O, sample_rate: u32, cadance: f64) -> Mixer<O> {
+        Mixer {
+            output: output,
+            sample_rate: sample_rate,
+            cadance: cadance,
+            remainder: 0.,
+            voices: HashMap::new(),
+            buffer: Vec::new()
+        }
+    }
+
+    pub fn output<'a>(&'a self) -> &'a O { &self.output }
+    pub fn output_mut<'a>(&'a mut self) -> &'a mut O { &mut self.output }
+    pub fn into_output(self) -> O { self.output }
+
+    fn frames_this_update(&mut self) -> usize {
+        let exact = self.sample_rate as f64 * self.cadance + self.remainder;
+        let frames = exact.floor();
+        self.remainder = exact - frames;
+        frames as usize
+    }
+
+    /// mix all the sources of `db` into the output
+    pub fn mix<T: Audio+Positions>(&mut self, db: &T) {
+        let frames = self.frames_this_update();
+        self.buffer.clear();
+        self.buffer.extend(repeat(0f32).take(frames * 2));
+
+        let (listener_pos, listener_right) = match db.listener() {
+            Some(l) => {
+                let m = db.position(l);
+                let p = m.mul_v(&Vector4::new(0f32, 0., 0., 1.));
+                let r = m.mul_v(&Vector4::new(1f32, 0., 0., 0.));
+                (Vector3::new(p.x, p.y, p.z), Vector3::new(r.x, r.y, r.z))
+            }
+            None => (Vector3::new(0f32, 0., 0.), Vector3::new(1f32, 0., 0.))
+        };
+
+        // forget about any voices whose source was removed
+        let removed: Vec<Entity> = self.voices.keys()
+            .filter(|&k| db.source(*k).is_none())
+            .map(|&k| k)
+            .collect();
+        for k in removed.iter() {
+            self.voices.remove(k);
+        }
+
+        let master = db.master_gain();
+        for (oid, source) in db.source_iter() {
+            let clip = match db.clip(source.clip) {
+                Some(clip) => clip,
+                None => continue
+            };
+
+            let voice = self.voices.entry(oid).get().unwrap_or_else(|v| {
+                v.insert(Voice {
+                    generation: source.generation,
+                    cursor: 0.,
+                    finished: false
+                })
+            });
+
+            if voice.generation != source.generation {
+                voice.generation = source.generation;
+                voice.cursor = 0.;
+                voice.finished = false;
+            }
+
+            // there is nothing to play, looping or not
+            if clip.frames() == 0 {
+                voice.finished = true;
+            }
+
+            if !source.playing || voice.finished {
+                continue;
+            }
+
+            let (left, right) = if source.spatial {
+                let m = db.position(oid);
+                let p = m.mul_v(&Vector4::new(0f32, 0., 0., 1.));
+                let dir = Vector3::new(p.x, p.y, p.z).sub_v(&listener_pos);
+                let gain = attenuation(source, dir.length());
+                let (l, r) = pan(&dir, &listener_right);
+                (l * gain, r * gain)
+            } else {
+                (1., 1.)
+            };
+            let gain = source.gain * master;
+
+            let step = clip.sample_rate() as f64 / self.sample_rate as f64;
+            for f in (0..frames) {
+                let idx = voice.cursor as usize;
+                if idx >= clip.frames() {
+                    if source.looping {
+                        // a step can be longer than the whole clip
+                        voice.cursor %= clip.frames() as f64;
+                    } else {
+                        voice.finished = true;
+                        break;
+                    }
+                }
+                let idx = voice.cursor as usize;
+
+                if source.spatial {
+                    let s = clip.mono(idx) * gain;
+                    self.buffer[f*2] += s * left;
+                    self.buffer[f*2+1] += s * right;
+                } else {
+                    let (l, r) = clip.stereo(idx);
+                    self.buffer[f*2] += l * gain;
+                    self.buffer[f*2+1] += r * gain;
+                }
+                voice.cursor += step;
+            }
+        }
+
+        for s in self.buffer.iter_mut() {
+            *s = s.max(-1.).min(1.);
+        }
+        self.output.write(&self.buffer);
+    }
+}
+
+impl<O: Output, T: Audio+Positions> AudioManager<T> for Mixer<O> {
+    fn update(&mut self, db: T) {
+        self.mix(&db);
+    }
+}
@@ -0,0 +1,154 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::num::Float;
+use std::old_io::{File, IoError, IoResult, SeekStyle, Seek, Writer};
+
+/// An output is the final destination of the mixer
+pub trait Output {
+    /// write interleaved stereo samples in the range of -1 to 1
+    fn write(&mut self, samples: &[f32]);
+}
+
+/// Throws away all the samples, only counting the number of frames
+pub struct NullOutput {
+    frames: usize
+}
+
+impl NullOutput {
+    pub fn new() -> NullOutput { NullOutput { frames: 0 } }
+
+    /// the number of stereo frames that have been written
+    pub fn frames(&self) -> usize { self.frames }
+}
+
+impl Output for NullOutput {
+    fn write(&mut self, samples: &[f32]) {
+        self.frames += samples.len() / 2;
+    }
+}
+
+/// Collects the samples into memory
+pub struct BufferOutput {
+    samples: Vec<f32>
+}
+
+impl BufferOutput {
+    pub fn new() -> BufferOutput { BufferOutput { samples: Vec::new() } }
+
+    /// the interleaved stereo samples that have been written
+    pub fn samples<'a>(&'a self) -> &'a [f32] { &self.samples }
+
+    pub fn clear(&mut self) { self.samples.clear() }
+}
+
+impl Output for BufferOutput {
+    fn write(&mut self, samples: &[f32]) {
+        self.samples.push_all(samples);
+    }
+}
+
+const WAV_HEADER_SIZE: u32 = 44;
+
+/// Writes the samples to a 16bit PCM stereo wav file. If a write
+/// fails the output stops writing, the error is returned by `finish`.
+pub struct WavOutput {
+    file: File,
+    sample_rate: u32,
+    data_bytes: u32,
+    finished: bool,
+    /// the first error the file returned
+    error: Option<IoError>
+}
+
+impl WavOutput {
+    pub fn create(path: &Path, sample_rate: u32) -> IoResult<WavOutput> {
+        let file = try!(File::create(path));
+        let mut wav = WavOutput {
+            file: file,
+            sample_rate: sample_rate,
+            data_bytes: 0,
+            finished: false,
+            error: None
+        };
+        try!(wav.write_header());
+        Ok(wav)
+    }
+
+    fn write_header(&mut self) -> IoResult<()> {
+        let channels = 2;
+        let bits = 16;
+        let block_align = channels * bits / 8;
+
+        try!(self.file.write_all(b"RIFF"));
+        try!(self.file.write_le_u32(WAV_HEADER_SIZE - 8 + self.data_bytes));
+        try!(self.file.write_all(b"WAVE"));
+        try!(self.file.write_all(b"fmt "));
+        try!(self.file.write_le_u32(16));
+        try!(self.file.write_le_u16(1)); // PCM
+        try!(self.file.write_le_u16(channels));
+        try!(self.file.write_le_u32(self.sample_rate));
+        try!(self.file.write_le_u32(self.sample_rate * block_align as u32));
+        try!(self.file.write_le_u16(block_align));
+        try!(self.file.write_le_u16(bits));
+        try!(self.file.write_all(b"data"));
+        self.file.write_le_u32(self.data_bytes)
+    }
+
+    fn patch_header(&mut self) -> IoResult<()> {
+        try!(self.file.seek(0, SeekStyle::SeekSet));
+        try!(self.write_header());
+        self.file.flush()
+    }
+
+    /// patch the header with the final size of the file, this is done
+    /// automatically when the output is dropped. The first error from
+    /// writing the samples or the header is returned.
+    pub fn finish(&mut self) -> IoResult<()> {
+        if !self.finished {
+            self.finished = true;
+            if let Err(err) = self.patch_header() {
+                if self.error.is_none() {
+                    self.error = Some(err);
+                }
+            }
+        }
+        match self.error {
+            Some(ref err) => Err(err.clone()),
+            None => Ok(())
+        }
+    }
+}
+
+impl Output for WavOutput {
+    fn write(&mut self, samples: &[f32]) {
+        if self.error.is_some() {
+            return;
+        }
+        for &s in samples.iter() {
+            let s = (s.max(-1.).min(1.) * 32767.) as i16;
+            if let Err(err) = self.file.write_le_i16(s) {
+                self.error = Some(err);
+                return;
+            }
+            self.data_bytes += 2;
+        }
+    }
+}
+
+impl Drop for WavOutput {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
@@ -197,7 +197,7 @@ impl Set<Entity, Parent<Entity>> for CommonData {
     }
 }
 
-#[derive(RustcEncodable, RustcDecodable, Copy, Clone)]
+#[derive(RustcEncodable, RustcDecodable, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Scene(pub Entity);
 
 impl ToEntity for Scene {
@@ -0,0 +1,45 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use snowmew::common::Entity;
+
+/// A single particle ready to be drawn as a camera facing quad. The
+/// position is in world space, `color` tints the material and `size`
+/// scales the geometry.
+#[derive(Clone, Copy, PartialEq, Debug, RustcEncodable, RustcDecodable)]
+pub struct Billboard {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+    pub size: f32
+}
+
+/// Billboards that are drawn as instances of `geometry` with `material`.
+/// The geometry is turned to face the camera, it should lie in the xy
+/// plane like the standard `plane`. Billboards do not cast shadows.
+#[derive(Clone, PartialEq, Debug, RustcEncodable, RustcDecodable)]
+pub struct BillboardSet {
+    pub geometry: Entity,
+    pub material: Entity,
+    pub billboards: Vec<Billboard>
+}
+
+impl BillboardSet {
+    pub fn new(geometry: Entity, material: Entity, billboards: Vec<Billboard>) -> BillboardSet {
+        BillboardSet {
+            geometry: geometry,
+            material: material,
+            billboards: billboards
+        }
+    }
+}
@@ -14,6 +14,7 @@
 
 
 use std::default::Default;
+use std::num::Float;
 use snowmew::common::Entity;
 use rustc_serialize::{Encodable, Decodable, Encoder, Decoder};
 
@@ -456,4 +457,83 @@ impl VertexBuffer {
             index: idx
         }
     }
+
+    /// Convert the vertices so they all have a tangent. Missing texture
+    /// coordinates, normals and tangents are zero, except for vertices
+    /// with both texture coordinates and normals whose tangents are
+    /// calculated from the triangles that use them.
+    pub fn tangent_vertices(&self) -> Vec<VertexGeoTexNormTan> {
+        let v = |position: [f32; 3], texture: [f32; 2], normal: [f32; 3]| {
+            VertexGeoTexNormTan {
+                position: position,
+                texture: texture,
+                normal: normal,
+                tangent: [0.; 3]
+            }
+        };
+
+        let mut out: Vec<VertexGeoTexNormTan> = match self.vertex {
+            Vertex::Geo(ref d) => {
+                return d.iter().map(|x| v(x.position, [0.; 2], [0.; 3])).collect()
+            }
+            Vertex::GeoTex(ref d) => {
+                return d.iter().map(|x| v(x.position, x.texture, [0.; 3])).collect()
+            }
+            Vertex::GeoNorm(ref d) => {
+                return d.iter().map(|x| v(x.position, [0.; 2], x.normal)).collect()
+            }
+            Vertex::GeoTexNormTan(ref d) => return d.clone(),
+            Vertex::GeoTexNorm(ref d) => {
+                d.iter().map(|x| v(x.position, x.texture, x.normal)).collect()
+            }
+        };
+
+        for tri in self.index.chunks(3) {
+            if tri.len() != 3 {
+                continue;
+            }
+            let (a, b, c) = (out[tri[0] as usize], out[tri[1] as usize], out[tri[2] as usize]);
+            let e1 = sub3(b.position, a.position);
+            let e2 = sub3(c.position, a.position);
+            let (du1, dv1) = (b.texture[0] - a.texture[0], b.texture[1] - a.texture[1]);
+            let (du2, dv2) = (c.texture[0] - a.texture[0], c.texture[1] - a.texture[1]);
+            let r = 1. / (du1 * dv2 - du2 * dv1);
+            if !r.is_finite() {
+                continue;
+            }
+            let t = [(e1[0] * dv2 - e2[0] * dv1) * r,
+                     (e1[1] * dv2 - e2[1] * dv1) * r,
+                     (e1[2] * dv2 - e2[2] * dv1) * r];
+            for &i in tri.iter() {
+                let v = &mut out[i as usize];
+                v.tangent = add3(v.tangent, t);
+            }
+        }
+
+        // make the tangent perpendicular to the normal
+        for v in out.iter_mut() {
+            let n = v.normal;
+            let d = dot3(n, v.tangent);
+            let t = sub3(v.tangent, [n[0] * d, n[1] * d, n[2] * d]);
+            let len = dot3(t, t).sqrt();
+            v.tangent = if len > 0. {
+                [t[0] / len, t[1] / len, t[2] / len]
+            } else {
+                [0.; 3]
+            };
+        }
+        out
+    }
+}
+
+fn sub3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn add3(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn dot3(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
 }
@@ -14,7 +14,7 @@
 
 #![crate_name = "snowmew-graphics"]
 #![crate_type = "lib"]
-#![feature(plugin)]
+#![feature(plugin, core, collections)]
 
 #![plugin(gfx_macros)]
 extern crate gfx_macros;
@@ -26,43 +26,41 @@ extern crate "rustc-serialize" as rustc_serialize;
 
 extern crate "snowmew-core" as snowmew;
 
-use std::cmp::Ordering::{Less, Equal, Greater};
-use std::cmp::Ordering;
 
 use snowmew::common::{Common, Entity, Duplicate, Delete};
 use snowmew::table::{Static, StaticIterator};
 
 pub use geometry::{Geometry, VertexBuffer};
-pub use material::Material;
-pub use texture::Texture;
+pub use material::{Material, BlendMode};
+pub use pbr::PbrMaterial;
+pub use texture::{Texture, rgbe, from_rgbe};
 pub use light::Light;
+pub use lod::Lod;
+pub use billboard::{Billboard, BillboardSet};
 
 pub use light::{
+    Attenuation,
     Directional,
-    Point
+    Point,
+    Shadow,
+    Spot
 };
 
 pub mod geometry;
 pub mod material;
+pub mod pbr;
 pub mod standard;
 pub mod texture;
 pub mod light;
+pub mod lod;
+pub mod billboard;
 
-#[derive(Clone, Default, Eq, PartialEq, PartialOrd, Hash, Debug, RustcEncodable, RustcDecodable, Copy)]
+#[derive(Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, RustcEncodable, RustcDecodable, Copy)]
 pub struct Drawable {
     pub geometry: Entity,
-    pub material: Entity
-}
-
-impl Ord for Drawable {
-    fn cmp(&self, other: &Drawable) -> Ordering {
-        let order = self.geometry.cmp(&other.geometry);
-        match order {
-            Equal => self.material.cmp(&other.material),
-            Greater => Greater,
-            Less => Less
-        }
-    }
+    pub material: Entity,
+    /// the object is drawn but is left out of the shadow maps
+    pub no_shadow: bool
 }
 
 #[derive(Clone, RustcEncodable, RustcDecodable)]
@@ -71,10 +69,16 @@ pub struct GraphicsData {
     geometry:           Static<Geometry>,
     vertex:             Static<VertexBuffer>,
     material:           Static<Material>,
+    pbr_material:       Static<PbrMaterial>,
     material_index:     Static<i32>,
     material_idx_last:  i32,
     texture:            Static<Texture>,
     lights:             Static<light::Light>,
+    lod:                Static<Lod>,
+    billboards:         Static<BillboardSet>,
+    /// bumped each time a vertex buffer, texture or material is set
+    revision:           Static<u64>,
+    revision_last:      u64,
     standard:           Option<standard::Standard>
 }
 
@@ -85,9 +89,14 @@ impl GraphicsData {
             geometry: Static::new(),
             vertex: Static::new(),
             material: Static::new(),
+            pbr_material: Static::new(),
             material_index: Static::new(),
             texture: Static::new(),
             lights: Static::new(),
+            lod: Static::new(),
+            billboards: Static::new(),
+            revision: Static::new(),
+            revision_last: 0,
             material_idx_last: 0,
             standard: None
         }
@@ -111,12 +120,36 @@ pub trait Graphics: Common + Sized {
         self.get_graphics().draw.get(key)
     }
 
+    /// The revision of a vertex buffer, texture or material. It changes
+    /// each time the resource is set so renders know to upload it again,
+    /// an entity without one of these resources is at revision 0.
+    fn revision(&self, oid: Entity) -> u64 {
+        self.get_graphics().revision.get(oid).map(|r| *r).unwrap_or(0)
+    }
+
+    /// mark a resource as changed, the `set_` methods do this for you
+    fn bump_revision(&mut self, oid: Entity) {
+        let graphics = self.get_graphics_mut();
+        graphics.revision_last += 1;
+        graphics.revision.insert(oid, graphics.revision_last);
+    }
+
     fn new_vertex_buffer(&mut self, vb: VertexBuffer) -> Entity {
         let oid = self.new_object(None);
-        self.get_graphics_mut().vertex.insert(oid, vb);
+        self.set_vertex_buffer(oid, vb);
         oid
     }
 
+    /// replace the vertices of a vertex buffer
+    fn set_vertex_buffer(&mut self, oid: Entity, vb: VertexBuffer) {
+        self.get_graphics_mut().vertex.insert(oid, vb);
+        self.bump_revision(oid);
+    }
+
+    fn vertex_buffer<'a>(&'a self, oid: Entity) -> Option<&'a VertexBuffer> {
+        self.get_graphics().vertex.get(oid)
+    }
+
     fn geometry<'a>(&'a self, oid: Entity) -> Option<&'a Geometry> {
         self.get_graphics().geometry.get(oid)
     }
@@ -141,25 +174,86 @@ pub trait Graphics: Common + Sized {
     fn new_material(&mut self, material: Material) -> Entity {
         let obj = self.new_object(None);
         self.get_graphics_mut().material.insert(obj, material);
+        self.bump_revision(obj);
         let idx = self.get_graphics().material_idx_last;
         self.get_graphics_mut().material_idx_last += 1;
         self.get_graphics_mut().material_index.insert(obj, idx);
         obj
     }
 
+    /// change a material, it keeps its material index
+    fn set_material(&mut self, oid: Entity, material: Material) {
+        self.get_graphics_mut().material.insert(oid, material);
+        self.bump_revision(oid);
+    }
+
     fn material_iter<'a>(&'a self) -> StaticIterator<'a, Material> {
         self.get_graphics().material.iter()
     }
 
+    fn pbr_material<'a>(&'a self, oid: Entity) -> Option<&'a PbrMaterial> {
+        self.get_graphics().pbr_material.get(oid)
+    }
+
+    /// a physically based material can be used anywhere a `Material` can
+    fn new_pbr_material(&mut self, material: PbrMaterial) -> Entity {
+        let obj = self.new_object(None);
+        self.get_graphics_mut().pbr_material.insert(obj, material);
+        self.bump_revision(obj);
+        let idx = self.get_graphics().material_idx_last;
+        self.get_graphics_mut().material_idx_last += 1;
+        self.get_graphics_mut().material_index.insert(obj, idx);
+        obj
+    }
+
+    fn set_pbr_material(&mut self, oid: Entity, material: PbrMaterial) {
+        self.get_graphics_mut().pbr_material.insert(oid, material);
+        self.bump_revision(oid);
+    }
+
+    fn pbr_material_iter<'a>(&'a self) -> StaticIterator<'a, PbrMaterial> {
+        self.get_graphics().pbr_material.iter()
+    }
+
     fn set_draw(&mut self, oid: Entity, geo: Entity, material: Entity) {
         let draw = Drawable {
             geometry: geo,
-            material: material
+            material: material,
+            no_shadow: false
         };
 
         self.get_graphics_mut().draw.insert(oid, draw.clone());
     }
 
+    /// set if a drawable casts shadows, drawables cast shadows by default
+    fn set_cast_shadow(&mut self, oid: Entity, cast: bool) {
+        self.get_graphics_mut().draw.get_mut(oid).map(|d| d.no_shadow = !cast);
+    }
+
+    /// pick the geometry of a drawable by its distance from the camera,
+    /// this replaces the geometry set by `set_draw`
+    fn set_lod(&mut self, oid: Entity, lod: Lod) {
+        self.get_graphics_mut().lod.insert(oid, lod);
+    }
+
+    fn lod<'a>(&'a self, oid: Entity) -> Option<&'a Lod> {
+        self.get_graphics().lod.get(oid)
+    }
+
+    /// draw camera facing billboards for the entity, this replaces
+    /// the billboards it had before
+    fn set_billboard_set(&mut self, oid: Entity, billboards: BillboardSet) {
+        self.get_graphics_mut().billboards.insert(oid, billboards);
+    }
+
+    fn billboard_set<'a>(&'a self, oid: Entity) -> Option<&'a BillboardSet> {
+        self.get_graphics().billboards.get(oid)
+    }
+
+    fn billboard_set_iter<'a>(&'a self) -> StaticIterator<'a, BillboardSet> {
+        self.get_graphics().billboards.iter()
+    }
+
     fn get_draw(&self, oid: Entity) -> Option<Drawable> {
         match self.get_graphics().draw.get(oid) {
             Some(d) => Some(d.clone()),
@@ -200,10 +294,16 @@ pub trait Graphics: Common + Sized {
 
     fn new_texture(&mut self, texture: Texture) -> Entity {
         let oid = self.new_object(None);
-        self.get_graphics_mut().texture.insert(oid, texture);
+        self.set_texture(oid, texture);
         oid
     }
 
+    /// replace the image of a texture
+    fn set_texture(&mut self, oid: Entity, texture: Texture) {
+        self.get_graphics_mut().texture.insert(oid, texture);
+        self.bump_revision(oid);
+    }
+
     fn get_texture<'a>(&'a self, oid: Entity) -> Option<&'a Texture> {
         self.get_graphics().texture.get(oid)
     }
@@ -238,12 +338,20 @@ impl Duplicate for GraphicsData {
         x.map(|x| self.vertex.insert(dst, x));
         let x = self.material.get(src).map(|x| x.clone());
         x.map(|x| self.material.insert(dst, x));
+        let x = self.pbr_material.get(src).map(|x| x.clone());
+        x.map(|x| self.pbr_material.insert(dst, x));
         let x = self.material_index.get(src).map(|x| x.clone());
         x.map(|x| self.material_index.insert(dst, x));
         let x = self.texture.get(src).map(|x| x.clone());
         x.map(|x| self.texture.insert(dst, x));
         let x = self.lights.get(src).map(|x| x.clone());
         x.map(|x| self.lights.insert(dst, x));
+        let x = self.lod.get(src).map(|x| x.clone());
+        x.map(|x| self.lod.insert(dst, x));
+        let x = self.billboards.get(src).map(|x| x.clone());
+        x.map(|x| self.billboards.insert(dst, x));
+        let x = self.revision.get(src).map(|x| x.clone());
+        x.map(|x| self.revision.insert(dst, x));
     }
 }
 
@@ -254,9 +362,13 @@ impl Delete for GraphicsData {
         self.geometry.remove(oid)         |
         self.vertex.remove(oid)           |
         self.material.remove(oid)         |
+        self.pbr_material.remove(oid)     |
         self.material_index.remove(oid)   |
         self.texture.remove(oid)          |
-        self.lights.remove(oid)
+        self.lights.remove(oid)           |
+        self.lod.remove(oid)              |
+        self.billboards.remove(oid)       |
+        self.revision.remove(oid)
     }
 }
 
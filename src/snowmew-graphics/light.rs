@@ -15,12 +15,82 @@
 use std::default::Default;
 use cgmath::Vector3;
 
+/// How the brightness of a light falls off with distance, the light
+/// is scaled by `1 / (constant + linear * d + quadratic * d * d)`.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Attenuation {
+        Attenuation {
+            constant: constant,
+            linear: linear,
+            quadratic: quadratic
+        }
+    }
+
+    /// The light does not fall off with distance
+    pub fn none() -> Attenuation {
+        Attenuation::new(1., 0., 0.)
+    }
+
+    /// The amount the light is scaled by at `distance`
+    pub fn factor(&self, distance: f32) -> f32 {
+        1. / (self.constant + self.linear * distance + self.quadratic * distance * distance)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Attenuation {
+        Attenuation::none()
+    }
+}
 
+/// How a light draws its shadow map. `resolution` is the width and
+/// height of the map and `bias` is subtracted from the depth of a
+/// surface before it is compared with the map. Directional lights split
+/// the first `distance` units in front of the camera into `cascades`
+/// maps, spot lights use a single map that reaches `distance` units.
+///
+/// A frame draws at most `MAX_SHADOW_MAPS` (4) maps between all of its
+/// lights, handed out to directional lights first and then spot lights.
+/// A directional light is given fewer cascades when there are not
+/// enough maps left, lights that find none left cast no shadow.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    pub resolution: u32,
+    pub bias: f32,
+    pub cascades: u32,
+    pub distance: f32
+}
+
+impl Shadow {
+    pub fn new(resolution: u32, bias: f32) -> Shadow {
+        Shadow {
+            resolution: resolution,
+            bias: bias,
+            cascades: 2,
+            distance: 100.
+        }
+    }
+}
+
+impl Default for Shadow {
+    fn default() -> Shadow {
+        Shadow::new(2048, 0.0002)
+    }
+}
 
+/// A light that shines in every direction from the position of its entity
 #[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
 pub struct Point {
     color: Vector3<f32>,
-    intensity: f32
+    intensity: f32,
+    attenuation: Attenuation
 }
 
 
@@ -30,18 +100,76 @@ impl Point {
         Point {
             color: color,
             intensity: intensity,
+            attenuation: Attenuation::none()
+        }
+    }
+
+    pub fn color(&self) -> Vector3<f32> {self.color.clone()}
+    pub fn intensity(&self) -> f32 {self.intensity.clone()}
+    pub fn attenuation(&self) -> Attenuation {self.attenuation}
+
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.attenuation = attenuation;
+    }
+}
+
+/// A light that shines in a cone from the position of its entity.
+/// The cone is fully lit inside of the `inner` angle and fades out
+/// to nothing at the `outer` angle, both are half angles in radians.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
+pub struct Spot {
+    direction: Vector3<f32>,
+    color: Vector3<f32>,
+    intensity: f32,
+    inner: f32,
+    outer: f32,
+    attenuation: Attenuation,
+    shadow: Option<Shadow>
+}
+
+impl Spot {
+    pub fn new(direction: Vector3<f32>,
+               color: Vector3<f32>,
+               intensity: f32,
+               inner: f32,
+               outer: f32) -> Spot {
+        Spot {
+            direction: direction,
+            color: color,
+            intensity: intensity,
+            inner: inner,
+            outer: outer,
+            attenuation: Attenuation::none(),
+            shadow: None
         }
     }
 
+    /// the direction the light points in, relative to its entity
+    pub fn direction(&self) -> Vector3<f32> {self.direction.clone()}
     pub fn color(&self) -> Vector3<f32> {self.color.clone()}
     pub fn intensity(&self) -> f32 {self.intensity.clone()}
+    pub fn inner(&self) -> f32 {self.inner}
+    pub fn outer(&self) -> f32 {self.outer}
+    pub fn attenuation(&self) -> Attenuation {self.attenuation}
+
+    pub fn set_attenuation(&mut self, attenuation: Attenuation) {
+        self.attenuation = attenuation;
+    }
+
+    /// spot lights do not cast shadows unless they are given a `Shadow`
+    pub fn shadow(&self) -> Option<Shadow> {self.shadow}
+
+    pub fn set_shadow(&mut self, shadow: Option<Shadow>) {
+        self.shadow = shadow;
+    }
 }
 
 #[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
 pub struct Directional {
     normal: Vector3<f32>,
     color: Vector3<f32>,
-    intensity: f32
+    intensity: f32,
+    shadow: Option<Shadow>
 }
 
 impl Directional {
@@ -52,25 +180,46 @@ impl Directional {
             normal: normal,
             color: color,
             intensity: intensity,
+            shadow: Some(Shadow::default())
         }
     }
 
     pub fn normal(&self) -> Vector3<f32> {self.normal.clone()}
     pub fn color(&self) -> Vector3<f32> {self.color.clone()}
     pub fn intensity(&self) -> f32 {self.intensity.clone()}
+
+    /// directional lights cast shadows with the default settings
+    pub fn shadow(&self) -> Option<Shadow> {self.shadow}
+
+    pub fn set_shadow(&mut self, shadow: Option<Shadow>) {
+        self.shadow = shadow;
+    }
 }
 
 #[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
 pub enum Light {
     Directional(Directional),
-    Point(Point)
+    Point(Point),
+    Spot(Spot)
+}
+
+impl Light {
+    /// the shadow settings of the light, point lights never cast shadows
+    pub fn shadow(&self) -> Option<Shadow> {
+        match *self {
+            Light::Directional(d) => d.shadow(),
+            Light::Point(_) => None,
+            Light::Spot(s) => s.shadow()
+        }
+    }
 }
 
 impl Default for Light {
     fn default() -> Light {
         Light::Point(Point {
             color: Vector3::new(0f32, 0., 0.),
-            intensity: 0.
+            intensity: 0.,
+            attenuation: Attenuation::none()
         })
     }
 }
\ No newline at end of file
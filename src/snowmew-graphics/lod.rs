@@ -0,0 +1,51 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+use snowmew::common::Entity;
+
+/// Level of detail for a drawable. Each level is the furthest distance
+/// from the camera that a geometry is used at, the closest level that
+/// covers the distance is drawn. Past the last level nothing is drawn.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct Lod {
+    levels: Vec<(f32, Entity)>
+}
+
+impl Lod {
+    pub fn new() -> Lod {
+        Lod {
+            levels: Vec::new()
+        }
+    }
+
+    /// Use `geometry` for anything closer than `distance`
+    pub fn add_level(&mut self, distance: f32, geometry: Entity) {
+        let idx = self.levels.iter().position(|&(d, _)| d > distance)
+                                    .unwrap_or(self.levels.len());
+        self.levels.insert(idx, (distance, geometry));
+    }
+
+    /// The levels sorted from closest to furthest
+    pub fn levels<'a>(&'a self) -> &'a [(f32, Entity)] {
+        &self.levels[..]
+    }
+
+    /// The geometry to draw at `distance`
+    pub fn select(&self, distance: f32) -> Option<Entity> {
+        self.levels.iter()
+                   .find(|&&(d, _)| distance < d)
+                   .map(|&(_, geo)| geo)
+    }
+}
@@ -13,11 +13,34 @@
 //   limitations under the License.
 
 use std::default::Default;
+use std::num::Float;
 use rustc_serialize::{Encodable, Encoder, Decoder};
 
 use snowmew::Entity;
 use super::geometry::F32v3;
 
+/// How the fragments of a material are combined with what has already
+/// been drawn. Opaque materials write depth, blended materials are drawn
+/// after them from back to front and do not.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BlendMode {
+    Opaque,
+    /// mixed with the frame by the material's opacity
+    Alpha,
+    /// added to the frame, scaled by the material's opacity
+    Additive
+}
+
+impl Default for BlendMode {
+    fn default() -> BlendMode { BlendMode::Opaque }
+}
+
+impl BlendMode {
+    pub fn is_opaque(&self) -> bool {
+        *self == BlendMode::Opaque
+    }
+}
+
 #[derive(RustcEncodable, RustcDecodable, PartialEq, Copy)]
 pub struct Material {
     ka: F32v3,
@@ -30,6 +53,7 @@ pub struct Material {
     tr: f32,
     d: f32,
     illum: i32,
+    blend: BlendMode,
 
     map_ka:   Option<Entity>,
     map_kd:   Option<Entity>,
@@ -54,6 +78,7 @@ impl Clone for Material {
             tr: self.tr,
             d: self.d,
             illum: self.illum,
+            blend: self.blend,
             map_ka: self.map_ka,
             map_kd: self.map_kd,
             map_ks: self.map_ks,
@@ -83,8 +108,9 @@ impl Material {
             ns: 0.,
             ni: 0.,
             tr: 0.,
-            d: 0.,
+            d: 1.,
             illum: 2,
+            blend: BlendMode::Opaque,
             map_ka:   None,
             map_kd:   None,
             map_ks:   None,
@@ -113,7 +139,7 @@ impl Material {
     pub fn ks(&self) -> [f32; 3] {self.ks.0}
     pub fn set_ks(&mut self, c: [f32; 3]) {self.ks = F32v3(c);}
 
-    pub fn ke(&self) -> [f32; 3] {self.ks.0}
+    pub fn ke(&self) -> [f32; 3] {self.ke.0}
     pub fn set_ke(&mut self, c: [f32; 3]) {self.ke = F32v3(c);}
 
     pub fn tf(&self) -> [f32; 3] {self.tf.0}
@@ -148,4 +174,24 @@ impl Material {
 
     pub fn ni(&self) -> f32 {self.ni}
     pub fn set_ni(&mut self, v: f32) {self.ni = v}
+
+    pub fn illum(&self) -> i32 {self.illum}
+    pub fn set_illum(&mut self, v: i32) {self.illum = v}
+
+    /// dissolve, 1 is opaque
+    pub fn d(&self) -> f32 {self.d}
+    pub fn set_d(&mut self, v: f32) {self.d = v}
+
+    /// transparency, the inverse of dissolve
+    pub fn tr(&self) -> f32 {self.tr}
+    pub fn set_tr(&mut self, v: f32) {self.tr = v}
+
+    /// How opaque the material is, files set either `d` or `tr`
+    /// so the more transparent of the two is used.
+    pub fn opacity(&self) -> f32 {
+        self.d.min(1. - self.tr).max(0.)
+    }
+
+    pub fn blend(&self) -> BlendMode {self.blend}
+    pub fn set_blend(&mut self, blend: BlendMode) {self.blend = blend}
 }
@@ -0,0 +1,156 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::default::Default;
+use std::num::Float;
+
+use snowmew::Entity;
+use super::geometry::F32v3;
+use super::material::{Material, BlendMode};
+
+/// A metallic-roughness material. The metallic-roughness map stores
+/// the roughness in its green channel and metallic in its blue channel,
+/// the occlusion map uses its red channel.
+#[derive(RustcEncodable, RustcDecodable, PartialEq, Copy)]
+pub struct PbrMaterial {
+    base_color: F32v3,
+    emissive: F32v3,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    opacity: f32,
+    blend: BlendMode,
+
+    base_color_map:         Option<Entity>,
+    metallic_roughness_map: Option<Entity>,
+    normal_map:             Option<Entity>,
+    occlusion_map:          Option<Entity>,
+    emissive_map:           Option<Entity>,
+}
+
+impl Clone for PbrMaterial {
+    fn clone(&self) -> PbrMaterial {
+        PbrMaterial {
+            base_color: self.base_color,
+            emissive: self.emissive,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            normal_scale: self.normal_scale,
+            occlusion_strength: self.occlusion_strength,
+            opacity: self.opacity,
+            blend: self.blend,
+            base_color_map: self.base_color_map,
+            metallic_roughness_map: self.metallic_roughness_map,
+            normal_map: self.normal_map,
+            occlusion_map: self.occlusion_map,
+            emissive_map: self.emissive_map
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> PbrMaterial {
+        PbrMaterial::new()
+    }
+}
+
+impl PbrMaterial {
+    /// A white, rough dielectric
+    pub fn new() -> PbrMaterial {
+        PbrMaterial {
+            base_color: F32v3([1.; 3]),
+            emissive: F32v3([0.; 3]),
+            metallic: 0.,
+            roughness: 1.,
+            normal_scale: 1.,
+            occlusion_strength: 1.,
+            opacity: 1.,
+            blend: BlendMode::Opaque,
+            base_color_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            occlusion_map: None,
+            emissive_map: None
+        }
+    }
+
+    pub fn simple(color: [f32; 3], metallic: f32, roughness: f32) -> PbrMaterial {
+        let mut mat = PbrMaterial::new();
+        mat.base_color = F32v3(color);
+        mat.metallic = metallic;
+        mat.roughness = roughness;
+        mat
+    }
+
+    /// Convert a MTL material. The diffuse colour is used as the base
+    /// colour, falling back to the ambient colour if it is black. The
+    /// specular exponent is turned into a roughness and materials with
+    /// a reflection model (`illum` 3 or a reflection map) are metallic.
+    /// The opacity and blend mode are kept.
+    pub fn from_mtl(mtl: &Material) -> PbrMaterial {
+        let mut mat = PbrMaterial::new();
+        let kd = mtl.kd();
+        mat.base_color = F32v3(if kd == [0.; 3] { mtl.ka() } else { kd });
+        mat.base_color_map = mtl.map_kd().or(mtl.map_ka());
+        mat.emissive = F32v3(mtl.ke());
+        mat.emissive_map = mtl.map_ke();
+        mat.normal_map = mtl.map_bump();
+        mat.roughness = (2. / (mtl.ns().max(0.) + 2.)).powf(0.25);
+        mat.metallic = if mtl.illum() == 3 || mtl.map_refl().is_some() { 1. } else { 0. };
+        mat.opacity = mtl.opacity();
+        mat.blend = mtl.blend();
+        mat
+    }
+
+    pub fn base_color(&self) -> [f32; 3] {self.base_color.0}
+    pub fn set_base_color(&mut self, c: [f32; 3]) {self.base_color = F32v3(c);}
+
+    pub fn emissive(&self) -> [f32; 3] {self.emissive.0}
+    pub fn set_emissive(&mut self, c: [f32; 3]) {self.emissive = F32v3(c);}
+
+    pub fn metallic(&self) -> f32 {self.metallic}
+    pub fn set_metallic(&mut self, v: f32) {self.metallic = v}
+
+    pub fn roughness(&self) -> f32 {self.roughness}
+    pub fn set_roughness(&mut self, v: f32) {self.roughness = v}
+
+    pub fn normal_scale(&self) -> f32 {self.normal_scale}
+    pub fn set_normal_scale(&mut self, v: f32) {self.normal_scale = v}
+
+    pub fn occlusion_strength(&self) -> f32 {self.occlusion_strength}
+    pub fn set_occlusion_strength(&mut self, v: f32) {self.occlusion_strength = v}
+
+    /// multiplied with the alpha of the base colour map
+    pub fn opacity(&self) -> f32 {self.opacity}
+    pub fn set_opacity(&mut self, v: f32) {self.opacity = v}
+
+    pub fn blend(&self) -> BlendMode {self.blend}
+    pub fn set_blend(&mut self, blend: BlendMode) {self.blend = blend}
+
+    pub fn base_color_map(&self) -> Option<Entity> {self.base_color_map}
+    pub fn set_base_color_map(&mut self, oid: Entity) {self.base_color_map = Some(oid);}
+
+    pub fn metallic_roughness_map(&self) -> Option<Entity> {self.metallic_roughness_map}
+    pub fn set_metallic_roughness_map(&mut self, oid: Entity) {self.metallic_roughness_map = Some(oid);}
+
+    pub fn normal_map(&self) -> Option<Entity> {self.normal_map}
+    pub fn set_normal_map(&mut self, oid: Entity) {self.normal_map = Some(oid);}
+
+    pub fn occlusion_map(&self) -> Option<Entity> {self.occlusion_map}
+    pub fn set_occlusion_map(&mut self, oid: Entity) {self.occlusion_map = Some(oid);}
+
+    pub fn emissive_map(&self) -> Option<Entity> {self.emissive_map}
+    pub fn set_emissive_map(&mut self, oid: Entity) {self.emissive_map = Some(oid);}
+}
@@ -15,6 +15,7 @@
 use snowmew::Entity;
 use geometry::{VertexBuffer, Geometry, VertexGeoTexNorm};
 use material::Material;
+use lod::Lod;
 use Graphics;
 
 use genmesh::generators::{Plane, Cube, SphereUV};
@@ -123,6 +124,21 @@ pub struct Spheres {
     pub uv_256: Entity,
 }
 
+impl Spheres {
+    /// Use the detailed spheres close to the camera and switch to
+    /// coarser ones each time the distance doubles. A sphere is not
+    /// drawn past `16 * near`.
+    pub fn lod(&self, near: f32) -> Lod {
+        let mut lod = Lod::new();
+        lod.add_level(near, self.uv_128);
+        lod.add_level(near * 2., self.uv_64);
+        lod.add_level(near * 4., self.uv_32);
+        lod.add_level(near * 8., self.uv_16);
+        lod.add_level(near * 16., self.uv_8);
+        lod
+    }
+}
+
 #[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
 pub struct Shapes {
     pub cube: Entity,
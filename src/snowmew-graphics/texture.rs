@@ -13,15 +13,43 @@
 //   limitations under the License.
 
 use std::default;
+use std::num::Float;
 
 #[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct Texture {
     width: u32,
     height: u32,
     depth: u32,
+    hdr: bool,
     data: Vec<u8>
 }
 
+/// Encode a linear colour as RGBE, a shared exponent in the alpha byte.
+/// This is how high dynamic range textures store their texels.
+pub fn rgbe(color: [f32; 3]) -> [u8; 4] {
+    let max = color[0].max(color[1]).max(color[2]);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let (mantissa, exponent) = max.frexp();
+    let scale = mantissa * 256. / max;
+    [(color[0] * scale) as u8,
+     (color[1] * scale) as u8,
+     (color[2] * scale) as u8,
+     (exponent + 128) as u8]
+}
+
+/// Decode an RGBE texel back into a linear colour
+pub fn from_rgbe(texel: [u8; 4]) -> [f32; 3] {
+    if texel[3] == 0 {
+        return [0., 0., 0.];
+    }
+    let scale = 2f32.powi(texel[3] as i32 - (128 + 8));
+    [texel[0] as f32 * scale,
+     texel[1] as f32 * scale,
+     texel[2] as f32 * scale]
+}
+
 fn offset(width: usize, depth: usize,
           row: usize, column: usize, component: usize) -> usize {
     width * depth * row +
@@ -57,6 +85,30 @@ impl Texture {
             width: width,
             height: height,
             depth: depth,
+            hdr: false,
+            data: data
+        }
+    }
+
+    /// Create a high dynamic range texture from linear rgb triples,
+    /// the texels are stored RGBE encoded with a depth of 4.
+    pub fn new_hdr(width: u32, height: u32, rgb: &[f32]) -> Texture {
+        assert!(rgb.len() == (width * height * 3) as usize);
+        let mut data = Vec::with_capacity((width * height * 4) as usize);
+        for c in rgb.chunks(3) {
+            data.push_all(&rgbe([c[0], c[1], c[2]]));
+        }
+        Texture::new_rgbe(width, height, data)
+    }
+
+    /// Create a high dynamic range texture from already RGBE encoded texels
+    pub fn new_rgbe(width: u32, height: u32, data: Vec<u8>) -> Texture {
+        assert!(data.len() == (width * height * 4) as usize);
+        Texture {
+            width: width,
+            height: height,
+            depth: 4,
+            hdr: true,
             data: data
         }
     }
@@ -65,6 +117,29 @@ impl Texture {
     pub fn height(&self) -> u32 { self.height }
     pub fn depth(&self) -> u32 { self.depth }
     pub fn data<'a>(&'a self) -> &'a [u8] { &self.data }
+    /// true if the data is RGBE encoded, see `new_hdr`
+    pub fn is_hdr(&self) -> bool { self.hdr }
+
+    /// Read the texel at `x`, `y` as a linear colour. Low dynamic range
+    /// textures are scaled into 0 to 1, missing channels read as opaque
+    /// grey scale, high dynamic range textures are decoded with an alpha of 1.
+    pub fn texel(&self, x: u32, y: u32) -> [f32; 4] {
+        let depth = self.depth as usize;
+        let at = offset(self.width as usize, depth, y as usize, x as usize, 0);
+        let d = &self.data[at..at+depth];
+        if self.hdr {
+            let rgb = from_rgbe([d[0], d[1], d[2], d[3]]);
+            return [rgb[0], rgb[1], rgb[2], 1.];
+        }
+        let c = |i: usize| d[i] as f32 / 255.;
+        match depth {
+            1 => [c(0), c(0), c(0), 1.],
+            2 => [c(0), c(0), c(0), c(1)],
+            3 => [c(0), c(1), c(2), 1.],
+            _ => [c(0), c(1), c(2), c(3)]
+        }
+    }
+
     pub fn flip(&mut self) {
         flip(&mut self.data, self.height as usize, self.width as usize, self.depth as usize);
     }
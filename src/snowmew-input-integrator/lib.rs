@@ -200,10 +200,12 @@ impl<GameData,
     }
 }
 
-impl<T> render::IntoRender for InputIntegratorGameData<T> {
+impl<T: render::IntoRender> render::IntoRender for InputIntegratorGameData<T> {
     type RenderGameState = T;
 
     fn into_render(self) -> T { self.inner }
+
+    fn end_frame(&mut self) { self.inner.end_frame() }
 }
 
 impl<T: GetIoState> GetIoState for InputIntegratorGameData<T> {
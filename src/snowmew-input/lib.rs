@@ -35,8 +35,10 @@ pub use input::{
     WindowEvent,
     EventGroup
 };
+pub use stats::RenderStats;
 
 mod input;
+mod stats;
 
 
 pub type WindowId = usize;
@@ -420,7 +422,10 @@ pub struct IoState {
     pub position: (i32, i32),
     pub show_mouse: bool,
     pub mouse_over: bool,
-    pub window_title: String
+    pub window_title: String,
+    /// what the render did to draw the last frame, this is updated
+    /// after each frame by `start`
+    pub render_stats: RenderStats
 }
 
 impl IoState {
@@ -431,7 +436,8 @@ impl IoState {
             position: (0, 0),
             show_mouse: true,
             mouse_over: false,
-            window_title: "snowmew".to_string()
+            window_title: "snowmew".to_string(),
+            render_stats: RenderStats::new()
         }
     }
 
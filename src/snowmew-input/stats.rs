@@ -0,0 +1,60 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+/// What a render did to draw its last frame, the game sees the stats
+/// of the last frame in `IoState::render_stats`
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct RenderStats {
+    /// the number of frames drawn so far
+    pub frame: u64,
+    pub draw_calls: usize,
+    pub instances: usize,
+    /// the triangles drawn, including those drawn into shadow maps
+    pub triangles: usize,
+    /// the resources uploaded for the frame
+    pub uploads: usize,
+    /// seconds spent uploading the game's resources
+    pub load_time: f64,
+    /// seconds spent drawing
+    pub draw_time: f64
+}
+
+impl RenderStats {
+    pub fn new() -> RenderStats {
+        RenderStats {
+            frame: 0,
+            draw_calls: 0,
+            instances: 0,
+            triangles: 0,
+            uploads: 0,
+            load_time: 0.,
+            draw_time: 0.
+        }
+    }
+
+    /// count a draw call of `instances` instances that are each
+    /// `triangles` triangles, lines and points have none
+    pub fn draw(&mut self, instances: usize, triangles: usize) {
+        self.draw_calls += 1;
+        self.instances += instances;
+        self.triangles += instances * triangles;
+    }
+
+    /// the stats as lines of text
+    pub fn text(&self) -> String {
+        format!("frame {}\ndraws {}\ninstances {}\ntriangles {}\nuploads {}\nload {:.2}ms\ndraw {:.2}ms",
+                self.frame, self.draw_calls, self.instances, self.triangles,
+                self.uploads, self.load_time * 1000., self.draw_time * 1000.)
+    }
+}
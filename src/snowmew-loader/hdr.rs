@@ -0,0 +1,140 @@
+//   Copyright 2014 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+//! A reader for Radiance `.hdr` (RGBE) images
+
+use std::old_io::{File, Reader, IoResult, IoError, InvalidInput};
+use graphics::Texture;
+
+fn invalid(desc: &'static str) -> IoError {
+    IoError {
+        kind: InvalidInput,
+        desc: desc,
+        detail: None
+    }
+}
+
+/// split off the next newline terminated line
+fn line<'a>(data: &'a [u8]) -> IoResult<(&'a [u8], &'a [u8])> {
+    match data.iter().position(|&c| c == b'\n') {
+        Some(i) => Ok((&data[..i], &data[i+1..])),
+        None => Err(invalid("unexpected end of header"))
+    }
+}
+
+/// parse the resolution line, only the common `-Y h +X w`
+/// (top to bottom, left to right) orientation is supported
+fn resolution(text: &[u8]) -> IoResult<(u32, u32)> {
+    let text = match String::from_utf8(text.to_vec()) {
+        Ok(t) => t,
+        Err(_) => return Err(invalid("resolution is not text"))
+    };
+    let parts: Vec<&str> = text.split(' ').filter(|s| !s.is_empty()).collect();
+    if parts.len() != 4 || parts[0] != "-Y" || parts[2] != "+X" {
+        return Err(invalid("unsupported image orientation"));
+    }
+    match (parts[1].parse(), parts[3].parse()) {
+        (Ok(h), Ok(w)) => Ok((w, h)),
+        _ => Err(invalid("invalid image resolution"))
+    }
+}
+
+/// read one run length encoded scanline, the four channels are
+/// stored one after another
+fn rle_scanline<'a>(width: usize, mut data: &'a [u8], out: &mut Vec<u8>)
+    -> IoResult<&'a [u8]> {
+
+    let start = out.len();
+    out.extend(::std::iter::repeat(0u8).take(width * 4));
+    for channel in (0..4) {
+        let mut x = 0;
+        while x < width {
+            if data.len() < 2 {
+                return Err(invalid("truncated scanline"));
+            }
+            let count = data[0] as usize;
+            if count > 128 {
+                let count = count - 128;
+                if x + count > width {
+                    return Err(invalid("run overflows scanline"));
+                }
+                for i in (x..x+count) {
+                    out[start + i * 4 + channel] = data[1];
+                }
+                data = &data[2..];
+                x += count;
+            } else {
+                if count == 0 || x + count > width || data.len() < count + 1 {
+                    return Err(invalid("invalid literal run"));
+                }
+                for i in (0..count) {
+                    out[start + (x + i) * 4 + channel] = data[1 + i];
+                }
+                data = &data[count+1..];
+                x += count;
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// Decode the contents of a Radiance `.hdr` file into a high dynamic
+/// range texture. Both run length encoded and flat scanlines are read.
+pub fn decode(data: &[u8]) -> IoResult<Texture> {
+    let (magic, mut data) = try!(line(data));
+    if !magic.starts_with(b"#?") {
+        return Err(invalid("not a radiance image"));
+    }
+
+    loop {
+        let (l, rest) = try!(line(data));
+        data = rest;
+        if l.is_empty() {
+            break;
+        }
+        if l.starts_with(b"FORMAT=") && l != &b"FORMAT=32-bit_rle_rgbe"[..] {
+            return Err(invalid("unsupported pixel format"));
+        }
+    }
+
+    let (res, mut data) = try!(line(data));
+    let (width, height) = try!(resolution(res));
+    let w = width as usize;
+
+    let mut out = Vec::with_capacity(w * height as usize * 4);
+    for _ in (0..height) {
+        let rle = w >= 8 && w < 0x8000 && data.len() >= 4 &&
+                  data[0] == 2 && data[1] == 2 &&
+                  ((data[2] as usize) << 8 | data[3] as usize) == w;
+        if rle {
+            data = try!(rle_scanline(w, &data[4..], &mut out));
+        } else {
+            if data.len() < w * 4 {
+                return Err(invalid("truncated scanline"));
+            }
+            out.push_all(&data[..w*4]);
+            data = &data[w*4..];
+        }
+    }
+
+    Ok(Texture::new_rgbe(width, height, out))
+}
+
+/// Load a Radiance `.hdr` file
+pub fn load_hdr(path: &Path) -> IoResult<Texture> {
+    let mut file = try!(File::open(path));
+    let data = try!(file.read_to_end());
+    decode(&data)
+}
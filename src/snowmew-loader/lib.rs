@@ -42,6 +42,10 @@ use genmesh::{
 };
 
 mod texture;
+pub mod hdr;
+
+pub use texture::load_texture;
+pub use hdr::load_hdr;
 
 pub struct Obj {
     path: Path,
@@ -118,6 +122,9 @@ impl Obj {
         if m.ke.is_some() { mat.set_ke(*m.ke.as_ref().unwrap()); }
             if m.ni.is_some() { mat.set_ni(*m.ni.as_ref().unwrap()); }
             if m.ns.is_some() { mat.set_ns(*m.ns.as_ref().unwrap()); }
+            if m.d.is_some() { mat.set_d(*m.d.as_ref().unwrap()); }
+            if m.tr.is_some() { mat.set_tr(*m.tr.as_ref().unwrap()); }
+            if mat.opacity() < 1. { mat.set_blend(graphics::BlendMode::Alpha); }
             if m.map_ka.is_some() { mat.set_map_ka(lookup(m.map_ka.as_ref().unwrap())); }
             if m.map_kd.is_some() { mat.set_map_kd(lookup(m.map_kd.as_ref().unwrap())); }
             if m.map_ks.is_some() { mat.set_map_ks(lookup(m.map_ks.as_ref().unwrap())); }
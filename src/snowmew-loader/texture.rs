@@ -14,8 +14,14 @@
 
 use image::{self, GenericImage};
 use graphics::Texture;
+use hdr;
 
+/// Load an image as a texture, Radiance `.hdr` files are
+/// read as high dynamic range textures
 pub fn load_texture(path: &Path) -> Texture {
+    if path.extension_str() == Some("hdr") {
+        return hdr::load_hdr(path).ok().expect("Failed to load hdr image.");
+    }
     let img = image::open(path).ok().expect("Failed to load image.")
                                .to_rgba();
     let (w, h) = img.dimensions();
@@ -0,0 +1,205 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![crate_name = "snowmew-nav"]
+#![crate_type = "lib"]
+#![feature(core, collections, std_misc)]
+
+extern crate cgmath;
+extern crate "rustc-serialize" as rustc_serialize;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+
+use cgmath::{Vector, Vector3, Vector4, Matrix, EuclideanVector};
+
+use snowmew::common::{Common, Entity, Duplicate, Delete};
+use snowmew::table::{Static, StaticIterator};
+use position::Positions;
+use graphics::Graphics;
+use graphics::geometry::Primative;
+
+pub use navmesh::{NavMesh, Triangle, string_pull};
+
+/// contains the navmesh and path searches
+pub mod navmesh;
+
+/// Moves an entity along a path found on a navmesh
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct PathFollower {
+    /// the points to walk through
+    pub path: Vec<Vector3<f32>>,
+    /// the index of the point being walked to
+    pub next: usize,
+    /// units per second
+    pub speed: f32
+}
+
+impl PathFollower {
+    pub fn new(path: Vec<Vector3<f32>>, speed: f32) -> PathFollower {
+        PathFollower {
+            path: path,
+            next: 0,
+            speed: speed
+        }
+    }
+
+    /// true if the end of the path has been reached
+    pub fn finished(&self) -> bool {
+        self.next >= self.path.len()
+    }
+}
+
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct NavData {
+    meshes:    Static<NavMesh>,
+    followers: Static<PathFollower>
+}
+
+impl NavData {
+    pub fn new() -> NavData {
+        NavData {
+            meshes: Static::new(),
+            followers: Static::new()
+        }
+    }
+}
+
+/// Collect the triangles of the drawables in world space. Geometry
+/// that is not made of triangles is skipped.
+pub fn world_triangles<G: Graphics + Positions>(gd: &G, drawables: &[Entity]) -> Vec<[Vector3<f32>; 3]> {
+    let mut out = Vec::new();
+    for &oid in drawables.iter() {
+        let draw = match gd.get_draw(oid) {
+            Some(d) => d,
+            None => continue
+        };
+        match gd.geometry(draw.geometry).map(|g| g.prim) {
+            Some(Primative::Triangle) => (),
+            _ => continue
+        }
+
+        let mat = gd.position(oid);
+        let points: Vec<Vector3<f32>> = match gd.geometry_vertex_iter(draw.geometry) {
+            Some(iter) => iter.map(|(_, p, _, _)| {
+                mat.mul_v(&Vector4::new(p[0], p[1], p[2], 1.)).truncate()
+            }).collect(),
+            None => continue
+        };
+
+        for t in points.chunks(3) {
+            if t.len() == 3 {
+                out.push([t[0], t[1], t[2]]);
+            }
+        }
+    }
+    out
+}
+
+pub trait Navigation: Common + Positions + Sized {
+    fn get_nav<'a>(&'a self) -> &'a NavData;
+    fn get_nav_mut<'a>(&'a mut self) -> &'a mut NavData;
+
+    fn new_navmesh(&mut self, mesh: NavMesh) -> Entity {
+        let oid = self.new_object(None);
+        self.get_nav_mut().meshes.insert(oid, mesh);
+        oid
+    }
+
+    /// Build a navmesh from the walkable triangles of the drawables,
+    /// this works on any scene such as one imported by the loader.
+    fn build_navmesh(&mut self, drawables: &[Entity], max_slope: f32, weld: f32) -> Entity
+        where Self: Graphics {
+        let triangles = world_triangles(self, drawables);
+        self.new_navmesh(NavMesh::new(&triangles, max_slope, weld))
+    }
+
+    fn navmesh<'a>(&'a self, oid: Entity) -> Option<&'a NavMesh> {
+        self.get_nav().meshes.get(oid)
+    }
+
+    /// Find a path for the entity from its current position to `goal`.
+    /// If a path is found the entity will walk it when
+    /// `follow_paths` is called.
+    fn set_destination(&mut self, oid: Entity, mesh: Entity, goal: Vector3<f32>, speed: f32) -> bool {
+        let start = self.position(oid).mul_v(&Vector4::new(0., 0., 0., 1.)).truncate();
+        let path = match self.navmesh(mesh).and_then(|m| m.find_path(&start, &goal)) {
+            Some(p) => p,
+            None => return false
+        };
+        self.get_nav_mut().followers.insert(oid, PathFollower::new(path, speed));
+        true
+    }
+
+    fn follower<'a>(&'a self, oid: Entity) -> Option<&'a PathFollower> {
+        self.get_nav().followers.get(oid)
+    }
+
+    fn follower_iter<'a>(&'a self) -> StaticIterator<'a, PathFollower> {
+        self.get_nav().followers.iter()
+    }
+
+    fn stop_following(&mut self, oid: Entity) -> bool {
+        self.get_nav_mut().followers.remove(oid)
+    }
+
+    /// Move every entity with a path `dt` seconds along it. The path is
+    /// in world space, so this expects followers to not have a parent.
+    fn follow_paths(&mut self, dt: f32) {
+        let followers: Vec<Entity> = self.follower_iter()
+            .filter(|&(_, f)| !f.finished())
+            .map(|(oid, _)| oid)
+            .collect();
+
+        for &oid in followers.iter() {
+            let mut f = self.follower(oid).unwrap().clone();
+            let mut pos = self.get_displacement(oid).map(|&d| d)
+                              .unwrap_or(Vector3::new(0., 0., 0.));
+            let mut budget = f.speed * dt;
+
+            while budget > 0. && !f.finished() {
+                let to = f.path[f.next].sub_v(&pos);
+                let distance = to.length();
+                if distance <= budget {
+                    pos = f.path[f.next];
+                    budget -= distance;
+                    f.next += 1;
+                } else {
+                    pos = pos.add_v(&to.mul_s(budget / distance));
+                    budget = 0.;
+                }
+            }
+
+            self.set_displacement(oid, pos);
+            self.get_nav_mut().followers.insert(oid, f);
+        }
+    }
+}
+
+impl Duplicate for NavData {
+    fn duplicate(&mut self, src: Entity, dst: Entity) {
+        let x = self.meshes.get(src).map(|x| x.clone());
+        x.map(|x| self.meshes.insert(dst, x));
+        let x = self.followers.get(src).map(|x| x.clone());
+        x.map(|x| self.followers.insert(dst, x));
+    }
+}
+
+impl Delete for NavData {
+    fn delete(&mut self, oid: Entity) -> bool {
+        self.meshes.remove(oid) |
+        self.followers.remove(oid)
+    }
+}
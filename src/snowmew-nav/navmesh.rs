@@ -0,0 +1,334 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::num::Float;
+
+use cgmath::{Vector, Vector3, EuclideanVector};
+
+/// A walkable triangle of the navmesh
+#[derive(Clone, Copy, RustcEncodable, RustcDecodable)]
+pub struct Triangle {
+    /// index of the three corners in `NavMesh::vertices`
+    pub vertices: [u32; 3],
+    /// the triangle on the other side of each edge, edge `n` is
+    /// between vertex `n` and vertex `n+1`
+    pub neighbours: [Option<u32>; 3],
+    pub center: Vector3<f32>
+}
+
+/// A navigation mesh, this is a set of connected walkable triangles
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct NavMesh {
+    vertices: Vec<Vector3<f32>>,
+    triangles: Vec<Triangle>
+}
+
+/// 2x the signed area of the triangle on the XZ plane, positive if
+/// `b` is counter-clockwise from `a` when seen from `o`.
+fn cross(o: &Vector3<f32>, a: &Vector3<f32>, b: &Vector3<f32>) -> f32 {
+    (a.x - o.x) * (b.z - o.z) - (a.z - o.z) * (b.x - o.x)
+}
+
+fn same(a: &Vector3<f32>, b: &Vector3<f32>) -> bool {
+    a.sub_v(b).length2() < 1e-12
+}
+
+impl NavMesh {
+    /// Create a navmesh from a list of triangles in world space.
+    ///
+    /// Only triangles with a slope less then `max_slope` (in radians)
+    /// are kept, triangles are expected to be wound counter-clockwise.
+    /// Vertices closer then `weld` are merged so that triangles from
+    /// different pieces of geometry are connected.
+    pub fn new(triangles: &[[Vector3<f32>; 3]], max_slope: f32, weld: f32) -> NavMesh {
+        let min_up = max_slope.cos();
+        let mut vertices = Vec::new();
+        let mut lookup: HashMap<(i64, i64, i64), u32> = HashMap::new();
+        let mut tris = Vec::new();
+
+        for t in triangles.iter() {
+            let normal = t[1].sub_v(&t[0]).cross(&t[2].sub_v(&t[0]));
+            if normal.length() == 0. || normal.normalize().y < min_up {
+                continue;
+            }
+
+            let mut idx = [0u32; 3];
+            for (i, v) in t.iter().enumerate() {
+                let key = ((v.x / weld).round() as i64,
+                           (v.y / weld).round() as i64,
+                           (v.z / weld).round() as i64);
+                idx[i] = *lookup.entry(key).get().unwrap_or_else(|e| {
+                    vertices.push(*v);
+                    e.insert((vertices.len() - 1) as u32)
+                });
+            }
+
+            // welding can collapse small triangles
+            if idx[0] == idx[1] || idx[1] == idx[2] || idx[2] == idx[0] {
+                continue;
+            }
+
+            let center = vertices[idx[0] as usize]
+                .add_v(&vertices[idx[1] as usize])
+                .add_v(&vertices[idx[2] as usize])
+                .div_s(3.);
+
+            tris.push(Triangle {
+                vertices: idx,
+                neighbours: [None, None, None],
+                center: center
+            });
+        }
+
+        // connect triangles that share an edge
+        let mut edges: HashMap<(u32, u32), Vec<(u32, usize)>> = HashMap::new();
+        for (i, t) in tris.iter().enumerate() {
+            for e in (0..3) {
+                let (a, b) = (t.vertices[e], t.vertices[(e+1) % 3]);
+                let key = if a < b { (a, b) } else { (b, a) };
+                edges.entry(key).get().unwrap_or_else(|v| v.insert(Vec::new()))
+                     .push((i as u32, e));
+            }
+        }
+
+        for (_, shared) in edges.iter() {
+            if shared.len() != 2 {
+                continue;
+            }
+            let (a, ea) = shared[0];
+            let (b, eb) = shared[1];
+            tris[a as usize].neighbours[ea] = Some(b);
+            tris[b as usize].neighbours[eb] = Some(a);
+        }
+
+        NavMesh {
+            vertices: vertices,
+            triangles: tris
+        }
+    }
+
+    pub fn vertices<'a>(&'a self) -> &'a [Vector3<f32>] { &self.vertices }
+    pub fn triangles<'a>(&'a self) -> &'a [Triangle] { &self.triangles }
+
+    fn corners(&self, tri: usize) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+        let t = &self.triangles[tri];
+        (self.vertices[t.vertices[0] as usize],
+         self.vertices[t.vertices[1] as usize],
+         self.vertices[t.vertices[2] as usize])
+    }
+
+    /// The height of the triangle at the point if the point is
+    /// inside of the triangle when seen from above.
+    fn height_at(&self, tri: usize, point: &Vector3<f32>) -> Option<f32> {
+        let (a, b, c) = self.corners(tri);
+        let area = cross(&a, &b, &c);
+        if area == 0. {
+            return None;
+        }
+        let u = cross(&b, &c, point) / area;
+        let v = cross(&c, &a, point) / area;
+        let w = cross(&a, &b, point) / area;
+        let eps = -1e-5;
+        if u < eps || v < eps || w < eps {
+            return None;
+        }
+        Some(a.y * u + b.y * v + c.y * w)
+    }
+
+    /// Find the triangle that the point is standing on. If there
+    /// are multiple floors the triangle closest in height is used.
+    pub fn find_triangle(&self, point: &Vector3<f32>) -> Option<usize> {
+        let mut best: Option<(f32, usize)> = None;
+        for i in (0..self.triangles.len()) {
+            if let Some(y) = self.height_at(i, point) {
+                let d = (y - point.y).abs();
+                if best.map(|(bd, _)| d < bd).unwrap_or(true) {
+                    best = Some((d, i));
+                }
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+
+    /// Project a point down (or up) on to the navmesh
+    pub fn project(&self, point: &Vector3<f32>) -> Option<Vector3<f32>> {
+        self.find_triangle(point).and_then(|t| {
+            self.height_at(t, point).map(|y| Vector3::new(point.x, y, point.z))
+        })
+    }
+
+    /// Search for the list of triangles that connect `start` to `goal`
+    /// using A* over the triangle centers.
+    pub fn find_corridor(&self, start: usize, goal: usize) -> Option<Vec<usize>> {
+        let count = self.triangles.len();
+        let mut cost: Vec<f32> = vec![Float::infinity(); count];
+        let mut from: Vec<Option<usize>> = vec![None; count];
+        let mut open = BinaryHeap::new();
+        let goal_center = self.triangles[goal].center;
+
+        cost[start] = 0.;
+        open.push(Open { estimate: 0., tri: start });
+
+        while let Some(Open { tri, .. }) = open.pop() {
+            if tri == goal {
+                let mut corridor = vec![goal];
+                let mut at = goal;
+                while let Some(prev) = from[at] {
+                    corridor.push(prev);
+                    at = prev;
+                }
+                corridor.reverse();
+                return Some(corridor);
+            }
+
+            let t = &self.triangles[tri];
+            for n in t.neighbours.iter() {
+                let n = match *n {
+                    Some(n) => n as usize,
+                    None => continue
+                };
+                let c = cost[tri] + self.triangles[n].center.sub_v(&t.center).length();
+                if c < cost[n] {
+                    cost[n] = c;
+                    from[n] = Some(tri);
+                    let h = self.triangles[n].center.sub_v(&goal_center).length();
+                    open.push(Open { estimate: c + h, tri: n });
+                }
+            }
+        }
+        None
+    }
+
+    /// The shared edge between two neighbouring triangles as a
+    /// (left, right) pair when walking from `a` to `b`.
+    fn portal(&self, a: usize, b: usize) -> (Vector3<f32>, Vector3<f32>) {
+        let t = &self.triangles[a];
+        for e in (0..3) {
+            if t.neighbours[e] == Some(b as u32) {
+                let p = self.vertices[t.vertices[e] as usize];
+                let q = self.vertices[t.vertices[(e+1) % 3] as usize];
+                return if cross(&t.center, &p, &q) > 0. { (q, p) } else { (p, q) };
+            }
+        }
+        panic!("triangles {} and {} are not neighbours", a, b);
+    }
+
+    /// Find a path from `start` to `goal`. The path is the shortest
+    /// line through the triangles found by A*, it includes both the
+    /// start and the goal.
+    pub fn find_path(&self, start: &Vector3<f32>, goal: &Vector3<f32>) -> Option<Vec<Vector3<f32>>> {
+        let (start_tri, goal_tri) = match (self.find_triangle(start), self.find_triangle(goal)) {
+            (Some(s), Some(g)) => (s, g),
+            _ => return None
+        };
+        let start = self.project(start).unwrap();
+        let goal = self.project(goal).unwrap();
+
+        self.find_corridor(start_tri, goal_tri).map(|corridor| {
+            let mut portals = vec![(start, start)];
+            for w in corridor.windows(2) {
+                portals.push(self.portal(w[0], w[1]));
+            }
+            portals.push((goal, goal));
+            string_pull(&portals)
+        })
+    }
+}
+
+struct Open {
+    estimate: f32,
+    tri: usize
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Open) -> bool { self.estimate == other.estimate }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    // reversed so that the BinaryHeap pops the lowest estimate first
+    fn cmp(&self, other: &Open) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The simple stupid funnel algorithm, this pulls a path tight
+/// through a list of (left, right) portals. The first and last
+/// portals are the start and the goal.
+pub fn string_pull(portals: &[(Vector3<f32>, Vector3<f32>)]) -> Vec<Vector3<f32>> {
+    let mut path = Vec::new();
+    if portals.len() == 0 {
+        return path;
+    }
+
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut apex_i, mut left_i, mut right_i) = (0, 0, 0);
+    path.push(apex);
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (l, r) = portals[i];
+
+        // try to narrow the right side of the funnel
+        if cross(&apex, &right, &r) >= 0. {
+            if same(&apex, &right) || cross(&apex, &left, &r) < 0. {
+                right = r;
+                right_i = i;
+            } else {
+                // right crossed over left, left becomes a corner
+                apex = left;
+                apex_i = left_i;
+                path.push(apex);
+                right = apex;
+                right_i = apex_i;
+                i = apex_i + 1;
+                continue;
+            }
+        }
+
+        // try to narrow the left side of the funnel
+        if cross(&apex, &left, &l) <= 0. {
+            if same(&apex, &left) || cross(&apex, &right, &l) > 0. {
+                left = l;
+                left_i = i;
+            } else {
+                // left crossed over right, right becomes a corner
+                apex = right;
+                apex_i = right_i;
+                path.push(apex);
+                left = apex;
+                left_i = apex_i;
+                i = apex_i + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    let goal = portals[portals.len()-1].0;
+    if !same(&path[path.len()-1], &goal) {
+        path.push(goal);
+    }
+    path
+}
@@ -0,0 +1,236 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![crate_name = "snowmew-particle"]
+#![crate_type = "lib"]
+#![feature(core, collections)]
+
+extern crate cgmath;
+extern crate rand;
+extern crate "rustc-serialize" as rustc_serialize;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-random" as random;
+extern crate "snowmew-graphics" as graphics;
+
+use cgmath::Vector3;
+
+use snowmew::common::{Common, Entity, Duplicate, Delete};
+use snowmew::game::Game;
+use snowmew::table::{Static, StaticIterator};
+use position::Positions;
+use random::Random;
+use graphics::{Graphics, BillboardSet};
+
+pub use graphics::Billboard;
+
+pub use simulate::{simulate, billboards, cone};
+
+/// contains the particle simulation
+pub mod simulate;
+
+/// Describes how an emitter creates and moves its particles
+#[derive(Clone, Copy, RustcEncodable, RustcDecodable)]
+pub struct Emitter {
+    /// particles emitted per second
+    pub rate: f32,
+    /// the (min, max) time in seconds a particle lives for
+    pub lifetime: (f32, f32),
+    /// the (min, max) starting speed of a particle
+    pub speed: (f32, f32),
+    /// the axis of the cone particles are emitted in, this is
+    /// relative to the emitter's rotation
+    pub direction: Vector3<f32>,
+    /// the half angle of the cone in radians
+    pub spread: f32,
+    /// acceleration applied to every particle
+    pub gravity: Vector3<f32>,
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub start_size: f32,
+    pub end_size: f32,
+    /// the emitter will not create particles past this count
+    pub max_particles: usize,
+    pub emitting: bool
+}
+
+impl Emitter {
+    pub fn new() -> Emitter {
+        Emitter {
+            rate: 10.,
+            lifetime: (1., 1.),
+            speed: (1., 1.),
+            direction: Vector3::new(0., 1., 0.),
+            spread: 0.,
+            gravity: Vector3::new(0., 0., 0.),
+            start_color: [1., 1., 1., 1.],
+            end_color: [1., 1., 1., 0.],
+            start_size: 0.1,
+            end_size: 0.1,
+            max_particles: 1000,
+            emitting: true
+        }
+    }
+}
+
+#[derive(Clone, Copy, RustcEncodable, RustcDecodable)]
+pub struct Particle {
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub age: f32,
+    pub lifetime: f32
+}
+
+/// The live particles of an emitter
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct ParticleSystem {
+    pub particles: Vec<Particle>,
+    /// fractional particles that are waiting to be emitted
+    pub accumulator: f32
+}
+
+impl ParticleSystem {
+    pub fn new() -> ParticleSystem {
+        ParticleSystem {
+            particles: Vec::new(),
+            accumulator: 0.
+        }
+    }
+}
+
+/// Hand the particles of the emitter `oid` to the renders. Each
+/// particle is drawn as an instance of `geometry` with `material`,
+/// tinted by its colour and scaled by its size. This has to be called
+/// after each step for the billboards to follow the particles.
+pub fn update_billboards<GD: Particles + Graphics>(gd: &mut GD,
+                                                   oid: Entity,
+                                                   geometry: Entity,
+                                                   material: Entity) {
+    let billboards = gd.billboards(oid);
+    gd.set_billboard_set(oid, BillboardSet::new(geometry, material, billboards));
+}
+
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct ParticleData {
+    emitters: Static<Emitter>,
+    systems:  Static<ParticleSystem>
+}
+
+impl ParticleData {
+    pub fn new() -> ParticleData {
+        ParticleData {
+            emitters: Static::new(),
+            systems: Static::new()
+        }
+    }
+}
+
+pub trait Particles: Common + Positions + Random + Sized {
+    fn get_particles<'a>(&'a self) -> &'a ParticleData;
+    fn get_particles_mut<'a>(&'a mut self) -> &'a mut ParticleData;
+
+    /// attach an emitter to an existing entity
+    fn set_emitter(&mut self, oid: Entity, emitter: Emitter) {
+        self.get_particles_mut().emitters.insert(oid, emitter);
+        if self.get_particles().systems.get(oid).is_none() {
+            self.get_particles_mut().systems.insert(oid, ParticleSystem::new());
+        }
+    }
+
+    fn emitter<'a>(&'a self, oid: Entity) -> Option<&'a Emitter> {
+        self.get_particles().emitters.get(oid)
+    }
+
+    fn emitter_mut<'a>(&'a mut self, oid: Entity) -> Option<&'a mut Emitter> {
+        self.get_particles_mut().emitters.get_mut(oid)
+    }
+
+    fn emitter_iter<'a>(&'a self) -> StaticIterator<'a, Emitter> {
+        self.get_particles().emitters.iter()
+    }
+
+    fn particle_system<'a>(&'a self, oid: Entity) -> Option<&'a ParticleSystem> {
+        self.get_particles().systems.get(oid)
+    }
+
+    /// get the billboards needed to draw the emitter's particles
+    fn billboards(&self, oid: Entity) -> Vec<Billboard> {
+        match (self.emitter(oid), self.particle_system(oid)) {
+            (Some(e), Some(s)) => billboards(e, s),
+            _ => Vec::new()
+        }
+    }
+
+    /// Advance every emitter by `dt` seconds. Emitters are visited in
+    /// entity order so the result is deterministic.
+    fn step_particles(&mut self, dt: f32) {
+        let emitters: Vec<(Entity, Emitter)> = self.emitter_iter()
+            .map(|(oid, e)| (oid, *e))
+            .collect();
+
+        let mut rng = *self.rng();
+        for &(oid, ref emitter) in emitters.iter() {
+            let origin = self.position(oid);
+            let mut system = self.particle_system(oid).map(|s| s.clone())
+                                 .unwrap_or_else(|| ParticleSystem::new());
+            simulate(emitter, &mut system, &origin, dt, &mut rng);
+            self.get_particles_mut().systems.insert(oid, system);
+        }
+        *self.rng() = rng;
+    }
+}
+
+impl Duplicate for ParticleData {
+    fn duplicate(&mut self, src: Entity, dst: Entity) {
+        let x = self.emitters.get(src).map(|x| x.clone());
+        x.map(|x| self.emitters.insert(dst, x));
+        // the copy starts without any live particles
+        if self.emitters.get(dst).is_some() {
+            self.systems.insert(dst, ParticleSystem::new());
+        }
+    }
+}
+
+impl Delete for ParticleData {
+    fn delete(&mut self, oid: Entity) -> bool {
+        self.emitters.remove(oid) |
+        self.systems.remove(oid)
+    }
+}
+
+/// Wraps a game and steps all the particle systems after each step
+/// of the wrapped game. This expects to be stepped once per cadence.
+pub struct ParticleSimulator<G> {
+    game: G,
+    cadance: f32
+}
+
+impl<G> ParticleSimulator<G> {
+    /// `cadance` is the time in seconds that passes every step
+    pub fn new(game: G, cadance: f32) -> ParticleSimulator<G> {
+        ParticleSimulator {
+            game: game,
+            cadance: cadance
+        }
+    }
+}
+
+impl<E, GD: Particles, G: Game<GD, E>> Game<GD, E> for ParticleSimulator<G> {
+    fn step(&mut self, event: E, gd: GD) -> GD {
+        let mut gd = self.game.step(event, gd);
+        gd.step_particles(self.cadance);
+        gd
+    }
+}
@@ -0,0 +1,120 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::f32::consts::PI;
+use std::num::Float;
+
+use cgmath::{Vector, Vector3, Vector4, Matrix, Matrix4, EuclideanVector};
+use rand::Rng;
+
+use {Emitter, Particle, ParticleSystem, Billboard};
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn range<R: Rng>(rng: &mut R, (min, max): (f32, f32)) -> f32 {
+    lerp(min, max, rng.next_f32())
+}
+
+/// Pick a random unit vector inside of a cone around `axis`,
+/// `spread` is the half angle of the cone in radians.
+pub fn cone<R: Rng>(rng: &mut R, axis: &Vector3<f32>, spread: f32) -> Vector3<f32> {
+    let axis = if axis.length() == 0. {
+        Vector3::new(0., 1., 0.)
+    } else {
+        axis.normalize()
+    };
+
+    let cos_theta = lerp(spread.cos(), 1., rng.next_f32());
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let phi = rng.next_f32() * 2. * PI;
+
+    // build a basis around the axis
+    let other = if axis.x.abs() < 0.9 {
+        Vector3::new(1., 0., 0.)
+    } else {
+        Vector3::new(0., 1., 0.)
+    };
+    let u = axis.cross(&other).normalize();
+    let v = axis.cross(&u);
+
+    axis.mul_s(cos_theta)
+        .add_v(&u.mul_s(sin_theta * phi.cos()))
+        .add_v(&v.mul_s(sin_theta * phi.sin()))
+}
+
+/// Advance a particle system by `dt` seconds. New particles are emitted
+/// from `origin` (the world matrix of the emitter) and all particles are
+/// simulated in world space, so moving the emitter leaves a trail.
+pub fn simulate<R: Rng>(emitter: &Emitter,
+                        system: &mut ParticleSystem,
+                        origin: &Matrix4<f32>,
+                        dt: f32,
+                        rng: &mut R) {
+    // age and move the existing particles
+    for p in system.particles.iter_mut() {
+        p.age += dt;
+        p.velocity = p.velocity.add_v(&emitter.gravity.mul_s(dt));
+        p.position = p.position.add_v(&p.velocity.mul_s(dt));
+    }
+    system.particles.retain(|p| p.age < p.lifetime);
+
+    if !emitter.emitting {
+        system.accumulator = 0.;
+        return;
+    }
+
+    system.accumulator += emitter.rate * dt;
+    let position = origin.mul_v(&Vector4::new(0., 0., 0., 1.)).truncate();
+    let d = &emitter.direction;
+    let direction = origin.mul_v(&Vector4::new(d.x, d.y, d.z, 0.)).truncate();
+
+    while system.accumulator >= 1. {
+        system.accumulator -= 1.;
+        if system.particles.len() >= emitter.max_particles {
+            continue;
+        }
+
+        let velocity = cone(rng, &direction, emitter.spread).mul_s(range(rng, emitter.speed));
+        let lifetime = range(rng, emitter.lifetime);
+
+        // particles emitted part way through the step have already
+        // lived for a fraction of it
+        let age = system.accumulator / emitter.rate;
+        system.particles.push(Particle {
+            position: position.add_v(&velocity.mul_s(age)),
+            velocity: velocity,
+            age: age,
+            lifetime: lifetime
+        });
+    }
+}
+
+/// Create the billboards to draw a particle system, the color and
+/// size are interpolated over the life of each particle.
+pub fn billboards(emitter: &Emitter, system: &ParticleSystem) -> Vec<Billboard> {
+    system.particles.iter().map(|p| {
+        let t = if p.lifetime > 0. { (p.age / p.lifetime).min(1.) } else { 1. };
+        let mut color = [0f32; 4];
+        for i in (0..4) {
+            color[i] = lerp(emitter.start_color[i], emitter.end_color[i], t);
+        }
+        Billboard {
+            position: [p.position.x, p.position.y, p.position.z],
+            color: color,
+            size: lerp(emitter.start_size, emitter.end_size, t)
+        }
+    }).collect()
+}
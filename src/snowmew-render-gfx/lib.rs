@@ -32,36 +32,41 @@ extern crate "gfx_device_gl" as device;
 extern crate genmesh;
 extern crate cgmath;
 extern crate draw_state;
+extern crate gl;
+extern crate time;
 
 extern crate "snowmew-core" as snowmew;
 extern crate "snowmew-position" as position;
 extern crate "snowmew-graphics" as graphics;
 extern crate "snowmew-render" as sm_render;
 extern crate "snowmew-input" as input;
-extern crate collect;
 
-use std::collections::{HashMap, BTreeSet};
+use std::collections::{HashMap, HashSet};
+use std::cmp::min;
+use std::iter::repeat;
 use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::{Arc, Mutex};
 use std::thread::spawn;
 
-#[cfg(feature="use_opencl")]
-use std::sync::Arc;
-
 #[cfg(feature="use_opencl")]
 use opencl::hl;
 use gfx::{Device, DeviceExt};
 use gfx::batch::RefBatch;
 use cgmath::*;
-use collect::iter::{OrderedMapIterator, OrderedSetIterator};
 
 use position::Positions;
 use graphics::Graphics;
-use snowmew::common::Entity;
+use snowmew::common::{Entity, Scene};
+use graphics::{Material, PbrMaterial, Texture, BlendMode};
+use graphics::geometry::Primative;
+use sm_render::{Renderable, RenderTarget, RenderView, Capture, Lights, MAX_LIGHTS};
+use sm_render::{ShadowPlan, Culler, RenderQueues, BatchPlan, Program, MAX_SHADOW_MAPS};
+use sm_render::billboard_matrix;
+use sm_render::{PostEffect, ToneMap, QuadTexture};
+use sm_render::{Change, ResourceTracker, ResourceStats, RenderStats};
+use sm_render::{Environment, Sky, Fog, FogMode};
 use sm_render::camera::Camera;
-use graphics::Material;
-use graphics::geometry::{VertexGeoTex, VertexGeoTexNorm};
-use graphics::geometry::Vertex::{Geo, GeoTex, GeoNorm, GeoTexNorm, GeoTexNormTan};
-use sm_render::Renderable;
+use sm_render::environment::bake_equirectangular;
 use input::{Window, GetIoState};
 use gfx::render;
 
@@ -71,157 +76,662 @@ struct SharedMatrix {
     view_mat: [[f32; 4]; 4]
 }
 
+/// A drawable's model matrix, material index and tint, read once per instance
+/// by `VERTEX_SRC` and `SHADOW_VERTEX_SRC`
+#[derive(Copy)]
+#[vertex_format]
+struct InstanceVertex {
+    model0: [f32; 4],
+    model1: [f32; 4],
+    model2: [f32; 4],
+    model3: [f32; 4],
+    /// the row of the material in the `MaterialTable`
+    material: f32,
+    /// multiplies the material's colour, white unless it is a billboard
+    tint: [f32; 4]
+}
+
+/// The lights uniform block, this matches the std140 layout of the
+/// `lights` block in `LIGHTING_SRC`.
+#[derive(Copy, Clone)]
+struct SharedLights {
+    directional_normal: [[f32; 4]; MAX_LIGHTS],
+    directional_color: [[f32; 4]; MAX_LIGHTS],
+
+    point_position: [[f32; 4]; MAX_LIGHTS],
+    point_color: [[f32; 4]; MAX_LIGHTS],
+    point_attenuation: [[f32; 4]; MAX_LIGHTS],
+
+    spot_position: [[f32; 4]; MAX_LIGHTS],
+    spot_direction: [[f32; 4]; MAX_LIGHTS],
+    spot_color: [[f32; 4]; MAX_LIGHTS],
+    spot_attenuation: [[f32; 4]; MAX_LIGHTS],
+    spot_cone: [[f32; 4]; MAX_LIGHTS],
+
+    directional_shadow: [[i32; 4]; MAX_LIGHTS],
+    spot_shadow: [[i32; 4]; MAX_LIGHTS],
+
+    light_count: [i32; 4]
+}
+
+impl SharedLights {
+    fn new(lights: &Lights, plan: &ShadowPlan) -> SharedLights {
+        let mut shared = SharedLights {
+            directional_normal: [[0.; 4]; MAX_LIGHTS],
+            directional_color: [[0.; 4]; MAX_LIGHTS],
+            point_position: [[0.; 4]; MAX_LIGHTS],
+            point_color: [[0.; 4]; MAX_LIGHTS],
+            point_attenuation: [[0.; 4]; MAX_LIGHTS],
+            spot_position: [[0.; 4]; MAX_LIGHTS],
+            spot_direction: [[0.; 4]; MAX_LIGHTS],
+            spot_color: [[0.; 4]; MAX_LIGHTS],
+            spot_attenuation: [[0.; 4]; MAX_LIGHTS],
+            spot_cone: [[0.; 4]; MAX_LIGHTS],
+            directional_shadow: [[0; 4]; MAX_LIGHTS],
+            spot_shadow: [[0; 4]; MAX_LIGHTS],
+            light_count: [
+                lights.directional.len() as i32,
+                lights.point.len() as i32,
+                lights.spot.len() as i32,
+                0
+            ]
+        };
+
+        for (i, d) in lights.directional.iter().enumerate() {
+            shared.directional_normal[i] = [d.normal.x, d.normal.y, d.normal.z, 0.];
+            shared.directional_color[i] = [d.color.x, d.color.y, d.color.z, 1.];
+        }
+        for (i, p) in lights.point.iter().enumerate() {
+            let a = p.attenuation;
+            shared.point_position[i] = [p.position.x, p.position.y, p.position.z, 1.];
+            shared.point_color[i] = [p.color.x, p.color.y, p.color.z, 1.];
+            shared.point_attenuation[i] = [a.constant, a.linear, a.quadratic, 0.];
+        }
+        for (i, s) in lights.spot.iter().enumerate() {
+            let a = s.attenuation;
+            shared.spot_position[i] = [s.position.x, s.position.y, s.position.z, 1.];
+            shared.spot_direction[i] = [s.direction.x, s.direction.y, s.direction.z, 0.];
+            shared.spot_color[i] = [s.color.x, s.color.y, s.color.z, 1.];
+            shared.spot_attenuation[i] = [a.constant, a.linear, a.quadratic, 0.];
+            shared.spot_cone[i] = [s.cos_inner, s.cos_outer, 0., 0.];
+        }
+        for (i, &(first, count)) in plan.directional.iter().enumerate() {
+            shared.directional_shadow[i] = [first as i32, count as i32, 0, 0];
+        }
+        for (i, &(first, count)) in plan.spot.iter().enumerate() {
+            shared.spot_shadow[i] = [first as i32, count as i32, 0, 0];
+        }
+        shared
+    }
+}
+
+/// The shadows uniform block, this matches the std140 layout of the
+/// `shadows` block in `LIGHTING_SRC`.
+#[derive(Copy, Clone)]
+struct SharedShadows {
+    shadow_mat: [[[f32; 4]; 4]; MAX_SHADOW_MAPS],
+    /// the bias and split of each map
+    shadow_info: [[f32; 4]; MAX_SHADOW_MAPS]
+}
+
+/// The environment uniform block, this matches the std140 layout of
+/// the `environment` block in `LIGHTING_SRC`.
+#[derive(Copy, Clone)]
+struct SharedEnvironment {
+    ambient_sh: [[f32; 4]; 9],
+    /// if there is a sky, its intensity and its reflection
+    environment_info: [f32; 4]
+}
+
+impl SharedEnvironment {
+    fn new(env: &Environment) -> SharedEnvironment {
+        let mut shared = SharedEnvironment {
+            ambient_sh: [[0.; 4]; 9],
+            environment_info: [
+                if env.sky.is_some() { 1. } else { 0. },
+                env.intensity,
+                env.reflection,
+                0.
+            ]
+        };
+        for (s, c) in shared.ambient_sh.iter_mut().zip(env.ambient.probe().coefficients.iter()) {
+            *s = [c.x, c.y, c.z, 0.];
+        }
+        shared
+    }
+}
+
+/// The fog uniform block, this matches the std140 layout of the `fog`
+/// block in `LIGHTING_SRC`.
 #[derive(Copy, Clone)]
-struct SharedMaterial {
-    ka_color: [f32; 4],
-    kd_color: [f32; 4],
-    ks_color: [f32; 4],
+struct SharedFog {
+    fog_color: [f32; 4],
+    /// the mode, then the start and end or the density
+    fog_params: [f32; 4],
+    /// if there is height fog, its base and falloff
+    fog_height: [f32; 4],
+    fog_eye: [f32; 4]
+}
 
-    ka_use_texture: i32,
-    kd_use_texture: i32,
-    ks_use_texture: i32,
+impl SharedFog {
+    fn new(fog: Option<Fog>, eye: Vector3<f32>) -> SharedFog {
+        let mut shared = SharedFog {
+            fog_color: [0.; 4],
+            fog_params: [0.; 4],
+            fog_height: [0.; 4],
+            fog_eye: [eye.x, eye.y, eye.z, 1.]
+        };
+        if let Some(fog) = fog {
+            shared.fog_color = [fog.color.x, fog.color.y, fog.color.z, 1.];
+            shared.fog_params = match fog.mode {
+                FogMode::Linear { start, end } => [1., start, end, 0.],
+                FogMode::Exponential { density } => [2., density, 0., 0.]
+            };
+            if let Some(h) = fog.height {
+                shared.fog_height = [1., h.base, h.falloff, 0.];
+            }
+        }
+        shared
+    }
 }
 
+/// moves a shadow map's clip space into texture space
+const SHADOW_BIAS_MAT: [[f32; 4]; 4] = [
+    [0.5, 0.0, 0.0, 0.0],
+    [0.0, 0.5, 0.0, 0.0],
+    [0.0, 0.0, 0.5, 0.0],
+    [0.5, 0.5, 0.5, 1.0],
+];
+
 const VERTEX_SRC: &'static [u8] = b"
     #version 150 core
-    layout(std140)
-    uniform shadow_shared_mat {
-        mat4 shadow_proj_mat;
-        mat4 shadow_view_mat;
-    };
-
     layout(std140)
     uniform shared_mat {
         mat4 proj_mat;
         mat4 view_mat;
     };
 
-    uniform model {
-        mat4 model_mat[512];
-    };
-    uniform int offset;
-
-    uniform mat4 shadow_bias_mat;
-
     in vec3 position;
     in vec2 texture;
     in vec3 normal;
+    in vec3 tangent;
+
+    // read once per instance, see InstanceVertex
+    in vec4 model0;
+    in vec4 model1;
+    in vec4 model2;
+    in vec4 model3;
+    in float material;
+    in vec4 tint;
 
     out vec2 o_texture;
     out vec3 o_normal;
-    out vec4 o_shadow_coord;
+    out vec3 o_tangent;
+    out vec3 o_position;
+    out float o_depth;
+    flat out int o_material;
+    flat out vec4 o_tint;
 
     void main() {
-        gl_Position =
-            proj_mat *
-            view_mat *
-            model_mat[gl_InstanceID + offset] *
-            vec4(position, 1.0);
+        mat4 model_mat = mat4(model0, model1, model2, model3);
+        vec4 world = model_mat * vec4(position, 1.0);
+        gl_Position = proj_mat * view_mat * world;
         o_texture = texture;
-        o_normal = normalize((model_mat[gl_InstanceID + offset] * vec4(normal, 0.)).xyz);
+        o_normal = normalize((model_mat * vec4(normal, 0.)).xyz);
+        // meshes without texture coordinates have no tangent
+        o_tangent = (model_mat * vec4(tangent, 0.)).xyz;
+        o_position = world.xyz;
+        o_depth = -(view_mat * world).z;
+        o_material = int(material);
+        o_tint = tint;
+    }
+";
+
+/// The lookup of a latitude-longitude sky, this matches
+/// `sm_render::environment::sample_sky`
+const SKY_SRC: &'static [u8] = b"
+    uniform sampler2D sky;
 
-        o_shadow_coord = shadow_bias_mat *
-                         shadow_proj_mat *
-                         shadow_view_mat *
-                         model_mat[gl_InstanceID + offset] *
-                         vec4(position, 1.0);
+    vec2 sky_uv(vec3 d) {
+        const float PI = 3.14159265;
+        return vec2(0.5 + atan(d.x, -d.z) / (2. * PI),
+                    acos(clamp(d.y, -1., 1.)) / PI);
     }
 ";
 
-const FRAGMENT_SRC: &'static [u8] = b"
-    #version 150 core
+/// The lights, shadow maps and environment shared by the fragment
+/// shaders, it is put after the `#version` line and `SKY_SRC` of each
+/// of them by `fragment_src`.
+const LIGHTING_SRC: &'static [u8] = b"
+    // the array sizes are sm_render::MAX_LIGHTS
+    layout(std140)
+    uniform lights {
+        vec4 directional_normal[8];
+        vec4 directional_color[8];
+
+        vec4 point_position[8];
+        vec4 point_color[8];
+        vec4 point_attenuation[8];
+
+        vec4 spot_position[8];
+        vec4 spot_direction[8];
+        vec4 spot_color[8];
+        vec4 spot_attenuation[8];
+        vec4 spot_cone[8];
+
+        // the first shadow map and the number of maps
+        ivec4 directional_shadow[8];
+        ivec4 spot_shadow[8];
+
+        ivec4 light_count;
+    };
+
+    // the array sizes are sm_render::MAX_SHADOW_MAPS
+    layout(std140)
+    uniform shadows {
+        mat4 shadow_mat[4];
+        vec4 shadow_info[4];
+    };
+
+    uniform sampler2DShadow shadow0;
+    uniform sampler2DShadow shadow1;
+    uniform sampler2DShadow shadow2;
+    uniform sampler2DShadow shadow3;
+
+    layout(std140)
+    uniform environment {
+        vec4 ambient_sh[9];
+        // if there is a sky, its intensity and its reflection
+        vec4 environment_info;
+    };
+
     layout(std140)
-    uniform material {
-        vec4 ka_color;
-        vec4 kd_color;
-        vec4 ks_color;
-
-        int ka_use_texture;
-        int kd_use_texture;
-        int ks_use_texture;
+    uniform fog {
+        vec4 fog_color;
+        // 0 for no fog, 1 for linear and 2 for exponential, then
+        // the start and end or the density
+        vec4 fog_params;
+        // if there is height fog, its base and falloff
+        vec4 fog_height;
+        vec4 fog_eye;
     };
 
+    in vec3 o_position;
+    in float o_depth;
+
+    // this matches sm_render::Fog::apply
+    vec3 apply_fog(vec3 color) {
+        if (fog_params.x < 0.5) {
+            return color;
+        }
+        float dist = length(o_position - fog_eye.xyz);
+        if (fog_height.x > 0.5) {
+            float a = fog_eye.y - fog_height.y;
+            float b = o_position.y - fog_height.y;
+            float rise = fog_height.z * (b - a);
+            if (abs(rise) < 1e-4) {
+                dist *= exp(-fog_height.z * a);
+            } else {
+                dist *= (exp(-fog_height.z * a) - exp(-fog_height.z * b)) / rise;
+            }
+        }
+        float amount;
+        if (fog_params.x < 1.5) {
+            if (fog_params.z <= fog_params.y) {
+                amount = dist < fog_params.y ? 0. : 1.;
+            } else {
+                amount = (dist - fog_params.y) / (fog_params.z - fog_params.y);
+            }
+        } else {
+            amount = 1. - exp(-fog_params.y * dist);
+        }
+        return mix(color, fog_color.rgb, clamp(amount, 0., 1.));
+    }
+
+    // this matches sm_render::ShProbe::irradiance
+    vec3 ambient(vec3 n) {
+        vec3 sum = ambient_sh[0].rgb * 0.282095 +
+                   ambient_sh[1].rgb * 0.488603 * n.y +
+                   ambient_sh[2].rgb * 0.488603 * n.z +
+                   ambient_sh[3].rgb * 0.488603 * n.x +
+                   ambient_sh[4].rgb * 1.092548 * n.x * n.y +
+                   ambient_sh[5].rgb * 1.092548 * n.y * n.z +
+                   ambient_sh[6].rgb * 0.315392 * (3. * n.z * n.z - 1.) +
+                   ambient_sh[7].rgb * 1.092548 * n.x * n.z +
+                   ambient_sh[8].rgb * 0.546274 * (n.x * n.x - n.y * n.y);
+        return max(sum, vec3(0.));
+    }
+
+    float toon(float level) {
+        return round(max(0., level) * 2.) / 2.;
+    }
+
+    float attenuate(vec4 a, float dist) {
+        return 1. / (a.x + a.y * dist + a.z * dist * dist);
+    }
+
+    float pcf(sampler2DShadow map, vec3 coord) {
+        float sum = 0;
+
+        sum += texture(map, coord) * 0.25;
+
+        sum += textureOffset(map, coord, ivec2(1, 0)) * 0.125;
+        sum += textureOffset(map, coord, ivec2(-1, 0)) * 0.125;
+        sum += textureOffset(map, coord, ivec2(0, 1)) * 0.125;
+        sum += textureOffset(map, coord, ivec2(0, -1)) * 0.125;
+
+        sum += textureOffset(map, coord, ivec2(-1,-1)) * 0.0625;
+        sum += textureOffset(map, coord, ivec2(-1, 1)) * 0.0625;
+        sum += textureOffset(map, coord, ivec2( 1,-1)) * 0.0625;
+        sum += textureOffset(map, coord, ivec2( 1, 1)) * 0.0625;
+        return sum;
+    }
+
+    // samplers can not be indexed by a variable in glsl 1.50
+    float shadow_map(int i) {
+        vec4 coord = shadow_mat[i] * vec4(o_position, 1.);
+        vec3 c = coord.xyz / coord.w;
+        c.z -= shadow_info[i].x;
+        if (i == 0) {
+            return pcf(shadow0, c);
+        } else if (i == 1) {
+            return pcf(shadow1, c);
+        } else if (i == 2) {
+            return pcf(shadow2, c);
+        } else {
+            return pcf(shadow3, c);
+        }
+    }
+
+    // pick the first map whose split is past the fragment
+    float shadow(ivec4 maps) {
+        for (int i = maps.x; i < maps.x + maps.y; i++) {
+            if (o_depth <= shadow_info[i].y) {
+                return shadow_map(i);
+            }
+        }
+        return 1.;
+    }
+";
+
+const FRAGMENT_SRC: &'static [u8] = b"
+    // a row of MaterialTable, see MaterialTable::phong
+    uniform sampler2D materials;
+    flat in int o_material;
+    flat in vec4 o_tint;
+
     uniform sampler2D ka_texture;
     uniform sampler2D kd_texture;
     uniform sampler2D ks_texture;
 
-    uniform vec4 light_normal;
-    uniform vec4 light_color;
-    uniform sampler2DShadow shadow;
-
     in vec2 o_texture;
     in vec3 o_normal;
-    in vec4 o_shadow_coord;
 
     out vec4 o_Color;
 
     void main() {
-        vec4 normal = vec4(o_normal, 0.);
-        vec3 shadow_coord = o_shadow_coord.xyz / o_shadow_coord.w;
-        shadow_coord.z -= 0.0002;
+        vec4 ka_color = texelFetch(materials, ivec2(0, o_material), 0);
+        vec4 kd_color = texelFetch(materials, ivec2(1, o_material), 0);
+        vec4 ks_color = texelFetch(materials, ivec2(2, o_material), 0);
+        // if the ka, kd and ks textures are used and the opacity
+        vec4 use_texture = texelFetch(materials, ivec2(3, o_material), 0);
+        float opacity = use_texture.w;
+
+        vec3 normal = o_normal;
         vec4 color;
         vec4 ka, kd, ks;
-        if (1 == ka_use_texture) {
+        if (use_texture.x > 0.5) {
             ka = texture(ka_texture, o_texture);
         } else {
             ka = ka_color;
         }
 
-        if (1 == ks_use_texture) {
+        if (use_texture.z > 0.5) {
             ks = texture(ks_texture, o_texture);
         } else {
             ks = ka_color;
         }
 
-        if (1 == kd_use_texture) {
+        if (use_texture.y > 0.5) {
             kd = texture(kd_texture, o_texture);
         } else {
             kd = ka_color;
         }
+        ka *= o_tint;
+        kd *= o_tint;
+
+        vec3 light = vec3(0.);
+        for (int i = 0; i < light_count.x; i++) {
+            float level = dot(directional_normal[i].xyz, normal) *
+                          shadow(directional_shadow[i]);
+            light += toon(level) * directional_color[i].rgb;
+        }
+
+        for (int i = 0; i < light_count.y; i++) {
+            vec3 l = point_position[i].xyz - o_position;
+            float dist = length(l);
+            float level = toon(dot(l / dist, normal)) *
+                          attenuate(point_attenuation[i], dist);
+            light += level * point_color[i].rgb;
+        }
 
-        float shadow_sum = 0;
-        float x, y;
+        for (int i = 0; i < light_count.z; i++) {
+            vec3 l = spot_position[i].xyz - o_position;
+            float dist = length(l);
+            l = l / dist;
+            float cone = smoothstep(spot_cone[i].y, spot_cone[i].x,
+                                    dot(-l, spot_direction[i].xyz));
+            float level = toon(dot(l, normal) * shadow(spot_shadow[i])) *
+                          attenuate(spot_attenuation[i], dist) *
+                          cone;
+            light += level * spot_color[i].rgb;
+        }
 
-        shadow_sum += texture(shadow, shadow_coord) * 0.25;
+        color = vec4(ka.rgb * ambient(normal) + kd.rgb * light, kd.a * opacity);
+        color.rgb = apply_fog(color.rgb);
 
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2(1, 0)) * 0.125;
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2(-1, 0)) * 0.125;
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2(0, 1)) * 0.125;
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2(0, -1)) * 0.125;
+        o_Color = color;
+    }
+";
 
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2(-1,-1)) * 0.0625;
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2(-1, 1)) * 0.0625;
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2( 1,-1)) * 0.0625;
-        shadow_sum += textureOffset(shadow, shadow_coord, ivec2( 1, 1)) * 0.0625;
+const PBR_FRAGMENT_SRC: &'static [u8] = b"
+    // a row of MaterialTable, see MaterialTable::pbr
+    uniform sampler2D materials;
+    flat in int o_material;
+    flat in vec4 o_tint;
 
-        float level = shadow_sum * max(0, dot(light_normal, normal));
-        level = round(level * 2.) / 2.;
+    uniform sampler2D base_color_map;
+    uniform sampler2D metallic_roughness_map;
+    uniform sampler2D normal_map;
+    uniform sampler2D occlusion_map;
+    uniform sampler2D emissive_map;
 
-        color = ka * 0.2 + kd * level * light_color;
+    uniform vec4 eye;
 
-        o_Color = color;
+    in vec2 o_texture;
+    in vec3 o_normal;
+    in vec3 o_tangent;
+
+    out vec4 o_Color;
+
+    const float PI = 3.14159265;
+
+    // this matches sm_render::Surface::brdf
+    vec3 brdf(vec3 n, vec3 v, vec3 l, vec3 base, float metallic, float roughness) {
+        float nl = dot(n, l);
+        if (nl <= 0.) {
+            return vec3(0.);
+        }
+        vec3 h = normalize(l + v);
+        float nv = max(dot(n, v), 0.0001);
+        float nh = max(dot(n, h), 0.);
+        float vh = max(dot(v, h), 0.);
+
+        float a = max(roughness * roughness, 0.001);
+        float a2 = a * a;
+        float dd = nh * nh * (a2 - 1.) + 1.;
+        float d = a2 / (PI * dd * dd);
+
+        float k = (roughness + 1.) * (roughness + 1.) / 8.;
+        float g = nv / (nv * (1. - k) + k) * nl / (nl * (1. - k) + k);
+
+        vec3 f0 = mix(vec3(0.04), base, metallic);
+        vec3 f = f0 + (1. - f0) * pow(1. - vh, 5.);
+
+        vec3 specular = f * d * g / (4. * nv * nl);
+        vec3 diffuse = (1. - f) * (1. - metallic) * base / PI;
+        return (diffuse + specular) * nl * PI;
+    }
+
+    // this matches sm_render::Environment::shade_pbr
+    vec3 environment_pbr(vec3 n, vec3 v, vec3 base, float metallic, float roughness) {
+        vec3 irradiance = ambient(n);
+        if (environment_info.x < 0.5) {
+            return irradiance * base;
+        }
+        float nv = max(dot(n, v), 0.);
+        vec3 r = normalize(reflect(-v, n));
+        vec3 f0 = mix(vec3(0.04), base, metallic);
+        float edge = 1. - roughness;
+        vec3 f = f0 + (max(vec3(edge), f0) - f0) * pow(1. - nv, 5.);
+
+        vec3 reflected = texture(sky, sky_uv(r)).rgb * environment_info.y * edge +
+                         ambient(r) * roughness;
+        vec3 specular = reflected * f * environment_info.z;
+        vec3 diffuse = irradiance * base * (1. - f) * (1. - metallic);
+        return diffuse + specular;
+    }
+
+    void main() {
+        vec4 base_color = texelFetch(materials, ivec2(0, o_material), 0);
+        vec4 emissive = texelFetch(materials, ivec2(1, o_material), 0);
+        // metallic, roughness, normal scale and occlusion strength
+        vec4 factors = texelFetch(materials, ivec2(2, o_material), 0);
+        // if the base color, metallic-roughness, normal, occlusion and
+        // emissive maps are used
+        vec4 use_map = texelFetch(materials, ivec2(3, o_material), 0);
+        bool use_emissive_map = texelFetch(materials, ivec2(4, o_material), 0).x > 0.5;
+
+        vec4 base = base_color;
+        float metallic = factors.x;
+        float roughness = factors.y;
+        float ao = 1.;
+        vec3 e = emissive.rgb;
+
+        if (use_map.x > 0.5) {
+            base *= texture(base_color_map, o_texture);
+        }
+        if (use_map.y > 0.5) {
+            vec4 mr = texture(metallic_roughness_map, o_texture);
+            metallic *= mr.b;
+            roughness *= mr.g;
+        }
+        if (use_map.w > 0.5) {
+            ao = 1. + factors.w * (texture(occlusion_map, o_texture).r - 1.);
+        }
+        if (use_emissive_map) {
+            e *= texture(emissive_map, o_texture).rgb;
+        }
+        base *= o_tint;
+        e *= o_tint.rgb;
+
+        vec3 n = normalize(o_normal);
+        if (use_map.z > 0.5 && dot(o_tangent, o_tangent) > 0.) {
+            vec3 t = normalize(o_tangent - n * dot(n, o_tangent));
+            vec3 b = cross(n, t);
+            vec3 m = texture(normal_map, o_texture).xyz * 2. - 1.;
+            m.xy *= factors.z;
+            n = normalize(mat3(t, b, n) * m);
+        }
+        vec3 v = normalize(eye.xyz - o_position);
+
+        vec3 light = vec3(0.);
+        for (int i = 0; i < light_count.x; i++) {
+            vec3 level = brdf(n, v, directional_normal[i].xyz, base.rgb, metallic, roughness) *
+                         shadow(directional_shadow[i]);
+            light += level * directional_color[i].rgb;
+        }
+
+        for (int i = 0; i < light_count.y; i++) {
+            vec3 l = point_position[i].xyz - o_position;
+            float dist = length(l);
+            vec3 level = brdf(n, v, l / dist, base.rgb, metallic, roughness) *
+                         attenuate(point_attenuation[i], dist);
+            light += level * point_color[i].rgb;
+        }
+
+        for (int i = 0; i < light_count.z; i++) {
+            vec3 l = spot_position[i].xyz - o_position;
+            float dist = length(l);
+            l = l / dist;
+            float cone = smoothstep(spot_cone[i].y, spot_cone[i].x,
+                                    dot(-l, spot_direction[i].xyz));
+            vec3 level = brdf(n, v, l, base.rgb, metallic, roughness) *
+                         shadow(spot_shadow[i]) *
+                         attenuate(spot_attenuation[i], dist) *
+                         cone;
+            light += level * spot_color[i].rgb;
+        }
+
+        vec3 color = environment_pbr(n, v, base.rgb, metallic, roughness) * ao + light + e;
+        o_Color = vec4(apply_fog(color), base.a);
     }
 ";
 
+/// prepend the version and the lighting code to the body of a fragment shader
+fn fragment_src(body: &[u8]) -> Vec<u8> {
+    let mut src = b"#version 150 core\n".to_vec();
+    src.push_all(SKY_SRC);
+    src.push_all(LIGHTING_SRC);
+    src.push_all(body);
+    src
+}
+
 #[shader_param]
 #[derive(Debug, Clone)]
 struct Params<R: gfx::Resources> {
-    shadow_shared_mat: gfx::RawBufferHandle<R>,
     shared_mat: gfx::RawBufferHandle<R>,
 
-    shadow_bias_mat: [[f32; 4]; 4],
-
-    material: gfx::RawBufferHandle<R>,
+    materials: gfx::shade::TextureParam<R>,
     ka_texture: gfx::shade::TextureParam<R>,
     kd_texture: gfx::shade::TextureParam<R>,
     ks_texture: gfx::shade::TextureParam<R>,
 
-    light_normal: [f32; 4],
-    light_color: [f32; 4],
-    shadow: gfx::shade::TextureParam<R>,
+    lights: gfx::RawBufferHandle<R>,
+    shadows: gfx::RawBufferHandle<R>,
+    shadow0: gfx::shade::TextureParam<R>,
+    shadow1: gfx::shade::TextureParam<R>,
+    shadow2: gfx::shade::TextureParam<R>,
+    shadow3: gfx::shade::TextureParam<R>,
 
-    model: gfx::RawBufferHandle<R>,
-    offset: i32
+    environment: gfx::RawBufferHandle<R>,
+    sky: gfx::shade::TextureParam<R>,
+    fog: gfx::RawBufferHandle<R>
+}
+
+#[shader_param]
+#[derive(Debug, Clone)]
+struct PbrParams<R: gfx::Resources> {
+    shared_mat: gfx::RawBufferHandle<R>,
+
+    materials: gfx::shade::TextureParam<R>,
+    base_color_map: gfx::shade::TextureParam<R>,
+    metallic_roughness_map: gfx::shade::TextureParam<R>,
+    normal_map: gfx::shade::TextureParam<R>,
+    occlusion_map: gfx::shade::TextureParam<R>,
+    emissive_map: gfx::shade::TextureParam<R>,
+    eye: [f32; 4],
+
+    lights: gfx::RawBufferHandle<R>,
+    shadows: gfx::RawBufferHandle<R>,
+    shadow0: gfx::shade::TextureParam<R>,
+    shadow1: gfx::shade::TextureParam<R>,
+    shadow2: gfx::shade::TextureParam<R>,
+    shadow3: gfx::shade::TextureParam<R>,
+
+    environment: gfx::RawBufferHandle<R>,
+    sky: gfx::shade::TextureParam<R>,
+    fog: gfx::RawBufferHandle<R>
 }
 
 const BACK_FRAGMENT_SRC: &'static [u8] = b"
@@ -241,60 +751,741 @@ static SHADOW_VERTEX_SRC: &'static [u8] = b"
         mat4 proj_mat;
         mat4 view_mat;
     };
-    uniform model {
-        mat4 model_mat[512];
+
+    in vec3 position;
+
+    in vec4 model0;
+    in vec4 model1;
+    in vec4 model2;
+    in vec4 model3;
+
+    void main() {
+        mat4 model_mat = mat4(model0, model1, model2, model3);
+        gl_Position = proj_mat * view_mat * model_mat * vec4(position, 1.0);
+    }
+";
+
+static SHADOW_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    void main() {}
+";
+
+#[shader_param]
+#[derive(Debug, Clone)]
+struct ShadowParams<R: gfx::Resources> {
+    shared_mat: gfx::RawBufferHandle<R>
+}
+
+/// a point of a debug line or of the debug text
+#[derive(Copy)]
+#[vertex_format]
+struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 4]
+}
+
+/// the most vertices the debug lines, or the debug text, can use
+const MAX_DEBUG_VERTICES: usize = 65536;
+
+static DEBUG_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+    layout(std140)
+    uniform shared_mat {
+        mat4 proj_mat;
+        mat4 view_mat;
+    };
+
+    in vec3 position;
+    in vec4 color;
+    out vec4 o_color;
+
+    void main() {
+        o_color = color;
+        gl_Position = proj_mat * view_mat * vec4(position, 1.);
+    }
+";
+
+static DEBUG_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec4 o_color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = o_color;
+    }
+";
+
+#[shader_param]
+#[derive(Debug, Clone)]
+struct DebugParams<R: gfx::Resources> {
+    shared_mat: gfx::RawBufferHandle<R>
+}
+
+/// A buffer that is refilled every frame and the batch that draws it
+struct DebugBatch {
+    buffer: gfx::BufferHandle<device::GlResources, DebugVertex>,
+    shared_mat: gfx::BufferHandle<device::GlResources, SharedMatrix>,
+    batch: RefBatch<DebugParams<device::GlResources>>
+}
+
+impl DebugBatch {
+    fn new(device: &mut device::GlDevice,
+           context: &mut gfx::batch::Context<device::GlResources>,
+           prog: &gfx::ProgramHandle<device::GlResources>,
+           prim: gfx::PrimitiveType) -> DebugBatch {
+        let buffer = device.create_buffer::<DebugVertex>(MAX_DEBUG_VERTICES, gfx::BufferUsage::Dynamic);
+        let mesh = gfx::Mesh::from_format(buffer.clone(), MAX_DEBUG_VERTICES as u32);
+        let shared_mat = device.create_buffer::<SharedMatrix>(1, gfx::BufferUsage::Dynamic);
+        let params = DebugParams {
+            shared_mat: shared_mat.raw()
+        };
+        let batch = context.make_batch(
+            prog,
+            params,
+            &mesh,
+            mesh.to_slice(prim),
+            &gfx::DrawState::new().blend(gfx::BlendPreset::Alpha)
+        ).ok().expect("Failed to create batch.");
+
+        DebugBatch {
+            buffer: buffer,
+            shared_mat: shared_mat,
+            batch: batch
+        }
+    }
+
+    /// upload the vertices, the next draw only draws these
+    fn load(&mut self, device: &mut device::GlDevice, vertices: &[DebugVertex]) {
+        let count = min(vertices.len(), MAX_DEBUG_VERTICES);
+        device.update_buffer(self.buffer.clone(), &vertices[..count], 0);
+        self.batch.slice.end = count as u32;
+    }
+
+    /// set the projection and view the vertices are drawn with
+    fn set_matrices(&self, device: &mut device::GlDevice, proj: Matrix4<f32>, view: Matrix4<f32>) {
+        device.update_buffer(self.shared_mat.clone(), &[SharedMatrix {
+            proj_mat: proj.into_fixed(),
+            view_mat: view.into_fixed()
+        }], 0);
+    }
+}
+
+/// a corner of an overlay quad, the position is in clip space
+#[derive(Copy)]
+#[vertex_format]
+struct OverlayVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4]
+}
+
+/// the most vertices the overlay can use, six for each quad
+const MAX_OVERLAY_VERTICES: usize = 65536;
+
+static OVERLAY_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec2 position;
+    in vec2 uv;
+    in vec4 color;
+    out vec2 o_uv;
+    out vec4 o_color;
+
+    void main() {
+        o_uv = uv;
+        o_color = color;
+        gl_Position = vec4(position, 0., 1.);
+    }
+";
+
+static OVERLAY_FRAGMENT_SRC: &'static [u8] = b"
+    #version 150 core
+    uniform sampler2D texture;
+
+    in vec2 o_uv;
+    in vec4 o_color;
+    out vec4 o_Color;
+
+    void main() {
+        o_Color = texture(texture, o_uv) * o_color;
+    }
+";
+
+#[shader_param]
+#[derive(Debug, Clone)]
+struct OverlayParams<R: gfx::Resources> {
+    texture: gfx::shade::TextureParam<R>
+}
+
+/// create a texture on the device with the contents of `text`
+fn upload_texture(device: &mut device::GlDevice,
+                  text: &Texture) -> gfx::TextureHandle<device::GlResources> {
+    if text.is_hdr() {
+        return upload_hdr_texture(device, text);
+    }
+    let tinfo = gfx::tex::TextureInfo {
+        width: text.width() as u16,
+        height: text.height() as u16,
+        depth: 1 as u16,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: match text.depth() {
+            4 => gfx::tex::Format::Unsigned(gfx::tex::Components::RGBA, 8, gfx::attrib::IntSubType::Normalized),
+            3 => gfx::tex::Format::Unsigned(gfx::tex::Components::RGB, 8, gfx::attrib::IntSubType::Normalized),
+            _ => panic!("Unsupported color depth")
+        }
+    };
+
+    let img_info = tinfo.to_image_info();
+    let texture = device.create_texture(tinfo)
+                        .ok().expect("Failed to create texture");
+    device.update_texture(&texture, &img_info, text.data())
+          .ok().expect("Failed to update texture.");
+    texture
+}
+
+/// create a float texture on the device with the decoded texels of `text`
+fn upload_hdr_texture(device: &mut device::GlDevice,
+                      text: &Texture) -> gfx::TextureHandle<device::GlResources> {
+    let tinfo = gfx::tex::TextureInfo {
+        width: text.width() as u16,
+        height: text.height() as u16,
+        depth: 1 as u16,
+        levels: 1,
+        kind: gfx::tex::TextureKind::Texture2D,
+        format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F32)
     };
-    uniform int offset;
+    let mut texels: Vec<[f32; 4]> = Vec::with_capacity((text.width() * text.height()) as usize);
+    for y in (0..text.height()) {
+        for x in (0..text.width()) {
+            texels.push(text.texel(x, y));
+        }
+    }
+
+    let img_info = tinfo.to_image_info();
+    let texture = device.create_texture(tinfo)
+                        .ok().expect("Failed to create texture");
+    device.update_texture(&texture, &img_info, &texels[..])
+          .ok().expect("Failed to update texture.");
+    texture
+}
+
+/// A matrix that moves the whole of clip space into `rect` of a
+/// window `width` by `height` pixels
+fn viewport_matrix(rect: (u32, u32, u32, u32), width: u32, height: u32) -> Matrix4<f32> {
+    let (x, y, w, h) = rect;
+    let (fw, fh) = (width as f32, height as f32);
+    let (sx, sy) = (w as f32 / fw, h as f32 / fh);
+    let tx = (2. * x as f32 + w as f32) / fw - 1.;
+    let ty = 1. - (2. * y as f32 + h as f32) / fh;
+    Matrix4::new(sx, 0., 0., 0.,
+                 0., sy, 0., 0.,
+                 0., 0., 1., 0.,
+                 tx, ty, 0., 1.)
+}
+
+/// the gfx primitive of a geometry, adjacency is drawn as plain triangles
+fn prim_type(prim: Primative) -> gfx::PrimitiveType {
+    match prim {
+        Primative::Point => gfx::PrimitiveType::Point,
+        Primative::Line => gfx::PrimitiveType::Line,
+        Primative::Triangle => gfx::PrimitiveType::TriangleList,
+        Primative::TriangleAdjacency => gfx::PrimitiveType::TriangleList
+    }
+}
+
+/// the triangles drawn by one instance of a slice
+fn slice_triangles(slice: &gfx::Slice<device::GlResources>) -> usize {
+    match slice.prim_type {
+        gfx::PrimitiveType::TriangleList => ((slice.end - slice.start) / 3) as usize,
+        _ => 0
+    }
+}
+
+/// a corner of the triangle that covers the screen for the post effects
+#[derive(Copy)]
+#[vertex_format]
+struct PostVertex {
+    position: [f32; 2]
+}
+
+const POST_TRIANGLE: [PostVertex; 3] = [
+    PostVertex { position: [-1., -1.] },
+    PostVertex { position: [3., -1.] },
+    PostVertex { position: [-1., 3.] }
+];
+
+/// the format of the frame the views are drawn to when there are post effects
+const HDR_FORMAT: gfx::tex::Format =
+    gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F16);
+
+static POST_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    in vec2 position;
+    out vec2 o_uv;
+
+    void main() {
+        o_uv = position * 0.5 + 0.5;
+        gl_Position = vec4(position, 0., 1.);
+    }
+";
+
+/// A triangle covering the view, each corner is given the world space
+/// direction it looks in
+static SKY_VERTEX_SRC: &'static [u8] = b"
+    #version 150 core
+
+    uniform mat4 inv_proj_view;
+
+    in vec2 position;
+    out vec3 o_direction;
+
+    void main() {
+        vec4 near = inv_proj_view * vec4(position, -1., 1.);
+        vec4 far = inv_proj_view * vec4(position, 1., 1.);
+        o_direction = far.xyz / far.w - near.xyz / near.w;
+        gl_Position = vec4(position, 1., 1.);
+    }
+";
+
+/// drawn after `SKY_SRC`, see `sky_src`
+const SKY_FRAGMENT_SRC: &'static [u8] = b"
+    uniform float intensity;
+
+    in vec3 o_direction;
+    out vec4 o_Color;
+
+    void main() {
+        vec3 c = texture(sky, sky_uv(normalize(o_direction))).rgb;
+        o_Color = vec4(c * intensity, 1.);
+    }
+";
+
+fn sky_src() -> Vec<u8> {
+    let mut src = b"#version 150 core\n".to_vec();
+    src.push_all(SKY_SRC);
+    src.push_all(SKY_FRAGMENT_SRC);
+    src
+}
+
+#[shader_param]
+#[derive(Debug, Clone)]
+struct SkyParams<R: gfx::Resources> {
+    sky: gfx::shade::TextureParam<R>,
+    inv_proj_view: [[f32; 4]; 4],
+    intensity: f32
+}
+
+/// the width of the texture skies that are not latitude-longitude
+/// textures are baked into, it is half as high
+const SKY_BAKE_WIDTH: u32 = 512;
+
+/// The inputs shared by the post effects, `params` and `extra` hold
+/// the settings of the effect. Each effect matches `PostEffect::apply`.
+const POST_SRC: &'static [u8] = b"
+    uniform sampler2D source;
+    uniform vec4 params;
+    uniform vec4 extra;
+    uniform vec2 texel;
+
+    in vec2 o_uv;
+    out vec4 o_Color;
+
+    float luma(vec3 c) {
+        return dot(c, vec3(0.299, 0.587, 0.114));
+    }
+";
+
+const TONE_MAP_SRC: &'static [u8] = b"
+    void main() {
+        vec4 c = texture(source, o_uv);
+        vec3 v = c.rgb * params.y;
+        if (params.x < 0.5) {
+            v = v / (1. + v);
+        } else {
+            v = clamp((v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14), 0., 1.);
+        }
+        o_Color = vec4(v, c.a);
+    }
+";
+
+const GAMMA_SRC: &'static [u8] = b"
+    void main() {
+        vec4 c = texture(source, o_uv);
+        o_Color = vec4(pow(max(c.rgb, 0.), vec3(1. / params.x)), c.a);
+    }
+";
+
+const FXAA_SRC: &'static [u8] = b"
+    void main() {
+        vec4 c = texture(source, o_uv);
+        vec4 n = texture(source, o_uv + vec2(0., texel.y));
+        vec4 s = texture(source, o_uv - vec2(0., texel.y));
+        vec4 w = texture(source, o_uv - vec2(texel.x, 0.));
+        vec4 e = texture(source, o_uv + vec2(texel.x, 0.));
+        float lm = luma(c.rgb);
+        float ln = luma(n.rgb);
+        float ls = luma(s.rgb);
+        float lw = luma(w.rgb);
+        float le = luma(e.rgb);
+        float hi = max(lm, max(max(ln, ls), max(lw, le)));
+        float lo = min(lm, min(min(ln, ls), min(lw, le)));
+        if (hi - lo < max(params.x, hi * 0.125)) {
+            o_Color = c;
+            return;
+        }
+        vec3 across = abs(ln + ls - 2. * lm) >= abs(lw + le - 2. * lm) ?
+                      n.rgb + s.rgb : w.rgb + e.rgb;
+        o_Color = vec4(c.rgb * 0.5 + across * 0.25, c.a);
+    }
+";
+
+const BLOOM_SRC: &'static [u8] = b"
+    void main() {
+        vec4 c = texture(source, o_uv);
+        int r = int(params.z);
+        vec3 sum = vec3(0.);
+        for (int y = -r; y <= r; y++) {
+            for (int x = -r; x <= r; x++) {
+                vec3 p = texture(source, o_uv + vec2(x, y) * texel).rgb;
+                sum += max(p - params.x, 0.);
+            }
+        }
+        float taps = float((2 * r + 1) * (2 * r + 1));
+        o_Color = vec4(c.rgb + params.y * sum / taps, c.a);
+    }
+";
+
+const VIGNETTE_SRC: &'static [u8] = b"
+    void main() {
+        vec4 c = texture(source, o_uv);
+        float d = length(o_uv - 0.5) * sqrt(2.);
+        o_Color = vec4(c.rgb * (1. - params.x * smoothstep(params.y, 1., d)), c.a);
+    }
+";
+
+const COLOR_GRADE_SRC: &'static [u8] = b"
+    void main() {
+        vec4 c = texture(source, o_uv);
+        vec3 g = (c.rgb * params.rgb - 0.5) * extra.x + 0.5 + params.w;
+        float l = luma(g);
+        o_Color = vec4(max(l + (g - l) * extra.y, 0.), c.a);
+    }
+";
+
+/// the program of each kind of post effect, see `PostEffect::name`
+const POST_EFFECTS: [(&'static str, &'static [u8]); 6] = [
+    ("tone_map", TONE_MAP_SRC),
+    ("gamma", GAMMA_SRC),
+    ("fxaa", FXAA_SRC),
+    ("bloom", BLOOM_SRC),
+    ("vignette", VIGNETTE_SRC),
+    ("color_grade", COLOR_GRADE_SRC)
+];
+
+fn post_src(body: &[u8]) -> Vec<u8> {
+    let mut src = b"#version 150 core\n".to_vec();
+    src.push_all(POST_SRC);
+    src.push_all(body);
+    src
+}
+
+/// the `params` and `extra` uniforms of an effect
+fn post_uniforms(effect: &PostEffect) -> ([f32; 4], [f32; 4]) {
+    match *effect {
+        PostEffect::ToneMap { operator, exposure } => {
+            let op = match operator {
+                ToneMap::Reinhard => 0.,
+                ToneMap::Aces => 1.
+            };
+            ([op, exposure, 0., 0.], [0.; 4])
+        }
+        PostEffect::Gamma(gamma) => ([gamma, 0., 0., 0.], [0.; 4]),
+        PostEffect::Fxaa { threshold } => ([threshold, 0., 0., 0.], [0.; 4]),
+        PostEffect::Bloom { threshold, intensity, radius } => {
+            let radius = radius.min(sm_render::post::MAX_BLOOM_RADIUS) as f32;
+            ([threshold, intensity, radius, 0.], [0.; 4])
+        }
+        PostEffect::Vignette { strength, radius } => ([strength, radius, 0., 0.], [0.; 4]),
+        PostEffect::ColorGrade { tint, brightness, contrast, saturation } => {
+            ([tint[0], tint[1], tint[2], brightness], [contrast, saturation, 0., 0.])
+        }
+    }
+}
+
+#[shader_param]
+#[derive(Debug, Clone)]
+struct PostParams<R: gfx::Resources> {
+    source: gfx::shade::TextureParam<R>,
+    params: [f32; 4],
+    extra: [f32; 4],
+    texel: [f32; 2]
+}
+
+struct Mesh<R: gfx::Resources> {
+    mesh: render::mesh::Mesh<R>,
+    index: gfx::BufferHandle<device::GlResources, u32>
+}
+
+impl Mesh<device::GlResources> {
+    /// free the vertex and index buffers
+    fn delete(self, device: &mut device::GlDevice) {
+        // every attribute made by `create_mesh` is in the same buffer
+        if let Some(attr) = self.mesh.attributes.get(0) {
+            device.delete_buffer_raw(attr.buffer.clone());
+        }
+        device.delete_buffer(self.index);
+    }
+}
+
+/// A depth texture that a shadow view is drawn into
+struct ShadowMap {
+    resolution: u32,
+    texture: gfx::TextureHandle<device::GlResources>,
+    frame: render::target::Frame<device::GlResources>,
+    shared_mat: gfx::BufferHandle<device::GlResources, SharedMatrix>
+}
+
+impl ShadowMap {
+    fn new(device: &mut device::GlDevice, resolution: u32) -> ShadowMap {
+        let info = gfx::tex::TextureInfo {
+            width: resolution as u16,
+            height: resolution as u16,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::DEPTH24STENCIL8,
+        };
+
+        let texture = device.create_texture(info)
+                            .ok().expect("Failed to create texture");
+
+        let mut frame = gfx::Frame::new(info.width, info.height);
+        frame.depth = Some(render::target::Plane::Texture(texture, 0, None));
+
+        ShadowMap {
+            resolution: resolution,
+            texture: texture,
+            frame: frame,
+            shared_mat: device.create_buffer::<SharedMatrix>(1, gfx::BufferUsage::Static)
+        }
+    }
+
+    /// free the depth texture and the matrix buffer
+    fn delete(self, device: &mut device::GlDevice) {
+        device.delete_texture(self.texture);
+        device.delete_buffer(self.shared_mat);
+    }
+}
+
+/// A colour texture and depth buffer that a view is drawn into
+struct ViewFrame {
+    width: u32,
+    height: u32,
+    format: gfx::tex::Format,
+    texture: gfx::TextureHandle<device::GlResources>,
+    depth: gfx::TextureHandle<device::GlResources>,
+    frame: render::target::Frame<device::GlResources>
+}
+
+impl ViewFrame {
+    fn new(device: &mut device::GlDevice,
+           width: u32,
+           height: u32,
+           format: gfx::tex::Format) -> ViewFrame {
+        let mut info = gfx::tex::TextureInfo {
+            width: width as u16,
+            height: height as u16,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: format,
+        };
+
+        let texture = device.create_texture(info)
+                            .ok().expect("Failed to create texture");
+        info.format = gfx::tex::Format::DEPTH24STENCIL8;
+        let depth = device.create_texture(info)
+                          .ok().expect("Failed to create texture");
+
+        let mut frame = gfx::Frame::new(info.width, info.height);
+        frame.colors.push(render::target::Plane::Texture(texture, 0, None));
+        frame.depth = Some(render::target::Plane::Texture(depth, 0, None));
+
+        ViewFrame {
+            width: width,
+            height: height,
+            format: format,
+            texture: texture,
+            depth: depth,
+            frame: frame
+        }
+    }
+
+    /// free the colour and depth textures
+    fn delete(self, device: &mut device::GlDevice) {
+        device.delete_texture(self.texture);
+        device.delete_texture(self.depth);
+    }
+}
+
+/// the gl rectangle of `rect` in a frame `height` pixels high, gl
+/// has its origin in the bottom left
+fn gl_rect(rect: (u32, u32, u32, u32), height: u32) -> gfx::Rect {
+    let (x, y, w, h) = rect;
+    gfx::Rect {
+        x: x as u16,
+        y: (height - y - h) as u16,
+        w: w as u16,
+        h: h as u16
+    }
+}
+
+/// the draw state of a batch, blended batches do not write depth
+fn blend_state(blend: BlendMode) -> gfx::DrawState {
+    let state = gfx::DrawState::new();
+    match blend {
+        BlendMode::Opaque => state.depth(gfx::state::Comparison::LessEqual, true),
+        BlendMode::Alpha => state.depth(gfx::state::Comparison::LessEqual, false)
+                                 .blend(gfx::BlendPreset::Alpha),
+        BlendMode::Additive => state.depth(gfx::state::Comparison::LessEqual, false)
+                                    .blend(gfx::BlendPreset::Additive)
+    }
+}
+
+/// the texels in a row of the `MaterialTable`
+const MATERIAL_TEXELS: usize = 5;
+
+/// The colours and factors of every material in a float texture, the
+/// row of a material is its `material_index`. Each instance reads its
+/// own row, so drawables that only differ in their material's colours
+/// share a draw call. The texture grows as materials are added.
+struct MaterialTable {
+    texels: Vec<[f32; 4]>,
+    texture: gfx::TextureHandle<device::GlResources>,
+    /// the rows `texture` has room for
+    capacity: usize,
+    dirty: bool
+}
+
+impl MaterialTable {
+    fn new(device: &mut device::GlDevice) -> MaterialTable {
+        let capacity = 64;
+        MaterialTable {
+            texels: Vec::new(),
+            texture: MaterialTable::create(device, capacity),
+            capacity: capacity,
+            dirty: false
+        }
+    }
 
-    in vec3 position;
+    fn info(rows: usize) -> gfx::tex::TextureInfo {
+        gfx::tex::TextureInfo {
+            width: MATERIAL_TEXELS as u16,
+            height: rows as u16,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::Format::Float(gfx::tex::Components::RGBA, gfx::attrib::FloatSize::F32)
+        }
+    }
 
-    void main() {
-        gl_Position =
-            proj_mat *
-            view_mat *
-            model_mat[gl_InstanceID+offset] *
-            vec4(position, 1.0);
+    fn create(device: &mut device::GlDevice, rows: usize) -> gfx::TextureHandle<device::GlResources> {
+        device.create_texture(MaterialTable::info(rows))
+              .ok().expect("Failed to create texture")
     }
-";
 
-static SHADOW_FRAGMENT_SRC: &'static [u8] = b"
-    #version 150 core
+    /// the row of a phong material, see `FRAGMENT_SRC`
+    fn phong(mat: &Material) -> [[f32; 4]; MATERIAL_TEXELS] {
+        let (ka, kd, ks) = (mat.ka(), mat.kd(), mat.ks());
+        let used = |map: Option<Entity>| if map.is_some() {1.} else {0.};
+        [[ka[0], ka[1], ka[2], 1.],
+         [kd[0], kd[1], kd[2], 1.],
+         [ks[0], ks[1], ks[2], 1.],
+         [used(mat.map_ka()), used(mat.map_kd()), used(mat.map_ks()), mat.opacity()],
+         [0.; 4]]
+    }
 
-    void main() {}
-";
+    /// the row of a pbr material, see `PBR_FRAGMENT_SRC`
+    fn pbr(mat: &PbrMaterial) -> [[f32; 4]; MATERIAL_TEXELS] {
+        let (base, e) = (mat.base_color(), mat.emissive());
+        let used = |map: Option<Entity>| if map.is_some() {1.} else {0.};
+        [[base[0], base[1], base[2], mat.opacity()],
+         [e[0], e[1], e[2], 1.],
+         [mat.metallic(), mat.roughness(), mat.normal_scale(), mat.occlusion_strength()],
+         [used(mat.base_color_map()), used(mat.metallic_roughness_map()),
+          used(mat.normal_map()), used(mat.occlusion_map())],
+         [used(mat.emissive_map()), 0., 0., 0.]]
+    }
 
-#[shader_param]
-#[derive(Debug, Clone)]
-struct ShadowParams<R: gfx::Resources> {
-    shared_mat: gfx::RawBufferHandle<R>,
-    model: gfx::RawBufferHandle<R>,
-    offset: i32
+    /// replace the row of material `idx`
+    fn set(&mut self, idx: i32, row: [[f32; 4]; MATERIAL_TEXELS]) {
+        let start = idx as usize * MATERIAL_TEXELS;
+        if self.texels.len() < start + MATERIAL_TEXELS {
+            let missing = start + MATERIAL_TEXELS - self.texels.len();
+            self.texels.extend(repeat([0.; 4]).take(missing));
+        }
+        for (i, texel) in row.iter().enumerate() {
+            self.texels[start + i] = *texel;
+        }
+        self.dirty = true;
+    }
+
+    /// upload the table if it was changed, the texture is replaced by
+    /// a larger one if the rows no longer fit
+    fn upload(&mut self, device: &mut device::GlDevice) {
+        if !self.dirty {
+            return;
+        }
+        let rows = self.texels.len() / MATERIAL_TEXELS;
+        if rows > self.capacity {
+            device.delete_texture(self.texture);
+            self.capacity = rows.next_power_of_two();
+            self.texture = MaterialTable::create(device, self.capacity);
+        }
+        let info = MaterialTable::info(rows).to_image_info();
+        device.update_texture(&self.texture, &info, &self.texels[..])
+              .ok().expect("Failed to update texture.");
+        self.dirty = false;
+    }
 }
 
-struct Mesh<R: gfx::Resources> {
-    mesh: render::mesh::Mesh<R>,
-    index: gfx::BufferHandle<device::GlResources, u32>
+/// A batch of the `BatchPlan` drawn with the program of its state
+enum DrawBatch {
+    Phong(RefBatch<Params<device::GlResources>>),
+    Pbr(RefBatch<PbrParams<device::GlResources>>)
 }
 
-struct RenderMaterial {
-    material: Material,
-    buffer: gfx::BufferHandle<device::GlResources, SharedMaterial>,
-    ka_texture: Option<Entity>,
-    kd_texture: Option<Entity>,
-    ks_texture: Option<Entity>,
+/// the texture of a map, or the dummy texture if the map is not used
+/// or its texture has been removed
+fn map_texture(textures: &HashMap<Entity, gfx::TextureHandle<device::GlResources>>,
+               map: Option<Entity>,
+               dummy: gfx::TextureHandle<device::GlResources>,
+               sampler: gfx::SamplerHandle<device::GlResources>)
+    -> gfx::shade::TextureParam<device::GlResources> {
+    let texture = map.and_then(|map| textures.get(&map)).map(|t| *t);
+    (texture.unwrap_or(dummy), Some(sampler))
 }
 
 pub struct RenderManagerContext {
     prog: gfx::ProgramHandle<device::GlResources>,
     data: Params<device::GlResources>,
+    pbr_prog: gfx::ProgramHandle<device::GlResources>,
+    pbr_data: PbrParams<device::GlResources>,
 
     shadow_data: ShadowParams<device::GlResources>,
     shadow_prog: gfx::ProgramHandle<device::GlResources>,
-    shadow_frame: render::target::Frame<device::GlResources>,
-    shadow: gfx::TextureHandle<device::GlResources>,
+    shadow_maps: Vec<ShadowMap>,
     shadow_sampler: gfx::SamplerHandle<device::GlResources>,
-    shadow_shared_mat: gfx::BufferHandle<device::GlResources, SharedMatrix>,
+    shadows: gfx::BufferHandle<device::GlResources, SharedShadows>,
     shared_mat: gfx::BufferHandle<device::GlResources, SharedMatrix>,
+    lights: gfx::BufferHandle<device::GlResources, SharedLights>,
+    environment: gfx::BufferHandle<device::GlResources, SharedEnvironment>,
+    fog: gfx::BufferHandle<device::GlResources, SharedFog>,
+    dummy_texture: gfx::TextureHandle<device::GlResources>,
 
     back_data: ShadowParams<device::GlResources>,
     back_prog: gfx::ProgramHandle<device::GlResources>,
@@ -310,21 +1501,60 @@ pub struct RenderManagerContext {
     sampler: gfx::SamplerHandle<device::GlResources>,
     window: Window,
 
-    material: HashMap<Entity, RenderMaterial>,
-
-    batch: BTreeSet<(Entity, Entity, Entity)>,
-    shadow_batches: HashMap<Entity, RefBatch<ShadowParams<device::GlResources>>>,
-    draw_batches: HashMap<Entity, RefBatch<Params<device::GlResources>>>,
-    draw_back_batches: HashMap<Entity, RefBatch<ShadowParams<device::GlResources>>>,
-
-    spare_matrix_buffers: Vec<gfx::BufferHandle<device::GlResources, [[f32; 4]; 4]>>,
-    used_matrix_buffers: Vec<gfx::BufferHandle<device::GlResources, [[f32; 4]; 4]>>,
-    shared_geometry: Vec<(u32, gfx::BufferHandle<device::GlResources, [[f32; 4]; 4]>, usize, usize)>,
-    shared_geometry_material: Vec<(u32, u32, gfx::BufferHandle<device::GlResources, [[f32; 4]; 4]>, usize, usize)>,
+    materials: MaterialTable,
+
+    culler: Culler,
+    /// the frames views are drawn into, keyed by their target. `None`
+    /// is used for views that cover part of the window.
+    view_frames: HashMap<Option<Entity>, ViewFrame>,
+    /// the HDR frame the window's views are drawn into when there are
+    /// post effects, followed by the two frames the effects run between
+    post_frames: Vec<ViewFrame>,
+    post_batches: HashMap<&'static str, RefBatch<PostParams<device::GlResources>>>,
+    post_sampler: gfx::SamplerHandle<device::GlResources>,
+    debug_lines: DebugBatch,
+    debug_text: DebugBatch,
+    overlay_buffer: gfx::BufferHandle<device::GlResources, OverlayVertex>,
+    overlay_batch: RefBatch<OverlayParams<device::GlResources>>,
+    sky_batch: RefBatch<SkyParams<device::GlResources>>,
+    /// the skies of each scene that had to be baked into a
+    /// latitude-longitude texture, with the revisions of the textures
+    /// they were baked from
+    baked_skies: HashMap<Entity, (Sky, Vec<u64>, gfx::TextureHandle<device::GlResources>)>,
+    /// the textures solid quads and text are drawn with
+    white_texture: gfx::TextureHandle<device::GlResources>,
+    font_texture: gfx::TextureHandle<device::GlResources>,
+    /// the draw calls of the view being drawn, `batches` and
+    /// `shadow_batches` match the plan's batches and shadows
+    plan: BatchPlan,
+    batches: Vec<DrawBatch>,
+    shadow_batches: Vec<RefBatch<ShadowParams<device::GlResources>>>,
+    /// the depth of the opaque batches, drawn before anything else
+    back_batches: Vec<RefBatch<ShadowParams<device::GlResources>>>,
+    /// every instance of the plan, it grows when a plan needs more room
+    instances: gfx::BufferHandle<device::GlResources, InstanceVertex>,
+    instance_capacity: usize,
+
+    captures: Vec<Sender<Texture>>,
+
+    /// what was last uploaded of the game's resources, targets of views
+    /// are left out of `texture_tracker` as they are drawn instead
+    mesh_tracker: ResourceTracker,
+    texture_tracker: ResourceTracker,
+    material_tracker: ResourceTracker,
+    pbr_tracker: ResourceTracker,
+    stats: ResourceStats,
+    /// what has been drawn so far in this frame
+    frame_stats: RenderStats
 }
 
 pub struct RenderManager<R> {
     channel: Sender<R>,
+    capture: Sender<Sender<Texture>>,
+    /// the stats of the last frame the render thread drew
+    resources: Arc<Mutex<ResourceStats>>,
+    /// what the render thread did to draw its last frame
+    stats: Arc<Mutex<RenderStats>>,
     res: std::thread::JoinHandle
 }
 
@@ -344,64 +1574,98 @@ impl RenderManagerContext {
             )
         );
 
-        let (shadow_prog, shadow_data, shadow_shared_mat) = {
+        let mut shadow_sampler = gfx::tex::SamplerInfo::new(
+            gfx::tex::FilterMethod::Bilinear, gfx::tex::WrapMode::Clamp
+        );
+        shadow_sampler.comparison = gfx::tex::ComparisonMode::CompareRefToTexture(gfx::state::Comparison::LessEqual);
+
+        let shadow_sampler = device.create_sampler(shadow_sampler);
+
+        let (shadow_prog, shadow_data) = {
             let buff = device.create_buffer::<SharedMatrix>(1, gfx::BufferUsage::Static);
             let data = ShadowParams {
-                shared_mat: buff.raw(),
-                model: buff.raw(),
-                offset: 0
+                shared_mat: buff.raw()
             };
             (device.link_program(SHADOW_VERTEX_SRC.clone(),
                                  SHADOW_FRAGMENT_SRC.clone())
                   .ok().expect("Failed to link program"),
-             data, buff)
+             data)
         };
 
-        let (prog, data, shared_mat) = {
-            let tinfo = gfx::tex::TextureInfo {
-                width: 1,
-                height: 1,
-                depth: 1,
-                levels: 1,
-                kind: gfx::tex::TextureKind::Texture2D,
-                format: gfx::tex::RGBA8,
-            };
+        let lights = device.create_buffer::<SharedLights>(1, gfx::BufferUsage::Static);
+        let shadows = device.create_buffer::<SharedShadows>(1, gfx::BufferUsage::Static);
+        let environment = device.create_buffer::<SharedEnvironment>(1, gfx::BufferUsage::Static);
+        let fog = device.create_buffer::<SharedFog>(1, gfx::BufferUsage::Static);
+
+        let tinfo = gfx::tex::TextureInfo {
+            width: 1,
+            height: 1,
+            depth: 1,
+            levels: 1,
+            kind: gfx::tex::TextureKind::Texture2D,
+            format: gfx::tex::RGBA8,
+        };
+
+        let dummy_texture = device.create_texture(tinfo)
+                                  .ok().expect("Failed to create texture");
 
-            let dummy_texture = device.create_texture(tinfo)
-                                      .ok().expect("Failed to create texture");
+        let materials = MaterialTable::new(&mut device);
 
+        let (prog, data, shared_mat) = {
             let buff = device.create_buffer::<SharedMatrix>(1, gfx::BufferUsage::Static);
-            let unused = device.create_buffer::<Material>(1, gfx::BufferUsage::Static);
             let data = Params {
                 shared_mat: buff.raw(),
-                shadow_shared_mat: shadow_shared_mat.raw(),
-                shadow_bias_mat: [
-                    [0.5, 0.0, 0.0, 0.0],
-                    [0.0, 0.5, 0.0, 0.0],
-                    [0.0, 0.0, 0.5, 0.0],
-                    [0.5, 0.5, 0.5, 1.0],
-                ],
-                material: unused.raw(),
+                materials: (materials.texture, None),
                 ka_texture: (dummy_texture, Some(sampler)),
                 kd_texture: (dummy_texture, Some(sampler)),
                 ks_texture: (dummy_texture, Some(sampler)),
 
-                light_color: [1., 1., 1., 1.],
-                light_normal: [1., 0., 0., 0.],
-                shadow: (dummy_texture, Some(sampler)),
-                model: buff.raw(),
-                offset: 0
+                lights: lights.raw(),
+                shadows: shadows.raw(),
+                shadow0: (dummy_texture, Some(shadow_sampler)),
+                shadow1: (dummy_texture, Some(shadow_sampler)),
+                shadow2: (dummy_texture, Some(shadow_sampler)),
+                shadow3: (dummy_texture, Some(shadow_sampler)),
+
+                environment: environment.raw(),
+                sky: (dummy_texture, Some(sampler)),
+                fog: fog.raw()
             };
-            (device.link_program(VERTEX_SRC.clone(), FRAGMENT_SRC.clone())
+            (device.link_program(VERTEX_SRC.clone(), &fragment_src(FRAGMENT_SRC)[..])
                   .ok().expect("Failed to link program"),
              data, buff)
         };
 
+        let (pbr_prog, pbr_data) = {
+            let data = PbrParams {
+                shared_mat: shared_mat.raw(),
+                materials: (materials.texture, None),
+                base_color_map: (dummy_texture, Some(sampler)),
+                metallic_roughness_map: (dummy_texture, Some(sampler)),
+                normal_map: (dummy_texture, Some(sampler)),
+                occlusion_map: (dummy_texture, Some(sampler)),
+                emissive_map: (dummy_texture, Some(sampler)),
+                eye: [0., 0., 0., 1.],
+
+                lights: lights.raw(),
+                shadows: shadows.raw(),
+                shadow0: (dummy_texture, Some(shadow_sampler)),
+                shadow1: (dummy_texture, Some(shadow_sampler)),
+                shadow2: (dummy_texture, Some(shadow_sampler)),
+                shadow3: (dummy_texture, Some(shadow_sampler)),
+
+                environment: environment.raw(),
+                sky: (dummy_texture, Some(sampler)),
+                fog: fog.raw()
+            };
+            (device.link_program(VERTEX_SRC.clone(), &fragment_src(PBR_FRAGMENT_SRC)[..])
+                  .ok().expect("Failed to link program"),
+             data)
+        };
+
         let (back_prog, back_data) = {
             let data = ShadowParams {
-                shared_mat: shared_mat.raw(),
-                model: shared_mat.raw(),
-                offset: 0
+                shared_mat: shared_mat.raw()
             };
             (device.link_program(SHADOW_VERTEX_SRC.clone(),
                                  BACK_FRAGMENT_SRC.clone())
@@ -409,361 +1673,594 @@ impl RenderManagerContext {
              data)
         };
 
-        let shadow_info = gfx::tex::TextureInfo {
-            width: 2048,
-            height: 2048,
-            depth: 1,
-            levels: 1,
-            kind: gfx::tex::TextureKind::Texture2D,
-            format: gfx::tex::Format::DEPTH24STENCIL8,
-        };
-
-        let mut shadow_sampler = gfx::tex::SamplerInfo::new(
-            gfx::tex::FilterMethod::Anisotropic(16), gfx::tex::WrapMode::Tile
+        let post_sampler = device.create_sampler(
+            gfx::tex::SamplerInfo::new(
+                gfx::tex::FilterMethod::Bilinear, gfx::tex::WrapMode::Clamp
+            )
         );
-        shadow_sampler.comparison = gfx::tex::ComparisonMode::CompareRefToTexture(gfx::state::Comparison::LessEqual);
-
-        let shadow_sampler = device.create_sampler(shadow_sampler);
 
-        let shadow = device.create_texture(shadow_info)
-                           .ok().expect("Failed to create texture");
+        let mut context = gfx::batch::Context::new();
+        let mut post_batches = HashMap::new();
+        let post_mesh = device.create_mesh(&POST_TRIANGLE);
+        for &(name, body) in POST_EFFECTS.iter() {
+            let prog = device.link_program(POST_VERTEX_SRC, &post_src(body)[..])
+                             .ok().expect("Failed to link program");
+            let params = PostParams {
+                source: (dummy_texture, Some(post_sampler)),
+                params: [0.; 4],
+                extra: [0.; 4],
+                texel: [0.; 2]
+            };
+            let batch: RefBatch<PostParams<device::GlResources>> = context.make_batch(
+                &prog,
+                params,
+                &post_mesh,
+                post_mesh.to_slice(gfx::PrimitiveType::TriangleList),
+                &gfx::DrawState::new()
+            ).ok().expect("Failed to create batch.");
+            post_batches.insert(name, batch);
+        }
 
-        let mut shadow_frame = gfx::Frame::new(
-            shadow_info.width as u16,
-            shadow_info.height as u16
+        let sky_prog = device.link_program(SKY_VERTEX_SRC, &sky_src()[..])
+                             .ok().expect("Failed to link program");
+        let sky_batch = context.make_batch(
+            &sky_prog,
+            SkyParams {
+                sky: (dummy_texture, Some(sampler)),
+                inv_proj_view: Matrix4::identity().into_fixed(),
+                intensity: 1.
+            },
+            &post_mesh,
+            post_mesh.to_slice(gfx::PrimitiveType::TriangleList),
+            &gfx::DrawState::new()
+        ).ok().expect("Failed to create batch.");
+
+        let debug_prog = device.link_program(DEBUG_VERTEX_SRC, DEBUG_FRAGMENT_SRC)
+                               .ok().expect("Failed to link program");
+        let debug_lines = DebugBatch::new(&mut device, &mut context, &debug_prog,
+                                          gfx::PrimitiveType::Line);
+        let debug_text = DebugBatch::new(&mut device, &mut context, &debug_prog,
+                                         gfx::PrimitiveType::TriangleList);
+
+        let white_texture = upload_texture(&mut device, &Texture::new(1, 1, 4, vec![255, 255, 255, 255]));
+        let font_texture = upload_texture(&mut device, &sm_render::font::atlas());
+        let overlay_sampler = device.create_sampler(
+            gfx::tex::SamplerInfo::new(
+                gfx::tex::FilterMethod::Scale, gfx::tex::WrapMode::Clamp
+            )
         );
-
-        shadow_frame.depth = Some(render::target::Plane::Texture(shadow, 0, None));
-
+        let overlay_prog = device.link_program(OVERLAY_VERTEX_SRC, OVERLAY_FRAGMENT_SRC)
+                                 .ok().expect("Failed to link program");
+        let overlay_buffer = device.create_buffer::<OverlayVertex>(MAX_OVERLAY_VERTICES, gfx::BufferUsage::Dynamic);
+        let overlay_mesh = gfx::Mesh::from_format(overlay_buffer.clone(), MAX_OVERLAY_VERTICES as u32);
+        let overlay_batch = context.make_batch(
+            &overlay_prog,
+            OverlayParams {
+                texture: (white_texture, Some(overlay_sampler))
+            },
+            &overlay_mesh,
+            overlay_mesh.to_slice(gfx::PrimitiveType::TriangleList),
+            &gfx::DrawState::new().blend(gfx::BlendPreset::Alpha)
+        ).ok().expect("Failed to create batch.");
+
+        let instance_capacity = 1024;
+        let instances = device.create_buffer::<InstanceVertex>(instance_capacity, gfx::BufferUsage::Dynamic);
 
         RenderManagerContext {
             data: data,
             render: device.create_renderer(),
             device: device,
-            context: gfx::batch::Context::new(),
+            context: context,
             frame: frame,
             state: state,
             back_state: back_state,
             prog: prog,
+            pbr_prog: pbr_prog,
+            pbr_data: pbr_data,
             meshes: HashMap::new(),
             textures: HashMap::new(),
-            material: HashMap::new(),
+            materials: materials,
             sampler: sampler,
             window: window,
             shadow_data: shadow_data,
             shadow_prog: shadow_prog,
-            shadow_shared_mat: shadow_shared_mat,
-            shared_mat: shared_mat,
-            shadow: shadow,
-            shadow_frame: shadow_frame,
+            shadow_maps: Vec::new(),
             shadow_sampler: shadow_sampler,
-            batch: BTreeSet::new(),
-            shadow_batches: HashMap::new(),
-            draw_batches: HashMap::new(),
-            draw_back_batches: HashMap::new(),
-            spare_matrix_buffers: Vec::new(),
-            used_matrix_buffers: Vec::new(),
-            shared_geometry: Vec::new(),
-            shared_geometry_material: Vec::new(),
+            shadows: shadows,
+            shared_mat: shared_mat,
+            lights: lights,
+            environment: environment,
+            fog: fog,
+            dummy_texture: dummy_texture,
+            culler: Culler::new(),
+            view_frames: HashMap::new(),
+            post_frames: Vec::new(),
+            post_batches: post_batches,
+            post_sampler: post_sampler,
+            debug_lines: debug_lines,
+            debug_text: debug_text,
+            overlay_buffer: overlay_buffer,
+            overlay_batch: overlay_batch,
+            sky_batch: sky_batch,
+            baked_skies: HashMap::new(),
+            white_texture: white_texture,
+            font_texture: font_texture,
+            plan: BatchPlan {
+                instances: Vec::new(),
+                batches: Vec::new(),
+                shadows: Vec::new()
+            },
+            batches: Vec::new(),
+            shadow_batches: Vec::new(),
+            back_batches: Vec::new(),
+            instances: instances,
+            instance_capacity: instance_capacity,
             back_prog: back_prog,
             back_data: back_data,
+            captures: Vec::new(),
+            mesh_tracker: ResourceTracker::new(),
+            texture_tracker: ResourceTracker::new(),
+            material_tracker: ResourceTracker::new(),
+            pbr_tracker: ResourceTracker::new(),
+            stats: ResourceStats::new(),
+            frame_stats: RenderStats::new()
         }
     }
 
+    /// Upload the vertex buffers that were added or set since the last
+    /// frame and free the ones that were removed
     fn load_meshes<RD: Renderable+GetIoState>(&mut self, db: &RD) {
-        for (oid, vb) in db.vertex_buffer_iter() {
-            if self.meshes.get(&oid).is_none() {
-                let mesh = match vb.vertex {
-                    Geo(ref d) => {
-                        let data: Vec<VertexGeoTex> = d.iter()
-                            .map(|v| {
-                                VertexGeoTex {
-                                    position: v.position,
-                                    texture: [0., 0.]
-                                }
-                            })
-                            .collect();
-                        self.device.create_mesh(&data)
-                    },
-                    GeoTex(ref d) => {
-                        self.device.create_mesh(&d)
-                    },
-                    GeoNorm(ref d) => {
-                        let data: Vec<VertexGeoTexNorm> = d.iter()
-                            .map(|v| {
-                                VertexGeoTexNorm {
-                                    position: v.position,
-                                    texture: [0., 0.],
-                                    normal: v.normal
-                                }
-                            })
-                            .collect();
-                        self.device.create_mesh(&data)
-                    },
-                    GeoTexNorm(ref d) => {
-                        self.device.create_mesh(&d)
-                    },
-                    GeoTexNormTan(ref d) => {
-                        self.device.create_mesh(&d)
-                    }
-                };
-
-                let vb: Vec<u32> = vb.index.iter().map(|&x| x as u32).collect();
-
-                let index = self.device.create_buffer_static(&vb);
-
-                self.meshes.insert(oid, Mesh {
-                    index: index,
-                    mesh: mesh
-                });
+        let current = db.vertex_buffer_iter().map(|(oid, _)| (oid, db.revision(oid)));
+        for change in self.mesh_tracker.update(current).into_iter() {
+            let old = match change {
+                Change::Added(oid) | Change::Modified(oid) => {
+                    let vb = db.vertex_buffer(oid).expect("Could not get vertex buffer");
+                    // every mesh has tangents so both programs can draw it
+                    let data = vb.tangent_vertices();
+                    let mesh = self.device.create_mesh(&data);
+
+                    let vb: Vec<u32> = vb.index.iter().map(|&x| x as u32).collect();
+
+                    let index = self.device.create_buffer_static(&vb);
+
+                    self.stats.uploads += 1;
+                    self.meshes.insert(oid, Mesh {
+                        index: index,
+                        mesh: mesh
+                    })
+                }
+                Change::Removed(oid) => self.meshes.remove(&oid)
+            };
+            if let Some(old) = old {
+                old.delete(&mut self.device);
+                self.stats.frees += 1;
             }
         }
+        self.stats.meshes = self.meshes.len();
     }
 
+    /// Upload the textures that were added or set since the last frame
+    /// and free the ones that were removed. The textures of view targets
+    /// are drawn by their view, their frames are freed once no view
+    /// draws to them.
     fn load_textures<RD: Renderable+GetIoState>(&mut self, db: &RD) {
-        for (oid, text) in db.texture_iter() {
-            if self.textures.get(&oid).is_none() {
-                let tinfo = gfx::tex::TextureInfo {
-                    width: text.width() as u16,
-                    height: text.height() as u16,
-                    depth: 1 as u16,
-                    levels: 1,
-                    kind: gfx::tex::TextureKind::Texture2D,
-                    format: match text.depth() {
-                        4 => gfx::tex::Format::Unsigned(gfx::tex::Components::RGBA, 8, gfx::attrib::IntSubType::Normalized),
-                        3 => gfx::tex::Format::Unsigned(gfx::tex::Components::RGB, 8, gfx::attrib::IntSubType::Normalized),
-                        _ => panic!("Unsupported color depth")
-                    }
-                };
+        let targets: HashSet<Entity> = db.view_iter()
+            .filter_map(|(_, v)| v.target)
+            .collect();
+        let stale: Vec<Entity> = self.view_frames.keys()
+            .filter_map(|k| *k)
+            .filter(|t| !targets.contains(t))
+            .collect();
+        for target in stale.iter() {
+            let frame = self.view_frames.remove(&Some(*target)).unwrap();
+            frame.delete(&mut self.device);
+            self.textures.remove(target);
+            self.stats.frees += 1;
+        }
 
-                let img_info = tinfo.to_image_info();
-                let texture = self.device.create_texture(tinfo)
-                                         .ok().expect("Failed to create texture");
-                self.device.update_texture(&texture, &img_info, text.data())
-                    .ok().expect("Failed to update texture.");
-                self.textures.insert(oid, texture);
+        let current = db.texture_iter()
+            .map(|(oid, _)| oid)
+            .filter(|oid| !targets.contains(oid))
+            .map(|oid| (oid, db.revision(oid)));
+        for change in self.texture_tracker.update(current).into_iter() {
+            let old = match change {
+                Change::Added(oid) | Change::Modified(oid) => {
+                    let text = db.get_texture(oid).expect("Could not get texture");
+                    let texture = upload_texture(&mut self.device, text);
+                    self.stats.uploads += 1;
+                    self.textures.insert(oid, texture)
+                }
+                Change::Removed(oid) => self.textures.remove(&oid)
+            };
+            if let Some(old) = old {
+                self.device.delete_texture(old);
+                self.stats.frees += 1;
             }
         }
+        self.stats.textures = self.textures.len();
     }
 
+    /// Write the materials that were added or set since the last frame
+    /// to their rows of the material table, the rows of removed
+    /// materials are left for the next material with that index
     fn load_materials<RD: Renderable+GetIoState>(&mut self, db: &RD) {
-        for (oid, &mat) in db.material_iter() {
-            let update = if let Some(material) = self.material.get(&oid) {
-                Some(mat != material.material)
-            } else {None};
-
-            if update == Some(true) {
-                self.material.remove(&oid);
-            } else if update == Some(false) {
-                continue;
+        let current = db.material_iter().map(|(oid, _)| (oid, db.revision(oid)));
+        for change in self.material_tracker.update(current).into_iter() {
+            match change {
+                Change::Added(oid) | Change::Modified(oid) => {
+                    let mat = db.material(oid).expect("Could not get material");
+                    let idx = db.material_index(oid).expect("Could not get material index");
+                    self.materials.set(idx, MaterialTable::phong(mat));
+                    self.stats.uploads += 1;
+                }
+                Change::Removed(_) => self.stats.frees += 1
             }
+        }
 
-            let ka = mat.ka();
-            let kd = mat.kd();
-            let ks = mat.ks();
-            let material = &[SharedMaterial {
-                ka_color: [ka[0], ka[1], ka[2], 1.],
-                kd_color: [kd[0], kd[1], kd[2], 1.],
-                ks_color: [ks[0], ks[1], ks[2], 1.],
-                ka_use_texture: if mat.map_ka().is_some() {1} else {0},
-                kd_use_texture: if mat.map_kd().is_some() {1} else {0},
-                ks_use_texture: if mat.map_ks().is_some() {1} else {0},
-            }];
-            let buff = self.device.create_buffer_static(material);
-            self.material.insert(oid, RenderMaterial {
-                material: mat,
-                buffer: buff,
-                ka_texture: mat.map_ka(),
-                ks_texture: mat.map_ks(),
-                kd_texture: mat.map_kd(),
-            }); 
-        }       
-    }
-
-    fn load_batches<RD: Renderable+GetIoState>(&mut self, db: &RD) {
-        let scene = db.scene().expect("no scene set");
-        self.batch.clear();
-        self.shadow_batches.clear();
-        self.draw_batches.clear();
-        self.draw_back_batches.clear();
-
-        for (id, draw) in db.scene_iter(scene).inner_join_map(db.drawable_iter()) {
-            self.batch.insert((draw.geometry, draw.material, id));
-
-            if !self.shadow_batches.contains_key(&draw.geometry) {
-                let geo = db.geometry(draw.geometry).expect("failed to find geometry");
-                let vb = self.meshes.get(&geo.vb).expect("Could not get vertex buffer");
-
-                let batch: RefBatch<ShadowParams<device::GlResources>> = self.context.make_batch(
-                    &self.shadow_prog,
-                    self.shadow_data.clone(),
-                    &vb.mesh,
-                    gfx::Slice {
-                        start: geo.offset as u32,
-                        end: (geo.offset + geo.count) as u32,
-                        prim_type: gfx::PrimitiveType::TriangleList,
-                        kind: gfx::SliceKind::Index32(vb.index.clone(), 0)
-                    },
-                    &self.state
-                ).ok().expect("Failed to create batch.");
-                self.shadow_batches.insert(draw.geometry, batch);
-
-                let batch: RefBatch<Params<device::GlResources>> = self.context.make_batch(
-                    &self.prog,
-                    self.data.clone(),
-                    &vb.mesh,
-                    gfx::Slice {
-                        start: geo.offset as u32,
-                        end: (geo.offset + geo.count) as u32,
-                        prim_type: gfx::PrimitiveType::TriangleList,
-                        kind: gfx::SliceKind::Index32(vb.index.clone(), 0)
-                    },
-                    &self.state
-                ).ok().expect("Failed to create batch.");
-                self.draw_batches.insert(draw.geometry, batch);
-
-                let batch: RefBatch<ShadowParams<device::GlResources>> = self.context.make_batch(
-                    &self.back_prog,
-                    self.back_data.clone(),
-                    &vb.mesh,
-                    gfx::Slice {
-                        start: geo.offset as u32,
-                        end: (geo.offset + geo.count) as u32,
-                        prim_type: gfx::PrimitiveType::TriangleList,
-                        kind: gfx::SliceKind::Index32(vb.index.clone(), 0)
-                    },
-                    &self.back_state
-                ).ok().expect("Failed to create batch.");
-                self.draw_back_batches.insert(draw.geometry, batch);
+        let current = db.pbr_material_iter().map(|(oid, _)| (oid, db.revision(oid)));
+        for change in self.pbr_tracker.update(current).into_iter() {
+            match change {
+                Change::Added(oid) | Change::Modified(oid) => {
+                    let mat = db.pbr_material(oid).expect("Could not get material");
+                    let idx = db.material_index(oid).expect("Could not get material index");
+                    self.materials.set(idx, MaterialTable::pbr(mat));
+                    self.stats.uploads += 1;
+                }
+                Change::Removed(_) => self.stats.frees += 1
             }
         }
+
+        let old = self.materials.texture;
+        self.materials.upload(&mut self.device);
+        if self.materials.texture != old {
+            self.data.materials = (self.materials.texture, None);
+            self.pbr_data.materials = (self.materials.texture, None);
+        }
+        self.stats.materials = self.material_tracker.len() + self.pbr_tracker.len();
     }
 
-    fn fetch_matrix(&mut self) -> gfx::BufferHandle<device::GlResources, [[f32; 4]; 4]> {
-        let buffer = if let Some(buffer) = self.spare_matrix_buffers.pop() {
-            buffer
-        } else {
-            let x = self.device.create_buffer(512, gfx::BufferUsage::Static);
-            x
-        };
-        let clone = buffer.clone();
-        self.used_matrix_buffers.push(clone);
-        buffer
+    /// a copy of a vertex buffer's mesh that also reads the instance
+    /// buffer, starting from instance `first`
+    fn instanced_mesh(&self, vb: Entity, first: usize) -> render::mesh::Mesh<device::GlResources> {
+        let mut mesh = self.meshes.get(&vb).expect("Could not get vertex buffer").mesh.clone();
+        let instances = gfx::Mesh::from_format(self.instances.clone(), self.instance_capacity as u32);
+        let offset = first * std::mem::size_of::<InstanceVertex>();
+        mesh.attributes.extend(instances.attributes.into_iter().map(|mut a| {
+            a.format.offset += offset as gfx::attrib::Offset;
+            a.format.instance_rate = 1;
+            a
+        }));
+        mesh
     }
 
-    fn load_matrices<RD: Renderable+GetIoState>(&mut self, db: &RD) {
-        let max = 512;
-        let mut matrices = Vec::new();
-        matrices.reserve(512);
+    /// Plan the draw calls of a view, write its instances and make the
+    /// batches that draw them. The shadow casters are culled against
+    /// the shadow maps of `shadows` rather than the camera.
+    fn load_batches<RD: Renderable+GetIoState>(&mut self,
+                                               db: &RD,
+                                               view: &RenderView,
+                                               camera: &Camera,
+                                               shadows: &ShadowPlan) {
+        let visible = self.culler.visible(db, view.scene, camera);
+        let billboards = self.culler.billboards(db, view.scene, camera);
+        let casters = self.culler.casters(db, view.scene, camera, &shadows.views[..]);
+        let queues = RenderQueues::with_billboards(db, camera, visible, billboards);
+        self.plan = BatchPlan::new(db, &queues, &casters[..]);
+        self.load_instances(db, camera);
+
+        self.batches.clear();
+        self.shadow_batches.clear();
+        self.back_batches.clear();
+
+        for batch in self.plan.batches.clone().iter() {
+            let (vb, slice) = self.slice(db, batch.geometry);
+            let mesh = self.instanced_mesh(vb, batch.first);
+            let state = blend_state(batch.state.blend);
+            let maps = batch.state.textures;
+            let (textures, dummy, sampler) = (&self.textures, self.dummy_texture, self.sampler);
+            let texture = |i: usize| map_texture(textures, maps[i], dummy, sampler);
+
+            let draw = match batch.state.program {
+                Program::Phong => {
+                    let mut params = self.data.clone();
+                    params.ka_texture = texture(0);
+                    params.kd_texture = texture(1);
+                    params.ks_texture = texture(2);
+                    DrawBatch::Phong(self.context.make_batch(
+                        &self.prog, params, &mesh, slice.clone(), &state
+                    ).ok().expect("Failed to create batch."))
+                }
+                Program::Pbr => {
+                    let mut params = self.pbr_data.clone();
+                    params.base_color_map = texture(0);
+                    params.metallic_roughness_map = texture(1);
+                    params.normal_map = texture(2);
+                    params.occlusion_map = texture(3);
+                    params.emissive_map = texture(4);
+                    DrawBatch::Pbr(self.context.make_batch(
+                        &self.pbr_prog, params, &mesh, slice.clone(), &state
+                    ).ok().expect("Failed to create batch."))
+                }
+            };
+            self.batches.push(draw);
 
-        let mut shared_g = Vec::new();
-        let mut shared_gm = Vec::new();
+            if batch.state.blend.is_opaque() {
+                let back = self.context.make_batch(
+                    &self.back_prog, self.back_data.clone(), &mesh, slice, &self.back_state
+                ).ok().expect("Failed to create batch.");
+                self.back_batches.push(back);
+            }
+        }
 
-        for m in self.used_matrix_buffers.drain() {
-            self.spare_matrix_buffers.push(m);
+        for shadow in self.plan.shadows.clone().iter() {
+            let (vb, slice) = self.slice(db, shadow.geometry);
+            let mesh = self.instanced_mesh(vb, shadow.first);
+            let batch = self.context.make_batch(
+                &self.shadow_prog, self.shadow_data.clone(), &mesh, slice, &self.state
+            ).ok().expect("Failed to create batch.");
+            self.shadow_batches.push(batch);
         }
+    }
 
-        let mut mat = self.fetch_matrix();
+    /// the vertex buffer of a geometry and the slice of it that is drawn
+    fn slice<RD: Renderable+GetIoState>(&self, db: &RD, geometry: Entity)
+        -> (Entity, gfx::Slice<device::GlResources>) {
+        let geo = db.geometry(geometry).expect("failed to find geometry");
+        let vb = self.meshes.get(&geo.vb).expect("Could not get vertex buffer");
+        (geo.vb, gfx::Slice {
+            start: geo.offset as u32,
+            end: (geo.offset + geo.count) as u32,
+            prim_type: prim_type(geo.prim),
+            kind: gfx::SliceKind::Index32(vb.index.clone(), 0)
+        })
+    }
 
-        let mut last = None;
-        for &(g, m, id) in self.batch.clone().iter() {
-            last = if let Some((lg, lm, mut idx_gm, mut idx_g)) = last {
-                if (lg, lm) != (g, m) {
-                    shared_gm.push((lg, lm, mat.clone(), matrices.len()-idx_gm, idx_gm));
-                    idx_gm = matrices.len();
-                }
-                if lg != g {
-                    shared_g.push((lg, mat.clone(), matrices.len()-idx_g, idx_g));
-                    idx_g = matrices.len();
-                }
-            
-                if matrices.len() == max {
-                    shared_gm.push((lg, lm, mat.clone(), matrices.len()-idx_gm, idx_gm));
-                    shared_g.push((lg, mat.clone(), matrices.len()-idx_g, idx_g));
-                    self.device.update_buffer(mat.clone(), &matrices, 0);
-                    mat = self.fetch_matrix();
-                    matrices.clear();
-                    None
-                } else {
-                    Some((g, m, idx_gm, idx_g))
-                }
-            } else {
-                Some((g, m, 0, 0))
-            };
+    /// Write the plan's instances, the buffer is replaced by a larger
+    /// one if they do not fit
+    fn load_instances<RD: Renderable+GetIoState>(&mut self, db: &RD, camera: &Camera) {
+        let instances: Vec<InstanceVertex> = self.plan.instances.iter().map(|i| {
+            let m = match i.billboard {
+                Some(ref b) => billboard_matrix(camera, b),
+                None => db.position(i.id)
+            }.into_fixed();
+            InstanceVertex {
+                model0: m[0],
+                model1: m[1],
+                model2: m[2],
+                model3: m[3],
+                material: i.material as f32,
+                tint: i.billboard.map(|b| b.color).unwrap_or([1., 1., 1., 1.])
+            }
+        }).collect();
 
-            matrices.push(db.position(id).into_fixed());
+        if instances.len() > self.instance_capacity {
+            self.device.delete_buffer(self.instances.clone());
+            self.instance_capacity = instances.len().next_power_of_two();
+            self.instances = self.device.create_buffer(self.instance_capacity, gfx::BufferUsage::Dynamic);
         }
+        self.device.update_buffer(self.instances.clone(), &instances, 0);
+    }
 
-        if let Some((g, m, idx_gm, idx_g)) = last {
-            shared_gm.push((g, m, mat.clone(), matrices.len()-idx_gm, idx_gm));
-            shared_g.push((g, mat.clone(), matrices.len()-idx_g, idx_g));
-            self.device.update_buffer(mat.clone(), &matrices, 0)
+    /// make sure shadow map `idx` exists and is `resolution` texels wide
+    fn load_shadow_map(&mut self, idx: usize, resolution: u32) {
+        if idx < self.shadow_maps.len() {
+            if self.shadow_maps[idx].resolution != resolution {
+                let map = ShadowMap::new(&mut self.device, resolution);
+                let old = std::mem::replace(&mut self.shadow_maps[idx], map);
+                old.delete(&mut self.device);
+                self.stats.frees += 1;
+            }
+        } else {
+            let map = ShadowMap::new(&mut self.device, resolution);
+            self.shadow_maps.push(map);
         }
-
-        self.shared_geometry = shared_g;
-        self.shared_geometry_material = shared_gm;
     }
 
-    fn draw_shadow(&mut self, cam: &Camera) {
+    /// draw every shadow map in the plan and upload the matrices
+    /// used to read them
+    fn draw_shadows(&mut self, plan: &ShadowPlan) {
         let cdata = gfx::ClearData {
             color: [0.3, 0.3, 0.3, 1.0],
             depth: 2.0,
             stencil: 0,
         };
-        self.render.clear(cdata, gfx::DEPTH, &self.shadow_frame.clone());
-
-        let pos = cam.move_with_vector(&Vector3::new(0f32, 15., 0.));
-        let proj = cgmath::ortho(
-            -25.,  // + pos.x,
-             25.,  // + pos.x,
-            -25.,  // + pos.y,
-             25.,  // + pos.y,
-            -500., // + pos.z,
-             500., // + pos.z
-        );
 
-        let dir = self.data.light_normal;
+        let mut shared = SharedShadows {
+            shadow_mat: [[[0.; 4]; 4]; MAX_SHADOW_MAPS],
+            shadow_info: [[0.; 4]; MAX_SHADOW_MAPS]
+        };
+        let bias = Matrix4::from_fixed(SHADOW_BIAS_MAT);
+
+        for (idx, view) in plan.views.iter().enumerate() {
+            self.load_shadow_map(idx, view.resolution);
+            let frame = self.shadow_maps[idx].frame.clone();
+            let shadow_mat = self.shadow_maps[idx].shared_mat.clone();
+            self.render.clear(cdata, gfx::DEPTH, &frame);
+
+            self.device.update_buffer(shadow_mat.clone(), &[SharedMatrix {
+                proj_mat: view.projection.into_fixed(),
+                view_mat: view.view.into_fixed()
+            }], 0);
+
+            for (batch, shadow) in self.shadow_batches.iter_mut().zip(self.plan.shadows.iter()) {
+                batch.params.shared_mat = shadow_mat.raw();
+                self.frame_stats.draw(shadow.count, slice_triangles(&batch.slice));
+                self.render.draw_instanced(
+                    &(&*batch, &self.context),
+                    shadow.count as u32,
+                    0,
+                    &frame,
+                ).unwrap();
+            }
 
-        let view: Matrix4<f32> = cgmath::Matrix4::look_at(
-            &pos.add_v(&Vector3::new(dir[0], dir[1], dir[2])),
-            &pos,
-            &Vector3::new(0f32, 1., 0.)
-        );
+            let mat = bias.mul_m(&view.projection).mul_m(&view.view);
+            shared.shadow_mat[idx] = mat.into_fixed();
+            shared.shadow_info[idx] = [view.bias, view.split, 0., 0.];
+        }
 
-        let shadow_mat = &[SharedMatrix {
-            proj_mat: proj.into_fixed(),
-            view_mat: view.into_fixed()
-        }];
+        self.device.update_buffer(self.shadows.clone(), &[shared], 0);
+    }
 
-        self.device.update_buffer(self.shadow_shared_mat.clone(), shadow_mat, 0);
+    /// The texture the sky of a scene's environment is read from. A
+    /// latitude-longitude sky uses its own texture, other skies are
+    /// baked into one that is kept until the sky or its faces change.
+    fn sky_texture<RD: Renderable+GetIoState>(&mut self,
+                                              db: &RD,
+                                              scene: Scene,
+                                              env: &Environment)
+        -> gfx::TextureHandle<device::GlResources> {
+        let Scene(key) = scene;
+        let sky = match env.sky {
+            Some(Sky::Equirectangular(t)) => {
+                return self.textures.get(&t).map(|t| *t).unwrap_or(self.dummy_texture);
+            }
+            Some(sky) => sky,
+            None => return self.dummy_texture
+        };
 
-        for &(geo, matrix, len, offset) in self.shared_geometry.iter() {
-            let batch = self.shadow_batches.get_mut(&geo).expect("Missing draw");
-            batch.params.model = matrix.raw();
-            batch.params.offset = offset as i32;
-            self.render.draw_instanced(
-                &(&*batch, &self.context),
-                len as u32,
-                0,
-                &self.shadow_frame.clone(),
-            ).unwrap();
+        let revisions: Vec<u64> = match sky {
+            Sky::Cube(faces) => faces.iter().map(|&f| db.revision(f)).collect(),
+            _ => Vec::new()
+        };
+        let fresh = match self.baked_skies.get(&key) {
+            Some(&(ref baked, ref r, _)) => *baked == sky && *r == revisions,
+            None => false
         };
+        if !fresh {
+            let text = bake_equirectangular(db, &sky, SKY_BAKE_WIDTH, SKY_BAKE_WIDTH / 2);
+            let texture = upload_texture(&mut self.device, &text);
+            self.stats.uploads += 1;
+            if let Some((_, _, old)) = self.baked_skies.insert(key, (sky, revisions, texture)) {
+                self.device.delete_texture(old);
+                self.stats.frees += 1;
+            }
+        }
+        self.baked_skies.get(&key).unwrap().2
+    }
+
+    /// Free the baked skies of scenes that no longer have a sky to bake
+    fn load_skies<RD: Renderable+GetIoState>(&mut self, db: &RD) {
+        let stale: Vec<Entity> = self.baked_skies.keys()
+            .filter(|&&key| match db.environment(Scene(key)).sky {
+                Some(Sky::Equirectangular(_)) | None => true,
+                Some(_) => false
+            })
+            .map(|k| *k)
+            .collect();
+        for key in stale.iter() {
+            let (_, _, old) = self.baked_skies.remove(key).unwrap();
+            self.device.delete_texture(old);
+            self.stats.frees += 1;
+        }
+    }
 
+    /// make sure the frame for `target` exists and is `width` by `height`
+    fn load_view_frame(&mut self,
+                       target: Option<Entity>,
+                       width: u32,
+                       height: u32,
+                       format: gfx::tex::Format)
+        -> (render::target::Frame<device::GlResources>, gfx::TextureHandle<device::GlResources>) {
+
+        let reuse = match self.view_frames.get(&target) {
+            Some(f) => f.width == width && f.height == height && f.format == format,
+            None => false
+        };
+        if !reuse {
+            let frame = ViewFrame::new(&mut self.device, width, height, format);
+            if let Some(old) = self.view_frames.insert(target, frame) {
+                old.delete(&mut self.device);
+            }
+        }
+        let frame = self.view_frames.get(&target).unwrap();
+        (frame.frame.clone(), frame.texture)
     }
 
-    fn draw<RD: Renderable+GetIoState>(&mut self, db: &RD) {
-        let camera = db.camera().expect("no camera set");
+    /// Draw a view and submit it. A view with a target is drawn to its
+    /// texture, which replaces the texture materials see. A view that
+    /// covers part of the window is drawn offscreen and copied into
+    /// place. `window` is the frame the window's views are drawn to,
+    /// the HDR frame if there are post effects.
+    fn draw_view<RD: Renderable+GetIoState>(&mut self,
+                                            db: &RD,
+                                            view: &RenderView,
+                                            window: &render::target::Frame<device::GlResources>,
+                                            window_format: gfx::tex::Format) {
+        let (width, height) = (self.frame.width as u32, self.frame.height as u32);
+        let rect = match view.target {
+            Some(target) => match db.get_texture(target) {
+                Some(t) => (0, 0, t.width(), t.height()),
+                None => return
+            },
+            None => view.viewport.pixels(width, height)
+        };
+        let (_, _, w, h) = rect;
+        let partial = view.target.is_none() && rect != (0, 0, width, height);
+
+        let (frame, texture) = if view.target.is_some() {
+            let (frame, texture) = self.load_view_frame(view.target, w, h, gfx::tex::RGBA8);
+            (frame, Some(texture))
+        } else if partial {
+            let (frame, texture) = self.load_view_frame(None, w, h, window_format);
+            (frame, Some(texture))
+        } else {
+            (window.clone(), None)
+        };
+
+        let local = gfx::Rect { x: 0, y: 0, w: w as u16, h: h as u16 };
+        if partial && view.clear.color.is_none() {
+            // the view is drawn over what is already in the window
+            self.render.blit(window, gl_rect(rect, height),
+                             &frame, local, gfx::COLOR);
+        }
 
+        let mut mask = gfx::Mask::empty();
+        if view.clear.color.is_some() {
+            mask = mask | gfx::COLOR;
+        }
+        if view.clear.depth || partial {
+            mask = mask | gfx::DEPTH;
+        }
+        let color = view.clear.color.unwrap_or(Vector4::new(0., 0., 0., 1.));
         let cdata = gfx::ClearData {
-            color: [0.3, 0.3, 0.3, 1.0],
+            color: [color.x, color.y, color.z, color.w],
             depth: 1.0,
             stencil: 0,
         };
-        self.render.clear(cdata, gfx::COLOR | gfx::DEPTH, &self.frame);
+        self.render.clear(cdata, mask, &frame);
 
-        let (width, height) = db.get_io_state().size;
-        let camera_trans = db.position(camera);
-        let camera = Camera::new(width, height, camera_trans);
+        let camera = db.view_camera(view.camera, w, h);
+        let lights = Lights::new(db);
+        let shadows = ShadowPlan::new(&camera, &lights);
+        self.load_batches(db, view, &camera, &shadows);
+        self.draw(db, view, &frame, &camera, &lights, &shadows);
+
+        if partial {
+            self.render.blit(&frame, local, window,
+                             gl_rect(rect, height), gfx::COLOR);
+        }
+
+        // the matrices and lights are shared by every view, so the
+        // view has to be drawn before the next one updates them
+        self.device.submit(self.render.as_buffer());
+        self.render.reset();
+
+        if let (Some(target), Some(texture)) = (view.target, texture) {
+            self.textures.insert(target, texture);
+        }
+    }
+
+    fn draw<RD: Renderable+GetIoState>(&mut self,
+                                       db: &RD,
+                                       view: &RenderView,
+                                       frame: &render::target::Frame<device::GlResources>,
+                                       camera: &Camera,
+                                       lights: &Lights,
+                                       plan: &ShadowPlan) {
+        let env = db.environment(view.scene);
+        let sky = self.sky_texture(db, view.scene, &env);
+        let draw_sky = env.sky.is_some() && view.clear.depth;
+        let fog = db.fog(view.scene);
 
         let proj = camera.projection_matrix();
         let view = camera.view_matrix();
@@ -775,76 +2272,243 @@ impl RenderManagerContext {
 
         self.device.update_buffer(self.shared_mat.clone(), shared_mat, 0);
 
-        let mut light_color = [0., 0., 0., 1.];
-        let mut light_normal = [0., 0., 0., 1.];
-        for (key, light) in db.light_iter() {
-            match light {
-                &graphics::Light::Point(_) => {}
-                &graphics::Light::Directional(d) => {
-                    let n = d.normal();
-                    let n = Vector4::new(n.x, n.y, n.z, 0.);
-                    let n = db.position(key).mul_v(&n).normalize();
-                    let color = d.color().mul_s(d.intensity());
-                    light_color = [color.x, color.y, color.z, 1.];
-                    light_normal = [n.x, n.y, n.z, n.w];
-                }
-            }
+        self.device.update_buffer(self.lights.clone(), &[SharedLights::new(lights, plan)], 0);
+        self.device.update_buffer(self.environment.clone(), &[SharedEnvironment::new(&env)], 0);
+        let eye = camera.origin();
+        let eye = Vector3::new(eye.x, eye.y, eye.z);
+        self.device.update_buffer(self.fog.clone(), &[SharedFog::new(fog, eye)], 0);
+        self.draw_shadows(plan);
+
+        // views that keep the depth are drawn into another scene
+        if draw_sky {
+            let inv = proj.mul_m(&view).invert().unwrap_or(Matrix4::identity());
+            self.sky_batch.params.sky = (sky, Some(self.sampler));
+            self.sky_batch.params.inv_proj_view = inv.into_fixed();
+            self.sky_batch.params.intensity = env.intensity;
+            self.render.draw(&(&self.sky_batch, &self.context), frame).unwrap();
+            self.frame_stats.draw(1, 1);
         }
+        let sky = (sky, Some(self.sampler));
 
-        self.draw_shadow(&camera);
-
-        for &(geo, _, ref matrix, len, offset) in self.shared_geometry_material.iter() {
-            let batch = self.draw_back_batches.get_mut(&geo).expect("Missing draw");
-            batch.params.model = matrix.clone().raw();
-            batch.params.offset = offset as i32;
+        let shadow_maps: Vec<gfx::shade::TextureParam<device::GlResources>> =
+            (0..MAX_SHADOW_MAPS).map(|i| {
+                match self.shadow_maps.get(i) {
+                    Some(map) if i < plan.views.len() => (map.texture, Some(self.shadow_sampler)),
+                    _ => (self.dummy_texture, Some(self.shadow_sampler))
+                }
+            }).collect();
 
+        // blended drawables must not hide what is behind them
+        let opaque = self.plan.batches.iter().filter(|b| b.state.blend.is_opaque());
+        for (batch, planned) in self.back_batches.iter().zip(opaque) {
+            self.frame_stats.draw(planned.count, slice_triangles(&batch.slice));
             self.render.draw_instanced(
-                &(&*batch, &self.context),
-                len as u32,
+                &(batch, &self.context),
+                planned.count as u32,
                 0,
-                &self.frame.clone(),
+                frame,
             ).unwrap();
-        };
+        }
 
-        for &(geo, mat, ref matrix, len, offset) in self.shared_geometry_material.iter() {
-            let batch = self.draw_batches.get_mut(&geo).expect("Missing draw");
-            let mat = self.material.get(&mat).expect("Could not find material");
-            if let Some(ka) = mat.ka_texture {
-                batch.params.ka_texture =
-                    (*self.textures.get(&ka)
-                          .expect("Could not find texture"),
-                     Some(self.sampler));
+        for (batch, planned) in self.batches.iter_mut().zip(self.plan.batches.iter()) {
+            match *batch {
+                DrawBatch::Phong(ref mut batch) => {
+                    self.frame_stats.draw(planned.count, slice_triangles(&batch.slice));
+                    batch.params.shadow0 = shadow_maps[0];
+                    batch.params.shadow1 = shadow_maps[1];
+                    batch.params.shadow2 = shadow_maps[2];
+                    batch.params.shadow3 = shadow_maps[3];
+                    batch.params.sky = sky;
+                    self.render.draw_instanced(
+                        &(&*batch, &self.context),
+                        planned.count as u32,
+                        0,
+                        frame,
+                    ).unwrap();
+                }
+                DrawBatch::Pbr(ref mut batch) => {
+                    self.frame_stats.draw(planned.count, slice_triangles(&batch.slice));
+                    batch.params.eye = [eye.x, eye.y, eye.z, 1.];
+                    batch.params.shadow0 = shadow_maps[0];
+                    batch.params.shadow1 = shadow_maps[1];
+                    batch.params.shadow2 = shadow_maps[2];
+                    batch.params.shadow3 = shadow_maps[3];
+                    batch.params.sky = sky;
+                    self.render.draw_instanced(
+                        &(&*batch, &self.context),
+                        planned.count as u32,
+                        0,
+                        frame,
+                    ).unwrap();
+                }
             }
-            if let Some(kd) = mat.kd_texture {
-                batch.params.kd_texture =
-                    (*self.textures.get(&kd)
-                          .expect("Could not find texture"),
-                     Some(self.sampler));
+        }
+    }
+
+    /// copy the back buffer into a texture, the rows are flipped so
+    /// that the top of the image is first
+    fn read_frame(&self) -> Texture {
+        let (width, height) = (self.frame.width as u32, self.frame.height as u32);
+        let mut data: Vec<u8> = repeat(0).take((width * height * 4) as usize).collect();
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl::ReadBuffer(gl::BACK);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, width as i32, height as i32,
+                           gl::RGBA, gl::UNSIGNED_BYTE,
+                           data.as_mut_ptr() as *mut gl::types::GLvoid);
+        }
+        let mut texture = Texture::new(width, height, 4, data);
+        texture.flip();
+        texture
+    }
+
+    /// make sure the HDR frame and the two frames the effects are run
+    /// between match the size of the window
+    fn load_post_frames(&mut self) {
+        let (width, height) = (self.frame.width as u32, self.frame.height as u32);
+        let fits = match self.post_frames.get(0) {
+            Some(f) => f.width == width && f.height == height,
+            None => false
+        };
+        if !fits {
+            let mut frames = Vec::new();
+            for _ in 0..3 {
+                frames.push(ViewFrame::new(&mut self.device, width, height, HDR_FORMAT));
             }
-            if let Some(ks) = mat.ks_texture {
-                batch.params.ks_texture =
-                    (*self.textures.get(&ks)
-                          .expect("Could not find texture"),
-                     Some(self.sampler));
+            for old in std::mem::replace(&mut self.post_frames, frames).into_iter() {
+                old.delete(&mut self.device);
             }
-            batch.params.material = mat.buffer.raw();
-            batch.params.shadow = (self.shadow, Some(self.shadow_sampler));
-            batch.params.model = matrix.clone().raw();
-            batch.params.offset = offset as i32;
-            batch.params.light_normal = light_normal;
-            batch.params.light_color = light_color;
+        }
+    }
 
-            self.render.draw_instanced(
-                &(&*batch, &self.context),
-                len as u32,
-                0,
-                &self.frame.clone(),
-            ).unwrap();
-        };
+    /// Run the effects over the HDR frame, each effect reads what the
+    /// one before it drew and the last one draws to the window.
+    fn post_process(&mut self, effects: &[PostEffect]) {
+        let texel = [1. / self.frame.width as f32, 1. / self.frame.height as f32];
+        let mut source = self.post_frames[0].texture;
+        for (i, effect) in effects.iter().enumerate() {
+            let dest = &self.post_frames[1 + i % 2];
+            let frame = if i + 1 == effects.len() {
+                self.frame.clone()
+            } else {
+                dest.frame.clone()
+            };
 
+            let (params, extra) = post_uniforms(effect);
+            let batch = self.post_batches.get_mut(effect.name()).expect("Missing post effect");
+            batch.params.source = (source, Some(self.post_sampler));
+            batch.params.params = params;
+            batch.params.extra = extra;
+            batch.params.texel = texel;
+            self.render.draw(&(&*batch, &self.context), &frame).unwrap();
+            self.frame_stats.draw(1, 1);
+            source = dest.texture;
+        }
         self.device.submit(self.render.as_buffer());
         self.render.reset();
-        self.window.swap_buffers();
+    }
+
+    /// Draw the overlay quads over the window. The quads are uploaded
+    /// together and drawn in runs that share a texture. `last` is drawn
+    /// over the overlay if the game asks for the stats.
+    fn draw_overlay<RD: Renderable+GetIoState>(&mut self, db: &RD, last: &RenderStats) {
+        let (width, height) = (self.frame.width as u32, self.frame.height as u32);
+        let mut quads = db.overlay_quads(width, height);
+        if db.show_stats() {
+            quads.extend(sm_render::stats::overlay(last).quads(width, height).into_iter());
+        }
+        if quads.len() == 0 {
+            return;
+        }
+
+        let (fw, fh) = (width as f32, height as f32);
+        let quads = &quads[..min(quads.len(), MAX_OVERLAY_VERTICES / 6)];
+        let mut vertices = Vec::with_capacity(quads.len() * 6);
+        for q in quads.iter() {
+            let (r, uv) = (q.rect, q.uv);
+            let (x0, x1) = (r[0] / fw * 2. - 1., (r[0] + r[2]) / fw * 2. - 1.);
+            let (y0, y1) = (1. - r[1] / fh * 2., 1. - (r[1] + r[3]) / fh * 2.);
+            let corners = [(x0, y0, uv[0], uv[1]), (x0, y1, uv[0], uv[3]), (x1, y1, uv[2], uv[3]),
+                           (x0, y0, uv[0], uv[1]), (x1, y1, uv[2], uv[3]), (x1, y0, uv[2], uv[1])];
+            for &(x, y, u, v) in corners.iter() {
+                vertices.push(OverlayVertex { position: [x, y], uv: [u, v], color: q.color });
+            }
+        }
+        self.device.update_buffer(self.overlay_buffer.clone(), &vertices[..], 0);
+
+        let mut start = 0;
+        while start < quads.len() {
+            let texture = quads[start].texture;
+            let mut end = start + 1;
+            while end < quads.len() && quads[end].texture == texture {
+                end += 1;
+            }
+            let handle = match texture {
+                QuadTexture::Solid => Some(self.white_texture),
+                QuadTexture::Font => Some(self.font_texture),
+                QuadTexture::Texture(t) => self.textures.get(&t).map(|t| *t)
+            };
+            if let Some(handle) = handle {
+                let sampler = self.overlay_batch.params.texture.1;
+                self.overlay_batch.params.texture = (handle, sampler);
+                self.overlay_batch.slice.start = (start * 6) as u32;
+                self.overlay_batch.slice.end = (end * 6) as u32;
+                self.render.draw(&(&self.overlay_batch, &self.context), &self.frame).unwrap();
+                self.frame_stats.draw(1, (end - start) * 2);
+            }
+            start = end;
+        }
+        self.device.submit(self.render.as_buffer());
+        self.render.reset();
+    }
+
+    /// Draw the debug lines over each of the window's views, then the
+    /// debug text over the whole window. The lines are not clipped to
+    /// their view's viewport.
+    fn draw_debug<RD: Renderable+GetIoState>(&mut self, db: &RD, views: &[RenderView]) {
+        let debug = db.debug_draw();
+        let (width, height) = (self.frame.width as u32, self.frame.height as u32);
+
+        if debug.lines().len() != 0 {
+            let vertices: Vec<DebugVertex> = debug.lines().iter().flat_map(|l| {
+                vec![DebugVertex { position: [l.from.x, l.from.y, l.from.z], color: l.color },
+                     DebugVertex { position: [l.to.x, l.to.y, l.to.z], color: l.color }].into_iter()
+            }).collect();
+            self.debug_lines.load(&mut self.device, &vertices[..]);
+
+            for view in views.iter().filter(|v| v.target.is_none()) {
+                let rect = view.viewport.pixels(width, height);
+                let (_, _, w, h) = rect;
+                let camera = db.view_camera(view.camera, w, h);
+                let proj = viewport_matrix(rect, width, height).mul_m(&camera.projection_matrix());
+                self.debug_lines.set_matrices(&mut self.device, proj, camera.view_matrix());
+                self.render.draw(&(&self.debug_lines.batch, &self.context), &self.frame).unwrap();
+                self.frame_stats.draw(1, 0);
+                // the matrices are shared by the views
+                self.device.submit(self.render.as_buffer());
+                self.render.reset();
+            }
+        }
+
+        let rects = debug.text_rects();
+        if rects.len() != 0 {
+            let (fw, fh) = (width as f32, height as f32);
+            let mut vertices = Vec::with_capacity(rects.len() * 6);
+            for &(r, color) in rects.iter() {
+                let (x0, x1) = (r[0] / fw * 2. - 1., (r[0] + r[2]) / fw * 2. - 1.);
+                let (y0, y1) = (1. - r[1] / fh * 2., 1. - (r[1] + r[3]) / fh * 2.);
+                for &(x, y) in [(x0, y0), (x0, y1), (x1, y1), (x0, y0), (x1, y1), (x1, y0)].iter() {
+                    vertices.push(DebugVertex { position: [x, y, 0.], color: color });
+                }
+            }
+            self.debug_text.load(&mut self.device, &vertices[..]);
+            self.debug_text.set_matrices(&mut self.device, Matrix4::identity(), Matrix4::identity());
+            self.render.draw(&(&self.debug_text.batch, &self.context), &self.frame).unwrap();
+            self.frame_stats.draw(1, rects.len() * 2);
+            self.device.submit(self.render.as_buffer());
+            self.render.reset();
+        }
     }
 
     fn config<RD: Renderable+GetIoState>(&mut self, db: &RD) {
@@ -856,13 +2520,86 @@ impl RenderManagerContext {
     }
 
     fn update<RD: Renderable+GetIoState>(&mut self, db: RD) {
+        let last = self.frame_stats;
+        self.frame_stats = RenderStats::new();
+        self.frame_stats.frame = last.frame + 1;
+        let uploads = self.stats.uploads;
+
+        let start = time::precise_time_s();
         self.config(&db);
         self.load_meshes(&db);
         self.load_textures(&db);
         self.load_materials(&db);
-        self.load_batches(&db);
-        self.load_matrices(&db);
-        self.draw(&db);
+        self.load_skies(&db);
+        self.frame_stats.load_time = time::precise_time_s() - start;
+        let start = time::precise_time_s();
+
+        let effects = db.post_chain().effects();
+        let (window, format) = if effects.len() != 0 {
+            self.load_post_frames();
+            (self.post_frames[0].frame.clone(), HDR_FORMAT)
+        } else {
+            (self.frame.clone(), gfx::tex::RGBA8)
+        };
+
+        let cdata = gfx::ClearData {
+            color: [0.3, 0.3, 0.3, 1.0],
+            depth: 1.0,
+            stencil: 0,
+        };
+        self.render.clear(cdata, gfx::COLOR | gfx::DEPTH, &window);
+        self.device.submit(self.render.as_buffer());
+        self.render.reset();
+
+        let views = db.render_views();
+        for view in views.iter() {
+            self.draw_view(&db, view, &window, format);
+        }
+
+        if effects.len() != 0 {
+            self.post_process(&effects[..]);
+        }
+        self.draw_overlay(&db, &last);
+        self.draw_debug(&db, &views[..]);
+
+        // this is the time taken to build and submit the frame, the
+        // gpu may still be drawing it
+        self.frame_stats.draw_time = time::precise_time_s() - start;
+        self.frame_stats.uploads = self.stats.uploads - uploads;
+
+        if self.captures.len() != 0 {
+            let texture = self.read_frame();
+            for capture in self.captures.drain() {
+                let _ = capture.send(texture.clone());
+            }
+        }
+        self.window.swap_buffers();
+    }
+}
+
+impl<RD: Send+'static> RenderManager<RD> {
+    /// A render for `RenderTarget::Sink`, the updates it is sent are
+    /// dropped without drawing. Its captures return `None` and its
+    /// stats stay empty.
+    fn sink() -> RenderManager<RD> {
+        let (sender, recv) = channel();
+        // nothing reads the captures, `capture` drops them unanswered
+        let (capture_send, _) = channel();
+
+        let res = spawn(move || {
+            let recv: Receiver<RD> = recv;
+            for db in recv.iter() {
+                drop(db);
+            }
+        });
+
+        RenderManager {
+            channel: sender,
+            capture: capture_send,
+            resources: Arc::new(Mutex::new(ResourceStats::new())),
+            stats: Arc::new(Mutex::new(RenderStats::new())),
+            res: res
+        }
     }
 }
 
@@ -870,19 +2607,44 @@ impl<RD: Renderable+GetIoState+Send+'static> sm_render::Render<RD> for RenderMan
     fn update(&mut self, db: RD) {
         self.channel.send(db).unwrap();
     }
+
+    fn capture(&mut self) -> Capture {
+        let (send, capture) = Capture::new();
+        // a sink has no render thread to answer, the failed send
+        // drops `send` and `wait` returns `None`
+        let _ = self.capture.send(send);
+        capture
+    }
+
+    fn resources(&self) -> ResourceStats {
+        *self.resources.lock().unwrap()
+    }
+
+    fn stats(&self) -> RenderStats {
+        *self.stats.lock().unwrap()
+    }
 }
 
 #[cfg(feature="use_opencl")]
 impl<RD: Renderable+GetIoState+Send+'static> sm_render::RenderFactory<RD, RenderManager<RD>> for RenderFactory {
     fn init(self: Box<RenderFactory>,
-            io: &input::IOManager,
-            mut window: Window,
-            size: (i32, i32),
+            target: RenderTarget,
             _: Option<Arc<hl::Device>>) -> RenderManager<RD> {
+        let (io, mut window, size) = match target {
+            RenderTarget::Window(io, window, size) => (io, window, size),
+            RenderTarget::Sink => return RenderManager::sink(),
+            RenderTarget::Offscreen(_, _) => panic!("snowmew-render-gfx can not render offscreen")
+        };
 
         let (sender, recv) = channel();
+        let (capture_send, capture_recv) = channel();
+        let resources = Arc::new(Mutex::new(ResourceStats::new()));
+        let thread_resources = resources.clone();
+        let stats = Arc::new(Mutex::new(RenderStats::new()));
+        let thread_stats = stats.clone();
         window.make_context_current();
         let device = gfx::GlDevice::new(|s| io.get_proc_address(s));
+        gl::load_with(|s| io.get_proc_address(s));
         glfw::make_context_current(None);
 
         let (free_send, free_recv) = channel();
@@ -896,6 +2658,7 @@ impl<RD: Renderable+GetIoState+Send+'static> sm_render::RenderFactory<RD, Render
             let mut window = window;
             window.make_context_current();
             let recv: Receiver<RD> = recv;
+            let capture_recv: Receiver<Sender<Texture>> = capture_recv;
 
             let mut rc = RenderManagerContext::_new(device, window, size);
             loop {
@@ -916,27 +2679,50 @@ impl<RD: Renderable+GetIoState+Send+'static> sm_render::RenderFactory<RD, Render
                         Err(std::sync::mpsc::TryRecvError::Disconnected) => return,
                     }
                 }
+                while let Ok(capture) = capture_recv.try_recv() {
+                    rc.captures.push(capture);
+                }
                 rc.update(db);
+                *thread_resources.lock().unwrap() = rc.stats;
+                *thread_stats.lock().unwrap() = rc.frame_stats;
             }
         });
 
         RenderManager {
             channel: sender,
+            capture: capture_send,
+            resources: resources,
+            stats: stats,
             res: res
         }
     }
+
+    fn supports(&self, target: &RenderTarget) -> bool {
+        match *target {
+            RenderTarget::Offscreen(_, _) => false,
+            _ => true
+        }
+    }
 }
 
 #[cfg(not(feature="use_opencl"))]
 impl<RD: Renderable+GetIoState+Send+'static> sm_render::RenderFactory<RD, RenderManager<RD>> for RenderFactory {
-    fn init(self: Box<RenderFactory>,
-            io: &input::IOManager,
-            mut window: Window,
-            size: (i32, i32)) -> RenderManager<RD> {
+    fn init(self: Box<RenderFactory>, target: RenderTarget) -> RenderManager<RD> {
+        let (io, mut window, size) = match target {
+            RenderTarget::Window(io, window, size) => (io, window, size),
+            RenderTarget::Sink => return RenderManager::sink(),
+            RenderTarget::Offscreen(_, _) => panic!("snowmew-render-gfx can not render offscreen")
+        };
 
         let (sender, recv) = channel();
+        let (capture_send, capture_recv) = channel();
+        let resources = Arc::new(Mutex::new(ResourceStats::new()));
+        let thread_resources = resources.clone();
+        let stats = Arc::new(Mutex::new(RenderStats::new()));
+        let thread_stats = stats.clone();
         window.make_context_current();
         let device = device::GlDevice::new(|s| io.get_proc_address(s));
+        gl::load_with(|s| io.get_proc_address(s));
         glfw::make_context_current(None);
 
         let (free_send, free_recv) = channel();
@@ -950,6 +2736,7 @@ impl<RD: Renderable+GetIoState+Send+'static> sm_render::RenderFactory<RD, Render
             let mut window = window;
             window.make_context_current();
             let recv: Receiver<RD> = recv;
+            let capture_recv: Receiver<Sender<Texture>> = capture_recv;
 
             let mut rc = RenderManagerContext::_new(device, window, size);
             loop {
@@ -970,15 +2757,30 @@ impl<RD: Renderable+GetIoState+Send+'static> sm_render::RenderFactory<RD, Render
                         Err(std::sync::mpsc::TryRecvError::Disconnected) => return,
                     }
                 }
+                while let Ok(capture) = capture_recv.try_recv() {
+                    rc.captures.push(capture);
+                }
                 rc.update(db);
+                *thread_resources.lock().unwrap() = rc.stats;
+                *thread_stats.lock().unwrap() = rc.frame_stats;
             }
         });
 
         RenderManager {
             channel: sender,
+            capture: capture_send,
+            resources: resources,
+            stats: stats,
             res: res
         }
     }
+
+    fn supports(&self, target: &RenderTarget) -> bool {
+        match *target {
+            RenderTarget::Offscreen(_, _) => false,
+            _ => true
+        }
+    }
 }
 
 #[derive(Copy)]
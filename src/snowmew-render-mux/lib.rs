@@ -19,6 +19,7 @@
 #[cfg(feature="use_opencl")]
 extern crate opencl;
 extern crate "snowmew-render-gfx" as gfx;
+extern crate "snowmew-render-soft" as soft;
 extern crate "snowmew-core" as snowmew;
 extern crate "snowmew-position" as position;
 extern crate "snowmew-graphics" as graphics;
@@ -30,54 +31,75 @@ use opencl::hl::Device;
 #[cfg(feature="use_opencl")]
 use std::sync::Arc;
 
-use input::{Window, GetIoState};
+use input::GetIoState;
 
-use render::Renderable;
+use render::{Renderable, RenderTarget};
 
 impl<'r, RD: Renderable+GetIoState+Send+> render::Render<RD> for RenderMux<'r, RD> {
     fn update(&mut self, db: RD) {
         self.render.update(db)
     }
+
+    fn capture(&mut self) -> render::Capture {
+        self.render.capture()
+    }
+
+    fn resources(&self) -> render::ResourceStats {
+        self.render.resources()
+    }
+
+    fn stats(&self) -> render::RenderStats {
+        self.render.stats()
+    }
 }
 
 pub struct RenderMux<'r, RD> {
     render: Box<render::Render<RD> + 'r>
 }
 
+fn boxed<'r, RD, R: render::Render<RD> + 'r>(r: R) -> RenderMux<'r, RD> {
+    RenderMux {
+        render: Box::new(r) as Box<render::Render<RD> + 'r>
+    }
+}
+
+/// Picks a backend based on the target, windows are drawn to by the
+/// gfx backend, offscreen images by the software backend.
 #[cfg(feature="use_opencl")]
 impl<'r, RD: Renderable+GetIoState+Send+'static> render::RenderFactory<RD, RenderMux<'r, RD>> for RenderFactory {
     fn init(self: Box<RenderFactory>,
-            io: &input::IOManager,
-            window: Window,
-            size: (i32, i32),
+            target: RenderTarget,
             cl: Option<Arc<Device>>) -> RenderMux<'r, RD> {
-
-        let rm: RenderMux<RD> = {
-            let rf: Box<render::RenderFactory<RD, gfx::RenderManager<RD>>> = Box::new(gfx::RenderFactory::new());
-            let render: Box<gfx::RenderManager<RD>> = Box::new(rf.init(io, window, size, cl));
-            RenderMux {
-                render: render as Box<render::Render<RD>>
+        match target {
+            RenderTarget::Window(_, _, _) => {
+                let rf: Box<render::RenderFactory<RD, gfx::RenderManager<RD>>> = Box::new(gfx::RenderFactory::new());
+                boxed(rf.init(target, cl))
             }
-        };
-        rm
+            RenderTarget::Offscreen(_, _) => {
+                let rf: Box<render::RenderFactory<RD, soft::SoftRender>> = Box::new(soft::RenderFactory::new());
+                boxed(rf.init(target, cl))
+            }
+            RenderTarget::Sink => boxed(render::NullRender)
+        }
     }
 }
 
+/// Picks a backend based on the target, windows are drawn to by the
+/// gfx backend, offscreen images by the software backend.
 #[cfg(not(feature="use_opencl"))]
 impl<'r, RD: Renderable+GetIoState+Send+'static> render::RenderFactory<RD, RenderMux<'r, RD>> for RenderFactory {
-    fn init(self: Box<RenderFactory>,
-            io: &input::IOManager,
-            window: Window,
-            size: (i32, i32)) -> RenderMux<'r, RD> {
-
-        let rm: RenderMux<RD> = {
-            let rf: Box<render::RenderFactory<RD, gfx::RenderManager<RD>>> = Box::new(gfx::RenderFactory::new());
-            let render: Box<gfx::RenderManager<RD>> = Box::new(rf.init(io, window, size));
-            RenderMux {
-                render: render as Box<render::Render<RD>>
+    fn init(self: Box<RenderFactory>, target: RenderTarget) -> RenderMux<'r, RD> {
+        match target {
+            RenderTarget::Window(_, _, _) => {
+                let rf: Box<render::RenderFactory<RD, gfx::RenderManager<RD>>> = Box::new(gfx::RenderFactory::new());
+                boxed(rf.init(target))
+            }
+            RenderTarget::Offscreen(_, _) => {
+                let rf: Box<render::RenderFactory<RD, soft::SoftRender>> = Box::new(soft::RenderFactory::new());
+                boxed(rf.init(target))
             }
-        };
-        rm
+            RenderTarget::Sink => boxed(render::NullRender)
+        }
     }
 }
 
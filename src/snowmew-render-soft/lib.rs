@@ -0,0 +1,665 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![crate_name = "snowmew-render-soft"]
+#![crate_type = "lib"]
+#![feature(core, std_misc, collections)]
+
+#[cfg(feature="use_opencl")]
+extern crate opencl;
+extern crate cgmath;
+extern crate time;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as sm_render;
+extern crate "snowmew-input" as input;
+
+#[cfg(feature="use_opencl")]
+use std::sync::Arc;
+use std::cmp::min;
+use std::num::Float;
+use std::sync::mpsc::Sender;
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature="use_opencl")]
+use opencl::hl::Device;
+use cgmath::{Matrix, Matrix4, Vector, EuclideanVector, Vector3, Vector4};
+
+use snowmew::common::Entity;
+use position::Positions;
+use graphics::{Graphics, Texture, BlendMode};
+use graphics::geometry::Primative;
+use sm_render::{Renderable, RenderTarget, RenderView, Capture, Culler, RenderQueues, Lights, Surface};
+use sm_render::{Environment, Fog};
+use sm_render::{PostChain, PostImage, DebugDraw, OverlayQuad, QuadTexture, ResourceStats, RenderStats};
+use input::GetIoState;
+
+pub use raster::{Vertex, ATTRIBUTES};
+
+/// contains the triangle rasterizer
+pub mod raster;
+
+/// An in memory RGBA colour buffer with a matching depth buffer.
+/// Rows are stored from the top of the image down.
+#[derive(Clone)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    color: Vec<u8>,
+    depth: Vec<f32>
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v.max(0.).min(1.) * 255.).round() as u8
+}
+
+impl Frame {
+    pub fn new(width: u32, height: u32) -> Frame {
+        let size = (width * height) as usize;
+        Frame {
+            width: width,
+            height: height,
+            color: (0..size * 4).map(|_| 0).collect(),
+            depth: (0..size).map(|_| 1.).collect()
+        }
+    }
+
+    pub fn width(&self) -> u32 { self.width }
+    pub fn height(&self) -> u32 { self.height }
+
+    /// the colour buffer, 4 bytes per pixel
+    pub fn color<'a>(&'a self) -> &'a [u8] { &self.color }
+
+    /// the depth buffer, 1 is the far plane
+    pub fn depth<'a>(&'a self) -> &'a [f32] { &self.depth }
+
+    /// fill the colour buffer with `color` and reset the depth buffer
+    pub fn clear(&mut self, color: [f32; 4]) {
+        let c = [to_u8(color[0]), to_u8(color[1]), to_u8(color[2]), to_u8(color[3])];
+        for p in self.color.chunks_mut(4) {
+            for i in 0..4 {
+                p[i] = c[i];
+            }
+        }
+        for d in self.depth.iter_mut() {
+            *d = 1.;
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.color[i], self.color[i+1], self.color[i+2], self.color[i+3]]
+    }
+
+    pub fn depth_at(&self, x: u32, y: u32) -> f32 {
+        self.depth[(y * self.width + x) as usize]
+    }
+
+    /// copy the colour buffer into an RGBA texture
+    pub fn to_texture(&self) -> Texture {
+        Texture::new(self.width, self.height, 4, self.color.clone())
+    }
+
+    /// Clear part of the frame, the `x`, `y`, `width` and `height` of
+    /// `rect` are in pixels. The colour is left alone if it is `None`.
+    pub fn clear_rect(&mut self, rect: (u32, u32, u32, u32), color: Option<[f32; 4]>, depth: bool) {
+        let (x, y, w, h) = rect;
+        let c = color.map(|c| [to_u8(c[0]), to_u8(c[1]), to_u8(c[2]), to_u8(c[3])]);
+        for py in y..min(y + h, self.height) {
+            for px in x..min(x + w, self.width) {
+                let i = (py * self.width + px) as usize;
+                if let Some(c) = c {
+                    for j in 0..4 {
+                        self.color[i*4 + j] = c[j];
+                    }
+                }
+                if depth {
+                    self.depth[i] = 1.;
+                }
+            }
+        }
+    }
+
+    /// Blend `color` over the pixels with their centre inside `rect`, the
+    /// `x`, `y`, `width` and `height` in pixels. This does not write depth.
+    pub fn fill_rect(&mut self, rect: [f32; 4], color: [f32; 4]) {
+        let x0 = (rect[0] - 0.5).ceil().max(0.) as u32;
+        let y0 = (rect[1] - 0.5).ceil().max(0.) as u32;
+        let x1 = ((rect[0] + rect[2] - 0.5).ceil().max(0.) as u32).min(self.width);
+        let y1 = ((rect[1] + rect[3] - 0.5).ceil().max(0.) as u32).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x, y, color, 0., BlendMode::Alpha);
+            }
+        }
+    }
+
+    /// Run the effects of a chain on the colour buffer. The frame only
+    /// holds 0 to 1 colours, so tone mapping sees a clamped image.
+    pub fn post_process(&mut self, chain: &PostChain) {
+        let pixels = self.color.chunks(4).map(|p| {
+            [p[0] as f32 / 255., p[1] as f32 / 255., p[2] as f32 / 255., p[3] as f32 / 255.]
+        }).collect();
+        let image = chain.apply(&PostImage::new(self.width, self.height, pixels));
+        for (p, c) in self.color.chunks_mut(4).zip(image.pixels.iter()) {
+            for i in 0..4 {
+                p[i] = to_u8(c[i]);
+            }
+        }
+    }
+
+    /// write a fragment, this does not depth test
+    pub fn write(&mut self, x: u32, y: u32, color: [f32; 4], depth: f32) {
+        let i = (y * self.width + x) as usize;
+        self.depth[i] = depth;
+        for c in 0..4 {
+            self.color[i*4 + c] = to_u8(color[c]);
+        }
+    }
+
+    /// combine a fragment with the frame, blended fragments do not
+    /// write depth and leave the alpha of the frame alone
+    pub fn blend(&mut self, x: u32, y: u32, color: [f32; 4], depth: f32, mode: BlendMode) {
+        let i = (y * self.width + x) as usize;
+        let a = color[3];
+        match mode {
+            BlendMode::Opaque => return self.write(x, y, [color[0], color[1], color[2], 1.], depth),
+            BlendMode::Alpha => {
+                for c in 0..3 {
+                    let dst = self.color[i*4 + c] as f32 / 255.;
+                    self.color[i*4 + c] = to_u8(color[c] * a + dst * (1. - a));
+                }
+            }
+            BlendMode::Additive => {
+                for c in 0..3 {
+                    let dst = self.color[i*4 + c] as f32 / 255.;
+                    self.color[i*4 + c] = to_u8(color[c] * a + dst);
+                }
+            }
+        }
+    }
+}
+
+/// Where a material reads one of its colours from
+#[derive(Copy)]
+enum Channel<'a> {
+    Color([f32; 4]),
+    Texture(&'a Texture)
+}
+
+impl<'a> Channel<'a> {
+    fn new<RD: Graphics>(db: &'a RD,
+                         targets: &'a HashMap<Entity, Texture>,
+                         map: Option<Entity>,
+                         color: [f32; 3]) -> Channel<'a> {
+        match map.and_then(|t| targets.get(&t).or_else(|| db.get_texture(t))) {
+            Some(t) => Channel::Texture(t),
+            None => Channel::Color([color[0], color[1], color[2], 1.])
+        }
+    }
+
+    /// nearest neighbour sampling with the texture tiled
+    fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        let t = match *self {
+            Channel::Color(c) => return c,
+            Channel::Texture(t) => t
+        };
+        if t.width() == 0 || t.height() == 0 {
+            return [0., 0., 0., 1.];
+        }
+        let x = ((u - u.floor()) * t.width() as f32) as u32;
+        let y = ((v - v.floor()) * t.height() as f32) as u32;
+        t.texel(min(x, t.width() - 1), min(y, t.height() - 1))
+    }
+}
+
+/// draw a list of triangles or lines, other primitives are skipped
+fn primitives<F>(frame: &mut Frame,
+                 prim: Primative,
+                 vertices: &[Vertex],
+                 viewport: (u32, u32, u32, u32),
+                 blend: BlendMode,
+                 shade: &mut F)
+    where F: FnMut(&[f32; ATTRIBUTES]) -> [f32; 4] {
+
+    match prim {
+        Primative::Triangle => {
+            for tri in vertices.chunks(3) {
+                if tri.len() == 3 {
+                    raster::triangle(frame, &[tri[0], tri[1], tri[2]], viewport, blend, shade);
+                }
+            }
+        }
+        Primative::Line => {
+            for line in vertices.chunks(2) {
+                if line.len() == 2 {
+                    raster::line(frame, &line[0], &line[1], viewport, blend, true, shade);
+                }
+            }
+        }
+        _ => ()
+    }
+}
+
+/// A renderer that draws the scene on the cpu into a `Frame`. It uses
+/// the same light models as the gfx renderer but without shadows or
+/// normal maps, so it can be used on machines without a display or
+/// a GPU.
+pub struct SoftRender {
+    frame: Frame,
+    clear_color: [f32; 4],
+    captures: Vec<Sender<Texture>>,
+    culler: Culler,
+    /// the last image drawn by each view with a target
+    targets: HashMap<Entity, Texture>,
+    font: Texture,
+    stats: ResourceStats,
+    /// what was drawn in the last frame
+    frame_stats: RenderStats
+}
+
+impl SoftRender {
+    pub fn new(width: u32, height: u32) -> SoftRender {
+        SoftRender {
+            frame: Frame::new(width, height),
+            clear_color: [0.3, 0.3, 0.3, 1.0],
+            captures: Vec::new(),
+            culler: Culler::new(),
+            targets: HashMap::new(),
+            font: sm_render::font::atlas(),
+            stats: ResourceStats::new(),
+            frame_stats: RenderStats::new()
+        }
+    }
+
+    /// the result of the last render
+    pub fn frame<'a>(&'a self) -> &'a Frame { &self.frame }
+
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+    }
+
+    /// Draw the views of the game, see `Renderable::render_views`, run
+    /// the post chain over the result and draw the overlay. The frame is resized to
+    /// match the size in the io state.
+    pub fn render<RD: Renderable+GetIoState>(&mut self, db: &RD) {
+        let start = time::precise_time_s();
+        let last = self.frame_stats;
+        let mut stats = RenderStats::new();
+        stats.frame = last.frame + 1;
+
+        let (width, height) = db.get_io_state().size;
+        if self.frame.width() != width || self.frame.height() != height {
+            self.frame = Frame::new(width, height);
+        }
+        self.frame.clear(self.clear_color);
+
+        let lights = Lights::new(db);
+        let views = db.render_views();
+
+        // drop the images of targets that are no longer drawn to
+        let drawn: HashSet<Entity> = views.iter()
+            .filter_map(|v| v.target)
+            .filter(|&t| db.get_texture(t).is_some())
+            .collect();
+        let stale: Vec<Entity> = self.targets.keys()
+            .filter(|t| !drawn.contains(t))
+            .map(|t| *t)
+            .collect();
+        for t in stale.iter() {
+            self.targets.remove(t);
+            self.stats.frees += 1;
+        }
+
+        for view in views.iter() {
+            match view.target {
+                Some(target) => {
+                    let (w, h) = match db.get_texture(target) {
+                        Some(t) => (t.width(), t.height()),
+                        None => continue
+                    };
+                    let mut frame = Frame::new(w, h);
+                    frame.clear(self.clear_color);
+                    draw_view(db, &mut self.culler, &self.targets, &lights,
+                              view, &mut frame, (0, 0, w, h), &mut stats);
+                    if self.targets.insert(target, frame.to_texture()).is_none() {
+                        self.stats.uploads += 1;
+                        stats.uploads += 1;
+                    }
+                }
+                None => {
+                    let rect = view.viewport.pixels(width, height);
+                    draw_view(db, &mut self.culler, &self.targets, &lights,
+                              view, &mut self.frame, rect, &mut stats);
+                }
+            }
+        }
+
+        if !db.post_chain().is_empty() {
+            self.frame.post_process(db.post_chain());
+        }
+
+        // the stats of this frame are not known until it is drawn
+        let mut quads = db.overlay_quads(width, height);
+        if db.show_stats() {
+            quads.extend(sm_render::stats::overlay(last).quads(width, height).into_iter());
+        }
+        draw_overlay(db, &self.targets, &self.font, &quads, &mut self.frame);
+
+        // the debug draw is drawn over every view of the window
+        let debug = db.debug_draw();
+        for view in views.iter().filter(|v| v.target.is_none()) {
+            let rect = view.viewport.pixels(width, height);
+            draw_debug_lines(db, debug, view, &mut self.frame, rect);
+        }
+        for &(rect, color) in debug.text_rects().iter() {
+            self.frame.fill_rect(rect, color);
+        }
+        self.stats.textures = self.targets.len();
+
+        // there is nothing to load, the resources are read as they are drawn
+        stats.draw_time = time::precise_time_s() - start;
+        self.frame_stats = stats;
+    }
+}
+
+/// Blend the overlay quads over the frame, pixels are sampled from
+/// their texture at the pixel's centre and multiplied by the quad's colour
+fn draw_overlay<RD: Graphics>(db: &RD,
+                              targets: &HashMap<Entity, Texture>,
+                              font: &Texture,
+                              quads: &[OverlayQuad],
+                              frame: &mut Frame) {
+    for quad in quads.iter() {
+        let channel = match quad.texture {
+            QuadTexture::Solid => Channel::Color([1., 1., 1., 1.]),
+            QuadTexture::Font => Channel::Texture(font),
+            QuadTexture::Texture(t) => Channel::new(db, targets, Some(t), [1., 1., 1.])
+        };
+        let (x, y, w, h) = (quad.rect[0], quad.rect[1], quad.rect[2], quad.rect[3]);
+        let uv = quad.uv;
+        if w <= 0. || h <= 0. {
+            continue;
+        }
+        let x0 = (x - 0.5).ceil().max(0.) as u32;
+        let y0 = (y - 0.5).ceil().max(0.) as u32;
+        let x1 = ((x + w - 0.5).ceil().max(0.) as u32).min(frame.width());
+        let y1 = ((y + h - 0.5).ceil().max(0.) as u32).min(frame.height());
+        for py in y0..y1 {
+            let v = uv[1] + (py as f32 + 0.5 - y) / h * (uv[3] - uv[1]);
+            for px in x0..x1 {
+                let u = uv[0] + (px as f32 + 0.5 - x) / w * (uv[2] - uv[0]);
+                let t = channel.sample(u, v);
+                let mut color = [0.; 4];
+                for i in 0..4 {
+                    color[i] = t[i] * quad.color[i];
+                }
+                frame.blend(px, py, color, 0., BlendMode::Alpha);
+            }
+        }
+    }
+}
+
+/// Draw the debug lines as seen from a view, over what is in the frame
+fn draw_debug_lines<RD: Renderable>(db: &RD,
+                                    debug: &DebugDraw,
+                                    view: &RenderView,
+                                    frame: &mut Frame,
+                                    rect: (u32, u32, u32, u32)) {
+    let (_, _, width, height) = rect;
+    let camera = db.view_camera(view.camera, width, height);
+    let proj_view = camera.projection_matrix().mul_m(&camera.view_matrix());
+    let vertex = |p: Vector3<f32>| Vertex {
+        position: proj_view.mul_v(&Vector4::new(p.x, p.y, p.z, 1.)),
+        attributes: [0.; ATTRIBUTES]
+    };
+    for line in debug.lines().iter() {
+        let color = line.color;
+        raster::line(frame, &vertex(line.from), &vertex(line.to), rect,
+                     BlendMode::Alpha, false, &mut |_: &[f32; ATTRIBUTES]| color);
+    }
+}
+
+/// mix the fog of the scene, if it has any, into a shaded fragment
+fn fogged(fog: &Option<Fog>, color: [f32; 4], eye: Vector3<f32>, position: Vector3<f32>) -> [f32; 4] {
+    match *fog {
+        Some(ref fog) => {
+            let c = fog.apply(Vector3::new(color[0], color[1], color[2]), eye, position);
+            [c.x, c.y, c.z, color[3]]
+        }
+        None => color
+    }
+}
+
+/// Replace the colour of the view with the sky of its scene, the
+/// direction of each pixel is found by unprojecting its centre.
+fn draw_sky<RD: Renderable>(db: &RD,
+                            env: &Environment,
+                            frame: &mut Frame,
+                            rect: (u32, u32, u32, u32),
+                            proj_view: Matrix4<f32>) {
+    if env.sky.is_none() {
+        return;
+    }
+    let inv = match proj_view.invert() {
+        Some(inv) => inv,
+        None => return
+    };
+    let (x, y, w, h) = rect;
+    for py in y..min(y + h, frame.height) {
+        for px in x..min(x + w, frame.width) {
+            let nx = (px - x) as f32 + 0.5;
+            let ny = (py - y) as f32 + 0.5;
+            let nx = nx / w as f32 * 2. - 1.;
+            let ny = 1. - ny / h as f32 * 2.;
+            let near = inv.mul_v(&Vector4::new(nx, ny, -1., 1.));
+            let far = inv.mul_v(&Vector4::new(nx, ny, 1., 1.));
+            let d = far.truncate().div_s(far.w).sub_v(&near.truncate().div_s(near.w));
+            let c = env.sky_color(db, d.normalize());
+            let depth = frame.depth_at(px, py);
+            frame.write(px, py, [c.x, c.y, c.z, 1.], depth);
+        }
+    }
+}
+
+/// Draw a view into `rect` of the frame, textures in `targets` are
+/// used in place of the textures in the database.
+fn draw_view<RD: Renderable>(db: &RD,
+                             culler: &mut Culler,
+                             targets: &HashMap<Entity, Texture>,
+                             lights: &Lights,
+                             view: &RenderView,
+                             frame: &mut Frame,
+                             rect: (u32, u32, u32, u32),
+                             stats: &mut RenderStats) {
+    let clear = view.clear.color.map(|c| [c.x, c.y, c.z, c.w]);
+    frame.clear_rect(rect, clear, view.clear.depth);
+
+    let (_, _, width, height) = rect;
+    let camera = db.view_camera(view.camera, width, height);
+    let proj_view = camera.projection_matrix().mul_m(&camera.view_matrix());
+    let eye = camera.origin();
+    let eye = Vector3::new(eye.x, eye.y, eye.z);
+
+    let env = db.environment(view.scene);
+    let fog = db.fog(view.scene);
+    // views that keep the depth are drawn into another scene
+    if view.clear.depth {
+        draw_sky(db, &env, frame, rect, proj_view);
+    }
+
+    let visible = culler.visible(db, view.scene, &camera);
+    let billboards = culler.billboards(db, view.scene, &camera);
+    let queues = RenderQueues::with_billboards(db, &camera, visible, billboards);
+    for item in queues.iter() {
+        let draw = item.draw;
+        let tint = item.tint();
+        let geo = match db.geometry(draw.geometry) {
+            Some(geo) => geo,
+            None => continue
+        };
+        let triangles = match geo.prim {
+            Primative::Triangle => geo.count / 3,
+            Primative::Line => 0,
+            _ => continue
+        };
+        let pbr = db.pbr_material(draw.material);
+        let mat = db.material(draw.material);
+        if pbr.is_none() && mat.is_none() {
+            continue;
+        }
+        let vertices = match db.geometry_vertex_iter(draw.geometry) {
+            Some(v) => v,
+            None => continue
+        };
+
+        let model = item.model(db, &camera);
+        let mvp = proj_view.mul_m(&model);
+        let vertices: Vec<Vertex> = vertices.map(|(_, p, t, n)| {
+            let t = t.map(|t| *t).unwrap_or([0., 0.]);
+            let n = n.map(|n| {
+                model.mul_v(&Vector4::new(n[0], n[1], n[2], 0.)).truncate().normalize()
+            }).unwrap_or(Vector3::new(0., 0., 0.));
+            let p = Vector4::new(p[0], p[1], p[2], 1.);
+            let w = model.mul_v(&p);
+            Vertex {
+                position: mvp.mul_v(&p),
+                attributes: [t[0], t[1], n.x, n.y, n.z, w.x, w.y, w.z]
+            }
+        }).collect();
+
+        if let Some(pbr) = pbr {
+            let white = [1., 1., 1.];
+            let base = Channel::new(db, targets, pbr.base_color_map(), white);
+            let mr = Channel::new(db, targets, pbr.metallic_roughness_map(), white);
+            let occlusion = Channel::new(db, targets, pbr.occlusion_map(), white);
+            let emissive = Channel::new(db, targets, pbr.emissive_map(), white);
+            let base_color = pbr.base_color();
+            let emissive_color = pbr.emissive();
+
+            let mut shade = |a: &[f32; ATTRIBUTES]| {
+                let mut b = base.sample(a[0], a[1]);
+                for i in 0..4 {
+                    b[i] *= tint[i];
+                }
+                let m = mr.sample(a[0], a[1]);
+                let e = emissive.sample(a[0], a[1]);
+                let ao = 1. + pbr.occlusion_strength() * (occlusion.sample(a[0], a[1])[0] - 1.);
+                let n = Vector3::new(a[2], a[3], a[4]);
+                let surface = Surface {
+                    position: Vector3::new(a[5], a[6], a[7]),
+                    normal: if n.length2() > 0. { n.normalize() } else { n },
+                    base_color: Vector3::new(base_color[0] * b[0],
+                                             base_color[1] * b[1],
+                                             base_color[2] * b[2]),
+                    metallic: pbr.metallic() * m[2],
+                    roughness: pbr.roughness() * m[1]
+                };
+                let light = lights.shade_pbr(&surface, eye, 1.);
+                let light = [light.x, light.y, light.z];
+                let to_eye = eye.sub_v(&surface.position).normalize();
+                let ambient = env.shade_pbr(db, &surface, to_eye);
+                let ambient = [ambient.x, ambient.y, ambient.z];
+                let mut color = [0.; 4];
+                for i in 0..3 {
+                    color[i] = ambient[i] * ao + light[i] + emissive_color[i] * e[i] * tint[i];
+                }
+                color[3] = pbr.opacity() * b[3];
+                fogged(&fog, color, eye, surface.position)
+            };
+            primitives(frame, geo.prim, &vertices, rect, item.blend, &mut shade);
+            stats.draw(1, triangles);
+        } else if let Some(mat) = mat {
+            let ka_color = mat.ka();
+            let ka = Channel::new(db, targets, mat.map_ka(), ka_color);
+            let kd = Channel::new(db, targets, mat.map_kd(), ka_color);
+
+            let mut shade = |a: &[f32; ATTRIBUTES]| {
+                let mut ka = ka.sample(a[0], a[1]);
+                let mut kd = kd.sample(a[0], a[1]);
+                for i in 0..4 {
+                    ka[i] *= tint[i];
+                    kd[i] *= tint[i];
+                }
+                let n = Vector3::new(a[2], a[3], a[4]);
+                let light = lights.shade(Vector3::new(a[5], a[6], a[7]), n, 1.);
+                let light = [light.x, light.y, light.z];
+                let n = if n.length2() > 0. { n.normalize() } else { n };
+                let ambient = env.ambient.irradiance(n);
+                let ambient = [ambient.x, ambient.y, ambient.z];
+                let mut color = [0.; 4];
+                for i in 0..3 {
+                    color[i] = ka[i] * ambient[i] + kd[i] * light[i];
+                }
+                color[3] = mat.opacity() * kd[3];
+                fogged(&fog, color, eye, Vector3::new(a[5], a[6], a[7]))
+            };
+            primitives(frame, geo.prim, &vertices, rect, item.blend, &mut shade);
+            stats.draw(1, triangles);
+        }
+    }
+}
+
+impl<RD: Renderable+GetIoState> sm_render::Render<RD> for SoftRender {
+    fn update(&mut self, db: RD) {
+        self.render(&db);
+        for capture in self.captures.drain() {
+            let _ = capture.send(self.frame.to_texture());
+        }
+    }
+
+    fn capture(&mut self) -> Capture {
+        let (send, capture) = Capture::new();
+        self.captures.push(send);
+        capture
+    }
+
+    /// the software render only holds the images of view targets
+    fn resources(&self) -> ResourceStats {
+        self.stats
+    }
+
+    fn stats(&self) -> RenderStats {
+        self.frame_stats
+    }
+}
+
+/// Creates a `SoftRender` the size of the target. If the target is
+/// a window the window is not drawn to.
+#[derive(Copy)]
+pub struct RenderFactory;
+
+impl RenderFactory {
+    pub fn new() -> RenderFactory { RenderFactory }
+}
+
+#[cfg(feature="use_opencl")]
+impl<RD: Renderable+GetIoState> sm_render::RenderFactory<RD, SoftRender> for RenderFactory {
+    fn init(self: Box<RenderFactory>,
+            target: RenderTarget,
+            _: Option<Arc<Device>>) -> SoftRender {
+        let (w, h) = target.size();
+        SoftRender::new(w, h)
+    }
+}
+
+#[cfg(not(feature="use_opencl"))]
+impl<RD: Renderable+GetIoState> sm_render::RenderFactory<RD, SoftRender> for RenderFactory {
+    fn init(self: Box<RenderFactory>, target: RenderTarget) -> SoftRender {
+        let (w, h) = target.size();
+        SoftRender::new(w, h)
+    }
+}
@@ -0,0 +1,249 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::num::Float;
+
+use cgmath::{Vector, Vector4};
+
+use graphics::BlendMode;
+
+use Frame;
+
+/// the number of values interpolated across a triangle, the
+/// texture coordinate followed by the normal and the world position
+pub const ATTRIBUTES: usize = 8;
+
+/// A vertex after the vertex stage has been run
+#[derive(Copy, Clone)]
+pub struct Vertex {
+    /// position in clip space
+    pub position: Vector4<f32>,
+    pub attributes: [f32; ATTRIBUTES]
+}
+
+fn lerp(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let mut attributes = [0.; ATTRIBUTES];
+    for i in 0..ATTRIBUTES {
+        attributes[i] = a.attributes[i] + (b.attributes[i] - a.attributes[i]) * t;
+    }
+    Vertex {
+        position: a.position.add_v(&b.position.sub_v(&a.position).mul_s(t)),
+        attributes: attributes
+    }
+}
+
+/// Clip a triangle against the near plane, this can produce
+/// zero, one or two triangles.
+fn clip_near(tri: &[Vertex; 3]) -> Vec<[Vertex; 3]> {
+    let dist = |v: &Vertex| v.position.z + v.position.w;
+
+    let mut poly = Vec::with_capacity(4);
+    for i in 0..3 {
+        let a = &tri[i];
+        let b = &tri[(i + 1) % 3];
+        let (da, db) = (dist(a), dist(b));
+        if da >= 0. {
+            poly.push(*a);
+        }
+        if (da >= 0.) != (db >= 0.) {
+            poly.push(lerp(a, b, da / (da - db)));
+        }
+    }
+
+    let mut out = Vec::new();
+    if poly.len() >= 3 {
+        for i in 1..poly.len()-1 {
+            out.push([poly[0], poly[i], poly[i+1]]);
+        }
+    }
+    out
+}
+
+/// The screen space x, y and depth of a clip space position in the
+/// viewport, with 1/w for perspective correction
+fn to_screen(p: &Vector4<f32>, left: f32, top: f32, width: f32, height: f32) -> ([f32; 3], f32) {
+    let inv_w = 1. / p.w;
+    ([left + (p.x * inv_w + 1.) * 0.5 * width,
+      top + (1. - p.y * inv_w) * 0.5 * height,
+      p.z * inv_w * 0.5 + 0.5], inv_w)
+}
+
+fn edge(a: &[f32; 3], b: &[f32; 3], x: f32, y: f32) -> f32 {
+    (b[0] - a[0]) * (y - a[1]) - (b[1] - a[1]) * (x - a[0])
+}
+
+/// Rasterize a triangle into the `viewport` of the frame, the
+/// viewport is the `x`, `y`, `width` and `height` in pixels. `shade`
+/// is called with the perspective correct attributes of every fragment
+/// that passes the depth test, the result is combined with the frame
+/// by `blend`.
+pub fn triangle<F>(frame: &mut Frame,
+                   tri: &[Vertex; 3],
+                   viewport: (u32, u32, u32, u32),
+                   blend: BlendMode,
+                   shade: &mut F)
+    where F: FnMut(&[f32; ATTRIBUTES]) -> [f32; 4] {
+
+    for tri in clip_near(tri).iter() {
+        rasterize(frame, tri, viewport, blend, shade);
+    }
+}
+
+fn rasterize<F>(frame: &mut Frame,
+                tri: &[Vertex; 3],
+                viewport: (u32, u32, u32, u32),
+                blend: BlendMode,
+                shade: &mut F)
+    where F: FnMut(&[f32; ATTRIBUTES]) -> [f32; 4] {
+
+    let (vx, vy, vw, vh) = viewport;
+    let (left, top) = (vx as f32, vy as f32);
+    let (width, height) = (vw as f32, vh as f32);
+    let right = (left + width).min(frame.width() as f32);
+    let bottom = (top + height).min(frame.height() as f32);
+
+    let mut screen = [[0f32; 3]; 3];
+    let mut inv_w = [0f32; 3];
+    for i in 0..3 {
+        let (s, w) = to_screen(&tri[i].position, left, top, width, height);
+        screen[i] = s;
+        inv_w[i] = w;
+    }
+
+    let area = edge(&screen[0], &screen[1], screen[2][0], screen[2][1]);
+    if area == 0. || area.is_nan() {
+        return;
+    }
+
+    let min_x = screen.iter().fold(right, |m, s| m.min(s[0])).floor().max(left) as u32;
+    let max_x = screen.iter().fold(left, |m, s| m.max(s[0])).ceil().min(right) as u32;
+    let min_y = screen.iter().fold(bottom, |m, s| m.min(s[1])).floor().max(top) as u32;
+    let max_y = screen.iter().fold(top, |m, s| m.max(s[1])).ceil().min(bottom) as u32;
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(&screen[1], &screen[2], px, py) / area;
+            let w1 = edge(&screen[2], &screen[0], px, py) / area;
+            let w2 = edge(&screen[0], &screen[1], px, py) / area;
+            if w0 < 0. || w1 < 0. || w2 < 0. {
+                continue;
+            }
+
+            let depth = w0 * screen[0][2] + w1 * screen[1][2] + w2 * screen[2][2];
+            if depth < 0. || depth > 1. || depth > frame.depth_at(x, y) {
+                continue;
+            }
+
+            let (p0, p1, p2) = (w0 * inv_w[0], w1 * inv_w[1], w2 * inv_w[2]);
+            let q = p0 + p1 + p2;
+            let mut attributes = [0.; ATTRIBUTES];
+            for i in 0..ATTRIBUTES {
+                attributes[i] = (tri[0].attributes[i] * p0 +
+                                 tri[1].attributes[i] * p1 +
+                                 tri[2].attributes[i] * p2) / q;
+            }
+
+            let color = shade(&attributes);
+            frame.blend(x, y, color, depth, blend);
+        }
+    }
+}
+
+/// Rasterize a line into the `viewport` of the frame, with one
+/// fragment for each pixel along its longest axis inside the viewport. A line that is not
+/// depth tested is drawn over everything, it should be given a
+/// blended mode so it does not write depth either.
+pub fn line<F>(frame: &mut Frame,
+               a: &Vertex,
+               b: &Vertex,
+               viewport: (u32, u32, u32, u32),
+               blend: BlendMode,
+               depth_test: bool,
+               shade: &mut F)
+    where F: FnMut(&[f32; ATTRIBUTES]) -> [f32; 4] {
+
+    let dist = |v: &Vertex| v.position.z + v.position.w;
+    let (da, db) = (dist(a), dist(b));
+    if da < 0. && db < 0. {
+        return;
+    }
+    let (a, b) = if da < 0. {
+        (lerp(a, b, da / (da - db)), *b)
+    } else if db < 0. {
+        (*a, lerp(a, b, da / (da - db)))
+    } else {
+        (*a, *b)
+    };
+
+    let (vx, vy, vw, vh) = viewport;
+    let (left, top) = (vx as f32, vy as f32);
+    let (width, height) = (vw as f32, vh as f32);
+    let right = (left + width).min(frame.width() as f32);
+    let bottom = (top + height).min(frame.height() as f32);
+
+    let (sa, wa) = to_screen(&a.position, left, top, width, height);
+    let (sb, wb) = to_screen(&b.position, left, top, width, height);
+    let (dx, dy) = (sb[0] - sa[0], sb[1] - sa[1]);
+    if dx.is_nan() || dx.is_infinite() || dy.is_nan() || dy.is_infinite() {
+        return;
+    }
+
+    // the part of the line that is inside the viewport, as a range of t
+    let (mut t0, mut t1) = (0f32, 1f32);
+    let edges = [(-dx, sa[0] - left), (dx, right - sa[0]), (-dy, sa[1] - top), (dy, bottom - sa[1])];
+    for &(p, q) in edges.iter() {
+        if p == 0. {
+            if q < 0. {
+                return;
+            }
+        } else if p < 0. {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    if t0 >= t1 {
+        return;
+    }
+
+    // an end close to w=0 can put the line far off screen, it never
+    // needs more steps than the viewport has pixels across
+    let size = (right - left).max(bottom - top).max(1.);
+    let steps = (dx.abs().max(dy.abs()) * (t1 - t0)).ceil().max(1.).min(size);
+
+    for i in 0..steps as u32 {
+        let t = t0 + (t1 - t0) * (i as f32 + 0.5) / steps;
+        let x = sa[0] + (sb[0] - sa[0]) * t;
+        let y = sa[1] + (sb[1] - sa[1]) * t;
+        if x < left || x >= right || y < top || y >= bottom {
+            continue;
+        }
+        let (x, y) = (x as u32, y as u32);
+
+        let depth = sa[2] + (sb[2] - sa[2]) * t;
+        if depth_test && (depth < 0. || depth > 1. || depth > frame.depth_at(x, y)) {
+            continue;
+        }
+
+        let (p0, p1) = ((1. - t) * wa, t * wb);
+        let mut attributes = [0.; ATTRIBUTES];
+        for i in 0..ATTRIBUTES {
+            attributes[i] = (a.attributes[i] * p0 + b.attributes[i] * p1) / (p0 + p1);
+        }
+
+        let color = shade(&attributes);
+        frame.blend(x, y, color, depth, blend);
+    }
+}
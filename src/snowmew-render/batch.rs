@@ -0,0 +1,210 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::collections::HashMap;
+
+use snowmew::common::Entity;
+use graphics::{Graphics, Drawable, BlendMode, Billboard};
+use queue::{DrawItem, RenderQueues};
+
+/// The program a drawable's material is drawn with
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Program {
+    Phong,
+    Pbr
+}
+
+/// What has to be bound to draw a batch. Materials that only differ in
+/// their colours and factors share a state, those are read per instance
+/// through the material's index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BatchState {
+    pub program: Program,
+    pub blend: BlendMode,
+    /// the ka, kd and ks maps of a phong material or the base colour,
+    /// metallic-roughness, normal, occlusion and emissive maps of a
+    /// pbr material
+    pub textures: [Option<Entity>; 5]
+}
+
+impl BatchState {
+    /// the state of `material`, drawables without a material are
+    /// drawn as untextured phong
+    pub fn new<G: Graphics>(db: &G, material: Entity, blend: BlendMode) -> BatchState {
+        let (program, textures) = if let Some(m) = db.pbr_material(material) {
+            (Program::Pbr, [m.base_color_map(), m.metallic_roughness_map(),
+                            m.normal_map(), m.occlusion_map(), m.emissive_map()])
+        } else if let Some(m) = db.material(material) {
+            (Program::Phong, [m.map_ka(), m.map_kd(), m.map_ks(), None, None])
+        } else {
+            (Program::Phong, [None; 5])
+        };
+        BatchState {
+            program: program,
+            blend: blend,
+            textures: textures
+        }
+    }
+}
+
+/// A drawable's entry in the instance buffer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub id: Entity,
+    /// the `material_index` of the drawable's material
+    pub material: i32,
+    /// the billboard the instance draws, its model matrix and tint
+    /// come from this instead of the entity
+    pub billboard: Option<Billboard>
+}
+
+/// Instances `first..first+count` of a geometry drawn with one call
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Batch {
+    pub state: BatchState,
+    pub geometry: Entity,
+    pub first: usize,
+    pub count: usize
+}
+
+/// Instances `first..first+count` of a geometry drawn into the shadow
+/// maps, these are independent of the material
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowBatch {
+    pub geometry: Entity,
+    pub first: usize,
+    pub count: usize
+}
+
+/// The draw calls of a view. Every drawable is written to a single
+/// instance buffer in the order of `instances`, the batches are ranges
+/// of it so there is no limit on how many instances a batch can draw.
+///
+/// Opaque drawables are grouped by their state and then by geometry
+/// so a program or texture is bound once for every drawable that uses
+/// it, drawables keep their front to back order inside a batch.
+/// Blended drawables keep their back to front order, only neighbours
+/// with the same state and geometry are merged. The drawables that
+/// cast shadows are written a second time after the others, grouped
+/// by geometry alone. The casters come from `Culler::casters`, so
+/// drawables the camera can not see still cast shadows into the view.
+#[derive(Clone, Debug)]
+pub struct BatchPlan {
+    pub instances: Vec<Instance>,
+    pub batches: Vec<Batch>,
+    pub shadows: Vec<ShadowBatch>
+}
+
+impl BatchPlan {
+    pub fn new<G: Graphics>(db: &G,
+                            queues: &RenderQueues,
+                            casters: &[(Entity, Drawable)]) -> BatchPlan {
+        let mut plan = BatchPlan {
+            instances: Vec::new(),
+            batches: Vec::new(),
+            shadows: Vec::new()
+        };
+
+        // the state of every material is worked out once
+        let mut states = HashMap::new();
+        for item in queues.iter() {
+            let key = (item.draw.material, item.blend);
+            if !states.contains_key(&key) {
+                states.insert(key, BatchState::new(db, item.draw.material, item.blend));
+            }
+        }
+        let state = |item: &DrawItem| *states.get(&(item.draw.material, item.blend)).unwrap();
+
+        // sort_by is stable, the front to back order is kept
+        let mut opaque: Vec<(BatchState, &DrawItem)> =
+            queues.opaque.iter().map(|item| (state(item), item)).collect();
+        opaque.sort_by(|a, b| (a.0, a.1.draw.geometry).cmp(&(b.0, b.1.draw.geometry)));
+        let transparent: Vec<(BatchState, &DrawItem)> =
+            queues.transparent.iter().map(|item| (state(item), item)).collect();
+
+        for &(state, item) in opaque.iter().chain(transparent.iter()) {
+            plan.push(db, state, item);
+        }
+
+        // sort_by is stable, casters with the same geometry keep their order
+        let mut casters: Vec<&(Entity, Drawable)> = casters.iter()
+            .filter(|&&(_, draw)| !draw.no_shadow)
+            .collect();
+        casters.sort_by(|a, b| a.1.geometry.cmp(&b.1.geometry));
+        for &&(id, draw) in casters.iter() {
+            let first = plan.instances.len();
+            plan.instances.push(instance(db, id, draw.material, None));
+            let merge = match plan.shadows.last() {
+                Some(last) => last.geometry == draw.geometry,
+                None => false
+            };
+            if merge {
+                plan.shadows.last_mut().unwrap().count += 1;
+            } else {
+                plan.shadows.push(ShadowBatch {
+                    geometry: draw.geometry,
+                    first: first,
+                    count: 1
+                });
+            }
+        }
+
+        plan
+    }
+
+    /// append `item` to the last batch, or start a new one
+    fn push<G: Graphics>(&mut self, db: &G, state: BatchState, item: &DrawItem) {
+        let first = self.instances.len();
+        self.instances.push(instance(db, item.id, item.draw.material, item.billboard));
+        let merge = match self.batches.last() {
+            Some(last) => last.state == state && last.geometry == item.draw.geometry,
+            None => false
+        };
+        if merge {
+            self.batches.last_mut().unwrap().count += 1;
+        } else {
+            self.batches.push(Batch {
+                state: state,
+                geometry: item.draw.geometry,
+                first: first,
+                count: 1
+            });
+        }
+    }
+
+    /// the number of times the state has to be changed between the
+    /// batches, including setting it for the first one
+    pub fn state_changes(&self) -> usize {
+        let mut last = None;
+        let mut changes = 0;
+        for batch in self.batches.iter() {
+            if last != Some(batch.state) {
+                changes += 1;
+            }
+            last = Some(batch.state);
+        }
+        changes
+    }
+}
+
+fn instance<G: Graphics>(db: &G,
+                         id: Entity,
+                         material: Entity,
+                         billboard: Option<Billboard>) -> Instance {
+    Instance {
+        id: id,
+        material: db.material_index(material).unwrap_or(0),
+        billboard: billboard
+    }
+}
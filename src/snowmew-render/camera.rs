@@ -12,21 +12,158 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
+use std::num::Float;
+
 use cgmath::{Matrix, Matrix4, ToMatrix4};
 use cgmath::{Vector3, Vector4, Vector, EuclideanVector};
 use cgmath::{Point, Point3, Ray, Ray3};
-use cgmath::perspective;
+use cgmath::{perspective, ortho, frustum};
 use cgmath::deg;
 
 // use ovr;
 use ovr::{EyeRenderDescriptor, FovPort, Pose,};
 
+/// the vertical field of view of the default projection in degrees
+const FOV: f32 = 80.;
+const NEAR: f32 = 0.01;
+const FAR: f32 = 10000.;
+
+/// How a camera projects the world on to the screen. The width of
+/// the perspective and orthographic projections follow the aspect
+/// ratio of the view they are drawn to.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov` is the vertical field of view in degrees
+    Perspective {
+        fov: f32,
+        near: f32,
+        far: f32
+    },
+    /// `height` is the height of the view in world units
+    Orthographic {
+        height: f32,
+        near: f32,
+        far: f32
+    },
+    /// A perspective projection with the edges of the view at the
+    /// near plane given explicitly, the view does not have to be
+    /// centred on the camera. This ignores the aspect ratio.
+    OffAxis {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Projection {
+        Projection::perspective(FOV, NEAR, FAR)
+    }
+}
+
+impl Projection {
+    pub fn perspective(fov: f32, near: f32, far: f32) -> Projection {
+        Projection::Perspective {
+            fov: fov,
+            near: near,
+            far: far
+        }
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Projection {
+        Projection::Orthographic {
+            height: height,
+            near: near,
+            far: far
+        }
+    }
+
+    pub fn off_axis(left: f32, right: f32, bottom: f32, top: f32,
+                    near: f32, far: f32) -> Projection {
+        Projection::OffAxis {
+            left: left,
+            right: right,
+            bottom: bottom,
+            top: top,
+            near: near,
+            far: far
+        }
+    }
+
+    /// The distance to the near clipping plane
+    pub fn near(&self) -> f32 {
+        match *self {
+            Projection::Perspective { near, .. } => near,
+            Projection::Orthographic { near, .. } => near,
+            Projection::OffAxis { near, .. } => near
+        }
+    }
+
+    /// The distance to the far clipping plane
+    pub fn far(&self) -> f32 {
+        match *self {
+            Projection::Perspective { far, .. } => far,
+            Projection::Orthographic { far, .. } => far,
+            Projection::OffAxis { far, .. } => far
+        }
+    }
+
+    /// true if the projection is orthographic
+    pub fn is_orthographic(&self) -> bool {
+        match *self {
+            Projection::Orthographic { .. } => true,
+            _ => false
+        }
+    }
+
+    /// The left, right, bottom and top of the view at `distance` in
+    /// front of the camera, for a view with the `aspect` ratio.
+    pub fn extents(&self, aspect: f32, distance: f32) -> (f32, f32, f32, f32) {
+        match *self {
+            Projection::Perspective { fov, .. } => {
+                let y = (fov.to_radians() / 2.).tan() * distance;
+                let x = y * aspect;
+                (-x, x, -y, y)
+            }
+            Projection::Orthographic { height, .. } => {
+                let y = height / 2.;
+                let x = y * aspect;
+                (-x, x, -y, y)
+            }
+            Projection::OffAxis { left, right, bottom, top, near, .. } => {
+                let s = distance / near;
+                (left * s, right * s, bottom * s, top * s)
+            }
+        }
+    }
+
+    /// The projection matrix for a view with the `aspect` ratio
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective { fov, near, far } => {
+                perspective(deg(fov), aspect, near, far)
+            }
+            Projection::Orthographic { near, far, .. } => {
+                let (l, r, b, t) = self.extents(aspect, near);
+                ortho(l, r, b, t, near, far)
+            }
+            Projection::OffAxis { left, right, bottom, top, near, far } => {
+                frustum(left, right, bottom, top, near, far)
+            }
+        }
+    }
+}
+
 #[derive(Copy)]
 /// Camera can be used to do Camera like actions
 pub struct Camera {
     width: f32,
     height: f32,
-    transform: Matrix4<f32>
+    transform: Matrix4<f32>,
+    projection: Projection
 }
 
 #[derive(Copy)]
@@ -52,20 +189,54 @@ fn view_matrix(t: &Matrix4<f32>) -> Matrix4<f32> {
 }
 
 impl Camera {
-    /// Create a new camera from a position matrix
+    /// Create a new camera from a position matrix, the camera uses
+    /// the default perspective projection
     pub fn new(width: u32, height: u32, transform: Matrix4<f32>) -> Camera {
+        Camera::with_projection(width, height, transform, Projection::default())
+    }
+
+    /// Create a new camera from a position matrix and a projection
+    pub fn with_projection(width: u32,
+                           height: u32,
+                           transform: Matrix4<f32>,
+                           projection: Projection) -> Camera {
         Camera {
             width: width as f32,
             height: height as f32,
-            transform: transform
+            transform: transform,
+            projection: projection
         }
     }
 
-    /// Create a perspective matrix for the Camera
+    /// The projection of the camera
+    pub fn projection(&self) -> Projection { self.projection }
+
+    /// Create a projection matrix for the Camera
     pub fn projection_matrix(&self) -> Matrix4<f32> {
-        perspective(
-            deg(80f32), self.width / self.height, 0.01f32, 10000f32
-        )
+        self.projection.matrix(self.width / self.height)
+    }
+
+    /// The distance to the near clipping plane
+    pub fn near(&self) -> f32 { self.projection.near() }
+
+    /// The distance to the far clipping plane
+    pub fn far(&self) -> f32 { self.projection.far() }
+
+    /// The world space corners of the slice of the view frustum between
+    /// `near` and `far`. The four corners at `near` come first.
+    pub fn frustum_corners(&self, near: f32, far: f32) -> [Point3<f32>; 8] {
+        let iview = self.view_matrix().invert().expect("could not invert view matrix");
+        let aspect = self.width / self.height;
+
+        let mut corners = [Point3::new(0., 0., 0.); 8];
+        for (i, &d) in [near, far].iter().enumerate() {
+            let (l, r, b, t) = self.projection.extents(aspect, d);
+            for (j, &(x, y)) in [(l, b), (r, b), (r, t), (l, t)].iter().enumerate() {
+                let p = iview.mul_v(&Vector4::new(x, y, -d, 1.));
+                corners[i * 4 + j] = Point3::new(p.x / p.w, p.y / p.w, p.z / p.w);
+            }
+        }
+        corners
     }
 
     /// Create a view matrix for the Camera
@@ -85,7 +256,7 @@ impl Camera {
     /// This requires supplying the `fov`, `eye` and `pose` that is supplied pereye
     /// by `vr-rs`
     pub fn ovr(&self, fov: &FovPort, eye: &EyeRenderDescriptor, pose: &Pose) -> DrawMatrices {
-        let projection = fov.projection(0.01, 10000., true);
+        let projection = fov.projection(self.near(), self.far(), true);
         let view = self.transform.mul_m(&pose.orientation.to_matrix4());
         let view = view_matrix(&view).mul_m(&Matrix4::from_translation(&eye.view_adjust));
 
@@ -112,7 +283,9 @@ impl Camera {
     }
 
     /// Creates a Vector into the world from the point of view of the camera
-    /// this takes a pixel coordinate and turns it into a ray
+    /// this takes a pixel coordinate and turns it into a ray. The rays
+    /// of an orthographic camera are parallel and start on the plane of
+    /// the camera.
     pub fn pixel_ray(&self, x: i32, y: i32) -> Ray3<f32> {
         let ray_nds = Vector3::new(
             2. * x as f32 / self.width - 1.,
@@ -127,6 +300,12 @@ impl Camera {
         let iproj = proj.invert().expect("could not invert proj matrix");
 
         let ray_eye = iproj.mul_v(&ray_clip);
+        if self.projection.is_orthographic() {
+            let origin = iview.mul_v(&Vector4::new(ray_eye.x, ray_eye.y, 0., 1.));
+            let origin = Point3::new(origin.x / origin.w, origin.y / origin.w, origin.z / origin.w);
+            let dir = iview.mul_v(&Vector4::new(0., 0., -1., 0.));
+            return Ray::new(origin, Vector3::new(dir.x, dir.y, dir.z).normalize());
+        }
         let ray_eye = Vector4::new(ray_eye.x, ray_eye.y, -1., 0.);
 
         let ray_world = iview.mul_v(&ray_eye);
@@ -0,0 +1,184 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::sync::mpsc::{channel, Sender, Receiver, TryRecvError};
+use std::old_io::{IoResult, IoError, InvalidInput, OtherIoError};
+
+use image;
+use graphics::Texture;
+
+use {Render, ResourceStats, RenderStats};
+
+/// A request for a copy of a frame, see `Render::capture`. The image is
+/// RGBA with the rows stored from the top of the image down.
+pub struct Capture {
+    recv: Receiver<Texture>
+}
+
+impl Capture {
+    /// Create a capture, the render completes it by sending the
+    /// frame on the returned `Sender`.
+    pub fn new() -> (Sender<Texture>, Capture) {
+        let (send, recv) = channel();
+        (send, Capture { recv: recv })
+    }
+
+    /// A capture that has already failed, `wait` returns `None` straight
+    /// away. This is used by renders that can not read back what they
+    /// have drawn.
+    pub fn unsupported() -> Capture {
+        let (_, capture) = Capture::new();
+        capture
+    }
+
+    /// Block until the frame is ready, `None` is returned if the
+    /// render could not capture the frame.
+    pub fn wait(self) -> Option<Texture> {
+        self.recv.recv().ok()
+    }
+
+    /// Check if the frame is ready without blocking
+    pub fn try_wait(&self) -> Result<Texture, TryRecvError> {
+        self.recv.try_recv()
+    }
+}
+
+/// Save a texture as a png, the texture must be RGB or RGBA.
+pub fn save_png(texture: &Texture, path: &Path) -> IoResult<()> {
+    let color = match texture.depth() {
+        3 => image::ColorType::RGB(8),
+        4 => image::ColorType::RGBA(8),
+        _ => return Err(IoError {
+            kind: InvalidInput,
+            desc: "only RGB and RGBA textures can be saved",
+            detail: Some(format!("texture has {} components", texture.depth()))
+        })
+    };
+    image::save_buffer(path, texture.data(), texture.width(), texture.height(), color)
+}
+
+/// Wraps a render and captures a number of frames from it, saving
+/// each one to a numbered png (`00000.png`, `00001.png` ...) in a directory.
+/// Frames that could not be captured or saved are kept in `errors`.
+pub struct Recorder<R> {
+    render: R,
+    dir: Path,
+    remaining: u32,
+    next: u32,
+    pending: Vec<(u32, Capture)>,
+    errors: Vec<IoError>
+}
+
+impl<R> Recorder<R> {
+    /// Record the next `frames` frames that `render` draws into `dir`
+    pub fn new(render: R, dir: Path, frames: u32) -> Recorder<R> {
+        Recorder {
+            render: render,
+            dir: dir,
+            remaining: frames,
+            next: 0,
+            pending: Vec::new(),
+            errors: Vec::new()
+        }
+    }
+
+    /// start recording another `frames` frames, the numbering continues
+    /// from the last frame recorded
+    pub fn record(&mut self, frames: u32) {
+        self.remaining += frames;
+    }
+
+    /// true if every frame has been requested and saved
+    pub fn finished(&self) -> bool {
+        self.remaining == 0 && self.pending.len() == 0
+    }
+
+    /// Block until every requested frame has been saved
+    pub fn flush(&mut self) {
+        self.save(true);
+    }
+
+    /// the frames that failed so far, in the order they failed
+    pub fn errors<'a>(&'a self) -> &'a [IoError] {
+        &self.errors
+    }
+
+    fn save(&mut self, block: bool) {
+        let mut pending = Vec::new();
+        for (idx, capture) in self.pending.drain() {
+            let texture = if block {
+                capture.wait()
+            } else {
+                match capture.try_wait() {
+                    Ok(t) => Some(t),
+                    Err(TryRecvError::Empty) => {
+                        pending.push((idx, capture));
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => None
+                }
+            };
+
+            let path = self.dir.join(format!("{:05}.png", idx));
+            let result = match texture {
+                Some(t) => save_png(&t, &path),
+                None => Err(IoError {
+                    kind: OtherIoError,
+                    desc: "the frame could not be captured",
+                    detail: None
+                })
+            };
+            if let Err(mut e) = result {
+                e.detail = Some(match e.detail {
+                    Some(detail) => format!("{}: {}", path.display(), detail),
+                    None => format!("{}", path.display())
+                });
+                self.errors.push(e);
+            }
+        }
+        self.pending = pending;
+    }
+}
+
+impl<T, R: Render<T>> Render<T> for Recorder<R> {
+    fn update(&mut self, db: T) {
+        if self.remaining > 0 {
+            let capture = self.render.capture();
+            self.pending.push((self.next, capture));
+            self.next += 1;
+            self.remaining -= 1;
+        }
+        self.render.update(db);
+        self.save(false);
+    }
+
+    fn capture(&mut self) -> Capture {
+        self.render.capture()
+    }
+
+    fn resources(&self) -> ResourceStats {
+        self.render.resources()
+    }
+
+    fn stats(&self) -> RenderStats {
+        self.render.stats()
+    }
+}
+
+#[unsafe_destructor]
+impl<R> Drop for Recorder<R> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
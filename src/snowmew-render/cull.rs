@@ -0,0 +1,261 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+use std::collections::HashMap;
+use std::f32;
+use std::num::Float;
+
+use cgmath::{Matrix, Matrix4, Vector, EuclideanVector, Vector3, Vector4};
+use cgmath::{Point, Point3};
+use collect::iter::{OrderedMapIterator, OrderedSetIterator};
+
+use snowmew::common::{Common, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Drawable, Billboard};
+use camera::Camera;
+use queue::billboard_matrix;
+use shadow::ShadowView;
+use Renderable;
+
+/// A bounding sphere
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+    pub center: Point3<f32>,
+    pub radius: f32
+}
+
+impl Bounds {
+    /// The bounds of a geometry in its own space, `None` if the
+    /// geometry or its vertex buffer does not exist.
+    pub fn from_geometry<G: Graphics>(db: &G, geometry: Entity) -> Option<Bounds> {
+        let vertices = match db.geometry_vertex_iter(geometry) {
+            Some(v) => v,
+            None => return None
+        };
+
+        let mut min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        let mut points = Vec::new();
+        for (_, p, _, _) in vertices {
+            let p = Vector3::new(p[0], p[1], p[2]);
+            min = Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+            points.push(p);
+        }
+        if points.len() == 0 {
+            return None;
+        }
+
+        let center = min.add_v(&max).div_s(2.);
+        let radius = points.iter().fold(0f32, |r, p| r.max(p.sub_v(&center).length()));
+        Some(Bounds {
+            center: Point3::from_vec(&center),
+            radius: radius
+        })
+    }
+
+    /// Move the bounds into the space of `mat`, the radius is scaled
+    /// by the largest scale of the matrix.
+    pub fn transform(&self, mat: &Matrix4<f32>) -> Bounds {
+        let c = mat.mul_v(&Vector4::new(self.center.x, self.center.y, self.center.z, 1.));
+        let scale = mat.x.truncate().length()
+                       .max(mat.y.truncate().length())
+                       .max(mat.z.truncate().length());
+        Bounds {
+            center: Point3::new(c.x / c.w, c.y / c.w, c.z / c.w),
+            radius: self.radius * scale
+        }
+    }
+}
+
+/// The six planes of a view frustum, each plane points into the frustum
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    planes: [Vector4<f32>; 6]
+}
+
+impl Frustum {
+    /// Extract the planes from a projection matrix multiplied by a view matrix
+    pub fn from_matrix(mat: &Matrix4<f32>) -> Frustum {
+        let row = |i: usize| Vector4::new(mat.x[i], mat.y[i], mat.z[i], mat.w[i]);
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let normalize = |p: Vector4<f32>| p.div_s(p.truncate().length());
+        Frustum {
+            planes: [
+                normalize(r3.add_v(&r0)),
+                normalize(r3.sub_v(&r0)),
+                normalize(r3.add_v(&r1)),
+                normalize(r3.sub_v(&r1)),
+                normalize(r3.add_v(&r2)),
+                normalize(r3.sub_v(&r2))
+            ]
+        }
+    }
+
+    /// The frustum of the camera
+    pub fn new(camera: &Camera) -> Frustum {
+        Frustum::from_matrix(&camera.projection_matrix().mul_m(&camera.view_matrix()))
+    }
+
+    /// The volume a shadow map is drawn from
+    pub fn from_shadow(view: &ShadowView) -> Frustum {
+        Frustum::from_matrix(&view.projection.mul_m(&view.view))
+    }
+
+    /// Is any part of the sphere inside of the frustum
+    pub fn intersects(&self, bounds: &Bounds) -> bool {
+        let c = bounds.center;
+        self.planes.iter().all(|p| {
+            p.x * c.x + p.y * c.y + p.z * c.z + p.w >= -bounds.radius
+        })
+    }
+}
+
+/// What the cached bounds of a geometry were calculated from, the
+/// vertex buffer with its revision and the range of indices
+type BoundsKey = (Entity, u64, usize, usize);
+
+/// Decides which drawables of a scene a render should draw. The bounds
+/// of each geometry are calculated the first time it is seen and kept
+/// until its vertex buffer or range changes, so a render should keep
+/// its `Culler` between frames.
+pub struct Culler {
+    bounds: HashMap<Entity, (BoundsKey, Option<Bounds>)>
+}
+
+impl Culler {
+    pub fn new() -> Culler {
+        Culler {
+            bounds: HashMap::new()
+        }
+    }
+
+    /// The bounds of a geometry in its own space. The bounds are found
+    /// again if the geometry or its vertex buffer has been replaced.
+    pub fn bounds<G: Graphics>(&mut self, db: &G, geometry: Entity) -> Option<Bounds> {
+        let key = match db.geometry(geometry) {
+            Some(geo) => (geo.vb, db.revision(geo.vb), geo.offset, geo.count),
+            None => {
+                self.bounds.remove(&geometry);
+                return None;
+            }
+        };
+        if let Some(&(cached, bounds)) = self.bounds.get(&geometry) {
+            if cached == key {
+                return bounds;
+            }
+        }
+        let bounds = Bounds::from_geometry(db, geometry);
+        self.bounds.insert(geometry, (key, bounds));
+        bounds
+    }
+
+    /// Forget the cached bounds
+    pub fn clear(&mut self) {
+        self.bounds.clear();
+    }
+
+    /// The drawables of `scene` that can be seen by the camera. The
+    /// geometry of the drawables with a `Lod` is replaced with the level
+    /// picked by their distance from the camera, drawables past their
+    /// last level are left out. Geometries without bounds are kept.
+    pub fn visible<RD: Renderable>(&mut self,
+                                   db: &RD,
+                                   scene: Scene,
+                                   camera: &Camera) -> Vec<(Entity, Drawable)> {
+        self.inside(db, scene, camera, &[Frustum::new(camera)], false)
+    }
+
+    /// The drawables of `scene` that cast a shadow into any of the
+    /// shadow `views`, whether or not the camera can see them. The
+    /// level of detail is picked by the distance from the camera like
+    /// `visible` does, so the shadow matches what is drawn.
+    pub fn casters<RD: Renderable>(&mut self,
+                                   db: &RD,
+                                   scene: Scene,
+                                   camera: &Camera,
+                                   views: &[ShadowView]) -> Vec<(Entity, Drawable)> {
+        let frustums: Vec<Frustum> = views.iter().map(|v| Frustum::from_shadow(v)).collect();
+        if frustums.len() == 0 {
+            return Vec::new();
+        }
+        self.inside(db, scene, camera, &frustums[..], true)
+    }
+
+    /// The billboards of `scene` that can be seen by the camera, each
+    /// with the geometry and material of its `BillboardSet`. Billboards
+    /// never cast shadows.
+    pub fn billboards<RD: Renderable>(&mut self,
+                                      db: &RD,
+                                      scene: Scene,
+                                      camera: &Camera) -> Vec<(Entity, Drawable, Billboard)> {
+        let frustum = Frustum::new(camera);
+
+        let mut visible = Vec::new();
+        for (id, set) in db.scene_iter(scene).inner_join_map(db.billboard_set_iter()) {
+            let draw = Drawable {
+                geometry: set.geometry,
+                material: set.material,
+                no_shadow: true
+            };
+            let bounds = self.bounds(db, set.geometry);
+            for b in set.billboards.iter() {
+                if let Some(bounds) = bounds {
+                    if !frustum.intersects(&bounds.transform(&billboard_matrix(camera, b))) {
+                        continue;
+                    }
+                }
+                visible.push((id, draw, *b));
+            }
+        }
+        visible
+    }
+
+    /// the drawables that are inside any of the `frustums`
+    fn inside<RD: Renderable>(&mut self,
+                              db: &RD,
+                              scene: Scene,
+                              camera: &Camera,
+                              frustums: &[Frustum],
+                              casters: bool) -> Vec<(Entity, Drawable)> {
+        let eye = camera.origin();
+
+        let mut inside = Vec::new();
+        for (id, draw) in db.scene_iter(scene).inner_join_map(db.drawable_iter()) {
+            let mut draw = *draw;
+            if casters && draw.no_shadow {
+                continue;
+            }
+            let model = db.position(id);
+
+            if let Some(lod) = db.lod(id) {
+                let origin = Point3::new(model.w.x, model.w.y, model.w.z);
+                match lod.select(origin.sub_p(&eye).length()) {
+                    Some(geo) => draw.geometry = geo,
+                    None => continue
+                }
+            }
+
+            if let Some(bounds) = self.bounds(db, draw.geometry) {
+                let bounds = bounds.transform(&model);
+                if !frustums.iter().any(|f| f.intersects(&bounds)) {
+                    continue;
+                }
+            }
+            inside.push((id, draw));
+        }
+        inside
+    }
+}
@@ -0,0 +1,184 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+
+use std::f32::consts::PI;
+use std::num::Float;
+
+use cgmath::{Matrix, Matrix4, Vector, Vector3, Vector4, Ray3};
+
+use snowmew::game::Game;
+use font;
+use Renderable;
+
+/// the number of segments in each circle of a debug sphere
+pub const SPHERE_SEGMENTS: usize = 16;
+
+/// A world space line, debug lines are drawn over the scene
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct DebugLine {
+    pub from: Vector3<f32>,
+    pub to: Vector3<f32>,
+    pub color: [f32; 4]
+}
+
+/// Text drawn in screen space, `x` and `y` are the top left corner
+/// in pixels. Each pixel of the font is `scale` pixels wide.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct DebugText {
+    pub x: f32,
+    pub y: f32,
+    pub scale: u32,
+    pub text: String,
+    pub color: [f32; 4]
+}
+
+/// Lines and text that are drawn for one frame. Games add to it while
+/// they step, the shapes are broken into lines as they are added.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct DebugDraw {
+    lines: Vec<DebugLine>,
+    text: Vec<DebugText>
+}
+
+impl DebugDraw {
+    pub fn new() -> DebugDraw {
+        DebugDraw {
+            lines: Vec::new(),
+            text: Vec::new()
+        }
+    }
+
+    /// remove everything
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.text.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.len() == 0 && self.text.len() == 0
+    }
+
+    pub fn lines(&self) -> &[DebugLine] { &self.lines }
+
+    pub fn text(&self) -> &[DebugText] { &self.text }
+
+    pub fn line(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: [f32; 4]) {
+        self.lines.push(DebugLine {
+            from: from,
+            to: to,
+            color: color
+        });
+    }
+
+    /// a ray `length` units long, like the ones from `Camera::pixel_ray`
+    pub fn ray(&mut self, ray: &Ray3<f32>, length: f32, color: [f32; 4]) {
+        let from = Vector3::new(ray.origin.x, ray.origin.y, ray.origin.z);
+        self.line(from, from.add_v(&ray.direction.mul_s(length)), color);
+    }
+
+    /// the twelve edges of an axis aligned box
+    pub fn aabb(&mut self, min: Vector3<f32>, max: Vector3<f32>, color: [f32; 4]) {
+        let corner = |i: usize| {
+            Vector3::new(if i & 1 == 0 { min.x } else { max.x },
+                         if i & 2 == 0 { min.y } else { max.y },
+                         if i & 4 == 0 { min.z } else { max.z })
+        };
+        for i in 0..8 {
+            // connect each corner to the corners one axis above it
+            for &axis in [1, 2, 4].iter() {
+                if i & axis == 0 {
+                    self.line(corner(i), corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    /// a circle around each axis
+    pub fn sphere(&mut self, center: Vector3<f32>, radius: f32, color: [f32; 4]) {
+        let point = |axis: usize, i: usize| {
+            let a = i as f32 * 2. * PI / SPHERE_SEGMENTS as f32;
+            let (s, c) = (a.sin() * radius, a.cos() * radius);
+            let offset = match axis {
+                0 => Vector3::new(0., c, s),
+                1 => Vector3::new(c, 0., s),
+                _ => Vector3::new(c, s, 0.)
+            };
+            center.add_v(&offset)
+        };
+        for axis in 0..3 {
+            for i in 0..SPHERE_SEGMENTS {
+                self.line(point(axis, i), point(axis, i + 1), color);
+            }
+        }
+    }
+
+    /// the x, y and z axes of a transform in red, green and blue
+    pub fn axes(&mut self, transform: &Matrix4<f32>, size: f32) {
+        let point = |v: Vector4<f32>| transform.mul_v(&v).truncate();
+        let origin = point(Vector4::new(0., 0., 0., 1.));
+        self.line(origin, point(Vector4::new(size, 0., 0., 1.)), [1., 0., 0., 1.]);
+        self.line(origin, point(Vector4::new(0., size, 0., 1.)), [0., 1., 0., 1.]);
+        self.line(origin, point(Vector4::new(0., 0., size, 1.)), [0., 0., 1., 1.]);
+    }
+
+    /// text with its top left at `x`, `y` in pixels, at the font's size
+    pub fn text_at(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        self.scaled_text_at(x, y, 1, text, color);
+    }
+
+    pub fn scaled_text_at(&mut self, x: f32, y: f32, scale: u32, text: &str, color: [f32; 4]) {
+        self.text.push(DebugText {
+            x: x,
+            y: y,
+            scale: scale,
+            text: text.to_string(),
+            color: color
+        });
+    }
+
+    /// The pixel rectangles, `x`, `y`, `width` and `height`, that make
+    /// up the text with their colour
+    pub fn text_rects(&self) -> Vec<([f32; 4], [f32; 4])> {
+        let mut rects = Vec::new();
+        for t in self.text.iter() {
+            for r in font::text_rects(&t.text, t.x, t.y, t.scale).into_iter() {
+                rects.push((r, t.color));
+            }
+        }
+        rects
+    }
+}
+
+/// Wraps a game and clears the debug draw before each step, so only
+/// what the last step drew is shown
+pub struct DebugDrawReset<G> {
+    game: G
+}
+
+impl<G> DebugDrawReset<G> {
+    pub fn new(game: G) -> DebugDrawReset<G> {
+        DebugDrawReset {
+            game: game
+        }
+    }
+}
+
+impl<E, GD: Renderable, G: Game<GD, E>> Game<GD, E> for DebugDrawReset<G> {
+    fn step(&mut self, event: E, mut gd: GD) -> GD {
+        gd.debug_draw_mut().clear();
+        self.game.step(event, gd)
+    }
+}
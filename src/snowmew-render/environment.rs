@@ -0,0 +1,276 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::f32::consts::PI;
+use std::num::Float;
+
+use cgmath::{Vector, EuclideanVector, Vector3};
+
+use snowmew::common::Entity;
+use graphics::{Graphics, Texture};
+use lights::Surface;
+
+/// What is seen behind everything else in a scene. Directions are in
+/// world space with +y up.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum Sky {
+    /// the same colour in every direction
+    Color(Vector3<f32>),
+    /// a texture in the latitude-longitude layout, the top row looks
+    /// straight up and the middle of the image looks down -z
+    Equirectangular(Entity),
+    /// six square textures for the +x, -x, +y, -y, +z and -z faces
+    /// of a cube, laid out like an OpenGL cubemap
+    Cube([Entity; 6])
+}
+
+/// The irradiance of the nine lowest order spherical harmonics, this
+/// is enough to light a diffuse surface from a whole sky.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct ShProbe {
+    pub coefficients: [Vector3<f32>; 9]
+}
+
+/// The light that reaches a surface from every direction
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum Ambient {
+    /// the same light in every direction
+    Color(Vector3<f32>),
+    /// light that changes with the direction the surface faces
+    Probe(ShProbe)
+}
+
+/// The sky and ambient lighting of a scene
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Environment {
+    /// drawn behind the scene and reflected by physically based
+    /// materials, without a sky the view's clear colour is left
+    pub sky: Option<Sky>,
+    pub ambient: Ambient,
+    /// scales the colour of the sky
+    pub intensity: f32,
+    /// scales the sky reflected by physically based materials
+    pub reflection: f32
+}
+
+/// the constant first band of the basis
+const SH_Y00: f32 = 0.282095;
+
+fn sh_basis(d: Vector3<f32>) -> [f32; 9] {
+    [SH_Y00,
+     0.488603 * d.y,
+     0.488603 * d.z,
+     0.488603 * d.x,
+     1.092548 * d.x * d.y,
+     1.092548 * d.y * d.z,
+     0.315392 * (3. * d.z * d.z - 1.),
+     1.092548 * d.x * d.z,
+     0.546274 * (d.x * d.x - d.y * d.y)]
+}
+
+/// the convolution of each band with a cosine lobe, divided by pi
+const SH_COSINE: [f32; 9] = [1.,
+                             2. / 3., 2. / 3., 2. / 3.,
+                             0.25, 0.25, 0.25, 0.25, 0.25];
+
+impl ShProbe {
+    /// A probe that is `color` in every direction
+    pub fn constant(color: Vector3<f32>) -> ShProbe {
+        let mut coefficients = [Vector3::new(0., 0., 0.); 9];
+        coefficients[0] = color.div_s(SH_Y00);
+        ShProbe { coefficients: coefficients }
+    }
+
+    /// Project the sky onto the probe, this reads every texel of the
+    /// sky so it should be done once when the sky is loaded rather
+    /// than every frame.
+    pub fn from_sky<G: Graphics>(db: &G, sky: &Sky) -> ShProbe {
+        let (rows, columns) = (32, 64);
+        let mut coefficients = [Vector3::new(0., 0., 0.); 9];
+        for row in (0..rows) {
+            let theta = (row as f32 + 0.5) / rows as f32 * PI;
+            let weight = theta.sin() * (PI / rows as f32) * (2. * PI / columns as f32);
+            for column in (0..columns) {
+                let phi = (column as f32 + 0.5) / columns as f32 * 2. * PI;
+                let d = Vector3::new(theta.sin() * phi.sin(),
+                                     theta.cos(),
+                                     -theta.sin() * phi.cos());
+                let color = sample_sky(db, sky, d).mul_s(weight);
+                for (c, b) in coefficients.iter_mut().zip(sh_basis(d).iter()) {
+                    c.add_self_v(&color.mul_s(*b));
+                }
+            }
+        }
+        for (c, a) in coefficients.iter_mut().zip(SH_COSINE.iter()) {
+            c.mul_self_s(*a);
+        }
+        ShProbe { coefficients: coefficients }
+    }
+
+    /// The light reaching a surface facing `normal`, scaled so that
+    /// a white diffuse surface reflects this colour.
+    pub fn irradiance(&self, normal: Vector3<f32>) -> Vector3<f32> {
+        let mut sum = Vector3::new(0., 0., 0.);
+        for (c, b) in self.coefficients.iter().zip(sh_basis(normal).iter()) {
+            sum.add_self_v(&c.mul_s(*b));
+        }
+        Vector3::new(sum.x.max(0.), sum.y.max(0.), sum.z.max(0.))
+    }
+}
+
+impl Ambient {
+    /// the ambient as a probe, a colour becomes a constant probe
+    pub fn probe(&self) -> ShProbe {
+        match *self {
+            Ambient::Color(c) => ShProbe::constant(c),
+            Ambient::Probe(p) => p
+        }
+    }
+
+    /// the ambient light reaching a surface facing `normal`
+    pub fn irradiance(&self, normal: Vector3<f32>) -> Vector3<f32> {
+        match *self {
+            Ambient::Color(c) => c,
+            Ambient::Probe(ref p) => p.irradiance(normal)
+        }
+    }
+}
+
+fn texel<G: Graphics>(db: &G, texture: Entity, u: f32, v: f32) -> Vector3<f32> {
+    let text: &Texture = match db.get_texture(texture) {
+        Some(t) => t,
+        None => return Vector3::new(0., 0., 0.)
+    };
+    if text.width() == 0 || text.height() == 0 {
+        return Vector3::new(0., 0., 0.);
+    }
+    let x = ((u * text.width() as f32) as u32).min(text.width() - 1);
+    let y = ((v * text.height() as f32) as u32).min(text.height() - 1);
+    let c = text.texel(x, y);
+    Vector3::new(c[0], c[1], c[2])
+}
+
+/// The colour of the sky looking along the normalized `direction`,
+/// textures are sampled without filtering.
+pub fn sample_sky<G: Graphics>(db: &G, sky: &Sky, direction: Vector3<f32>) -> Vector3<f32> {
+    let d = direction;
+    match *sky {
+        Sky::Color(c) => c,
+        Sky::Equirectangular(t) => {
+            let u = 0.5 + d.x.atan2(-d.z) / (2. * PI);
+            let v = d.y.max(-1.).min(1.).acos() / PI;
+            texel(db, t, u.max(0.), v)
+        }
+        Sky::Cube(faces) => {
+            let (ax, ay, az) = (d.x.abs(), d.y.abs(), d.z.abs());
+            let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+                if d.x > 0. { (0, -d.z, -d.y, ax) } else { (1, d.z, -d.y, ax) }
+            } else if ay >= az {
+                if d.y > 0. { (2, d.x, d.z, ay) } else { (3, d.x, -d.z, ay) }
+            } else {
+                if d.z > 0. { (4, d.x, -d.y, az) } else { (5, -d.x, -d.y, az) }
+            };
+            let u = (sc / ma + 1.) * 0.5;
+            let v = (tc / ma + 1.) * 0.5;
+            texel(db, faces[face], u, v)
+        }
+    }
+}
+
+/// Resample any sky into a `width` by `height` latitude-longitude high
+/// dynamic range texture, renders that only handle one layout use this.
+pub fn bake_equirectangular<G: Graphics>(db: &G, sky: &Sky, width: u32, height: u32) -> Texture {
+    let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+    for y in (0..height) {
+        let theta = (y as f32 + 0.5) / height as f32 * PI;
+        for x in (0..width) {
+            let phi = ((x as f32 + 0.5) / width as f32 - 0.5) * 2. * PI;
+            let d = Vector3::new(theta.sin() * phi.sin(),
+                                 theta.cos(),
+                                 -theta.sin() * phi.cos());
+            let c = sample_sky(db, sky, d);
+            rgb.push_all(&[c.x, c.y, c.z]);
+        }
+    }
+    Texture::new_hdr(width, height, &rgb)
+}
+
+impl Environment {
+    /// No sky and the ambient light the renders have always used
+    pub fn new() -> Environment {
+        Environment {
+            sky: None,
+            ambient: Ambient::Color(Vector3::new(0.2, 0.2, 0.2)),
+            intensity: 1.,
+            reflection: 1.
+        }
+    }
+
+    /// An environment lit by its sky, the ambient light is projected
+    /// from the sky with `ShProbe::from_sky`.
+    pub fn from_sky<G: Graphics>(db: &G, sky: Sky) -> Environment {
+        Environment {
+            sky: Some(sky),
+            ambient: Ambient::Probe(ShProbe::from_sky(db, &sky)),
+            intensity: 1.,
+            reflection: 1.
+        }
+    }
+
+    /// the colour of the sky in `direction`, black without a sky
+    pub fn sky_color<G: Graphics>(&self, db: &G, direction: Vector3<f32>) -> Vector3<f32> {
+        match self.sky {
+            Some(ref sky) => sample_sky(db, sky, direction).mul_s(self.intensity),
+            None => Vector3::new(0., 0., 0.)
+        }
+    }
+
+    /// The ambient light reflected by a toon shaded surface with an
+    /// ambient colour of `ka`
+    pub fn shade(&self, normal: Vector3<f32>, ka: Vector3<f32>) -> Vector3<f32> {
+        self.ambient.irradiance(normal).mul_v(&ka)
+    }
+
+    /// The environment light reflected from `surface` towards the eye,
+    /// `view` points from the surface to the eye. Without a sky this
+    /// is only the ambient light, with one the sky is reflected with
+    /// a Schlick fresnel and blurred towards the ambient light as the
+    /// surface gets rougher.
+    pub fn shade_pbr<G: Graphics>(&self, db: &G, surface: &Surface, view: Vector3<f32>) -> Vector3<f32> {
+        let n = surface.normal;
+        let ambient = self.ambient.irradiance(n);
+        let sky = match self.sky {
+            Some(ref sky) => sky,
+            None => return ambient.mul_v(&surface.base_color)
+        };
+
+        let nv = n.dot(&view).max(0.);
+        let r = n.mul_s(2. * n.dot(&view)).sub_v(&view).normalize();
+        let f0 = Vector3::new(0.04, 0.04, 0.04).mul_s(1. - surface.metallic)
+                                              .add_v(&surface.base_color.mul_s(surface.metallic));
+        let fresnel = (1. - nv).powi(5);
+        let edge = 1. - surface.roughness;
+        let f = Vector3::new(f0.x + (edge.max(f0.x) - f0.x) * fresnel,
+                             f0.y + (edge.max(f0.y) - f0.y) * fresnel,
+                             f0.z + (edge.max(f0.z) - f0.z) * fresnel);
+
+        let reflected = sample_sky(db, sky, r).mul_s(self.intensity * edge)
+                            .add_v(&self.ambient.irradiance(r).mul_s(surface.roughness));
+        let specular = reflected.mul_v(&f).mul_s(self.reflection);
+        let diffuse = ambient.mul_v(&surface.base_color)
+                             .mul_v(&Vector3::new(1., 1., 1.).sub_v(&f))
+                             .mul_s(1. - surface.metallic);
+        diffuse.add_v(&specular)
+    }
+}
@@ -0,0 +1,103 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::num::Float;
+
+use cgmath::{Vector, EuclideanVector, Vector3};
+
+/// How the fog thickens with distance
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum FogMode {
+    /// no fog nearer than `start`, the fog hides everything past `end`
+    Linear { start: f32, end: f32 },
+    /// the fog covers `1 - e^(-density * distance)`
+    Exponential { density: f32 }
+}
+
+/// Fog that is thick below `base` and thins out above it, the density
+/// falls by `e^(-falloff * (height - base))`.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct HeightFog {
+    pub base: f32,
+    pub falloff: f32
+}
+
+/// The fog of a scene, it is mixed into everything that is lit in the
+/// scene by how much air is between the eye and the surface. The sky
+/// is left clear.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Fog {
+    pub color: Vector3<f32>,
+    pub mode: FogMode,
+    /// `None` for fog that is as thick at every height
+    pub height: Option<HeightFog>
+}
+
+impl Fog {
+    /// fog that starts at `start` and hides everything past `end`
+    pub fn linear(color: Vector3<f32>, start: f32, end: f32) -> Fog {
+        Fog {
+            color: color,
+            mode: FogMode::Linear { start: start, end: end },
+            height: None
+        }
+    }
+
+    pub fn exponential(color: Vector3<f32>, density: f32) -> Fog {
+        Fog {
+            color: color,
+            mode: FogMode::Exponential { density: density },
+            height: None
+        }
+    }
+
+    /// thin the fog out above `base`
+    pub fn with_height(mut self, base: f32, falloff: f32) -> Fog {
+        self.height = Some(HeightFog { base: base, falloff: falloff });
+        self
+    }
+
+    /// How much of a surface at `position` is hidden from the `eye`,
+    /// from 0 for none to 1 for all of it. Height fog scales the
+    /// distance by the average density along the line between them.
+    pub fn amount(&self, eye: Vector3<f32>, position: Vector3<f32>) -> f32 {
+        let mut distance = position.sub_v(&eye).length();
+        if let Some(h) = self.height {
+            let (a, b) = (eye.y - h.base, position.y - h.base);
+            let rise = h.falloff * (b - a);
+            distance *= if rise.abs() < 1e-4 {
+                (-h.falloff * a).exp()
+            } else {
+                ((-h.falloff * a).exp() - (-h.falloff * b).exp()) / rise
+            };
+        }
+        let amount = match self.mode {
+            FogMode::Linear { start, end } => {
+                if end <= start {
+                    if distance < start { 0. } else { 1. }
+                } else {
+                    (distance - start) / (end - start)
+                }
+            }
+            FogMode::Exponential { density } => 1. - (-density * distance).exp()
+        };
+        amount.max(0.).min(1.)
+    }
+
+    /// mix the fog into the `color` of a surface at `position`
+    pub fn apply(&self, color: Vector3<f32>, eye: Vector3<f32>, position: Vector3<f32>) -> Vector3<f32> {
+        let t = self.amount(eye, position);
+        color.mul_s(1. - t).add_v(&self.color.mul_s(t))
+    }
+}
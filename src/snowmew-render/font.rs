@@ -0,0 +1,216 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+
+use graphics::Texture;
+
+/// the width of a glyph in pixels
+pub const GLYPH_WIDTH: u32 = 5;
+/// the height of a glyph in pixels
+pub const GLYPH_HEIGHT: u32 = 7;
+/// how far the pen moves for each character
+pub const ADVANCE: u32 = 6;
+/// how far the pen moves for each line
+pub const LINE_HEIGHT: u32 = 9;
+
+/// the number of glyphs in each row of the atlas
+pub const ATLAS_COLUMNS: u32 = 16;
+/// The size of a glyph's cell in the atlas, the glyph is in the top
+/// left and the rest is empty so filtering does not bleed between glyphs
+pub const CELL_WIDTH: u32 = 6;
+pub const CELL_HEIGHT: u32 = 8;
+
+/// One byte per column, the lowest bit is the top row. The glyphs
+/// start at `' '` and end at `'~'`.
+const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x00, 0x08, 0x14, 0x22, 0x41], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x41, 0x22, 0x14, 0x08, 0x00], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x01, 0x01], // F
+    [0x3E, 0x41, 0x41, 0x51, 0x32], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x04, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x7F, 0x20, 0x18, 0x20, 0x7F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x00, 0x7F, 0x41, 0x41], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x41, 0x41, 0x7F, 0x00, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x08, 0x14, 0x54, 0x54, 0x3C], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x00, 0x7F, 0x10, 0x28, 0x44], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x10, 0x08, 0x08, 0x10, 0x08]  // ~
+];
+
+/// The columns of a character's glyph, characters outside of
+/// printable ASCII are drawn as `?`.
+pub fn glyph(c: char) -> [u8; 5] {
+    let c = c as u32;
+    if c >= 0x20 && c <= 0x7E {
+        GLYPHS[(c - 0x20) as usize]
+    } else {
+        GLYPHS[('?' as u32 - 0x20) as usize]
+    }
+}
+
+/// true if the pixel at `x`, `y` of a character's glyph is set, `y`
+/// counts down from the top of the glyph
+pub fn glyph_pixel(c: char, x: u32, y: u32) -> bool {
+    x < GLYPH_WIDTH && y < GLYPH_HEIGHT && glyph(c)[x as usize] & (1 << y) != 0
+}
+
+/// The width and height in pixels of a block of text drawn at
+/// `scale`, lines are split on `'\n'`.
+pub fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let longest = lines.iter().map(|l| l.chars().count() as u32).max().unwrap_or(0);
+    let width = if longest == 0 { 0 } else { (longest * ADVANCE - 1) * scale };
+    let height = ((lines.len() as u32 - 1) * LINE_HEIGHT + GLYPH_HEIGHT) * scale;
+    (width, height)
+}
+
+/// The rectangles, `x`, `y`, `width` and `height` in pixels, that
+/// cover the set pixels of a block of text with its top left at `x`, `y`
+pub fn text_rects(text: &str, x: f32, y: f32, scale: u32) -> Vec<[f32; 4]> {
+    let s = scale as f32;
+    let mut rects = Vec::new();
+    for (row, line) in text.split('\n').enumerate() {
+        let top = y + (row as u32 * LINE_HEIGHT) as f32 * s;
+        for (col, c) in line.chars().enumerate() {
+            let left = x + (col as u32 * ADVANCE) as f32 * s;
+            for gy in 0..GLYPH_HEIGHT {
+                for gx in 0..GLYPH_WIDTH {
+                    if glyph_pixel(c, gx, gy) {
+                        rects.push([left + gx as f32 * s, top + gy as f32 * s, s, s]);
+                    }
+                }
+            }
+        }
+    }
+    rects
+}
+
+/// The glyphs drawn into a white RGBA texture, the alpha is set where
+/// a glyph has a pixel. There is a cell for every character from `' '`
+/// to `'\u{7f}'`, which shows the `?` glyph.
+pub fn atlas() -> Texture {
+    let rows = (96 + ATLAS_COLUMNS - 1) / ATLAS_COLUMNS;
+    let (width, height) = (ATLAS_COLUMNS * CELL_WIDTH, rows * CELL_HEIGHT);
+    let mut data: Vec<u8> = (0..width * height * 4).map(|_| 0).collect();
+    for i in 0..96 {
+        let c = ::std::char::from_u32(0x20 + i).unwrap();
+        let (cx, cy) = (i % ATLAS_COLUMNS * CELL_WIDTH, i / ATLAS_COLUMNS * CELL_HEIGHT);
+        for y in 0..GLYPH_HEIGHT {
+            for x in 0..GLYPH_WIDTH {
+                let p = (((cy + y) * width + cx + x) * 4) as usize;
+                for j in 0..3 {
+                    data[p + j] = 255;
+                }
+                data[p + 3] = if glyph_pixel(c, x, y) { 255 } else { 0 };
+            }
+        }
+    }
+    Texture::new(width, height, 4, data)
+}
+
+/// The left, top, right and bottom texture coordinates of a
+/// character's glyph in the `atlas`
+pub fn atlas_uv(c: char) -> [f32; 4] {
+    let c = c as u32;
+    let i = if c >= 0x20 && c <= 0x7F { c - 0x20 } else { '?' as u32 - 0x20 };
+    let rows = (96 + ATLAS_COLUMNS - 1) / ATLAS_COLUMNS;
+    let (width, height) = ((ATLAS_COLUMNS * CELL_WIDTH) as f32, (rows * CELL_HEIGHT) as f32);
+    let (x, y) = ((i % ATLAS_COLUMNS * CELL_WIDTH) as f32, (i / ATLAS_COLUMNS * CELL_HEIGHT) as f32);
+    [x / width, y / height,
+     (x + GLYPH_WIDTH as f32) / width, (y + GLYPH_HEIGHT as f32) / height]
+}
@@ -14,40 +14,124 @@
 
 #![crate_name = "snowmew-render"]
 #![crate_type = "lib"]
-
+#![feature(core, old_io, old_path, collections, unsafe_destructor)]
 
 extern crate "rustc-serialize" as rustc_serialize;
 extern crate cgmath;
 extern crate ovr;
+extern crate image;
+extern crate collect;
 
 extern crate "snowmew-core" as snowmew;
 extern crate "snowmew-position" as position;
 extern crate "snowmew-graphics" as graphics;
 extern crate "snowmew-input" as input;
 
+use std::fmt;
 use std::marker::PhantomFn;
 
+use snowmew::common::Delete;
+use snowmew::table::{Static, StaticIterator};
+
 #[cfg(feature="use_opencl")]
 extern crate opencl;
 
 #[cfg(feature="use_opencl")]
 use std::sync::Arc;
 
+pub use batch::{BatchPlan, BatchState, Batch, ShadowBatch, Instance, Program};
+pub use capture::{Capture, Recorder, save_png};
+pub use cull::{Bounds, Culler, Frustum};
+pub use debug::{DebugDraw, DebugDrawReset, DebugLine, DebugText};
+pub use environment::{Environment, Sky, Ambient, ShProbe};
+pub use fog::{Fog, FogMode, HeightFog};
+pub use queue::{DrawItem, RenderQueues, billboard_matrix};
+pub use resources::{Change, ResourceTracker, ResourceStats};
+pub use view::{RenderView, Viewport, Clear};
+pub use camera::Projection;
+pub use post::{PostChain, PostEffect, PostImage, PostStage, ToneMap};
+pub use overlay::{Anchor, Overlay, OverlayContent, OverlayQuad, QuadTexture};
+pub use lights::{Lights, Surface, MAX_LIGHTS};
+pub use shadow::{ShadowPlan, ShadowView, MAX_SHADOW_MAPS};
+pub use stats::RenderStats;
+
+/// contains the planning of instanced draw calls
+pub mod batch;
 /// contains utility functions for managing a camera
 pub mod camera;
+/// contains types for reading back what a render has drawn
+pub mod capture;
+/// contains frustum culling and level of detail selection
+pub mod cull;
+/// contains the lines and text drawn over a frame for debugging
+pub mod debug;
+/// contains the sky and ambient lighting of a scene
+pub mod environment;
+/// contains the distance fog of a scene
+pub mod fog;
+/// contains the bitmap font used to draw text
+pub mod font;
+/// contains the world space lights shared by the renders
+pub mod lights;
+/// contains the screen space items drawn over a frame
+pub mod overlay;
+/// contains the full screen effects run on a finished frame
+pub mod post;
+/// contains the sorting of drawables into the order they are drawn
+pub mod queue;
+/// contains the tracking of what a render has uploaded
+pub mod resources;
+/// contains the placement of the shadow maps for the lights
+pub mod shadow;
+/// contains the per frame statistics of the renders
+pub mod stats;
+/// contains the views that a frame is drawn from
+pub mod view;
 
-#[derive(Clone, RustcEncodable, RustcDecodable, Copy)]
+#[derive(Clone, RustcEncodable, RustcDecodable)]
 pub struct RenderData {
     camera: Option<snowmew::Entity>,
-    scene: Option<snowmew::common::Scene>
+    scene: Option<snowmew::common::Scene>,
+    views: Static<RenderView>,
+    projections: Static<Projection>,
+    post: PostChain,
+    debug: DebugDraw,
+    overlays: Static<Overlay>,
+    environments: Static<Environment>,
+    fogs: Static<Fog>,
+    show_stats: bool
 }
 
 impl RenderData {
     pub fn new() -> RenderData {
         RenderData {
             camera: None,
-            scene: None
+            scene: None,
+            views: Static::new(),
+            projections: Static::new(),
+            post: PostChain::new(),
+            debug: DebugDraw::new(),
+            overlays: Static::new(),
+            environments: Static::new(),
+            fogs: Static::new(),
+            show_stats: false
+        }
+    }
+}
+
+impl Delete for RenderData {
+    fn delete(&mut self, oid: snowmew::Entity) -> bool {
+        if self.camera == Some(oid) {
+            self.camera = None;
         }
+        if self.scene == Some(snowmew::common::Scene(oid)) {
+            self.scene = None;
+        }
+        self.views.remove(oid)          |
+        self.projections.remove(oid)    |
+        self.overlays.remove(oid)       |
+        self.environments.remove(oid)   |
+        self.fogs.remove(oid)
     }
 }
 
@@ -74,31 +158,283 @@ pub trait Renderable: graphics::Graphics + position::Positions {
     fn scene(&self) -> Option<snowmew::common::Scene> {
         self.get_render_data().scene
     }
+
+    /// set the projection of a camera
+    fn set_projection(&mut self, camera: snowmew::Entity, projection: Projection) {
+        self.get_render_data_mut().projections.insert(camera, projection);
+    }
+
+    /// the projection of a camera, cameras without one use the
+    /// default perspective projection
+    fn projection(&self, camera: snowmew::Entity) -> Projection {
+        self.get_render_data().projections.get(camera)
+            .map(|p| *p).unwrap_or(Projection::default())
+    }
+
+    fn remove_projection(&mut self, camera: snowmew::Entity) -> bool {
+        self.get_render_data_mut().projections.remove(camera)
+    }
+
+    fn projection_iter<'a>(&'a self) -> StaticIterator<'a, Projection> {
+        self.get_render_data().projections.iter()
+    }
+
+    /// the camera entity as seen in a view of `width` by `height` pixels
+    fn view_camera(&self, camera: snowmew::Entity, width: u32, height: u32) -> camera::Camera {
+        camera::Camera::with_projection(width, height,
+                                        self.position(camera),
+                                        self.projection(camera))
+    }
+
+    /// set the sky and ambient lighting of a scene
+    fn set_environment(&mut self, scene: snowmew::common::Scene, environment: Environment) {
+        let snowmew::common::Scene(oid) = scene;
+        self.get_render_data_mut().environments.insert(oid, environment);
+    }
+
+    /// the environment of a scene, scenes without one have no sky and
+    /// the default ambient light
+    fn environment(&self, scene: snowmew::common::Scene) -> Environment {
+        let snowmew::common::Scene(oid) = scene;
+        self.get_render_data().environments.get(oid)
+            .map(|e| *e).unwrap_or(Environment::new())
+    }
+
+    fn remove_environment(&mut self, scene: snowmew::common::Scene) -> bool {
+        let snowmew::common::Scene(oid) = scene;
+        self.get_render_data_mut().environments.remove(oid)
+    }
+
+    fn environment_iter<'a>(&'a self) -> StaticIterator<'a, Environment> {
+        self.get_render_data().environments.iter()
+    }
+
+    /// set the fog of a scene
+    fn set_fog(&mut self, scene: snowmew::common::Scene, fog: Fog) {
+        let snowmew::common::Scene(oid) = scene;
+        self.get_render_data_mut().fogs.insert(oid, fog);
+    }
+
+    /// the fog of a scene, `None` if the scene is clear
+    fn fog(&self, scene: snowmew::common::Scene) -> Option<Fog> {
+        let snowmew::common::Scene(oid) = scene;
+        self.get_render_data().fogs.get(oid).map(|f| *f)
+    }
+
+    fn remove_fog(&mut self, scene: snowmew::common::Scene) -> bool {
+        let snowmew::common::Scene(oid) = scene;
+        self.get_render_data_mut().fogs.remove(oid)
+    }
+
+    fn fog_iter<'a>(&'a self) -> StaticIterator<'a, Fog> {
+        self.get_render_data().fogs.iter()
+    }
+
+    /// the effects run on the frame after the views are drawn
+    fn post_chain<'a>(&'a self) -> &'a PostChain {
+        &self.get_render_data().post
+    }
+
+    fn post_chain_mut<'a>(&'a mut self) -> &'a mut PostChain {
+        &mut self.get_render_data_mut().post
+    }
+
+    fn set_post_chain(&mut self, chain: PostChain) {
+        self.get_render_data_mut().post = chain;
+    }
+
+    /// the debug lines and text drawn over the next frame
+    fn debug_draw<'a>(&'a self) -> &'a DebugDraw {
+        &self.get_render_data().debug
+    }
+
+    fn debug_draw_mut<'a>(&'a mut self) -> &'a mut DebugDraw {
+        &mut self.get_render_data_mut().debug
+    }
+
+    /// add an item to the overlay that is drawn over the frame
+    fn new_overlay(&mut self, overlay: Overlay) -> snowmew::Entity {
+        let oid = self.new_object(None);
+        self.get_render_data_mut().overlays.insert(oid, overlay);
+        oid
+    }
+
+    fn overlay<'a>(&'a self, oid: snowmew::Entity) -> Option<&'a Overlay> {
+        self.get_render_data().overlays.get(oid)
+    }
+
+    fn set_overlay(&mut self, oid: snowmew::Entity, overlay: Overlay) {
+        self.get_render_data_mut().overlays.insert(oid, overlay);
+    }
+
+    fn remove_overlay(&mut self, oid: snowmew::Entity) -> bool {
+        self.get_render_data_mut().overlays.remove(oid)
+    }
+
+    fn overlay_iter<'a>(&'a self) -> StaticIterator<'a, Overlay> {
+        self.get_render_data().overlays.iter()
+    }
+
+    /// The quads that make up the overlay on a screen `width` by
+    /// `height` pixels, in the order they are drawn
+    fn overlay_quads(&self, width: u32, height: u32) -> Vec<OverlayQuad> {
+        overlay::quads(self, width, height)
+    }
+
+    /// draw the render's `RenderStats` over the overlay
+    fn set_show_stats(&mut self, show: bool) {
+        self.get_render_data_mut().show_stats = show;
+    }
+
+    fn show_stats(&self) -> bool {
+        self.get_render_data().show_stats
+    }
+
+    /// add a view to the frame, once a view is added the camera and
+    /// scene are no longer drawn on their own
+    fn new_view(&mut self, view: RenderView) -> snowmew::Entity {
+        let oid = self.new_object(None);
+        self.get_render_data_mut().views.insert(oid, view);
+        oid
+    }
+
+    fn view<'a>(&'a self, oid: snowmew::Entity) -> Option<&'a RenderView> {
+        self.get_render_data().views.get(oid)
+    }
+
+    fn set_view(&mut self, oid: snowmew::Entity, view: RenderView) {
+        self.get_render_data_mut().views.insert(oid, view);
+    }
+
+    fn remove_view(&mut self, oid: snowmew::Entity) -> bool {
+        self.get_render_data_mut().views.remove(oid)
+    }
+
+    fn view_iter<'a>(&'a self) -> StaticIterator<'a, RenderView> {
+        self.get_render_data().views.iter()
+    }
+
+    /// The views to draw in the order they have to be drawn. Without
+    /// any views the camera and scene are drawn to the whole frame.
+    fn render_views(&self) -> Vec<RenderView> {
+        let views: Vec<RenderView> = self.view_iter().map(|(_, v)| *v).collect();
+        if views.len() != 0 {
+            return view::order_views(self, views);
+        }
+        match (self.camera(), self.scene()) {
+            (Some(camera), Some(scene)) => vec![RenderView::new(camera, scene)],
+            _ => Vec::new()
+        }
+    }
 }
 
 /// Render is a trait that describes the describes how a render is implemented
 /// in the engine. `update` is called once per cadence pulse.
 pub trait Render<T> {
     fn update(&mut self, db: T);
+
+    /// Request a copy of the next frame that is drawn. Renders that can
+    /// not read back their frames return `Capture::unsupported`, its
+    /// `wait` returns `None` without blocking.
+    fn capture(&mut self) -> Capture {
+        Capture::unsupported()
+    }
+
+    /// The resources the render has loaded, as of the last frame it
+    /// drew. Renders that do not hold resources report none.
+    fn resources(&self) -> ResourceStats {
+        ResourceStats::new()
+    }
+
+    /// What the render did to draw the last frame. Renders that do
+    /// not draw report nothing.
+    fn stats(&self) -> RenderStats {
+        RenderStats::new()
+    }
+}
+
+/// A render that throws away everything it is given. This is what
+/// a factory should return for `RenderTarget::Sink`.
+#[derive(Copy)]
+pub struct NullRender;
+
+impl<T> Render<T> for NullRender {
+    fn update(&mut self, _: T) {}
+}
+
+/// Where a render should draw to.
+pub enum RenderTarget<'a> {
+    /// A window created by the `IOManager`, the `IOManager` is needed to
+    /// load the OpenGL functions. The size is the size of the framebuffer.
+    Window(&'a input::IOManager, input::Window, (i32, i32)),
+    /// An image in memory of the supplied width and height
+    Offscreen(u32, u32),
+    /// Nothing is drawn, useful for servers and tests that only need
+    /// the render to accept updates.
+    Sink
+}
+
+impl<'a> RenderTarget<'a> {
+    /// The size in pixels of the target, a `Sink` has no size.
+    pub fn size(&self) -> (u32, u32) {
+        match *self {
+            RenderTarget::Window(_, _, (w, h)) => (w as u32, h as u32),
+            RenderTarget::Offscreen(w, h) => (w, h),
+            RenderTarget::Sink => (0, 0)
+        }
+    }
+
+    /// Does this target require a display
+    pub fn is_window(&self) -> bool {
+        match *self {
+            RenderTarget::Window(_, _, _) => true,
+            _ => false
+        }
+    }
+
+    /// A short name for the kind of target, used in errors.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            RenderTarget::Window(_, _, _) => "window",
+            RenderTarget::Offscreen(_, _) => "offscreen",
+            RenderTarget::Sink => "sink"
+        }
+    }
+}
+
+/// Returned when a factory is asked to draw to a target that it
+/// does not support, `target` is the `kind` of the target.
+#[derive(Copy, Debug, PartialEq)]
+pub struct UnsupportedTarget {
+    pub target: &'static str
+}
+
+impl fmt::Display for UnsupportedTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the render can not draw to a {} target", self.target)
+    }
 }
 
 /// RenderFactor is used to create a `Render` object. This is used to pass a configured
-/// Window to the Render.
+/// `RenderTarget` to the Render.
 #[cfg(feature="use_opencl")]
 pub trait RenderFactory<T, R: Render<T>>: PhantomFn<T> {
     fn init(self: Box<Self>,
-            im: &input::IOManager,
-            window: input::Window,
-            size: (i32, i32),
+            target: RenderTarget,
             cl: Option<Arc<opencl::hl::Device>>) -> R;
+
+    /// Can the factory's render draw to `target`, this is checked
+    /// before `init` is called.
+    fn supports(&self, _: &RenderTarget) -> bool { true }
 }
 
 #[cfg(not(feature="use_opencl"))]
 pub trait RenderFactory<T, R: Render<T>>: PhantomFn<T> {
-    fn init(self: Box<Self>,
-            im: &input::IOManager,
-            window: input::Window,
-            size: (i32, i32)) -> R;
+    fn init(self: Box<Self>, target: RenderTarget) -> R;
+
+    /// Can the factory's render draw to `target`, this is checked
+    /// before `init` is called.
+    fn supports(&self, _: &RenderTarget) -> bool { true }
 }
 
 /// Convert your game into
@@ -107,6 +443,10 @@ pub trait IntoRender {
     type RenderGameState;
 
     fn into_render(self) -> Self::RenderGameState;
+
+    /// Called by the engine once a frame has been handed to the render,
+    /// this clears anything that is only drawn for one frame.
+    fn end_frame(&mut self) {}
 }
 
 impl<T> IntoRender for T
@@ -125,6 +465,11 @@ impl<T> IntoRender for T
         }
     }
 
+    /// clears the debug draw, so only what was drawn for the last
+    /// frame is shown
+    fn end_frame(&mut self) {
+        self.debug_draw_mut().clear();
+    }
 }
 
 #[derive(Clone)]
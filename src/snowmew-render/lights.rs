@@ -0,0 +1,236 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::f32::consts::PI;
+use std::num::Float;
+
+use cgmath::{Matrix, Vector, EuclideanVector, Vector3, Vector4};
+
+use graphics::light::{Light, Attenuation, Shadow};
+use Renderable;
+
+/// The most lights of each kind a render has to support. Lights past
+/// this are ignored.
+pub const MAX_LIGHTS: usize = 8;
+
+/// A directional light in world space, the colour is scaled by the
+/// intensity of the light.
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    /// points towards the light
+    pub normal: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub shadow: Option<Shadow>
+}
+
+/// A point light in world space
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub attenuation: Attenuation
+}
+
+/// A spot light in world space, the cone is stored as the cosine
+/// of the inner and outer angles.
+#[derive(Copy, Clone, Debug)]
+pub struct SpotLight {
+    pub position: Vector3<f32>,
+    /// the direction the light points in
+    pub direction: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub attenuation: Attenuation,
+    /// the outer angle of the cone in radians
+    pub outer: f32,
+    pub cos_inner: f32,
+    pub cos_outer: f32,
+    pub shadow: Option<Shadow>
+}
+
+/// Every light in the database moved into world space, this is what
+/// a render uploads to the GPU each frame.
+#[derive(Clone, Debug)]
+pub struct Lights {
+    pub directional: Vec<DirectionalLight>,
+    pub point: Vec<PointLight>,
+    pub spot: Vec<SpotLight>
+}
+
+/// What the physically based light model needs to know about a point
+/// on a surface
+#[derive(Copy, Clone, Debug)]
+pub struct Surface {
+    pub position: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    pub base_color: Vector3<f32>,
+    pub metallic: f32,
+    pub roughness: f32
+}
+
+impl Surface {
+    /// Cook-Torrance with a GGX distribution, Schlick's fresnel and
+    /// Smith's geometry term. `view` and `light` point away from the
+    /// surface. The result is scaled by pi so a white light with an
+    /// intensity of 1 lights a white rough surface facing it to 1,
+    /// like the toon model does.
+    pub fn brdf(&self, view: Vector3<f32>, light: Vector3<f32>) -> Vector3<f32> {
+        let n = self.normal;
+        let nl = n.dot(&light);
+        if nl <= 0. {
+            return Vector3::new(0., 0., 0.);
+        }
+        let h = light.add_v(&view).normalize();
+        let nv = n.dot(&view).max(0.0001);
+        let nh = n.dot(&h).max(0.);
+        let vh = view.dot(&h).max(0.);
+
+        let a = (self.roughness * self.roughness).max(0.001);
+        let a2 = a * a;
+        let dd = nh * nh * (a2 - 1.) + 1.;
+        let d = a2 / (PI * dd * dd);
+
+        let k = (self.roughness + 1.) * (self.roughness + 1.) / 8.;
+        let g = nv / (nv * (1. - k) + k) * nl / (nl * (1. - k) + k);
+
+        let f0 = Vector3::new(0.04, 0.04, 0.04).mul_s(1. - self.metallic)
+                                              .add_v(&self.base_color.mul_s(self.metallic));
+        let fresnel = (1. - vh).powi(5);
+        let f = f0.add_v(&Vector3::new(1., 1., 1.).sub_v(&f0).mul_s(fresnel));
+
+        let specular = f.mul_s(d * g / (4. * nv * nl));
+        let diffuse = Vector3::new(1., 1., 1.).sub_v(&f)
+                                              .mul_s(1. - self.metallic)
+                                              .mul_v(&self.base_color)
+                                              .div_s(PI);
+        diffuse.add_v(&specular).mul_s(nl * PI)
+    }
+}
+
+/// lights are shaded in half steps to match the look of the renders
+fn toon(level: f32) -> f32 {
+    (level.max(0.) * 2.).round() / 2.
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge0 == edge1 {
+        return if x < edge0 { 0. } else { 1. };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).max(0.).min(1.);
+    t * t * (3. - 2. * t)
+}
+
+impl Lights {
+    /// Collect the lights from the database, at most `MAX_LIGHTS` of
+    /// each kind are kept.
+    pub fn new<RD: Renderable>(db: &RD) -> Lights {
+        let mut lights = Lights {
+            directional: Vec::new(),
+            point: Vec::new(),
+            spot: Vec::new()
+        };
+
+        for (key, light) in db.light_iter() {
+            let mat = db.position(key);
+            let position = mat.mul_v(&Vector4::new(0., 0., 0., 1.)).truncate();
+            match *light {
+                Light::Directional(d) => {
+                    if lights.directional.len() == MAX_LIGHTS { continue; }
+                    let n = d.normal();
+                    lights.directional.push(DirectionalLight {
+                        normal: mat.mul_v(&Vector4::new(n.x, n.y, n.z, 0.)).truncate().normalize(),
+                        color: d.color().mul_s(d.intensity()),
+                        shadow: d.shadow()
+                    });
+                }
+                Light::Point(p) => {
+                    if lights.point.len() == MAX_LIGHTS { continue; }
+                    lights.point.push(PointLight {
+                        position: position,
+                        color: p.color().mul_s(p.intensity()),
+                        attenuation: p.attenuation()
+                    });
+                }
+                Light::Spot(s) => {
+                    if lights.spot.len() == MAX_LIGHTS { continue; }
+                    let d = s.direction();
+                    lights.spot.push(SpotLight {
+                        position: position,
+                        direction: mat.mul_v(&Vector4::new(d.x, d.y, d.z, 0.)).truncate().normalize(),
+                        color: s.color().mul_s(s.intensity()),
+                        attenuation: s.attenuation(),
+                        outer: s.outer(),
+                        cos_inner: s.inner().cos(),
+                        cos_outer: s.outer().cos(),
+                        shadow: s.shadow()
+                    });
+                }
+            }
+        }
+        lights
+    }
+
+    /// The light that reaches a surface at `position` facing `normal`.
+    /// `shadow` scales the first directional light, this is used by
+    /// renders that do not draw shadow maps.
+    pub fn shade(&self, position: Vector3<f32>, normal: Vector3<f32>, shadow: f32) -> Vector3<f32> {
+        let mut sum = Vector3::new(0., 0., 0.);
+        for (i, d) in self.directional.iter().enumerate() {
+            let shadow = if i == 0 { shadow } else { 1. };
+            sum.add_self_v(&d.color.mul_s(toon(shadow * d.normal.dot(&normal))));
+        }
+        for p in self.point.iter() {
+            let l = p.position.sub_v(&position);
+            let dist = l.length();
+            let level = toon(l.div_s(dist).dot(&normal)) * p.attenuation.factor(dist);
+            sum.add_self_v(&p.color.mul_s(level));
+        }
+        for s in self.spot.iter() {
+            let l = s.position.sub_v(&position);
+            let dist = l.length();
+            let l = l.div_s(dist);
+            let cone = smoothstep(s.cos_outer, s.cos_inner, -l.dot(&s.direction));
+            let level = toon(l.dot(&normal)) * s.attenuation.factor(dist) * cone;
+            sum.add_self_v(&s.color.mul_s(level));
+        }
+        sum
+    }
+
+    /// The light reflected from `surface` towards the `eye` with the
+    /// physically based light model. `shadow` scales the first
+    /// directional light like it does for `shade`.
+    pub fn shade_pbr(&self, surface: &Surface, eye: Vector3<f32>, shadow: f32) -> Vector3<f32> {
+        let view = eye.sub_v(&surface.position).normalize();
+        let mut sum = Vector3::new(0., 0., 0.);
+        for (i, d) in self.directional.iter().enumerate() {
+            let shadow = if i == 0 { shadow } else { 1. };
+            let level = surface.brdf(view, d.normal).mul_s(shadow);
+            sum.add_self_v(&d.color.mul_v(&level));
+        }
+        for p in self.point.iter() {
+            let l = p.position.sub_v(&surface.position);
+            let dist = l.length();
+            let level = surface.brdf(view, l.div_s(dist)).mul_s(p.attenuation.factor(dist));
+            sum.add_self_v(&p.color.mul_v(&level));
+        }
+        for s in self.spot.iter() {
+            let l = s.position.sub_v(&surface.position);
+            let dist = l.length();
+            let l = l.div_s(dist);
+            let cone = smoothstep(s.cos_outer, s.cos_inner, -l.dot(&s.direction));
+            let level = surface.brdf(view, l).mul_s(s.attenuation.factor(dist) * cone);
+            sum.add_self_v(&s.color.mul_v(&level));
+        }
+        sum
+    }
+}
@@ -0,0 +1,229 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+
+
+use snowmew::common::Entity;
+use font;
+use Renderable;
+
+/// The point of the screen, and of an overlay item, that the item is
+/// placed relative to
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight
+}
+
+impl Anchor {
+    /// the anchor point of a box `width` by `height` pixels
+    pub fn point(&self, width: f32, height: f32) -> [f32; 2] {
+        let (x, y) = match *self {
+            Anchor::TopLeft => (0., 0.),
+            Anchor::Top => (0.5, 0.),
+            Anchor::TopRight => (1., 0.),
+            Anchor::Left => (0., 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1., 0.5),
+            Anchor::BottomLeft => (0., 1.),
+            Anchor::Bottom => (0.5, 1.),
+            Anchor::BottomRight => (1., 1.)
+        };
+        [x * width, y * height]
+    }
+}
+
+/// What an overlay item draws
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub enum OverlayContent {
+    /// a solid rectangle
+    Rect,
+    /// Part of a texture entity, `uv` is the left, top, right and
+    /// bottom of the part in texture coordinates
+    Sprite{texture: Entity, uv: [f32; 4]},
+    /// text in the bitmap font, each pixel of the font is `scale` pixels
+    Text{text: String, scale: u32}
+}
+
+/// An item drawn in screen space over the finished frame. It is placed
+/// so its `anchor` point sits `offset` pixels from the same point of
+/// the screen, so an item anchored to `BottomRight` stays in the bottom
+/// right corner as the window is resized.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct Overlay {
+    pub content: OverlayContent,
+    pub anchor: Anchor,
+    pub offset: [f32; 2],
+    /// the size in pixels of rectangles and sprites, text is sized by its font
+    pub size: [f32; 2],
+    /// the colour the item is multiplied by
+    pub color: [f32; 4],
+    /// items on higher layers are drawn over lower ones
+    pub layer: i32,
+    pub visible: bool
+}
+
+impl Overlay {
+    fn new(content: OverlayContent, size: [f32; 2], color: [f32; 4]) -> Overlay {
+        Overlay {
+            content: content,
+            anchor: Anchor::TopLeft,
+            offset: [0., 0.],
+            size: size,
+            color: color,
+            layer: 0,
+            visible: true
+        }
+    }
+
+    /// a solid rectangle `width` by `height` pixels
+    pub fn rect(width: f32, height: f32, color: [f32; 4]) -> Overlay {
+        Overlay::new(OverlayContent::Rect, [width, height], color)
+    }
+
+    /// a whole texture entity drawn `width` by `height` pixels
+    pub fn sprite(texture: Entity, width: f32, height: f32) -> Overlay {
+        Overlay::new(OverlayContent::Sprite{texture: texture, uv: [0., 0., 1., 1.]},
+                     [width, height], [1., 1., 1., 1.])
+    }
+
+    pub fn text(text: &str, scale: u32, color: [f32; 4]) -> Overlay {
+        Overlay::new(OverlayContent::Text{text: text.to_string(), scale: scale},
+                     [0., 0.], color)
+    }
+
+    /// place the item's `anchor` point `x`, `y` pixels from the screen's
+    pub fn anchored(mut self, anchor: Anchor, x: f32, y: f32) -> Overlay {
+        self.anchor = anchor;
+        self.offset = [x, y];
+        self
+    }
+
+    pub fn layered(mut self, layer: i32) -> Overlay {
+        self.layer = layer;
+        self
+    }
+
+    /// the width and height of the item in pixels
+    pub fn size(&self) -> [f32; 2] {
+        match self.content {
+            OverlayContent::Text{ref text, scale} => {
+                let (w, h) = font::text_size(text, scale);
+                [w as f32, h as f32]
+            }
+            _ => self.size
+        }
+    }
+
+    /// The `x`, `y`, `width` and `height` in pixels of the item on
+    /// a screen `width` by `height` pixels
+    pub fn screen_rect(&self, width: u32, height: u32) -> [f32; 4] {
+        let size = self.size();
+        let screen = self.anchor.point(width as f32, height as f32);
+        let item = self.anchor.point(size[0], size[1]);
+        [screen[0] - item[0] + self.offset[0],
+         screen[1] - item[1] + self.offset[1],
+         size[0], size[1]]
+    }
+
+    /// the quads of the item on a screen `width` by `height` pixels
+    pub fn quads(&self, width: u32, height: u32) -> Vec<OverlayQuad> {
+        let mut quads = Vec::new();
+        let rect = self.screen_rect(width, height);
+        match self.content {
+            OverlayContent::Rect => {
+                quads.push(OverlayQuad {
+                    rect: rect,
+                    uv: [0., 0., 1., 1.],
+                    texture: QuadTexture::Solid,
+                    color: self.color
+                });
+            }
+            OverlayContent::Sprite{texture, uv} => {
+                quads.push(OverlayQuad {
+                    rect: rect,
+                    uv: uv,
+                    texture: QuadTexture::Texture(texture),
+                    color: self.color
+                });
+            }
+            OverlayContent::Text{ref text, scale} => {
+                let s = scale as f32;
+                let (w, h) = ((font::GLYPH_WIDTH * scale) as f32, (font::GLYPH_HEIGHT * scale) as f32);
+                for (row, line) in text.split('\n').enumerate() {
+                    let top = rect[1] + (row as u32 * font::LINE_HEIGHT) as f32 * s;
+                    for (col, c) in line.chars().enumerate() {
+                        if c == ' ' {
+                            continue;
+                        }
+                        let left = rect[0] + (col as u32 * font::ADVANCE) as f32 * s;
+                        quads.push(OverlayQuad {
+                            rect: [left, top, w, h],
+                            uv: font::atlas_uv(c),
+                            texture: QuadTexture::Font,
+                            color: self.color
+                        });
+                    }
+                }
+            }
+        }
+        quads
+    }
+}
+
+/// The texture a quad reads from
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum QuadTexture {
+    /// no texture, the quad is its colour
+    Solid,
+    /// the font atlas, see `font::atlas`
+    Font,
+    Texture(Entity)
+}
+
+/// A screen space rectangle ready to be drawn, this is what the
+/// overlay is broken into for the renders
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct OverlayQuad {
+    /// `x`, `y`, `width` and `height` in pixels
+    pub rect: [f32; 4],
+    /// left, top, right and bottom in texture coordinates
+    pub uv: [f32; 4],
+    pub texture: QuadTexture,
+    pub color: [f32; 4]
+}
+
+/// The quads of the visible overlay items on a screen `width` by
+/// `height` pixels, in the order they are drawn. Items are sorted by
+/// layer, items on the same layer are drawn in the order they were made.
+pub fn quads<RD: Renderable>(db: &RD, width: u32, height: u32) -> Vec<OverlayQuad> {
+    let mut items: Vec<(Entity, &Overlay)> = db.overlay_iter()
+        .filter(|&(_, o)| o.visible)
+        .collect();
+    items.sort_by(|a, b| (a.1.layer, a.0).cmp(&(b.1.layer, b.0)));
+
+    let mut quads = Vec::new();
+    for &(_, item) in items.iter() {
+        quads.extend(item.quads(width, height).into_iter());
+    }
+    quads
+}
@@ -0,0 +1,286 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+
+use std::num::Float;
+
+/// How a tone mapping operator squeezes HDR colours into 0 to 1
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum ToneMap {
+    /// `c / (1 + c)`
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    Aces
+}
+
+/// A full screen effect that is run on the frame after the views are
+/// drawn. The math for each effect is the same in the gfx shaders and
+/// in `PostEffect::apply`.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub enum PostEffect {
+    /// scale the colour by `exposure` and map it into 0 to 1
+    ToneMap {
+        operator: ToneMap,
+        exposure: f32
+    },
+    /// raise the colour to `1 / gamma`
+    Gamma(f32),
+    /// A simplified FXAA, pixels on an edge with more than `threshold`
+    /// contrast are mixed with their neighbours across the edge
+    Fxaa {
+        threshold: f32
+    },
+    /// add the blurred parts of the frame that are brighter than
+    /// `threshold`, the blur is a box `radius` pixels wide
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        radius: u32
+    },
+    /// darken the frame towards the corners, `radius` is where the
+    /// darkening starts, 0 is the centre and 1 the corners
+    Vignette {
+        strength: f32,
+        radius: f32
+    },
+    /// multiply by `tint`, then adjust the brightness, contrast and
+    /// saturation, 1 leaves the contrast and saturation alone
+    ColorGrade {
+        tint: [f32; 3],
+        brightness: f32,
+        contrast: f32,
+        saturation: f32
+    }
+}
+
+/// The maximum blur radius of a bloom
+pub const MAX_BLOOM_RADIUS: u32 = 8;
+
+/// An image of floating point RGBA colours, rows are top to bottom
+#[derive(Clone, Debug, PartialEq)]
+pub struct PostImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>
+}
+
+impl PostImage {
+    pub fn new(width: u32, height: u32, pixels: Vec<[f32; 4]>) -> PostImage {
+        assert!(pixels.len() == (width * height) as usize);
+        PostImage {
+            width: width,
+            height: height,
+            pixels: pixels
+        }
+    }
+
+    /// the pixel at `x`, `y`, coordinates outside of the image are
+    /// clamped to the edge
+    pub fn get(&self, x: i32, y: i32) -> [f32; 4] {
+        let x = x.max(0).min(self.width as i32 - 1);
+        let y = y.max(0).min(self.height as i32 - 1);
+        self.pixels[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    fn map<F>(&self, mut f: F) -> PostImage where F: FnMut(i32, i32, [f32; 4]) -> [f32; 4] {
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                pixels.push(f(x, y, self.get(x, y)));
+            }
+        }
+        PostImage::new(self.width, self.height, pixels)
+    }
+}
+
+fn rgb<F>(c: [f32; 4], f: F) -> [f32; 4] where F: Fn(f32) -> f32 {
+    [f(c[0]), f(c[1]), f(c[2]), c[3]]
+}
+
+/// the perceived brightness of a colour
+pub fn luma(c: [f32; 4]) -> f32 {
+    c[0] * 0.299 + c[1] * 0.587 + c[2] * 0.114
+}
+
+fn smoothstep(e0: f32, e1: f32, x: f32) -> f32 {
+    let t = ((x - e0) / (e1 - e0)).max(0.).min(1.);
+    t * t * (3. - 2. * t)
+}
+
+impl PostEffect {
+    /// a name for the effect, each kind of effect has its own
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PostEffect::ToneMap { .. } => "tone_map",
+            PostEffect::Gamma(_) => "gamma",
+            PostEffect::Fxaa { .. } => "fxaa",
+            PostEffect::Bloom { .. } => "bloom",
+            PostEffect::Vignette { .. } => "vignette",
+            PostEffect::ColorGrade { .. } => "color_grade"
+        }
+    }
+
+    /// run the effect on an image
+    pub fn apply(&self, image: &PostImage) -> PostImage {
+        match *self {
+            PostEffect::ToneMap { operator, exposure } => image.map(|_, _, c| {
+                rgb(c, |v| {
+                    let v = v * exposure;
+                    match operator {
+                        ToneMap::Reinhard => v / (1. + v),
+                        ToneMap::Aces => {
+                            let m = (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14);
+                            m.max(0.).min(1.)
+                        }
+                    }
+                })
+            }),
+            PostEffect::Gamma(gamma) => image.map(|_, _, c| {
+                rgb(c, |v| v.max(0.).powf(1. / gamma))
+            }),
+            PostEffect::Fxaa { threshold } => image.map(|x, y, c| {
+                let (n, s) = (image.get(x, y - 1), image.get(x, y + 1));
+                let (w, e) = (image.get(x - 1, y), image.get(x + 1, y));
+                let (lm, ln, ls, lw, le) = (luma(c), luma(n), luma(s), luma(w), luma(e));
+                let max = lm.max(ln).max(ls).max(lw).max(le);
+                let min = lm.min(ln).min(ls).min(lw).min(le);
+                if max - min < threshold.max(max * 0.125) {
+                    return c;
+                }
+                // a horizontal edge is blurred vertically
+                let (a, b) = if (ln + ls - 2. * lm).abs() >= (lw + le - 2. * lm).abs() {
+                    (n, s)
+                } else {
+                    (w, e)
+                };
+                [c[0] * 0.5 + (a[0] + b[0]) * 0.25,
+                 c[1] * 0.5 + (a[1] + b[1]) * 0.25,
+                 c[2] * 0.5 + (a[2] + b[2]) * 0.25,
+                 c[3]]
+            }),
+            PostEffect::Bloom { threshold, intensity, radius } => {
+                let r = radius.min(MAX_BLOOM_RADIUS) as i32;
+                let taps = ((2 * r + 1) * (2 * r + 1)) as f32;
+                image.map(|x, y, c| {
+                    let mut sum = [0.; 3];
+                    for dy in -r..r+1 {
+                        for dx in -r..r+1 {
+                            let p = image.get(x + dx, y + dy);
+                            for i in 0..3 {
+                                sum[i] += (p[i] - threshold).max(0.);
+                            }
+                        }
+                    }
+                    [c[0] + intensity * sum[0] / taps,
+                     c[1] + intensity * sum[1] / taps,
+                     c[2] + intensity * sum[2] / taps,
+                     c[3]]
+                })
+            }
+            PostEffect::Vignette { strength, radius } => {
+                let (w, h) = (image.width as f32, image.height as f32);
+                image.map(|x, y, c| {
+                    // 0 at the centre and 1 in the corners
+                    let u = (x as f32 + 0.5) / w - 0.5;
+                    let v = (y as f32 + 0.5) / h - 0.5;
+                    let d = (u * u + v * v).sqrt() * 2f32.sqrt();
+                    let f = 1. - strength * smoothstep(radius, 1., d);
+                    rgb(c, |v| v * f)
+                })
+            }
+            PostEffect::ColorGrade { tint, brightness, contrast, saturation } => image.map(|_, _, c| {
+                let mut g = [0.; 4];
+                for i in 0..3 {
+                    g[i] = (c[i] * tint[i] - 0.5) * contrast + 0.5 + brightness;
+                }
+                let l = luma(g);
+                [(l + (g[0] - l) * saturation).max(0.),
+                 (l + (g[1] - l) * saturation).max(0.),
+                 (l + (g[2] - l) * saturation).max(0.),
+                 c[3]]
+            })
+        }
+    }
+}
+
+/// An effect in a chain, disabled effects are skipped
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct PostStage {
+    pub effect: PostEffect,
+    pub enabled: bool
+}
+
+/// The effects run on the frame, in order. A render with an empty
+/// chain, or one where every effect is disabled, draws straight to
+/// the window.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct PostChain {
+    stages: Vec<PostStage>
+}
+
+impl PostChain {
+    pub fn new() -> PostChain {
+        PostChain {
+            stages: Vec::new()
+        }
+    }
+
+    /// add an enabled effect to the end of the chain, returning its index
+    pub fn push(&mut self, effect: PostEffect) -> usize {
+        self.stages.push(PostStage {
+            effect: effect,
+            enabled: true
+        });
+        self.stages.len() - 1
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&PostStage> {
+        self.stages.get(idx)
+    }
+
+    /// replace the effect at `idx`, this keeps if it is enabled
+    pub fn set(&mut self, idx: usize, effect: PostEffect) {
+        self.stages[idx].effect = effect;
+    }
+
+    /// turn the effect at `idx` on or off
+    pub fn set_enabled(&mut self, idx: usize, enabled: bool) {
+        self.stages[idx].enabled = enabled;
+    }
+
+    pub fn remove(&mut self, idx: usize) -> PostStage {
+        self.stages.remove(idx)
+    }
+
+    pub fn len(&self) -> usize { self.stages.len() }
+
+    /// the stages of the chain, including the disabled ones
+    pub fn stages(&self) -> &[PostStage] { &self.stages }
+
+    /// the enabled effects in the order they are run
+    pub fn effects(&self) -> Vec<PostEffect> {
+        self.stages.iter().filter(|s| s.enabled).map(|s| s.effect).collect()
+    }
+
+    /// true if no effect is enabled
+    pub fn is_empty(&self) -> bool {
+        self.stages.iter().all(|s| !s.enabled)
+    }
+
+    /// run every enabled effect on an image
+    pub fn apply(&self, image: &PostImage) -> PostImage {
+        self.effects().iter().fold(image.clone(), |img, e| e.apply(&img))
+    }
+}
@@ -0,0 +1,140 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+use std::cmp::Ordering;
+
+use cgmath::{Matrix, Matrix4, Vector4};
+
+use snowmew::common::Entity;
+use position::Positions;
+use graphics::{Graphics, Drawable, BlendMode, Billboard};
+use camera::Camera;
+use Renderable;
+
+/// A drawable that is ready to be drawn
+#[derive(Copy, Clone, Debug)]
+pub struct DrawItem {
+    pub id: Entity,
+    pub draw: Drawable,
+    pub blend: BlendMode,
+    /// how far in front of the camera the drawable is
+    pub depth: f32,
+    /// the item is a billboard of `id`'s `BillboardSet`
+    pub billboard: Option<Billboard>
+}
+
+impl DrawItem {
+    /// The model matrix of the item, billboards are turned to face the camera
+    pub fn model<RD: Renderable>(&self, db: &RD, camera: &Camera) -> Matrix4<f32> {
+        match self.billboard {
+            Some(ref b) => billboard_matrix(camera, b),
+            None => db.position(self.id)
+        }
+    }
+
+    /// The colour the item's material is multiplied by
+    pub fn tint(&self) -> [f32; 4] {
+        self.billboard.map(|b| b.color).unwrap_or([1., 1., 1., 1.])
+    }
+}
+
+/// The model matrix of a billboard, it undoes the rotation of the
+/// camera so the billboard's xy plane faces it.
+pub fn billboard_matrix(camera: &Camera, billboard: &Billboard) -> Matrix4<f32> {
+    let v = camera.view_matrix();
+    let (p, s) = (billboard.position, billboard.size);
+    Matrix4::new(v.x.x * s, v.y.x * s, v.z.x * s, 0.,
+                 v.x.y * s, v.y.y * s, v.z.y * s, 0.,
+                 v.x.z * s, v.y.z * s, v.z.z * s, 0.,
+                 p[0], p[1], p[2], 1.)
+}
+
+/// The blend mode of a material, drawables without a material are opaque
+pub fn material_blend<G: Graphics>(db: &G, material: Entity) -> BlendMode {
+    if let Some(mat) = db.material(material) {
+        mat.blend()
+    } else if let Some(mat) = db.pbr_material(material) {
+        mat.blend()
+    } else {
+        BlendMode::Opaque
+    }
+}
+
+fn by_depth(a: &DrawItem, b: &DrawItem) -> Ordering {
+    a.depth.partial_cmp(&b.depth).unwrap_or(Ordering::Equal)
+}
+
+/// The drawables of a frame split by how they are blended. Opaque
+/// drawables are sorted front to back so the depth test can skip
+/// hidden fragments, blended drawables are sorted back to front so
+/// they are mixed with what is behind them.
+#[derive(Clone, Debug)]
+pub struct RenderQueues {
+    pub opaque: Vec<DrawItem>,
+    pub transparent: Vec<DrawItem>
+}
+
+impl RenderQueues {
+    /// Sort `visible`, the drawables returned by `Culler::visible`
+    pub fn new<RD: Renderable>(db: &RD,
+                               camera: &Camera,
+                               visible: Vec<(Entity, Drawable)>) -> RenderQueues {
+        RenderQueues::with_billboards(db, camera, visible, Vec::new())
+    }
+
+    /// Sort `visible` together with `billboards`, the billboards
+    /// returned by `Culler::billboards`
+    pub fn with_billboards<RD: Renderable>(db: &RD,
+                                           camera: &Camera,
+                                           visible: Vec<(Entity, Drawable)>,
+                                           billboards: Vec<(Entity, Drawable, Billboard)>) -> RenderQueues {
+        let view = camera.view_matrix();
+        let mut queues = RenderQueues {
+            opaque: Vec::new(),
+            transparent: Vec::new()
+        };
+
+        let items = visible.into_iter().map(|(id, draw)| (id, draw, None))
+            .chain(billboards.into_iter().map(|(id, draw, b)| (id, draw, Some(b))));
+        for (id, draw, billboard) in items {
+            let origin = match billboard {
+                Some(b) => Vector4::new(b.position[0], b.position[1], b.position[2], 1.),
+                None => db.position(id).mul_v(&Vector4::new(0., 0., 0., 1.))
+            };
+            let p = view.mul_v(&origin);
+            let item = DrawItem {
+                id: id,
+                draw: draw,
+                blend: material_blend(db, draw.material),
+                depth: -p.z,
+                billboard: billboard
+            };
+            if item.blend.is_opaque() {
+                queues.opaque.push(item);
+            } else {
+                queues.transparent.push(item);
+            }
+        }
+
+        queues.opaque.sort_by(|a, b| by_depth(a, b));
+        queues.transparent.sort_by(|a, b| by_depth(b, a));
+        queues
+    }
+
+    /// every item, opaque ones first
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item=&'a DrawItem> + 'a> {
+        Box::new(self.opaque.iter().chain(self.transparent.iter()))
+    }
+}
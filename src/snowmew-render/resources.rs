@@ -0,0 +1,107 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+
+
+use std::collections::BTreeMap;
+
+use snowmew::common::Entity;
+
+/// What happened to a resource since a render last loaded it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Added(Entity),
+    /// the resource was set again, it has to be uploaded again
+    Modified(Entity),
+    /// the resource no longer exists, what was uploaded can be freed
+    Removed(Entity)
+}
+
+/// Remembers the revision of each resource a render has loaded, see
+/// `Graphics::revision`. Comparing it with the resources in the game
+/// tells a render what to upload and what to free.
+#[derive(Clone, Debug)]
+pub struct ResourceTracker {
+    loaded: BTreeMap<Entity, u64>
+}
+
+impl ResourceTracker {
+    pub fn new() -> ResourceTracker {
+        ResourceTracker {
+            loaded: BTreeMap::new()
+        }
+    }
+
+    /// the number of resources that are loaded
+    pub fn len(&self) -> usize { self.loaded.len() }
+
+    pub fn contains(&self, oid: Entity) -> bool {
+        self.loaded.contains_key(&oid)
+    }
+
+    /// Compare the entity and revision of every resource that should be
+    /// loaded with what is loaded. The changes are sorted by entity and
+    /// the tracker is left matching `current`.
+    pub fn update<I>(&mut self, current: I) -> Vec<Change>
+        where I: Iterator<Item=(Entity, u64)> {
+
+        let mut changes = Vec::new();
+        let mut next = BTreeMap::new();
+        for (oid, revision) in current {
+            match self.loaded.remove(&oid) {
+                None => changes.push(Change::Added(oid)),
+                Some(old) if old != revision => changes.push(Change::Modified(oid)),
+                Some(_) => ()
+            }
+            next.insert(oid, revision);
+        }
+        for (&oid, _) in self.loaded.iter() {
+            changes.push(Change::Removed(oid));
+        }
+        changes.sort_by(|a, b| entity(a).cmp(&entity(b)));
+        self.loaded = next;
+        changes
+    }
+}
+
+fn entity(change: &Change) -> Entity {
+    match *change {
+        Change::Added(oid) | Change::Modified(oid) | Change::Removed(oid) => oid
+    }
+}
+
+/// The resources a render is holding for the game. The counts are of
+/// what is loaded now, `uploads` and `frees` count every upload and
+/// free since the render started.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct ResourceStats {
+    pub meshes: usize,
+    pub textures: usize,
+    pub materials: usize,
+    pub uploads: usize,
+    pub frees: usize
+}
+
+impl ResourceStats {
+    pub fn new() -> ResourceStats {
+        ResourceStats {
+            meshes: 0,
+            textures: 0,
+            materials: 0,
+            uploads: 0,
+            frees: 0
+        }
+    }
+}
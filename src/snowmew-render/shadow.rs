@@ -0,0 +1,184 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::f32;
+use std::cmp::min;
+use std::num::Float;
+
+use cgmath::{Matrix, Matrix4, Vector, EuclideanVector, Vector3, Vector4};
+use cgmath::{Point, Point3};
+use cgmath::{ortho, perspective, rad};
+
+use graphics::light::Shadow;
+use camera::Camera;
+use lights::{Lights, DirectionalLight, SpotLight};
+
+/// The most shadow maps a render has to draw in a frame. Directional
+/// lights are given fewer cascades once the maps run low, lights that
+/// find no maps left do not cast shadows.
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+/// How far behind a cascade an object can be and still cast a shadow into it
+const CASTER_DEPTH: f32 = 500.;
+
+/// How much the cascades favour logarithmic splits over even splits
+const SPLIT_LAMBDA: f32 = 0.75;
+
+/// A shadow map that has to be drawn this frame
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowView {
+    pub projection: Matrix4<f32>,
+    pub view: Matrix4<f32>,
+    pub resolution: u32,
+    pub bias: f32,
+    /// The map covers everything closer to the camera than this,
+    /// measured along the camera's view direction.
+    pub split: f32
+}
+
+/// The split distances for `count` cascades between `near` and `far`,
+/// the last split is always `far`.
+pub fn cascade_splits(near: f32, far: f32, count: u32) -> Vec<f32> {
+    (1..count + 1).map(|i| {
+        let f = i as f32 / count as f32;
+        let log = near * (far / near).powf(f);
+        let even = near + (far - near) * f;
+        SPLIT_LAMBDA * log + (1. - SPLIT_LAMBDA) * even
+    }).collect()
+}
+
+/// The views of each cascade of a directional light. Each cascade is
+/// an orthographic projection around a bounding sphere of its slice of
+/// the camera's frustum, snapped to the texels of the map so the
+/// shadow edges do not shimmer as the camera moves.
+pub fn directional_views(camera: &Camera,
+                         light: &DirectionalLight,
+                         shadow: &Shadow) -> Vec<ShadowView> {
+    let dir = light.normal;
+    let up = if dir.y.abs() > 0.99 {
+        Vector3::new(0., 0., 1.)
+    } else {
+        Vector3::new(0., 1., 0.)
+    };
+
+    let mut near = camera.near();
+    let mut views = Vec::new();
+    for split in cascade_splits(camera.near(), shadow.distance, shadow.cascades).into_iter() {
+        let corners = camera.frustum_corners(near, split);
+        let center = corners.iter()
+                            .fold(Vector3::new(0., 0., 0.), |s, c| s.add_v(&c.to_vec()))
+                            .div_s(8.);
+        let center = Point3::from_vec(&center);
+        let radius = corners.iter()
+                            .fold(0f32, |r, c| r.max(c.sub_p(&center).length()))
+                            .ceil();
+
+        let view = Matrix4::look_at(&center.add_v(&dir), &center, &up);
+        let mut projection = ortho(-radius, radius, -radius, radius,
+                                   -radius - CASTER_DEPTH, radius + CASTER_DEPTH);
+
+        // move the projection so the world origin lands on a texel
+        let half = shadow.resolution as f32 / 2.;
+        let origin = projection.mul_m(&view).mul_v(&Vector4::new(0., 0., 0., 1.));
+        let (x, y) = (origin.x * half, origin.y * half);
+        projection.w.x += (x.round() - x) / half;
+        projection.w.y += (y.round() - y) / half;
+
+        views.push(ShadowView {
+            projection: projection,
+            view: view,
+            resolution: shadow.resolution,
+            bias: shadow.bias,
+            split: split
+        });
+        near = split;
+    }
+    views
+}
+
+/// The view of a spot light, a perspective projection that covers its cone
+pub fn spot_view(light: &SpotLight, shadow: &Shadow) -> ShadowView {
+    let dir = light.direction;
+    let up = if dir.y.abs() > 0.99 {
+        Vector3::new(0., 0., 1.)
+    } else {
+        Vector3::new(0., 1., 0.)
+    };
+    let eye = Point3::from_vec(&light.position);
+    let fov = (light.outer * 2.).min(3.);
+
+    ShadowView {
+        projection: perspective(rad(fov), 1., 0.05, shadow.distance),
+        view: Matrix4::look_at(&eye, &eye.add_v(&dir), &up),
+        resolution: shadow.resolution,
+        bias: shadow.bias,
+        split: f32::INFINITY
+    }
+}
+
+/// The shadow maps needed to draw a frame. Lights are given maps in
+/// order, directional lights first, until `MAX_SHADOW_MAPS` is reached.
+/// A directional light that wants more cascades than there are maps
+/// left has its view split into as many cascades as still fit.
+#[derive(Clone, Debug)]
+pub struct ShadowPlan {
+    pub views: Vec<ShadowView>,
+    /// the first map and the number of maps of each directional light
+    pub directional: Vec<(usize, usize)>,
+    /// the first map and the number of maps of each spot light
+    pub spot: Vec<(usize, usize)>
+}
+
+impl ShadowPlan {
+    pub fn new(camera: &Camera, lights: &Lights) -> ShadowPlan {
+        let mut plan = ShadowPlan {
+            views: Vec::new(),
+            directional: Vec::new(),
+            spot: Vec::new()
+        };
+
+        for light in lights.directional.iter() {
+            let free = (MAX_SHADOW_MAPS - plan.views.len()) as u32;
+            let views = match light.shadow {
+                Some(mut shadow) if free > 0 && shadow.cascades > 0 => {
+                    shadow.cascades = min(shadow.cascades, free);
+                    directional_views(camera, light, &shadow)
+                }
+                _ => Vec::new()
+            };
+            let maps = plan.add(views);
+            plan.directional.push(maps);
+        }
+
+        for light in lights.spot.iter() {
+            let views = match light.shadow {
+                Some(ref shadow) => vec![spot_view(light, shadow)],
+                None => Vec::new()
+            };
+            let maps = plan.add(views);
+            plan.spot.push(maps);
+        }
+        plan
+    }
+
+    fn add(&mut self, views: Vec<ShadowView>) -> (usize, usize) {
+        let first = self.views.len();
+        if views.len() == 0 || first + views.len() > MAX_SHADOW_MAPS {
+            return (0, 0);
+        }
+        let count = views.len();
+        self.views.extend(views.into_iter());
+        (first, count)
+    }
+}
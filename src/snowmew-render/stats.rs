@@ -0,0 +1,27 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use overlay::{Anchor, Overlay};
+
+pub use input::RenderStats;
+
+/// the layer the stats are drawn on, over the game's overlay
+pub const STATS_LAYER: i32 = 2000;
+
+/// The text drawn in the top left corner, see `Renderable::set_show_stats`
+pub fn overlay(stats: &RenderStats) -> Overlay {
+    Overlay::text(&stats.text(), 1, [1., 1., 0., 1.])
+        .anchored(Anchor::TopLeft, 4., 4.)
+        .layered(STATS_LAYER)
+}
@@ -0,0 +1,163 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+use std::collections::HashSet;
+
+use cgmath::Vector4;
+use collect::iter::{OrderedMapIterator, OrderedSetIterator};
+
+use snowmew::common::{Common, Entity, Scene};
+use graphics::Graphics;
+use Renderable;
+
+/// The part of the frame a view draws to. The values are fractions of
+/// the width and height of the frame with the origin in the top left.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+impl Viewport {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport {
+            x: x,
+            y: y,
+            width: width,
+            height: height
+        }
+    }
+
+    /// the whole frame
+    pub fn full() -> Viewport {
+        Viewport::new(0., 0., 1., 1.)
+    }
+
+    /// The x, y, width and height in pixels of the viewport in a frame
+    /// of `width` by `height` pixels. The viewport is at least one pixel
+    /// and always inside the frame, a viewport past the right or bottom
+    /// edge is moved onto the last column or row.
+    pub fn pixels(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let (w, h) = (width as f32, height as f32);
+        let x = ((self.x * w).round() as u32).min(width.max(1) - 1);
+        let y = ((self.y * h).round() as u32).min(height.max(1) - 1);
+        let x2 = (((self.x + self.width) * w).round() as u32).min(width);
+        let y2 = (((self.y + self.height) * h).round() as u32).min(height);
+        (x, y, (x2 - x).max(1), (y2 - y).max(1))
+    }
+}
+
+/// What is cleared before a view is drawn, the colour is left alone
+/// if it is `None`.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Clear {
+    pub color: Option<Vector4<f32>>,
+    pub depth: bool
+}
+
+impl Clear {
+    /// clear the depth and the colour to `color`
+    pub fn color(color: Vector4<f32>) -> Clear {
+        Clear {
+            color: Some(color),
+            depth: true
+        }
+    }
+
+    /// only clear the depth, the view is drawn over what is there
+    pub fn depth() -> Clear {
+        Clear {
+            color: None,
+            depth: true
+        }
+    }
+}
+
+/// A camera drawing a scene to part of the frame, or to a texture
+/// when `target` is set. The target is a texture entity, the view is
+/// drawn at the size of the texture and materials that use it as a map
+/// see the result.
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct RenderView {
+    pub camera: Entity,
+    pub scene: Scene,
+    pub viewport: Viewport,
+    pub clear: Clear,
+    pub target: Option<Entity>
+}
+
+impl RenderView {
+    /// a view of the whole frame that is drawn over what is there
+    pub fn new(camera: Entity, scene: Scene) -> RenderView {
+        RenderView {
+            camera: camera,
+            scene: scene,
+            viewport: Viewport::full(),
+            clear: Clear::depth(),
+            target: None
+        }
+    }
+}
+
+/// Every texture the materials of a scene's drawables read from
+pub fn scene_textures<RD: Renderable>(db: &RD, scene: Scene) -> HashSet<Entity> {
+    let mut textures = HashSet::new();
+    for (_, draw) in db.scene_iter(scene).inner_join_map(db.drawable_iter()) {
+        let maps = if let Some(m) = db.material(draw.material) {
+            vec![m.map_ka(), m.map_kd(), m.map_ks(), m.map_ke(), m.map_ns(),
+                 m.map_d(), m.map_bump(), m.map_refl()]
+        } else if let Some(m) = db.pbr_material(draw.material) {
+            vec![m.base_color_map(), m.metallic_roughness_map(), m.normal_map(),
+                 m.occlusion_map(), m.emissive_map()]
+        } else {
+            continue
+        };
+        textures.extend(maps.into_iter().filter_map(|m| m));
+    }
+    textures
+}
+
+/// Put the views in the order they have to be drawn, a view that draws
+/// to a texture comes before the views that show that texture. Views
+/// that do not depend on each other keep their order, so later views
+/// are drawn over earlier ones. If the views form a cycle the first
+/// remaining view is drawn and sees the texture from the last frame.
+pub fn order_views<RD: Renderable>(db: &RD, views: Vec<RenderView>) -> Vec<RenderView> {
+    let reads: Vec<HashSet<Entity>> = views.iter()
+                                           .map(|v| scene_textures(db, v.scene))
+                                           .collect();
+
+    let mut done: Vec<bool> = views.iter().map(|_| false).collect();
+    let mut order = Vec::with_capacity(views.len());
+    while order.len() < views.len() {
+        // a view is ready once no other remaining view draws a texture it reads
+        let ready = (0..views.len()).find(|&i| {
+            !done[i] && (0..views.len()).all(|j| {
+                j == i || done[j] || match views[j].target {
+                    Some(t) => !reads[i].contains(&t),
+                    None => true
+                }
+            })
+        });
+        let next = ready.unwrap_or_else(|| {
+            (0..views.len()).find(|&i| !done[i]).unwrap()
+        });
+        done[next] = true;
+        order.push(views[next]);
+    }
+    order
+}
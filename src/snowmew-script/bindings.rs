@@ -0,0 +1,368 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! The functions scripts use to talk to the engine
+
+use std::num::Float;
+
+use cgmath::{Vector, Vector3, Vector4, Matrix, EuclideanVector, Rotation3, deg, ToRad};
+use rustc_serialize::json;
+
+use snowmew::common::{Common, Entity};
+use position::Positions;
+use graphics::Graphics;
+use input::Button;
+
+use Value;
+use eval::{Context, Bindings};
+
+fn arity(args: &[Value], n: usize) -> Result<(), String> {
+    if args.len() != n {
+        Err(format!("expected {} arguments got {}", n, args.len()))
+    } else {
+        Ok(())
+    }
+}
+
+fn num(v: &Value) -> Result<f64, String> {
+    match *v {
+        Value::Num(n) => Ok(n),
+        ref v => Err(format!("expected a number got {}", v.type_name()))
+    }
+}
+
+fn vec3(v: &Value) -> Result<Vector3<f32>, String> {
+    match *v {
+        Value::Vec3(v) => Ok(v),
+        ref v => Err(format!("expected a vec3 got {}", v.type_name()))
+    }
+}
+
+fn entity(v: &Value) -> Result<Entity, String> {
+    match *v {
+        Value::Entity(e) => Ok(e),
+        Value::Num(n) if n >= 0. => Ok(n as Entity),
+        ref v => Err(format!("expected an entity got {}", v.type_name()))
+    }
+}
+
+/// Find a button by name, this is the name of the `Button` variant
+/// with or without the `Keyboard` prefix, `"W"` and `"KeyboardW"`
+/// are the same button.
+pub fn button(name: &str) -> Option<Button> {
+    json::decode(&format!("\"{}\"", name))
+        .or_else(|_| json::decode(&format!("\"Keyboard{}\"", name)))
+        .ok()
+}
+
+fn button_arg(v: &Value) -> Result<Button, String> {
+    match *v {
+        Value::Str(ref s) => button(s).ok_or(format!("unknown button `{}`", s)),
+        ref v => Err(format!("expected a button name got {}", v.type_name()))
+    }
+}
+
+fn make_vec3<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 3));
+    Ok(Value::Vec3(Vector3::new(try!(num(&args[0])) as f32,
+                                try!(num(&args[1])) as f32,
+                                try!(num(&args[2])) as f32)))
+}
+
+fn length<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    Ok(Value::Num(try!(vec3(&args[0])).length() as f64))
+}
+
+fn normalize<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let v = try!(vec3(&args[0]));
+    if v.length() == 0. {
+        Ok(Value::Vec3(v))
+    } else {
+        Ok(Value::Vec3(v.normalize()))
+    }
+}
+
+fn dot<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    Ok(Value::Num(try!(vec3(&args[0])).dot(&try!(vec3(&args[1]))) as f64))
+}
+
+fn cross<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    Ok(Value::Vec3(try!(vec3(&args[0])).cross(&try!(vec3(&args[1])))))
+}
+
+fn min<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    Ok(Value::Num(try!(num(&args[0])).min(try!(num(&args[1])))))
+}
+
+fn max<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    Ok(Value::Num(try!(num(&args[0])).max(try!(num(&args[1])))))
+}
+
+fn abs<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    Ok(Value::Num(try!(num(&args[0])).abs()))
+}
+
+fn sqrt<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    Ok(Value::Num(try!(num(&args[0])).sqrt()))
+}
+
+fn sin<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    Ok(Value::Num(try!(num(&args[0])).sin()))
+}
+
+fn cos<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    Ok(Value::Num(try!(num(&args[0])).cos()))
+}
+
+fn floor<GD>(_: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    Ok(Value::Num(try!(num(&args[0])).floor()))
+}
+
+fn print<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    let text: Vec<String> = args.iter().map(|a| format!("{}", a)).collect();
+    println!("[{}] {}", ctx.entity, text.connect(" "));
+    Ok(Value::Nil)
+}
+
+fn this<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 0));
+    Ok(Value::Entity(ctx.entity))
+}
+
+fn dt<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 0));
+    Ok(Value::Num(ctx.dt))
+}
+
+fn time<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 0));
+    Ok(Value::Num(ctx.input.map(|i| i.time()).unwrap_or(0.)))
+}
+
+fn spawn<GD: Common>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    let parent = match args.len() {
+        0 => None,
+        1 => Some(try!(entity(&args[0]))),
+        _ => return Err("expected 0 or 1 arguments".to_string())
+    };
+    Ok(Value::Entity(ctx.gd.new_object(parent)))
+}
+
+fn position<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let e = try!(entity(&args[0]));
+    let p = ctx.gd.position(e).mul_v(&Vector4::new(0., 0., 0., 1.)).truncate();
+    Ok(Value::Vec3(p))
+}
+
+fn displacement<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let e = try!(entity(&args[0]));
+    Ok(ctx.gd.get_displacement(e).map(|&d| Value::Vec3(d)).unwrap_or(Value::Nil))
+}
+
+fn set_displacement<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    let (e, v) = (try!(entity(&args[0])), try!(vec3(&args[1])));
+    ctx.gd.set_displacement(e, v);
+    Ok(Value::Nil)
+}
+
+fn translate<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    let (e, v) = (try!(entity(&args[0])), try!(vec3(&args[1])));
+    let d = ctx.gd.get_displacement(e).map(|&d| d).unwrap_or(Vector3::new(0., 0., 0.));
+    ctx.gd.set_displacement(e, d.add_v(&v));
+    Ok(Value::Nil)
+}
+
+fn scale<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let e = try!(entity(&args[0]));
+    Ok(ctx.gd.get_scale(e).map(|&s| Value::Num(s as f64)).unwrap_or(Value::Nil))
+}
+
+fn set_scale<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    let (e, s) = (try!(entity(&args[0])), try!(num(&args[1])));
+    ctx.gd.set_scale(e, s as f32);
+    Ok(Value::Nil)
+}
+
+/// set_rotation(entity, x, y, z) with the euler angles in degrees
+fn set_rotation<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 4));
+    let e = try!(entity(&args[0]));
+    let (x, y, z) = (try!(num(&args[1])) as f32,
+                     try!(num(&args[2])) as f32,
+                     try!(num(&args[3])) as f32);
+    ctx.gd.set_rotation(e, Rotation3::from_euler(deg(x).to_rad(),
+                                                 deg(y).to_rad(),
+                                                 deg(z).to_rad()));
+    Ok(Value::Nil)
+}
+
+fn parent<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let e = try!(entity(&args[0]));
+    Ok(match ctx.gd.get_parent(e) {
+        Some(&Some(p)) => Value::Entity(p),
+        _ => Value::Nil
+    })
+}
+
+fn set_parent<GD: Positions>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 2));
+    let e = try!(entity(&args[0]));
+    let p = match args[1] {
+        Value::Nil => None,
+        ref v => Some(try!(entity(v)))
+    };
+    ctx.gd.set_parent(e, p);
+    Ok(Value::Nil)
+}
+
+fn set_draw<GD: Graphics>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 3));
+    let (e, geo, mat) = (try!(entity(&args[0])),
+                         try!(entity(&args[1])),
+                         try!(entity(&args[2])));
+    ctx.gd.set_draw(e, geo, mat);
+    Ok(Value::Nil)
+}
+
+fn draw_geometry<GD: Graphics>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let e = try!(entity(&args[0]));
+    Ok(ctx.gd.get_draw(e).map(|d| Value::Entity(d.geometry)).unwrap_or(Value::Nil))
+}
+
+fn draw_material<GD: Graphics>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let e = try!(entity(&args[0]));
+    Ok(ctx.gd.get_draw(e).map(|d| Value::Entity(d.material)).unwrap_or(Value::Nil))
+}
+
+fn button_down<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let b = try!(button_arg(&args[0]));
+    Ok(Value::Bool(ctx.input.map(|i| i.button_down(b)).unwrap_or(false)))
+}
+
+fn button_pressed<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let b = try!(button_arg(&args[0]));
+    Ok(Value::Bool(ctx.input.map(|i| i.button_pressed(b)).unwrap_or(false)))
+}
+
+fn button_released<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 1));
+    let b = try!(button_arg(&args[0]));
+    Ok(Value::Bool(ctx.input.map(|i| i.button_released(b)).unwrap_or(false)))
+}
+
+fn mouse<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 0));
+    let (x, y) = ctx.input.map(|i| i.mouse_position()).unwrap_or((0., 0.));
+    Ok(Value::Vec3(Vector3::new(x as f32, y as f32, 0.)))
+}
+
+fn mouse_delta<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 0));
+    let (x, y) = ctx.input.map(|i| i.mouse_delta()).unwrap_or((0., 0.));
+    Ok(Value::Vec3(Vector3::new(x as f32, y as f32, 0.)))
+}
+
+fn scroll_delta<GD>(ctx: &mut Context<GD>, args: &[Value]) -> Result<Value, String> {
+    try!(arity(args, 0));
+    let (x, y) = ctx.input.map(|i| i.scroll_delta()).unwrap_or((0., 0.));
+    Ok(Value::Vec3(Vector3::new(x as f32, y as f32, 0.)))
+}
+
+/// Math and utility functions that do not touch the game
+pub fn math<GD>(b: &mut Bindings<GD>) {
+    b.register("vec3", make_vec3);
+    b.register("length", length);
+    b.register("normalize", normalize);
+    b.register("dot", dot);
+    b.register("cross", cross);
+    b.register("min", min);
+    b.register("max", max);
+    b.register("abs", abs);
+    b.register("sqrt", sqrt);
+    b.register("sin", sin);
+    b.register("cos", cos);
+    b.register("floor", floor);
+    b.register("print", print);
+    b.register("self", this);
+    b.register("dt", dt);
+    b.register("time", time);
+}
+
+/// Bindings to `Common`
+pub fn common<GD: Common>(b: &mut Bindings<GD>) {
+    b.register("spawn", spawn);
+}
+
+/// Bindings to `Positions`
+pub fn positions<GD: Positions>(b: &mut Bindings<GD>) {
+    b.register("position", position);
+    b.register("displacement", displacement);
+    b.register("set_displacement", set_displacement);
+    b.register("translate", translate);
+    b.register("scale", scale);
+    b.register("set_scale", set_scale);
+    b.register("set_rotation", set_rotation);
+    b.register("parent", parent);
+    b.register("set_parent", set_parent);
+}
+
+/// Bindings to `Graphics`
+pub fn graphics<GD: Graphics>(b: &mut Bindings<GD>) {
+    b.register("set_draw", set_draw);
+    b.register("draw_geometry", draw_geometry);
+    b.register("draw_material", draw_material);
+}
+
+/// Bindings to the `InputIntegratorState`
+pub fn input<GD>(b: &mut Bindings<GD>) {
+    b.register("button_down", button_down);
+    b.register("button_pressed", button_pressed);
+    b.register("button_released", button_released);
+    b.register("mouse", mouse);
+    b.register("mouse_delta", mouse_delta);
+    b.register("scroll_delta", scroll_delta);
+}
+
+/// All of the bindings to the engine
+pub fn standard<GD: Common + Positions + Graphics>() -> Bindings<GD> {
+    let mut b = Bindings::new();
+    math(&mut b);
+    common(&mut b);
+    positions(&mut b);
+    graphics(&mut b);
+    input(&mut b);
+    b
+}
@@ -0,0 +1,218 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+
+use cgmath::Vector;
+use snowmew::common::Entity;
+use input_integrator::InputIntegratorState;
+
+use {Value, ScriptError};
+use parse::{Expr, Stmt, Block, UnOp, BinOp};
+
+/// the most times a `while` loop can run in one step before
+/// the script is considered stuck
+pub const LOOP_LIMIT: u32 = 100000;
+
+/// Everything a binding can see while a script is running
+pub struct Context<'a, GD: 'a> {
+    pub gd: &'a mut GD,
+    /// the entity the script is attached to
+    pub entity: Entity,
+    pub input: Option<&'a InputIntegratorState>,
+    /// seconds since the last step
+    pub dt: f64
+}
+
+/// A function that can be called by a script. Errors are reported
+/// to the script writer with the line that made the call.
+pub type Binding<GD> = fn(&mut Context<GD>, &[Value]) -> Result<Value, String>;
+
+/// The set of functions that scripts can call
+pub struct Bindings<GD> {
+    functions: HashMap<String, Binding<GD>>
+}
+
+impl<GD> Bindings<GD> {
+    /// an empty set of bindings, see `bindings::standard` for
+    /// the bindings to the engine.
+    pub fn new() -> Bindings<GD> {
+        Bindings { functions: HashMap::new() }
+    }
+
+    pub fn register(&mut self, name: &str, f: Binding<GD>) {
+        self.functions.insert(name.to_string(), f);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Binding<GD>> {
+        self.functions.get(name).map(|&f| f)
+    }
+}
+
+struct Interpreter<'a, 'b: 'a, GD: 'b> {
+    ctx: &'a mut Context<'b, GD>,
+    vars: &'a mut BTreeMap<String, Value>,
+    bindings: &'a Bindings<GD>
+}
+
+fn error(line: u32, message: String) -> ScriptError {
+    ScriptError { line: line, message: message }
+}
+
+fn arith(line: u32, op: BinOp, a: Value, b: Value) -> Result<Value, ScriptError> {
+    match (op, a, b) {
+        (BinOp::Add, Value::Num(a), Value::Num(b)) => Ok(Value::Num(a + b)),
+        (BinOp::Sub, Value::Num(a), Value::Num(b)) => Ok(Value::Num(a - b)),
+        (BinOp::Mul, Value::Num(a), Value::Num(b)) => Ok(Value::Num(a * b)),
+        (BinOp::Div, Value::Num(a), Value::Num(b)) => Ok(Value::Num(a / b)),
+        (BinOp::Rem, Value::Num(a), Value::Num(b)) => Ok(Value::Num(a % b)),
+        (BinOp::Add, Value::Vec3(a), Value::Vec3(b)) => Ok(Value::Vec3(a.add_v(&b))),
+        (BinOp::Sub, Value::Vec3(a), Value::Vec3(b)) => Ok(Value::Vec3(a.sub_v(&b))),
+        (BinOp::Mul, Value::Vec3(a), Value::Num(b)) => Ok(Value::Vec3(a.mul_s(b as f32))),
+        (BinOp::Mul, Value::Num(a), Value::Vec3(b)) => Ok(Value::Vec3(b.mul_s(a as f32))),
+        (BinOp::Div, Value::Vec3(a), Value::Num(b)) => Ok(Value::Vec3(a.div_s(b as f32))),
+        (BinOp::Add, Value::Str(a), b) => Ok(Value::Str(format!("{}{}", a, b))),
+        (BinOp::Lt, Value::Num(a), Value::Num(b)) => Ok(Value::Bool(a < b)),
+        (BinOp::Le, Value::Num(a), Value::Num(b)) => Ok(Value::Bool(a <= b)),
+        (BinOp::Gt, Value::Num(a), Value::Num(b)) => Ok(Value::Bool(a > b)),
+        (BinOp::Ge, Value::Num(a), Value::Num(b)) => Ok(Value::Bool(a >= b)),
+        (BinOp::Eq, a, b) => Ok(Value::Bool(a == b)),
+        (BinOp::Ne, a, b) => Ok(Value::Bool(a != b)),
+        (op, a, b) => Err(error(line, format!("can not apply {:?} to {} and {}",
+                                              op, a.type_name(), b.type_name())))
+    }
+}
+
+impl<'a, 'b, GD> Interpreter<'a, 'b, GD> {
+    fn block(&mut self, block: &Block) -> Result<(), ScriptError> {
+        for &(line, ref stmt) in block.iter() {
+            try!(self.statement(line, stmt));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, line: u32, stmt: &Stmt) -> Result<(), ScriptError> {
+        match *stmt {
+            Stmt::Let(ref name, ref expr) => {
+                if !self.vars.contains_key(name) {
+                    let value = try!(self.expr(line, expr));
+                    self.vars.insert(name.clone(), value);
+                }
+            }
+            Stmt::Assign(ref name, ref expr) => {
+                if !self.vars.contains_key(name) {
+                    return Err(error(line, format!("`{}` is not defined", name)));
+                }
+                let value = try!(self.expr(line, expr));
+                self.vars.insert(name.clone(), value);
+            }
+            Stmt::If(ref cond, ref then, ref otherwise) => {
+                if try!(self.expr(line, cond)).truthy() {
+                    try!(self.block(then));
+                } else {
+                    try!(self.block(otherwise));
+                }
+            }
+            Stmt::While(ref cond, ref body) => {
+                let mut count = 0;
+                while try!(self.expr(line, cond)).truthy() {
+                    count += 1;
+                    if count > LOOP_LIMIT {
+                        return Err(error(line, "loop ran too many times".to_string()));
+                    }
+                    try!(self.block(body));
+                }
+            }
+            Stmt::Expr(ref expr) => {
+                try!(self.expr(line, expr));
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, line: u32, expr: &Expr) -> Result<Value, ScriptError> {
+        match *expr {
+            Expr::Nil => Ok(Value::Nil),
+            Expr::Bool(b) => Ok(Value::Bool(b)),
+            Expr::Num(n) => Ok(Value::Num(n)),
+            Expr::Str(ref s) => Ok(Value::Str(s.clone())),
+            Expr::Var(ref name) => {
+                match self.vars.get(name) {
+                    Some(v) => Ok(v.clone()),
+                    None => Err(error(line, format!("`{}` is not defined", name)))
+                }
+            }
+            Expr::Unary(op, ref e) => {
+                match (op, try!(self.expr(line, e))) {
+                    (UnOp::Neg, Value::Num(n)) => Ok(Value::Num(-n)),
+                    (UnOp::Neg, Value::Vec3(v)) => Ok(Value::Vec3(v.mul_s(-1.))),
+                    (UnOp::Not, v) => Ok(Value::Bool(!v.truthy())),
+                    (_, v) => Err(error(line, format!("can not negate {}", v.type_name())))
+                }
+            }
+            Expr::Binary(BinOp::And, ref a, ref b) => {
+                if try!(self.expr(line, a)).truthy() {
+                    Ok(Value::Bool(try!(self.expr(line, b)).truthy()))
+                } else {
+                    Ok(Value::Bool(false))
+                }
+            }
+            Expr::Binary(BinOp::Or, ref a, ref b) => {
+                if try!(self.expr(line, a)).truthy() {
+                    Ok(Value::Bool(true))
+                } else {
+                    Ok(Value::Bool(try!(self.expr(line, b)).truthy()))
+                }
+            }
+            Expr::Binary(op, ref a, ref b) => {
+                let a = try!(self.expr(line, a));
+                let b = try!(self.expr(line, b));
+                arith(line, op, a, b)
+            }
+            Expr::Field(ref e, ref field) => {
+                match (try!(self.expr(line, e)), &field[..]) {
+                    (Value::Vec3(v), "x") => Ok(Value::Num(v.x as f64)),
+                    (Value::Vec3(v), "y") => Ok(Value::Num(v.y as f64)),
+                    (Value::Vec3(v), "z") => Ok(Value::Num(v.z as f64)),
+                    (v, f) => Err(error(line, format!("{} has no field `{}`", v.type_name(), f)))
+                }
+            }
+            Expr::Call(ref name, ref args) => {
+                let f = match self.bindings.get(name) {
+                    Some(f) => f,
+                    None => return Err(error(line, format!("`{}` is not a function", name)))
+                };
+                let mut values = Vec::with_capacity(args.len());
+                for a in args.iter() {
+                    values.push(try!(self.expr(line, a)));
+                }
+                f(self.ctx, &values).map_err(|msg| error(line, format!("{}: {}", name, msg)))
+            }
+        }
+    }
+}
+
+/// Run a script once. `vars` are the variables the script has
+/// defined on previous runs, they are updated in place.
+pub fn run<GD>(program: &Block,
+               ctx: &mut Context<GD>,
+               vars: &mut BTreeMap<String, Value>,
+               bindings: &Bindings<GD>) -> Result<(), ScriptError> {
+    let mut interp = Interpreter {
+        ctx: ctx,
+        vars: vars,
+        bindings: bindings
+    };
+    interp.block(program)
+}
@@ -0,0 +1,367 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![crate_name = "snowmew-script"]
+#![crate_type = "lib"]
+#![feature(old_io, old_path, core, collections, std_misc)]
+
+extern crate cgmath;
+extern crate "rustc-serialize" as rustc_serialize;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-input" as input;
+extern crate "snowmew-input-integrator" as input_integrator;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem;
+use std::old_io::File;
+use std::old_io::fs;
+
+use cgmath::Vector3;
+
+use snowmew::common::{Common, Entity, Duplicate, Delete};
+use snowmew::game::Game;
+use snowmew::table::Static;
+use position::Positions;
+use graphics::Graphics;
+use input_integrator::InputIntegratorState;
+
+pub use eval::{Context, Binding, Bindings, run};
+pub use parse::{parse, Block};
+
+/// contains the parser for the scripting language
+pub mod parse;
+/// contains the interpreter
+pub mod eval;
+/// contains the functions scripts can use to talk to the engine
+pub mod bindings;
+
+/// A value in a script
+#[derive(Clone, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Vec3(Vector3<f32>),
+    Entity(Entity)
+}
+
+impl Value {
+    /// `nil` and `false` are false, everything else is true
+    pub fn truthy(&self) -> bool {
+        match *self {
+            Value::Nil | Value::Bool(false) => false,
+            _ => true
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Num(_) => "number",
+            Value::Str(_) => "string",
+            Value::Vec3(_) => "vec3",
+            Value::Entity(_) => "entity"
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(ref s) => write!(f, "{}", s),
+            Value::Vec3(v) => write!(f, "vec3({}, {}, {})", v.x, v.y, v.z),
+            Value::Entity(e) => write!(f, "entity({})", e)
+        }
+    }
+}
+
+/// An error from parsing or running a script
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptError {
+    /// the line of the script the error happened on, this is
+    /// 0 if the error is not from a line of the script.
+    pub line: u32,
+    pub message: String
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// A parsed script, if the script was loaded from a file it
+/// will be reloaded when the file changes.
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Script {
+    program: Block,
+    path: Option<String>,
+    modified: u64
+}
+
+impl Script {
+    pub fn from_str(src: &str) -> Result<Script, ScriptError> {
+        parse(src).map(|program| {
+            Script {
+                program: program,
+                path: None,
+                modified: 0
+            }
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Script, ScriptError> {
+        let modified = try!(modified(path));
+        let src = try!(File::open(path).read_to_string().map_err(|e| {
+            ScriptError { line: 0, message: format!("{}", e) }
+        }));
+        Script::from_str(&src).map(|mut s| {
+            s.path = Some(format!("{}", path.display()));
+            s.modified = modified;
+            s
+        })
+    }
+
+    pub fn program<'a>(&'a self) -> &'a Block { &self.program }
+}
+
+fn modified(path: &Path) -> Result<u64, ScriptError> {
+    fs::stat(path)
+        .map(|s| s.modified)
+        .map_err(|e| ScriptError { line: 0, message: format!("{}", e) })
+}
+
+/// The script attached to an entity and the variables it has defined
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct Attached {
+    pub script: Entity,
+    pub vars: BTreeMap<String, Value>
+}
+
+#[derive(Clone, RustcEncodable, RustcDecodable)]
+pub struct ScriptData {
+    scripts:  Static<Script>,
+    attached: Static<Attached>
+}
+
+impl ScriptData {
+    pub fn new() -> ScriptData {
+        ScriptData {
+            scripts: Static::new(),
+            attached: Static::new()
+        }
+    }
+}
+
+pub trait Scripting: Common + Sized {
+    fn get_scripting<'a>(&'a self) -> &'a ScriptData;
+    fn get_scripting_mut<'a>(&'a mut self) -> &'a mut ScriptData;
+
+    fn new_script(&mut self, script: Script) -> Entity {
+        let oid = self.new_object(None);
+        self.get_scripting_mut().scripts.insert(oid, script);
+        oid
+    }
+
+    fn script<'a>(&'a self, oid: Entity) -> Option<&'a Script> {
+        self.get_scripting().scripts.get(oid)
+    }
+
+    /// Run `script` on `oid` every step. The entity starts without
+    /// any variables, they are created by the `let`s in the script.
+    fn attach_script(&mut self, oid: Entity, script: Entity) {
+        self.get_scripting_mut().attached.insert(oid, Attached {
+            script: script,
+            vars: BTreeMap::new()
+        });
+    }
+
+    fn detach_script(&mut self, oid: Entity) -> bool {
+        self.get_scripting_mut().attached.remove(oid)
+    }
+
+    /// read a variable that the entity's script has defined
+    fn script_var(&self, oid: Entity, name: &str) -> Option<Value> {
+        self.get_scripting().attached.get(oid)
+            .and_then(|a| a.vars.get(name))
+            .map(|v| v.clone())
+    }
+
+    /// Check the files that scripts were loaded from and reload any that
+    /// have changed. The variables of attached entities are kept. If a
+    /// script fails to reload the old version is kept and the error is
+    /// returned.
+    fn reload_scripts(&mut self) -> Vec<(Entity, ScriptError)> {
+        let files: Vec<(Entity, String, u64)> = self.get_scripting().scripts.iter()
+            .filter_map(|(oid, s)| s.path.clone().map(|p| (oid, p, s.modified)))
+            .collect();
+
+        let mut errors = Vec::new();
+        for (oid, path, last) in files.into_iter() {
+            let path = Path::new(path);
+            match modified(&path) {
+                Ok(m) if m == last => continue,
+                Ok(_) => (),
+                Err(e) => {
+                    errors.push((oid, e));
+                    continue;
+                }
+            }
+            match Script::from_file(&path) {
+                Ok(s) => { self.get_scripting_mut().scripts.insert(oid, s); }
+                Err(e) => {
+                    // don't report the same broken file every step
+                    let m = modified(&path).unwrap_or(last);
+                    self.get_scripting_mut().scripts.get_mut(oid).map(|s| s.modified = m);
+                    errors.push((oid, e));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Run the script of every entity that has one, in entity order.
+    /// A script that fails stops at the error, its variables
+    /// keep any changes made before the error.
+    fn run_scripts(&mut self,
+                   bindings: &Bindings<Self>,
+                   input: Option<&InputIntegratorState>,
+                   dt: f64) -> Vec<(Entity, ScriptError)> {
+        let attached: Vec<(Entity, Entity)> = self.get_scripting().attached.iter()
+            .map(|(oid, a)| (oid, a.script))
+            .collect();
+
+        let mut errors = Vec::new();
+        let mut current: Option<(Entity, Block)> = None;
+        for &(oid, script) in attached.iter() {
+            if current.as_ref().map(|&(s, _)| s != script).unwrap_or(true) {
+                current = match self.script(script) {
+                    Some(s) => Some((script, s.program.clone())),
+                    None => {
+                        errors.push((oid, ScriptError {
+                            line: 0,
+                            message: format!("script {} does not exist", script)
+                        }));
+                        None
+                    }
+                };
+            }
+            let program = match current {
+                Some((_, ref p)) => p,
+                None => continue
+            };
+
+            let mut vars = mem::replace(
+                &mut self.get_scripting_mut().attached.get_mut(oid).unwrap().vars,
+                BTreeMap::new()
+            );
+            let result = {
+                let mut ctx = Context {
+                    gd: &mut *self,
+                    entity: oid,
+                    input: input,
+                    dt: dt
+                };
+                run(program, &mut ctx, &mut vars, bindings)
+            };
+            // the script may have detached itself
+            self.get_scripting_mut().attached.get_mut(oid).map(|a| a.vars = vars);
+            if let Err(e) = result {
+                errors.push((oid, e));
+            }
+        }
+        errors
+    }
+}
+
+impl Duplicate for ScriptData {
+    fn duplicate(&mut self, src: Entity, dst: Entity) {
+        let x = self.scripts.get(src).map(|x| x.clone());
+        x.map(|x| self.scripts.insert(dst, x));
+        let x = self.attached.get(src).map(|x| x.clone());
+        x.map(|x| self.attached.insert(dst, x));
+    }
+}
+
+impl Delete for ScriptData {
+    fn delete(&mut self, oid: Entity) -> bool {
+        self.scripts.remove(oid) |
+        self.attached.remove(oid)
+    }
+}
+
+/// Wraps a game so that the scripts are run after each step of the
+/// wrapped game. This is meant to be used inside of the input integrator.
+pub struct ScriptRunner<G, GD> {
+    game: G,
+    bindings: Bindings<GD>,
+    hot_reload: bool,
+    errors: Vec<(Entity, ScriptError)>
+}
+
+impl<G, GD: Common + Positions + Graphics> ScriptRunner<G, GD> {
+    /// create a runner with the standard bindings
+    pub fn new(game: G) -> ScriptRunner<G, GD> {
+        ScriptRunner::with_bindings(game, bindings::standard())
+    }
+}
+
+impl<G, GD> ScriptRunner<G, GD> {
+    pub fn with_bindings(game: G, bindings: Bindings<GD>) -> ScriptRunner<G, GD> {
+        ScriptRunner {
+            game: game,
+            bindings: bindings,
+            hot_reload: true,
+            errors: Vec::new()
+        }
+    }
+
+    /// turn checking script files for changes on or off
+    pub fn set_hot_reload(&mut self, enabled: bool) {
+        self.hot_reload = enabled;
+    }
+
+    pub fn bindings_mut<'a>(&'a mut self) -> &'a mut Bindings<GD> {
+        &mut self.bindings
+    }
+
+    /// the errors from the last step
+    pub fn errors<'a>(&'a self) -> &'a [(Entity, ScriptError)] {
+        &self.errors
+    }
+}
+
+impl<GD: Scripting, G: Game<GD, InputIntegratorState>>
+    Game<GD, InputIntegratorState> for ScriptRunner<G, GD> {
+    fn step(&mut self, input: InputIntegratorState, gd: GD) -> GD {
+        let mut gd = self.game.step(input.clone(), gd);
+        self.errors.clear();
+        if self.hot_reload {
+            let errors = gd.reload_scripts();
+            self.errors.extend(errors.into_iter());
+        }
+        let errors = gd.run_scripts(&self.bindings, Some(&input), input.time_delta());
+        self.errors.extend(errors.into_iter());
+        gd
+    }
+}
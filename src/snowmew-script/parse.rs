@@ -0,0 +1,384 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use ScriptError;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum UnOp {
+    Neg,
+    Not
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum BinOp {
+    Add, Sub, Mul, Div, Rem,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    And, Or
+}
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Expr {
+    Nil,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Var(String),
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    /// call a binding with arguments
+    Call(String, Vec<Expr>),
+    /// read `.x`, `.y` or `.z` of a vector
+    Field(Box<Expr>, String)
+}
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Stmt {
+    /// define a variable if it does not already exist
+    Let(String, Expr),
+    Assign(String, Expr),
+    If(Expr, Block, Block),
+    While(Expr, Block),
+    Expr(Expr)
+}
+
+/// A list of statements with the line they start on
+pub type Block = Vec<(u32, Stmt)>;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Let, If, Else, While, True, False, Nil,
+    LParen, RParen, LBrace, RBrace,
+    Comma, Semi, Dot,
+    Plus, Minus, Star, Slash, Percent,
+    Assign, Eq, Ne, Lt, Le, Gt, Ge,
+    And, Or, Not,
+    Eof
+}
+
+fn error(line: u32, message: String) -> ScriptError {
+    ScriptError { line: line, message: message }
+}
+
+fn lex(src: &str) -> Result<Vec<(u32, Token)>, ScriptError> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = if i + 1 < chars.len() { Some(chars[i+1]) } else { None };
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        if c.is_digit(10) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_digit(10) || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|&c| c).collect();
+            match text.parse() {
+                Ok(n) => tokens.push((line, Token::Num(n))),
+                Err(_) => return Err(error(line, format!("invalid number `{}`", text)))
+            }
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|&c| c).collect();
+            let token = match &text[..] {
+                "let" => Token::Let,
+                "if" => Token::If,
+                "else" => Token::Else,
+                "while" => Token::While,
+                "true" => Token::True,
+                "false" => Token::False,
+                "nil" => Token::Nil,
+                _ => Token::Ident(text)
+            };
+            tokens.push((line, token));
+            continue;
+        }
+
+        if c == '"' {
+            let start_line = line;
+            let mut text = String::new();
+            i += 1;
+            loop {
+                if i >= chars.len() {
+                    return Err(error(start_line, "unterminated string".to_string()));
+                }
+                match chars[i] {
+                    '"' => break,
+                    '\n' => { line += 1; text.push('\n'); }
+                    ch => text.push(ch)
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push((start_line, Token::Str(text)));
+            continue;
+        }
+
+        let (token, len) = match (c, next) {
+            ('=', Some('=')) => (Token::Eq, 2),
+            ('!', Some('=')) => (Token::Ne, 2),
+            ('<', Some('=')) => (Token::Le, 2),
+            ('>', Some('=')) => (Token::Ge, 2),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', _) => (Token::Assign, 1),
+            ('<', _) => (Token::Lt, 1),
+            ('>', _) => (Token::Gt, 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('{', _) => (Token::LBrace, 1),
+            ('}', _) => (Token::RBrace, 1),
+            (',', _) => (Token::Comma, 1),
+            (';', _) => (Token::Semi, 1),
+            ('.', _) => (Token::Dot, 1),
+            ('+', _) => (Token::Plus, 1),
+            ('-', _) => (Token::Minus, 1),
+            ('*', _) => (Token::Star, 1),
+            ('/', _) => (Token::Slash, 1),
+            ('%', _) => (Token::Percent, 1),
+            _ => return Err(error(line, format!("unexpected character `{}`", c)))
+        };
+        tokens.push((line, token));
+        i += len;
+    }
+
+    tokens.push((line, Token::Eof));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(u32, Token)>,
+    pos: usize
+}
+
+impl Parser {
+    fn peek(&self) -> &Token { &self.tokens[self.pos].1 }
+    fn line(&self) -> u32 { self.tokens[self.pos].0 }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.pos].1.clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        if *self.peek() == token {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), ScriptError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(error(self.line(), format!("expected {} found {:?}", what, self.peek())))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ScriptError> {
+        match self.next() {
+            Token::Ident(name) => Ok(name),
+            t => Err(error(self.line(), format!("expected a name found {:?}", t)))
+        }
+    }
+
+    fn block(&mut self) -> Result<Block, ScriptError> {
+        try!(self.expect(Token::LBrace, "`{`"));
+        let mut stmts = Vec::new();
+        while !self.eat(Token::RBrace) {
+            if *self.peek() == Token::Eof {
+                return Err(error(self.line(), "expected `}`".to_string()));
+            }
+            stmts.push(try!(self.statement()));
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<(u32, Stmt), ScriptError> {
+        let line = self.line();
+        let token = self.peek().clone();
+        let stmt = match token {
+            Token::Let => {
+                self.next();
+                let name = try!(self.ident());
+                try!(self.expect(Token::Assign, "`=`"));
+                let value = try!(self.expr());
+                try!(self.expect(Token::Semi, "`;`"));
+                Stmt::Let(name, value)
+            }
+            Token::If => {
+                self.next();
+                let cond = try!(self.expr());
+                let then = try!(self.block());
+                let otherwise = if self.eat(Token::Else) {
+                    if *self.peek() == Token::If {
+                        vec![try!(self.statement())]
+                    } else {
+                        try!(self.block())
+                    }
+                } else {
+                    Vec::new()
+                };
+                Stmt::If(cond, then, otherwise)
+            }
+            Token::While => {
+                self.next();
+                let cond = try!(self.expr());
+                let body = try!(self.block());
+                Stmt::While(cond, body)
+            }
+            _ => {
+                let expr = try!(self.expr());
+                let stmt = if self.eat(Token::Assign) {
+                    match expr {
+                        Expr::Var(name) => Stmt::Assign(name, try!(self.expr())),
+                        _ => return Err(error(line, "can only assign to a variable".to_string()))
+                    }
+                } else {
+                    Stmt::Expr(expr)
+                };
+                try!(self.expect(Token::Semi, "`;`"));
+                stmt
+            }
+        };
+        Ok((line, stmt))
+    }
+
+    fn expr(&mut self) -> Result<Expr, ScriptError> {
+        self.binary(0)
+    }
+
+    /// precedence climbing, `level` is the lowest precedence
+    /// operator that can be consumed
+    fn binary(&mut self, level: u32) -> Result<Expr, ScriptError> {
+        let mut lhs = try!(self.unary());
+        loop {
+            let (op, prec) = match *self.peek() {
+                Token::Or => (BinOp::Or, 0),
+                Token::And => (BinOp::And, 1),
+                Token::Eq => (BinOp::Eq, 2),
+                Token::Ne => (BinOp::Ne, 2),
+                Token::Lt => (BinOp::Lt, 3),
+                Token::Le => (BinOp::Le, 3),
+                Token::Gt => (BinOp::Gt, 3),
+                Token::Ge => (BinOp::Ge, 3),
+                Token::Plus => (BinOp::Add, 4),
+                Token::Minus => (BinOp::Sub, 4),
+                Token::Star => (BinOp::Mul, 5),
+                Token::Slash => (BinOp::Div, 5),
+                Token::Percent => (BinOp::Rem, 5),
+                _ => return Ok(lhs)
+            };
+            if prec < level {
+                return Ok(lhs);
+            }
+            self.next();
+            let rhs = try!(self.binary(prec + 1));
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        if self.eat(Token::Minus) {
+            return Ok(Expr::Unary(UnOp::Neg, Box::new(try!(self.unary()))));
+        }
+        if self.eat(Token::Not) {
+            return Ok(Expr::Unary(UnOp::Not, Box::new(try!(self.unary()))));
+        }
+        let mut expr = try!(self.primary());
+        while self.eat(Token::Dot) {
+            expr = Expr::Field(Box::new(expr), try!(self.ident()));
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        let line = self.line();
+        match self.next() {
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::True => Ok(Expr::Bool(true)),
+            Token::False => Ok(Expr::Bool(false)),
+            Token::Nil => Ok(Expr::Nil),
+            Token::LParen => {
+                let e = try!(self.expr());
+                try!(self.expect(Token::RParen, "`)`"));
+                Ok(e)
+            }
+            Token::Ident(name) => {
+                if !self.eat(Token::LParen) {
+                    return Ok(Expr::Var(name));
+                }
+                let mut args = Vec::new();
+                if !self.eat(Token::RParen) {
+                    loop {
+                        args.push(try!(self.expr()));
+                        if self.eat(Token::RParen) {
+                            break;
+                        }
+                        try!(self.expect(Token::Comma, "`,` or `)`"));
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            t => Err(error(line, format!("unexpected {:?}", t)))
+        }
+    }
+}
+
+/// Parse the source of a script
+pub fn parse(src: &str) -> Result<Block, ScriptError> {
+    let mut parser = Parser {
+        tokens: try!(lex(src)),
+        pos: 0
+    };
+    let mut stmts = Vec::new();
+    while *parser.peek() != Token::Eof {
+        stmts.push(try!(parser.statement()));
+    }
+    Ok(stmts)
+}
@@ -0,0 +1,137 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+
+
+use snowmew::common::Entity;
+use render::{Renderable, Overlay, Anchor};
+
+/// the overlay layer the ui is drawn on, above the rest of the HUD
+pub const UI_LAYER: i32 = 1000;
+
+/// A shape drawn by a widget, positions are in pixels from the top
+/// left of the screen
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub enum DrawCommand {
+    /// `x`, `y`, `width` and `height`
+    Rect{rect: [f32; 4], color: [f32; 4]},
+    /// text in the bitmap font with its top left at `x`, `y`
+    Text{x: f32, y: f32, scale: u32, text: String, color: [f32; 4]}
+}
+
+impl DrawCommand {
+    /// the command as an overlay item
+    pub fn overlay(&self) -> Overlay {
+        match *self {
+            DrawCommand::Rect{rect, color} => {
+                Overlay::rect(rect[2], rect[3], color)
+                    .anchored(Anchor::TopLeft, rect[0], rect[1])
+            }
+            DrawCommand::Text{x, y, scale, ref text, color} => {
+                Overlay::text(text, scale, color)
+                    .anchored(Anchor::TopLeft, x, y)
+            }
+        }
+    }
+}
+
+/// The shapes a frame of the ui draws, in the order they are drawn
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct DrawList {
+    commands: Vec<DrawCommand>
+}
+
+impl DrawList {
+    pub fn new() -> DrawList {
+        DrawList {
+            commands: Vec::new()
+        }
+    }
+
+    pub fn commands(&self) -> &[DrawCommand] { &self.commands }
+
+    pub fn len(&self) -> usize { self.commands.len() }
+
+    pub fn push(&mut self, command: DrawCommand) {
+        self.commands.push(command);
+    }
+
+    pub fn rect(&mut self, rect: [f32; 4], color: [f32; 4]) {
+        self.commands.push(DrawCommand::Rect {
+            rect: rect,
+            color: color
+        });
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, scale: u32, text: &str, color: [f32; 4]) {
+        self.commands.push(DrawCommand::Text {
+            x: x,
+            y: y,
+            scale: scale,
+            text: text.to_string(),
+            color: color
+        });
+    }
+
+    /// add the commands of another list after this one's
+    pub fn append(&mut self, other: DrawList) {
+        self.commands.extend(other.commands.into_iter());
+    }
+
+    /// the commands as overlay items on the ui's layer
+    pub fn overlays(&self) -> Vec<Overlay> {
+        self.commands.iter().map(|c| c.overlay().layered(UI_LAYER)).collect()
+    }
+}
+
+/// The overlay entities a ui draws with. Entities are kept from frame
+/// to frame, ones that are not needed are hidden rather than removed.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct UiOverlays {
+    entities: Vec<Entity>
+}
+
+impl UiOverlays {
+    pub fn new() -> UiOverlays {
+        UiOverlays {
+            entities: Vec::new()
+        }
+    }
+
+    pub fn entities(&self) -> &[Entity] { &self.entities }
+
+    /// Replace what was shown with the draw list. The entities are
+    /// made in order, so items later in the list are drawn on top.
+    pub fn show<RD: Renderable>(&mut self, gd: &mut RD, list: &DrawList) {
+        let overlays = list.overlays();
+        for (i, overlay) in overlays.into_iter().enumerate() {
+            if i < self.entities.len() {
+                gd.set_overlay(self.entities[i], overlay);
+            } else {
+                self.entities.push(gd.new_overlay(overlay));
+            }
+        }
+        for &oid in self.entities[list.len()..].iter() {
+            let hidden = gd.overlay(oid).map(|o| {
+                let mut o = o.clone();
+                o.visible = false;
+                o
+            });
+            if let Some(hidden) = hidden {
+                gd.set_overlay(oid, hidden);
+            }
+        }
+    }
+}
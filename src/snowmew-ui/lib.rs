@@ -0,0 +1,567 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+#![crate_name = "snowmew-ui"]
+#![crate_type = "lib"]
+#![feature(core)]
+
+extern crate "rustc-serialize" as rustc_serialize;
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-input" as input;
+extern crate "snowmew-input-integrator" as integrator;
+extern crate "snowmew-render" as render;
+
+use std::num::Float;
+
+use input::Button;
+use integrator::InputIntegratorState;
+use render::font;
+
+pub use draw::{DrawCommand, DrawList, UiOverlays, UI_LAYER};
+
+/// contains the draw lists the widgets produce
+pub mod draw;
+
+/// The keys that type a character, with the character typed with and
+/// without shift held
+static TEXT_KEYS: &'static [(Button, char, char)] = &[
+    (Button::KeyboardA, 'a', 'A'), (Button::KeyboardB, 'b', 'B'),
+    (Button::KeyboardC, 'c', 'C'), (Button::KeyboardD, 'd', 'D'),
+    (Button::KeyboardE, 'e', 'E'), (Button::KeyboardF, 'f', 'F'),
+    (Button::KeyboardG, 'g', 'G'), (Button::KeyboardH, 'h', 'H'),
+    (Button::KeyboardI, 'i', 'I'), (Button::KeyboardJ, 'j', 'J'),
+    (Button::KeyboardK, 'k', 'K'), (Button::KeyboardL, 'l', 'L'),
+    (Button::KeyboardM, 'm', 'M'), (Button::KeyboardN, 'n', 'N'),
+    (Button::KeyboardO, 'o', 'O'), (Button::KeyboardP, 'p', 'P'),
+    (Button::KeyboardQ, 'q', 'Q'), (Button::KeyboardR, 'r', 'R'),
+    (Button::KeyboardS, 's', 'S'), (Button::KeyboardT, 't', 'T'),
+    (Button::KeyboardU, 'u', 'U'), (Button::KeyboardV, 'v', 'V'),
+    (Button::KeyboardW, 'w', 'W'), (Button::KeyboardX, 'x', 'X'),
+    (Button::KeyboardY, 'y', 'Y'), (Button::KeyboardZ, 'z', 'Z'),
+    (Button::Keyboard0, '0', ')'), (Button::Keyboard1, '1', '!'),
+    (Button::Keyboard2, '2', '@'), (Button::Keyboard3, '3', '#'),
+    (Button::Keyboard4, '4', '$'), (Button::Keyboard5, '5', '%'),
+    (Button::Keyboard6, '6', '^'), (Button::Keyboard7, '7', '&'),
+    (Button::Keyboard8, '8', '*'), (Button::Keyboard9, '9', '('),
+    (Button::KeyboardSpace, ' ', ' '), (Button::KeyboardApostrophe, '\'', '"'),
+    (Button::KeyboardComma, ',', '<'), (Button::KeyboardMinus, '-', '_'),
+    (Button::KeyboardPeriod, '.', '>'), (Button::KeyboardSlash, '/', '?'),
+    (Button::KeyboardSemicolon, ';', ':'), (Button::KeyboardEqual, '=', '+'),
+    (Button::KeyboardLeftBracket, '[', '{'), (Button::KeyboardRightBracket, ']', '}'),
+    (Button::KeyboardBackslash, '\\', '|'), (Button::KeyboardGraveAccent, '`', '~')
+];
+
+/// true if `point` is inside `rect`, the left and top edges are inside
+/// and the right and bottom edges are not
+pub fn hit(rect: [f32; 4], point: [f32; 2]) -> bool {
+    point[0] >= rect[0] && point[0] < rect[0] + rect[2] &&
+    point[1] >= rect[1] && point[1] < rect[1] + rect[3]
+}
+
+/// The input the ui sees for one frame, the mouse is in pixels from
+/// the top left of the window and the button is the left mouse button
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct UiInput {
+    pub mouse: [f32; 2],
+    pub down: bool,
+    /// the button went down this frame
+    pub pressed: bool,
+    /// the button went up this frame
+    pub released: bool,
+    /// how far the scroll wheel moved this frame
+    pub scroll: [f32; 2],
+    /// the characters typed this frame
+    pub text: String,
+    pub backspace: bool,
+    pub enter: bool
+}
+
+impl UiInput {
+    /// no buttons held with the mouse at `x`, `y`
+    pub fn new(x: f32, y: f32) -> UiInput {
+        UiInput {
+            mouse: [x, y],
+            down: false,
+            pressed: false,
+            released: false,
+            scroll: [0., 0.],
+            text: String::new(),
+            backspace: false,
+            enter: false
+        }
+    }
+
+    /// the input of the frame the integrator has collected
+    pub fn from_integrator(state: &InputIntegratorState) -> UiInput {
+        let (x, y) = state.mouse_position();
+        let (sx, sy) = state.scroll_delta();
+        let shift = state.button_down(Button::KeyboardLeftShift) ||
+                    state.button_down(Button::KeyboardRightShift);
+        let text = TEXT_KEYS.iter()
+            .filter(|&&(key, _, _)| state.button_pressed(key))
+            .map(|&(_, lower, upper)| if shift { upper } else { lower })
+            .collect();
+        UiInput {
+            mouse: [x as f32, y as f32],
+            down: state.button_down(Button::MouseLeft),
+            pressed: state.button_pressed(Button::MouseLeft),
+            released: state.button_released(Button::MouseLeft),
+            scroll: [sx as f32, sy as f32],
+            text: text,
+            backspace: state.button_pressed(Button::KeyboardBackspace),
+            enter: state.button_pressed(Button::KeyboardEnter)
+        }
+    }
+}
+
+/// The sizes and colours the widgets are drawn with
+#[derive(Clone, RustcEncodable, RustcDecodable, Copy, Debug, PartialEq)]
+pub struct Style {
+    /// the scale of the bitmap font
+    pub scale: u32,
+    /// the space between a widget's edge and its text
+    pub padding: f32,
+    /// the space between widgets
+    pub spacing: f32,
+    /// the width of widgets that are not in a window
+    pub width: f32,
+    pub text: [f32; 4],
+    pub background: [f32; 4],
+    pub title: [f32; 4],
+    pub widget: [f32; 4],
+    /// a widget under the mouse
+    pub hot: [f32; 4],
+    /// a widget that is held
+    pub active: [f32; 4],
+    /// check marks, slider fills and carets
+    pub accent: [f32; 4]
+}
+
+impl Style {
+    pub fn new() -> Style {
+        Style {
+            scale: 1,
+            padding: 2.,
+            spacing: 2.,
+            width: 160.,
+            text: [1., 1., 1., 1.],
+            background: [0.1, 0.1, 0.1, 0.8],
+            title: [0.2, 0.2, 0.4, 1.],
+            widget: [0.25, 0.25, 0.25, 1.],
+            hot: [0.35, 0.35, 0.35, 1.],
+            active: [0.45, 0.45, 0.45, 1.],
+            accent: [0.4, 0.6, 1., 1.]
+        }
+    }
+
+    /// the height of a widget with a single line of text
+    pub fn row_height(&self) -> f32 {
+        (font::GLYPH_HEIGHT * self.scale) as f32 + self.padding * 2.
+    }
+}
+
+/// What the mouse did to a widget this frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interaction {
+    /// the mouse is over the widget and nothing is covering it
+    pub hovered: bool,
+    /// the button went down over the widget
+    pub pressed: bool,
+    /// the widget was pressed and the button is still down
+    pub held: bool,
+    /// the widget was pressed and the button went up over it
+    pub clicked: bool
+}
+
+/// What the ui remembers between frames. Widgets are known by their
+/// id, which is their label prefixed by the title of their window.
+#[derive(Clone, RustcEncodable, RustcDecodable, Debug, PartialEq)]
+pub struct UiState {
+    pub style: Style,
+    /// the widget the button went down on
+    active: Option<String>,
+    /// the text field that is typed into
+    focus: Option<String>,
+    /// the title and rect of each window, from the bottom to the top
+    windows: Vec<(String, [f32; 4])>,
+    /// where a dragged window was grabbed from its top left
+    drag: Option<[f32; 2]>
+}
+
+impl UiState {
+    pub fn new() -> UiState {
+        UiState {
+            style: Style::new(),
+            active: None,
+            focus: None,
+            windows: Vec::new(),
+            drag: None
+        }
+    }
+
+    pub fn active(&self) -> Option<&str> { self.active.as_ref().map(|s| &s[..]) }
+
+    pub fn focus(&self) -> Option<&str> { self.focus.as_ref().map(|s| &s[..]) }
+
+    /// the rect of a window as it was last drawn
+    pub fn window_rect(&self, title: &str) -> Option<[f32; 4]> {
+        self.windows.iter().find(|w| w.0 == title).map(|w| w.1)
+    }
+
+    /// the titles of the windows, from the bottom to the top
+    pub fn window_order(&self) -> Vec<&str> {
+        self.windows.iter().map(|w| &w.0[..]).collect()
+    }
+
+    /// the top most window under a point
+    pub fn window_at(&self, point: [f32; 2]) -> Option<&str> {
+        self.windows.iter().rev().find(|w| hit(w.1, point)).map(|w| &w.0[..])
+    }
+}
+
+/// a window that is being filled with widgets
+struct OpenWindow {
+    title: String,
+    rect: [f32; 4],
+    list: DrawList,
+    /// the layout outside of the window
+    cursor: [f32; 2],
+    width: f32
+}
+
+/// One frame of an immediate mode ui. Widgets are laid out from top to
+/// bottom as they are called and report what the mouse did to them, the
+/// result is a `DrawList` once the frame is finished. Windows are hit
+/// tested with where they were last frame, so a widget covered by a
+/// window does not see the mouse.
+pub struct Ui<'a> {
+    state: &'a mut UiState,
+    input: UiInput,
+    style: Style,
+    /// the window under the mouse when the frame started
+    hover: Option<String>,
+    root: DrawList,
+    window: Option<OpenWindow>,
+    /// the windows drawn this frame with their rect
+    windows: Vec<(String, [f32; 4], DrawList)>,
+    raise: Option<String>,
+    /// a text field was clicked this frame
+    focused: bool,
+    cursor: [f32; 2],
+    width: f32
+}
+
+impl<'a> Ui<'a> {
+    pub fn new(state: &'a mut UiState, input: UiInput) -> Ui<'a> {
+        let style = state.style;
+        let hover = state.window_at(input.mouse).map(|s| s.to_string());
+        Ui {
+            state: state,
+            input: input,
+            style: style,
+            hover: hover,
+            root: DrawList::new(),
+            window: None,
+            windows: Vec::new(),
+            raise: None,
+            focused: false,
+            cursor: [style.padding, style.padding],
+            width: style.width
+        }
+    }
+
+    pub fn input(&self) -> &UiInput { &self.input }
+
+    /// true if the mouse is over a window or is held on a widget, the
+    /// game should not act on the mouse when this is true
+    pub fn wants_mouse(&self) -> bool {
+        self.hover.is_some() || self.state.active.is_some()
+    }
+
+    /// the id of a widget in the window that is open
+    fn id(&self, label: &str) -> String {
+        match self.window {
+            Some(ref w) => format!("{}/{}", w.title, label),
+            None => label.to_string()
+        }
+    }
+
+    fn list(&mut self) -> &mut DrawList {
+        match self.window {
+            Some(ref mut w) => &mut w.list,
+            None => &mut self.root
+        }
+    }
+
+    /// the rect of the next widget, `height` pixels high
+    pub fn next_rect(&mut self, height: f32) -> [f32; 4] {
+        let rect = [self.cursor[0], self.cursor[1], self.width, height];
+        self.cursor[1] += height + self.style.spacing;
+        rect
+    }
+
+    /// Hit test a widget. The widget is hovered if the mouse is over it
+    /// and it is in the top most window under the mouse. A widget that
+    /// is pressed stays active until the button is released.
+    pub fn interact(&mut self, id: &str, rect: [f32; 4]) -> Interaction {
+        let window = self.window.as_ref().map(|w| &w.title[..]);
+        let hovered = window == self.hover.as_ref().map(|s| &s[..]) &&
+                      hit(rect, self.input.mouse);
+        let mut result = Interaction {
+            hovered: hovered,
+            pressed: false,
+            held: false,
+            clicked: false
+        };
+        if hovered && self.input.pressed && self.state.active.is_none() {
+            self.state.active = Some(id.to_string());
+            result.pressed = true;
+        }
+        if self.state.active.as_ref().map(|s| &s[..]) == Some(id) {
+            result.held = self.input.down;
+            if !self.input.down {
+                result.clicked = hovered && self.input.released;
+                self.state.active = None;
+            }
+        }
+        result
+    }
+
+    fn widget_color(&self, i: Interaction) -> [f32; 4] {
+        if i.held {
+            self.style.active
+        } else if i.hovered {
+            self.style.hot
+        } else {
+            self.style.widget
+        }
+    }
+
+    /// Start a window, the widgets that follow are placed in it until
+    /// `end_window`. The window starts at `x`, `y` and can be dragged by
+    /// its title bar, clicking a window raises it over the others.
+    /// Windows can not be nested.
+    pub fn begin_window(&mut self, title: &str, x: f32, y: f32, width: f32) {
+        assert!(self.window.is_none(), "windows can not be nested");
+        let pos = match self.state.window_rect(title) {
+            Some(r) => [r[0], r[1]],
+            None => [x, y]
+        };
+        self.window = Some(OpenWindow {
+            title: title.to_string(),
+            rect: [pos[0], pos[1], width, 0.],
+            list: DrawList::new(),
+            cursor: self.cursor,
+            width: self.width
+        });
+        if self.hover.as_ref().map(|s| &s[..]) == Some(title) && self.input.pressed {
+            self.raise = Some(title.to_string());
+        }
+
+        let row = self.style.row_height();
+        let id = self.id("#title");
+        let bar = self.interact(&id, [pos[0], pos[1], width, row]);
+        if bar.pressed {
+            self.state.drag = Some([self.input.mouse[0] - pos[0], self.input.mouse[1] - pos[1]]);
+        }
+        let pos = match self.state.drag {
+            Some(grab) if bar.held => [self.input.mouse[0] - grab[0], self.input.mouse[1] - grab[1]],
+            _ => pos
+        };
+
+        let (style, pad) = (self.style, self.style.padding);
+        if let Some(ref mut w) = self.window {
+            w.rect[0] = pos[0];
+            w.rect[1] = pos[1];
+            w.list.rect([pos[0], pos[1], width, row], style.title);
+            w.list.text(pos[0] + pad, pos[1] + pad, style.scale, title, style.text);
+        }
+        self.cursor = [pos[0] + pad, pos[1] + row + pad];
+        self.width = width - pad * 2.;
+    }
+
+    /// finish the window that was started by `begin_window`
+    pub fn end_window(&mut self) {
+        let mut w = self.window.take().expect("end_window without begin_window");
+        w.rect[3] = self.cursor[1] - self.style.spacing + self.style.padding - w.rect[1];
+        let mut list = DrawList::new();
+        list.rect(w.rect, self.style.background);
+        list.append(w.list);
+        self.windows.push((w.title, w.rect, list));
+        self.cursor = w.cursor;
+        self.width = w.width;
+    }
+
+    /// a line of text
+    pub fn label(&mut self, text: &str) {
+        let (_, h) = font::text_size(text, self.style.scale);
+        let rect = self.next_rect(h as f32);
+        let style = self.style;
+        self.list().text(rect[0], rect[1], style.scale, text, style.text);
+    }
+
+    /// a button, true if it was clicked
+    pub fn button(&mut self, label: &str) -> bool {
+        let row = self.style.row_height();
+        let rect = self.next_rect(row);
+        let id = self.id(label);
+        let i = self.interact(&id, rect);
+        let (style, color) = (self.style, self.widget_color(i));
+        let (w, _) = font::text_size(label, style.scale);
+        let list = self.list();
+        list.rect(rect, color);
+        list.text(rect[0] + (rect[2] - w as f32) / 2., rect[1] + style.padding,
+                  style.scale, label, style.text);
+        i.clicked
+    }
+
+    /// a box that is ticked when `value` is true, clicking it flips
+    /// `value`. True if `value` was changed.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let row = self.style.row_height();
+        let rect = self.next_rect(row);
+        let id = self.id(label);
+        let i = self.interact(&id, rect);
+        if i.clicked {
+            *value = !*value;
+        }
+        let (style, color) = (self.style, self.widget_color(i));
+        let pad = style.padding;
+        let checked = *value;
+        let list = self.list();
+        list.rect([rect[0], rect[1], row, row], color);
+        if checked {
+            list.rect([rect[0] + pad, rect[1] + pad, row - pad * 2., row - pad * 2.], style.accent);
+        }
+        list.text(rect[0] + row + pad, rect[1] + pad, style.scale, label, style.text);
+        i.clicked
+    }
+
+    /// A bar that sets `value` between `min` and `max`, it is set by
+    /// dragging across the bar or by scrolling over it. True if `value`
+    /// was changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let row = self.style.row_height();
+        let rect = self.next_rect(row);
+        let id = self.id(label);
+        let i = self.interact(&id, rect);
+        let old = *value;
+        if i.held {
+            let t = (self.input.mouse[0] - rect[0]) / rect[2];
+            *value = min + t.max(0.).min(1.) * (max - min);
+        } else if i.hovered && self.input.scroll[1] != 0. {
+            let step = (max - min) / 20.;
+            *value = (*value + self.input.scroll[1] * step).max(min).min(max);
+        }
+
+        let (style, color) = (self.style, self.widget_color(i));
+        let t = if max > min { ((*value - min) / (max - min)).max(0.).min(1.) } else { 0. };
+        let text = format!("{}: {:.2}", label, *value);
+        let list = self.list();
+        list.rect(rect, color);
+        list.rect([rect[0], rect[1], rect[2] * t, rect[3]], style.accent);
+        list.text(rect[0] + style.padding, rect[1] + style.padding, style.scale, &text, style.text);
+        *value != old
+    }
+
+    /// A line of text that can be typed into once it is clicked, enter
+    /// or clicking somewhere else stops the typing. The label is shown
+    /// when the text is empty. True if the text was changed.
+    pub fn text_field(&mut self, label: &str, text: &mut String) -> bool {
+        let row = self.style.row_height();
+        let rect = self.next_rect(row);
+        let id = self.id(label);
+        let i = self.interact(&id, rect);
+        if i.pressed {
+            self.state.focus = Some(id.clone());
+            self.focused = true;
+        }
+
+        let mut changed = false;
+        let focused = self.state.focus.as_ref() == Some(&id);
+        if focused {
+            if self.input.backspace {
+                changed |= text.pop().is_some();
+            }
+            if self.input.text.len() != 0 {
+                text.push_str(&self.input.text);
+                changed = true;
+            }
+            if self.input.enter {
+                self.state.focus = None;
+            }
+        }
+
+        let style = self.style;
+        let color = if focused { style.hot } else { self.widget_color(i) };
+        let (shown, shown_color) = if text.len() == 0 {
+            (label, [style.text[0], style.text[1], style.text[2], style.text[3] * 0.5])
+        } else {
+            (&text[..], style.text)
+        };
+        let (w, _) = font::text_size(&text[..], style.scale);
+        let (x, y) = (rect[0] + style.padding, rect[1] + style.padding);
+        let list = self.list();
+        list.rect(rect, color);
+        list.text(x, y, style.scale, shown, shown_color);
+        if focused {
+            let caret = if w == 0 { x } else { x + w as f32 + style.scale as f32 };
+            list.rect([caret, y, style.scale as f32, (font::GLYPH_HEIGHT * style.scale) as f32], style.accent);
+        }
+        changed
+    }
+
+    /// End the frame, the windows are drawn over the widgets outside of
+    /// them in the order they are stacked
+    pub fn finish(mut self) -> DrawList {
+        if self.window.is_some() {
+            self.end_window();
+        }
+        if self.input.pressed && !self.focused {
+            self.state.focus = None;
+        }
+        if !self.input.down {
+            self.state.active = None;
+            self.state.drag = None;
+        }
+
+        // windows keep their place in the stack, new windows go on top
+        let mut drawn = self.windows;
+        let mut order: Vec<String> = self.state.windows.iter()
+            .map(|w| w.0.clone())
+            .filter(|t| drawn.iter().any(|d| d.0 == *t))
+            .collect();
+        for d in drawn.iter() {
+            if !order.contains(&d.0) {
+                order.push(d.0.clone());
+            }
+        }
+        if let Some(raise) = self.raise {
+            order.retain(|t| *t != raise);
+            order.push(raise);
+        }
+
+        let mut list = self.root;
+        self.state.windows.clear();
+        for title in order.into_iter() {
+            let i = drawn.iter().position(|d| d.0 == title).unwrap();
+            let (title, rect, window) = drawn.remove(i);
+            self.state.windows.push((title, rect));
+            list.append(window);
+        }
+        list
+    }
+}
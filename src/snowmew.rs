@@ -22,6 +22,7 @@ extern crate "snowmew-loader"       as _loader;
 extern crate "snowmew-position"     as _position;
 extern crate "snowmew-render-mux"   as _mux;
 extern crate "snowmew-render"  as _render;
+extern crate "snowmew-render-soft" as _soft;
 #[cfg(feature="debugger")]
 extern crate "snowmew-debugger" as _debugger;
 extern crate "snowmew-random" as _random;
@@ -31,6 +32,12 @@ extern crate "snowmew-timer" as _timer;
 extern crate "snowmew-network" as _network;
 extern crate "snowmew-input" as _input;
 extern crate "snowmew-input-integrator" as _input_integrator;
+extern crate "snowmew-audio" as _audio;
+extern crate "snowmew-ai" as _ai;
+extern crate "snowmew-nav" as _nav;
+extern crate "snowmew-particle" as _particle;
+extern crate "snowmew-script" as _script;
+extern crate "snowmew-ui" as _ui;
 #[cfg(feature="use_opencl")]
 extern crate opencl;
 extern crate glfw;
@@ -43,20 +50,84 @@ pub mod render {
     pub use _render::{
         RenderData,
         Renderable,
-        IntoRender
+        IntoRender,
+        BasicRenderData
     };
     pub use _render::{
         RenderFactory,
-        Render
+        RenderTarget,
+        UnsupportedTarget,
+        Render,
+        NullRender,
+        Capture,
+        Recorder,
+        save_png,
+        Lights,
+        Surface,
+        MAX_LIGHTS,
+        ShadowPlan,
+        ShadowView,
+        MAX_SHADOW_MAPS,
+        Bounds,
+        Culler,
+        DebugDraw,
+        DebugDrawReset,
+        DebugLine,
+        DebugText,
+        Environment,
+        Sky,
+        Ambient,
+        ShProbe,
+        Fog,
+        FogMode,
+        HeightFog,
+        Frustum,
+        Anchor,
+        Overlay,
+        OverlayContent,
+        OverlayQuad,
+        QuadTexture,
+        DrawItem,
+        RenderQueues,
+        BatchPlan,
+        BatchState,
+        Batch,
+        ShadowBatch,
+        Instance,
+        Program,
+        Change,
+        ResourceTracker,
+        ResourceStats,
+        RenderStats,
+        RenderView,
+        Viewport,
+        Clear,
+        PostChain,
+        PostEffect,
+        PostImage,
+        PostStage,
+        ToneMap
     };
     pub use _input::DisplayConfig;
     pub use _render::camera::{
-        Camera
+        Camera,
+        Projection
     };
+
+    pub mod soft {
+        pub use _soft::{
+            SoftRender,
+            RenderFactory,
+            Frame
+        };
+    }
 }
 
 pub mod graphics {
     pub use _graphics::{
+        billboard,
+        Billboard,
+        BillboardSet,
         Drawable,
         Geometry,
         geometry,
@@ -64,8 +135,13 @@ pub mod graphics {
         GraphicsData,
         light,
         Light,
+        lod,
+        Lod,
         material,
         Material,
+        BlendMode,
+        pbr,
+        PbrMaterial,
         texture,
         Texture,
         VertexBuffer,
@@ -137,9 +213,90 @@ pub mod random {
     };
 }
 
+pub mod audio {
+    pub use _audio::{
+        Audio,
+        AudioData,
+        AudioManager,
+        Clip,
+        Source,
+        Mixer,
+        Output,
+        NullOutput,
+        BufferOutput,
+        WavOutput
+    };
+}
+
+pub mod ai {
+    pub use _ai::{
+        Ai,
+        AiData,
+        Agent,
+        Brain,
+        Node,
+        Status,
+        Value,
+        Blackboard,
+        Actions,
+        Steering,
+        Behaviour,
+        Target
+    };
+}
+
+pub mod nav {
+    pub use _nav::{
+        Navigation,
+        NavData,
+        NavMesh,
+        PathFollower
+    };
+}
+
+pub mod particle {
+    pub use _particle::{
+        Particles,
+        ParticleData,
+        ParticleSimulator,
+        Emitter,
+        Billboard,
+        update_billboards
+    };
+}
+
+pub mod script {
+    pub use _script::{
+        Scripting,
+        ScriptData,
+        Script,
+        ScriptError,
+        ScriptRunner,
+        Value,
+        Bindings,
+        Context,
+        bindings
+    };
+}
+
+pub mod ui {
+    pub use _ui::{
+        Ui,
+        UiState,
+        UiInput,
+        UiOverlays,
+        Style,
+        Interaction,
+        DrawCommand,
+        DrawList,
+        UI_LAYER,
+        hit
+    };
+}
+
 #[cfg(feature="loader")]
 pub mod loader {
-    pub use _loader::Obj;
+    pub use _loader::{Obj, load_texture, load_hdr};
 }
 
 #[cfg(feature="timer")]
@@ -166,7 +323,7 @@ pub mod config {
 
     use super::input::{Event, EventGroup, DisplayConfig};
     use super::core;
-    use super::render::{self, IntoRender};
+    use super::render::{self, IntoRender, RenderTarget, UnsupportedTarget};
     use super::input;
 
     #[cfg(feature="use_opencl")]
@@ -213,7 +370,12 @@ pub mod config {
         /// Configure if the engine should use OpenCL
         pub use_opencl: bool,
         /// Configure the cadence, the minimum peroid for a frame update
-        pub cadance_ms: i64
+        pub cadance_ms: i64,
+        /// Run without creating a display, the render is given an offscreen
+        /// target the size of `display.resolution` (or 800x600 if it is not set)
+        pub headless: bool,
+        /// Stop the engine after this many frames
+        pub frame_limit: Option<u64>
     }
 
     impl SnowmewConfig {
@@ -228,7 +390,9 @@ pub mod config {
                     window: true,
                 },
                 use_opencl: true,
-                cadance_ms: 15
+                cadance_ms: 15,
+                headless: false,
+                frame_limit: None
             }
         }
 
@@ -241,31 +405,47 @@ pub mod config {
                     hmd: true,
                     window: true,
                 },
-                cadance_ms: 15
+                cadance_ms: 15,
+                headless: false,
+                frame_limit: None
             }
         }
 
-        // helper to create the render
+        // helper to create the render, fails if the factory can not
+        // draw to the target
         #[cfg(feature="use_opencl")]
         fn config_render<GameData: Clone,
                          R: render::Render<GameData>,
                          RF: render::RenderFactory<GameData, R>>
-                         (&self, im: &mut input::IOManager, display: input::Window, render: Box<RF>) -> R {
-            let res = im.get_framebuffer_size(&display);            
+                         (&self, target: RenderTarget, render: Box<RF>) -> Result<R, UnsupportedTarget> {
+            if !render.supports(&target) {
+                return Err(UnsupportedTarget { target: target.kind() });
+            }
             let dev = if self.use_opencl { get_cl() } else { None };
-            render.init(im, display, res, dev)
+            Ok(render.init(target, dev))
         }
 
         #[cfg(not(feature="use_opencl"))]
         fn config_render<GameData: Clone,
                          R: render::Render<GameData>,
                          RF: render::RenderFactory<GameData, R>>
-                         (&self, im: &mut input::IOManager, display: input::Window, render: Box<RF>) -> R {
-            let res = im.get_framebuffer_size(&display);            
-            render.init(im, display, res)
+                         (&self, target: RenderTarget, render: Box<RF>) -> Result<R, UnsupportedTarget> {
+            if !render.supports(&target) {
+                return Err(UnsupportedTarget { target: target.kind() });
+            }
+            Ok(render.init(target))
         }
 
-        /// Start the game engine running based on the confirmation.
+        fn running(&self, frames: u64) -> bool {
+            self.frame_limit.map(|limit| frames < limit).unwrap_or(true)
+        }
+
+        /// Start the game engine running based on the confirmation. The
+        /// state of the game is returned when the engine stops. After
+        /// each frame the render's stats are written to the game's
+        /// `IoState::render_stats` and the game's debug draw is cleared.
+        /// If the render can not draw to the target the configuration
+        /// needs, an error is returned before the game is stepped.
         pub fn start<GameData: Clone+input::GetIoState+IntoRender<RenderGameState=RD>,
                      Game: core::Game<GameData, Event>,
                      RD: Clone,
@@ -273,24 +453,42 @@ pub mod config {
                      RF: render::RenderFactory<RD, R>>
                      (self,
                       render: Box<RF>,
-                      mut game: Game,
-                      mut gd: GameData) {
+                      game: Game,
+                      gd: GameData) -> Result<GameData, UnsupportedTarget> {
+            if self.headless {
+                self.start_headless(render, game, gd)
+            } else {
+                self.start_window(render, game, gd)
+            }
+        }
+
+        fn start_window<GameData: Clone+input::GetIoState+IntoRender<RenderGameState=RD>,
+                        Game: core::Game<GameData, Event>,
+                        RD: Clone,
+                        R: render::Render<RD>,
+                        RF: render::RenderFactory<RD, R>>
+                        (self,
+                         render: Box<RF>,
+                         mut game: Game,
+                         mut gd: GameData) -> Result<GameData, UnsupportedTarget> {
             let mut im = input::IOManager::new(setup_glfw());
 
             // create display
             let display = match self.display.create_display(&mut im) {
-                None => return,
+                None => return Ok(gd),
                 Some(display) => display
             };
             let ih = display.handle();
 
-            let mut render = self.config_render(&mut im, display, render);
+            let size = im.get_framebuffer_size(&display);
+            let mut render = try!(self.config_render(RenderTarget::Window(&im, display, size), render));
 
             let mut timer = Timer::new().unwrap();
             let timer_port = timer.periodic(Duration::milliseconds(self.cadance_ms));
             let candance_scale = self.cadance_ms as f64 / 1000.;
 
-            while !im.should_close(&ih) {
+            let mut frames = 0;
+            while !im.should_close(&ih) && self.running(frames) {
                 timer_port.recv().ok().expect("failed to recv");
                 im.poll();
                 loop {
@@ -306,7 +504,45 @@ pub mod config {
                 let next_title = gd.get_io_state().window_title.clone();
                 im.set_title(&ih, next_title);
                 render.update(gd.clone().into_render());
+                gd.get_io_state_mut().render_stats = render.stats();
+                gd.end_frame();
+                frames += 1;
+            }
+            Ok(gd)
+        }
+
+        fn start_headless<GameData: Clone+input::GetIoState+IntoRender<RenderGameState=RD>,
+                          Game: core::Game<GameData, Event>,
+                          RD: Clone,
+                          R: render::Render<RD>,
+                          RF: render::RenderFactory<RD, R>>
+                          (self,
+                           render: Box<RF>,
+                           mut game: Game,
+                           mut gd: GameData) -> Result<GameData, UnsupportedTarget> {
+            let (width, height) = self.display.resolution.unwrap_or((800, 600));
+            {
+                let io = gd.get_io_state_mut();
+                io.size = (width, height);
+                io.render_size = (width, height);
+            }
+
+            let mut render = try!(self.config_render(RenderTarget::Offscreen(width, height), render));
+
+            let mut timer = Timer::new().unwrap();
+            let timer_port = timer.periodic(Duration::milliseconds(self.cadance_ms));
+            let candance_scale = self.cadance_ms as f64 / 1000.;
+
+            let mut frames = 0;
+            while self.running(frames) {
+                timer_port.recv().ok().expect("failed to recv");
+                gd = game.step(Event::Cadance(candance_scale), gd);
+                render.update(gd.clone().into_render());
+                gd.get_io_state_mut().render_stats = render.stats();
+                gd.end_frame();
+                frames += 1;
             }
+            Ok(gd)
         }
     }
 }
@@ -0,0 +1,180 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-random" as random;
+extern crate "snowmew-ai" as ai;
+extern crate cgmath;
+
+use snowmew::common::{Common, CommonData, Entity};
+use position::{Positions, PositionData};
+use random::{Random, RandomData};
+use ai::{Ai, AiData, Agent, Node, Status, Value, Blackboard, Actions};
+use ai::{Steering, Behaviour, Target};
+use cgmath::{Vector, Vector3, EuclideanVector};
+
+#[derive(Clone)]
+struct GameData {
+    common: CommonData,
+    position: PositionData,
+    random: RandomData,
+    ai: AiData,
+    counter: u32
+}
+
+impl Common for GameData {
+    fn get_common<'a>(&'a self) -> &'a CommonData { &self.common }
+    fn get_common_mut<'a>(&'a mut self) -> &'a mut CommonData { &mut self.common }
+}
+
+impl Positions for GameData {
+    fn get_position<'a>(&'a self) -> &'a PositionData { &self.position }
+    fn get_position_mut<'a>(&'a mut self) -> &'a mut PositionData { &mut self.position }
+}
+
+impl Random for GameData {
+    fn rng(&mut self) -> &mut RandomData { &mut self.random }
+}
+
+impl Ai for GameData {
+    fn get_ai<'a>(&'a self) -> &'a AiData { &self.ai }
+    fn get_ai_mut<'a>(&'a mut self) -> &'a mut AiData { &mut self.ai }
+}
+
+fn new_game() -> GameData {
+    GameData {
+        common: CommonData::new(),
+        position: PositionData::new(),
+        random: RandomData::new(),
+        ai: AiData::new(),
+        counter: 0
+    }
+}
+
+fn count(gd: &mut GameData, _: Entity, _: &mut Blackboard) -> Status {
+    gd.counter += 1;
+    Status::Success
+}
+
+fn spot(_: &mut GameData, _: Entity, bb: &mut Blackboard) -> Status {
+    bb.set("spotted", Value::Bool(true));
+    Status::Failure
+}
+
+fn agent(gd: &mut GameData, tree: Option<Entity>) -> Entity {
+    let oid = gd.new_object(None);
+    gd.set_to_identity(oid);
+    gd.set_agent(oid, Agent::new(tree));
+    oid
+}
+
+#[test]
+fn sequence_stops_on_failure() {
+    let mut actions = Actions::new();
+    actions.register("count", count);
+    actions.register("spot", spot);
+
+    let mut gd = new_game();
+    let tree = gd.new_behaviour(Node::sequence(vec![
+        Node::action("count"),
+        Node::action("spot"),
+        Node::action("count")
+    ]));
+    let a = agent(&mut gd, Some(tree));
+
+    gd.think(&actions);
+    assert_eq!(gd.counter, 1);
+    assert_eq!(gd.agent(a).unwrap().blackboard.get_bool("spotted"), Some(true));
+}
+
+#[test]
+fn selector_uses_blackboard() {
+    let mut actions = Actions::new();
+    actions.register("count", count);
+    actions.register("spot", spot);
+
+    let mut gd = new_game();
+    let tree = gd.new_behaviour(Node::selector(vec![
+        Node::sequence(vec![Node::condition("spotted"), Node::repeat(3, Node::action("count"))]),
+        Node::action("spot")
+    ]));
+    agent(&mut gd, Some(tree));
+
+    gd.think(&actions);
+    assert_eq!(gd.counter, 0);
+    gd.think(&actions);
+    assert_eq!(gd.counter, 3);
+}
+
+#[test]
+fn missing_action_fails() {
+    let actions: Actions<GameData> = Actions::new();
+    let mut gd = new_game();
+    let a = agent(&mut gd, None);
+    let mut bb = Blackboard::new();
+    let status = Node::inverter(Node::action("nothing")).evaluate(&mut gd, a, &mut bb, &actions);
+    assert_eq!(status, Status::Success);
+}
+
+#[test]
+fn arrive_reaches_target() {
+    let mut gd = new_game();
+    let a = agent(&mut gd, None);
+    let mut steering = Steering::new(2., 4.);
+    steering.add(Behaviour::Arrive(Target::Point(Vector3::new(5., 0., 0.)), 2.), 1.);
+    gd.agent_mut(a).unwrap().steering = Some(steering);
+
+    for _ in (0..600) {
+        gd.steer(1. / 60.);
+    }
+
+    let pos = *gd.get_displacement(a).unwrap();
+    assert!(pos.sub_v(&Vector3::new(5., 0., 0.)).length() < 0.1);
+}
+
+#[test]
+fn flee_moves_away() {
+    let mut gd = new_game();
+    let a = agent(&mut gd, None);
+    let threat = gd.new_object(None);
+    gd.set_displacement(threat, Vector3::new(0., 0., 1.));
+
+    let mut steering = Steering::new(1., 10.);
+    steering.add(Behaviour::Flee(Target::Entity(threat)), 1.);
+    gd.agent_mut(a).unwrap().steering = Some(steering);
+
+    for _ in (0..10) {
+        gd.steer(0.1);
+    }
+    assert!(gd.get_displacement(a).unwrap().z < -0.5);
+}
+
+#[test]
+fn wander_is_deterministic() {
+    let run = || {
+        let mut gd = new_game();
+        gd.set_nonce(7);
+        let a = agent(&mut gd, None);
+        let mut steering = Steering::new(1., 1.);
+        steering.add(Behaviour::Wander { radius: 1., distance: 2., jitter: 0.5 }, 1.);
+        gd.agent_mut(a).unwrap().steering = Some(steering);
+        for _ in (0..100) {
+            gd.steer(0.1);
+        }
+        *gd.get_displacement(a).unwrap()
+    };
+
+    assert!(run() == run());
+}
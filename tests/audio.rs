@@ -0,0 +1,158 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-audio" as audio;
+extern crate cgmath;
+
+use std::num::Float;
+use std::old_io::TempDir;
+use std::old_io::fs::PathExtensions;
+
+use snowmew::common::{Common, CommonData};
+use position::{Positions, PositionData};
+use audio::{Audio, AudioData, Clip, Source, Mixer, BufferOutput, WavOutput, Output, AudioManager};
+use cgmath::Vector3;
+
+#[derive(Clone)]
+struct GameData {
+    common: CommonData,
+    position: PositionData,
+    audio: AudioData
+}
+
+impl Common for GameData {
+    fn get_common<'a>(&'a self) -> &'a CommonData { &self.common }
+    fn get_common_mut<'a>(&'a mut self) -> &'a mut CommonData { &mut self.common }
+}
+
+impl Positions for GameData {
+    fn get_position<'a>(&'a self) -> &'a PositionData { &self.position }
+    fn get_position_mut<'a>(&'a mut self) -> &'a mut PositionData { &mut self.position }
+}
+
+impl Audio for GameData {
+    fn get_audio<'a>(&'a self) -> &'a AudioData { &self.audio }
+    fn get_audio_mut<'a>(&'a mut self) -> &'a mut AudioData { &mut self.audio }
+}
+
+fn scene(source_pos: Vector3<f32>) -> GameData {
+    let mut gd = GameData {
+        common: CommonData::new(),
+        position: PositionData::new(),
+        audio: AudioData::new()
+    };
+
+    let clip = gd.new_clip(Clip::new(100, 1, vec![0.5; 100]));
+    let listener = gd.new_object(None);
+    gd.set_to_identity(listener);
+    gd.set_listener(listener);
+
+    let speaker = gd.new_object(None);
+    gd.set_displacement(speaker, source_pos);
+    gd.set_source(speaker, Source::new(clip));
+    gd
+}
+
+#[test]
+fn attenuation_falls_off() {
+    let source = Source::new(0);
+    assert_eq!(audio::attenuation(&source, 0.), 1.);
+    assert_eq!(audio::attenuation(&source, 1.), 1.);
+    assert_eq!(audio::attenuation(&source, 2.), 0.5);
+    assert!(audio::attenuation(&source, 10.) < audio::attenuation(&source, 5.));
+}
+
+#[test]
+fn pan_follows_listener() {
+    let right = Vector3::new(1f32, 0., 0.);
+    let (l, r) = audio::pan(&Vector3::new(5f32, 0., 0.), &right);
+    assert!(r > 0.99 && l < 0.01);
+    let (l, r) = audio::pan(&Vector3::new(-5f32, 0., 0.), &right);
+    assert!(l > 0.99 && r < 0.01);
+    let (l, r) = audio::pan(&Vector3::new(0f32, 0., -5.), &right);
+    assert!((l - r).abs() < 0.0001);
+}
+
+#[test]
+fn mixer_writes_a_frame_per_cadance() {
+    let gd = scene(Vector3::new(1f32, 0., 0.));
+    let mut mixer = Mixer::new(BufferOutput::new(), 100, 0.25);
+    mixer.update(gd.clone());
+    mixer.update(gd.clone());
+    assert_eq!(mixer.output().samples().len(), 2 * 50);
+
+    // the source is to the right of the listener
+    let samples = mixer.output().samples();
+    assert!(samples[1] > samples[0]);
+}
+
+#[test]
+fn mixer_stops_at_end_of_clip() {
+    let gd = scene(Vector3::new(0f32, 0., -1.));
+    let mut mixer = Mixer::new(BufferOutput::new(), 100, 1.5);
+    mixer.update(gd.clone());
+    let samples = mixer.output().samples();
+    assert_eq!(samples.len(), 300);
+    assert!(samples[199] != 0.);
+    assert_eq!(samples[200], 0.);
+}
+
+#[test]
+fn mixer_loops_a_one_frame_clip() {
+    let mut gd = scene(Vector3::new(0f32, 0., -1.));
+    // four times the mixer's rate, each step skips past the clip
+    let clip = gd.new_clip(Clip::new(400, 1, vec![0.5]));
+    let speaker = gd.new_object(None);
+    let mut source = Source::new(clip);
+    source.looping = true;
+    source.spatial = false;
+    gd.set_source(speaker, source);
+
+    let mut mixer = Mixer::new(BufferOutput::new(), 100, 1.5);
+    mixer.update(gd.clone());
+    let samples = mixer.output().samples();
+    assert_eq!(samples.len(), 300);
+    // the scene's own clip has ended, only the loop is heard
+    assert!(samples[240] != 0.);
+    assert!(samples[299] != 0.);
+}
+
+#[test]
+fn mixer_skips_empty_looping_clips() {
+    let mut gd = scene(Vector3::new(0f32, 0., -1.));
+    let clip = gd.new_clip(Clip::new(100, 1, Vec::new()));
+    let speaker = gd.new_object(None);
+    let mut source = Source::new(clip);
+    source.looping = true;
+    gd.set_source(speaker, source);
+
+    let mut mixer = Mixer::new(BufferOutput::new(), 100, 0.5);
+    mixer.update(gd.clone());
+    assert_eq!(mixer.output().samples().len(), 100);
+}
+
+#[test]
+fn wav_output_finishes_header() {
+    let dir = TempDir::new("snowmew-audio").unwrap();
+    let path = dir.path().join("out.wav");
+    let mut wav = WavOutput::create(&path, 44100).unwrap();
+    wav.write(&[0., 0.5, -0.5, 1.]);
+    assert!(wav.finish().is_ok());
+    // a second finish reports the same result
+    assert!(wav.finish().is_ok());
+    // 44 bytes of header and two 16bit stereo frames
+    assert_eq!(path.stat().unwrap().size, 44 + 8);
+}
@@ -0,0 +1,205 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-input" as input;
+
+mod common;
+
+use snowmew::common::{Common, Entity};
+use graphics::{Graphics, Material, PbrMaterial, BlendMode, Drawable, Geometry, VertexBuffer};
+use graphics::geometry::VertexGeo;
+use render::{BatchPlan, BatchState, DrawItem, RenderQueues, Program};
+use common::{GameData, new_game};
+
+fn geometry(gd: &mut GameData) -> Entity {
+    let v = |x: f32, y: f32| VertexGeo { position: [x, y, 0.] };
+    let vb = gd.new_vertex_buffer(
+        VertexBuffer::new_position(vec![v(0., 0.), v(1., 0.), v(0., 1.)], vec![0, 1, 2])
+    );
+    gd.new_geometry(Geometry::triangles(vb, 0, 3))
+}
+
+/// a drawable of `geometry` and `material`, `depth` in front of the camera
+fn item(gd: &mut GameData, geometry: Entity, material: Entity, depth: f32) -> DrawItem {
+    let blend = match gd.material(material) {
+        Some(m) => m.blend(),
+        None => gd.pbr_material(material).map(|m| m.blend()).unwrap_or(BlendMode::Opaque)
+    };
+    DrawItem {
+        id: gd.new_object(None),
+        draw: Drawable {
+            geometry: geometry,
+            material: material,
+            no_shadow: false
+        },
+        blend: blend,
+        depth: depth,
+        billboard: None
+    }
+}
+
+fn opaque(items: Vec<DrawItem>) -> RenderQueues {
+    RenderQueues {
+        opaque: items,
+        transparent: Vec::new()
+    }
+}
+
+/// the drawables of `items` as shadow casters
+fn casters(items: &[DrawItem]) -> Vec<(Entity, Drawable)> {
+    items.iter().map(|item| (item.id, item.draw)).collect()
+}
+
+#[test]
+fn materials_that_only_differ_in_colour_share_a_batch() {
+    let mut gd = new_game();
+    let (a, b) = (geometry(&mut gd), geometry(&mut gd));
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    let green = gd.new_material(Material::simple([0., 1., 0.]));
+    let items = vec![
+        item(&mut gd, a, red, 1.),
+        item(&mut gd, b, green, 2.),
+        item(&mut gd, a, green, 3.),
+        item(&mut gd, b, red, 4.)
+    ];
+    let plan = BatchPlan::new(&gd, &opaque(items.clone()), &casters(&items[..])[..]);
+
+    assert_eq!(plan.batches.len(), 2);
+    assert_eq!(plan.state_changes(), 1);
+    assert_eq!((plan.batches[0].geometry, plan.batches[0].first, plan.batches[0].count), (a, 0, 2));
+    assert_eq!((plan.batches[1].geometry, plan.batches[1].first, plan.batches[1].count), (b, 2, 2));
+
+    // each instance keeps its material's index and the front to back order
+    let drawn: Vec<(Entity, i32)> = plan.instances[..4].iter().map(|i| (i.id, i.material)).collect();
+    assert_eq!(drawn, vec![
+        (items[0].id, gd.material_index(red).unwrap()),
+        (items[2].id, gd.material_index(green).unwrap()),
+        (items[1].id, gd.material_index(green).unwrap()),
+        (items[3].id, gd.material_index(red).unwrap())
+    ]);
+}
+
+#[test]
+fn programs_and_textures_are_bound_once() {
+    let mut gd = new_game();
+    let g = geometry(&mut gd);
+    let plain = gd.new_material(Material::simple([1., 1., 1.]));
+    let mut textured = Material::simple([1., 1., 1.]);
+    textured.set_map_kd(100);
+    let textured = gd.new_material(textured);
+    let pbr = gd.new_pbr_material(PbrMaterial::simple([1., 1., 1.], 0., 0.5));
+
+    let mut items = Vec::new();
+    for i in 0..4 {
+        for &mat in [pbr, textured, plain].iter() {
+            let it = item(&mut gd, g, mat, i as f32);
+            items.push(it);
+        }
+    }
+    let plan = BatchPlan::new(&gd, &opaque(items.clone()), &casters(&items[..])[..]);
+
+    assert_eq!(plan.batches.len(), 3);
+    assert_eq!(plan.state_changes(), 3);
+    assert!(plan.batches.iter().all(|b| b.count == 4));
+    assert_eq!(plan.batches[2].state.program, Program::Pbr);
+    assert_eq!(BatchState::new(&gd, textured, BlendMode::Opaque).textures,
+               [None, Some(100), None, None, None]);
+    // the textures make up part of the state
+    assert!(plan.batches[0].state != plan.batches[1].state);
+}
+
+#[test]
+fn batches_are_not_limited_in_size() {
+    let mut gd = new_game();
+    let g = geometry(&mut gd);
+    let mat = gd.new_material(Material::simple([1., 1., 1.]));
+    let items: Vec<DrawItem> = (0..2000).map(|i| item(&mut gd, g, mat, i as f32)).collect();
+    let plan = BatchPlan::new(&gd, &opaque(items.clone()), &casters(&items[..])[..]);
+
+    assert_eq!(plan.batches.len(), 1);
+    assert_eq!(plan.batches[0].count, 2000);
+    assert_eq!(plan.shadows.len(), 1);
+    assert_eq!((plan.shadows[0].first, plan.shadows[0].count), (2000, 2000));
+    assert_eq!(plan.instances.len(), 4000);
+}
+
+#[test]
+fn blended_drawables_keep_their_order() {
+    let mut gd = new_game();
+    let (a, b) = (geometry(&mut gd), geometry(&mut gd));
+    let mut glass = Material::simple([1., 1., 1.]);
+    glass.set_blend(BlendMode::Alpha);
+    let glass = gd.new_material(glass);
+
+    let transparent = vec![
+        item(&mut gd, a, glass, 4.),
+        item(&mut gd, a, glass, 3.),
+        item(&mut gd, b, glass, 2.),
+        item(&mut gd, a, glass, 1.)
+    ];
+    let queues = RenderQueues {
+        opaque: Vec::new(),
+        transparent: transparent
+    };
+    let plan = BatchPlan::new(&gd, &queues, &[]);
+
+    let batches: Vec<(Entity, usize)> = plan.batches.iter().map(|b| (b.geometry, b.count)).collect();
+    assert_eq!(batches, vec![(a, 2), (b, 1), (a, 1)]);
+    assert_eq!(plan.state_changes(), 1);
+    assert!(plan.batches.iter().all(|b| b.state.blend == BlendMode::Alpha));
+}
+
+#[test]
+fn shadows_are_grouped_by_geometry() {
+    let mut gd = new_game();
+    let (a, b) = (geometry(&mut gd), geometry(&mut gd));
+    let phong = gd.new_material(Material::simple([1., 1., 1.]));
+    let pbr = gd.new_pbr_material(PbrMaterial::simple([1., 1., 1.], 1., 0.2));
+    let mut hidden = item(&mut gd, b, phong, 1.);
+    hidden.draw.no_shadow = true;
+    let items = vec![
+        item(&mut gd, a, phong, 2.),
+        hidden,
+        item(&mut gd, b, pbr, 3.),
+        item(&mut gd, a, pbr, 4.)
+    ];
+    let plan = BatchPlan::new(&gd, &opaque(items.clone()), &casters(&items[..])[..]);
+
+    assert_eq!(plan.batches.len(), 4);
+    let shadows: Vec<(Entity, usize, usize)> =
+        plan.shadows.iter().map(|s| (s.geometry, s.first, s.count)).collect();
+    assert_eq!(shadows, vec![(a, 4, 2), (b, 6, 1)]);
+    assert_eq!(plan.instances[6].id, items[2].id);
+}
+
+#[test]
+fn casters_do_not_have_to_be_drawn() {
+    let mut gd = new_game();
+    let (a, b) = (geometry(&mut gd), geometry(&mut gd));
+    let mat = gd.new_material(Material::simple([1., 1., 1.]));
+    let drawn = vec![item(&mut gd, a, mat, 1.)];
+    let hidden = item(&mut gd, b, mat, 2.);
+    let plan = BatchPlan::new(&gd, &opaque(drawn), &casters(&[hidden.clone()])[..]);
+
+    assert_eq!(plan.batches.len(), 1);
+    assert_eq!(plan.batches[0].geometry, a);
+    let shadows: Vec<(Entity, usize, usize)> =
+        plan.shadows.iter().map(|s| (s.geometry, s.first, s.count)).collect();
+    assert_eq!(shadows, vec![(b, 1, 1)]);
+    assert_eq!(plan.instances[1].id, hidden.id);
+}
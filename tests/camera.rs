@@ -15,8 +15,8 @@
 extern crate "snowmew-render" as render;
 extern crate cgmath;
 
-use render::camera::Camera;
-use cgmath::{Matrix4};
+use render::camera::{Camera, Projection};
+use cgmath::{Matrix, Matrix4, Vector4};
 use cgmath::{Vector3};
 use cgmath::{Point3, Point};
 
@@ -44,4 +44,62 @@ fn test_ray() {
     let ray = camera.pixel_ray(5, 5);
     assert_eq!(ray.origin, o);
     assert_eq!(ray.direction, Vector3::new(0., 0., -1.));
-}
\ No newline at end of file
+}
+
+#[test]
+fn default_projection() {
+    let camera = Camera::new(10, 10, Matrix4::identity());
+    assert_eq!(camera.projection(), Projection::perspective(80., 0.01, 10000.));
+    assert_eq!(camera.near(), 0.01);
+    assert_eq!(camera.far(), 10000.);
+}
+
+#[test]
+fn orthographic_projection() {
+    let p = Projection::orthographic(4., 1., 10.);
+    assert_eq!(p.extents(2., 1.), (-4., 4., -2., 2.));
+    assert_eq!(p.extents(2., 9.), (-4., 4., -2., 2.));
+
+    let camera = Camera::with_projection(20, 10, Matrix4::identity(), p);
+    let clip = camera.projection_matrix().mul_v(&Vector4::new(4., 2., -10., 1.));
+    // the far top right corner of the view
+    assert_eq!((clip.x, clip.y, clip.w), (1., 1., 1.));
+    assert!((clip.z - 1.).abs() < 1e-5);
+    assert_eq!(camera.near(), 1.);
+    assert_eq!(camera.far(), 10.);
+}
+
+#[test]
+fn off_axis_extents() {
+    let p = Projection::off_axis(0., 1., -1., 2., 1., 10.);
+    assert_eq!(p.extents(5., 1.), (0., 1., -1., 2.));
+    assert_eq!(p.extents(5., 2.), (0., 2., -2., 4.));
+    assert!(!p.is_orthographic());
+}
+
+#[test]
+fn orthographic_rays_are_parallel() {
+    let v = Vector3::new(1., 1., 1.);
+    let m = Matrix4::from_translation(&v);
+    let p = Projection::orthographic(10., 1., 100.);
+    let camera = Camera::with_projection(10, 10, m, p);
+
+    let ray = camera.pixel_ray(5, 5);
+    assert_eq!(ray.origin, Point3::new(1., 1., 1.));
+    assert_eq!(ray.direction, Vector3::new(0., 0., -1.));
+
+    // the left edge is half the height of the view away
+    let ray = camera.pixel_ray(0, 5);
+    assert!((ray.origin.x + 4.).abs() < 1e-4);
+    assert!((ray.origin.y - 1.).abs() < 1e-4);
+    assert_eq!(ray.direction, Vector3::new(0., 0., -1.));
+}
+
+#[test]
+fn orthographic_frustum_corners() {
+    let p = Projection::orthographic(2., 1., 10.);
+    let camera = Camera::with_projection(10, 10, Matrix4::identity(), p);
+    let corners = camera.frustum_corners(1., 10.);
+    assert_eq!(corners[0], Point3::new(-1., -1., -1.));
+    assert_eq!(corners[6], Point3::new(1., 1., -10.));
+}
@@ -0,0 +1,91 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! The game data shared by the render tests, each test crate pulls
+//! this in with `mod common;`. Not every crate uses every helper.
+#![allow(dead_code)]
+
+use snowmew::common::{Common, CommonData, Scene};
+use position::{Positions, PositionData};
+use graphics::{Graphics, GraphicsData};
+use render::{Renderable, RenderData};
+use input::{GetIoState, IoState};
+
+#[derive(Clone)]
+pub struct GameData {
+    pub common: CommonData,
+    pub position: PositionData,
+    pub graphics: GraphicsData,
+    pub render: RenderData,
+    pub io: IoState,
+    /// the number of times the game was stepped, for the tests that
+    /// run the engine
+    pub steps: u32
+}
+
+impl Common for GameData {
+    fn get_common<'a>(&'a self) -> &'a CommonData { &self.common }
+    fn get_common_mut<'a>(&'a mut self) -> &'a mut CommonData { &mut self.common }
+}
+
+impl Positions for GameData {
+    fn get_position<'a>(&'a self) -> &'a PositionData { &self.position }
+    fn get_position_mut<'a>(&'a mut self) -> &'a mut PositionData { &mut self.position }
+}
+
+impl Graphics for GameData {
+    fn get_graphics<'a>(&'a self) -> &'a GraphicsData { &self.graphics }
+    fn get_graphics_mut<'a>(&'a mut self) -> &'a mut GraphicsData { &mut self.graphics }
+}
+
+impl Renderable for GameData {
+    fn get_render_data<'a>(&'a self) -> &'a RenderData { &self.render }
+    fn get_render_data_mut<'a>(&'a mut self) -> &'a mut RenderData { &mut self.render }
+}
+
+impl GetIoState for GameData {
+    fn get_io_state<'a>(&'a self) -> &'a IoState { &self.io }
+    fn get_io_state_mut<'a>(&'a mut self) -> &'a mut IoState { &mut self.io }
+}
+
+/// an empty game with a `width` by `height` window
+pub fn new_game_sized(width: u32, height: u32) -> GameData {
+    let mut gd = GameData {
+        common: CommonData::new(),
+        position: PositionData::new(),
+        graphics: GraphicsData::new(),
+        render: RenderData::new(),
+        io: IoState::new(),
+        steps: 0
+    };
+    gd.io.size = (width, height);
+    gd
+}
+
+/// an empty game with a 32 by 32 window
+pub fn new_game() -> GameData {
+    new_game_sized(32, 32)
+}
+
+/// a game with a 32 by 32 window that draws `scene` from a camera at
+/// the origin, looking down -z
+pub fn new_scene_game() -> (GameData, Scene) {
+    let mut gd = new_game();
+    let scene = gd.new_scene();
+    let camera = gd.new_object(None);
+    gd.set_to_identity(camera);
+    gd.set_scene(scene);
+    gd.set_camera(camera);
+    (gd, scene)
+}
@@ -0,0 +1,252 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material, BlendMode, Lod};
+use graphics::{Billboard, BillboardSet};
+use graphics::geometry::VertexGeo;
+use render::{Renderable, Bounds, Culler, Frustum, RenderQueues, ShadowView};
+use render::camera::Camera;
+use cgmath::{Matrix4, Point3, Vector3, ortho};
+use common::{GameData, new_scene_game};
+
+/// a triangle that fits in a unit sphere around the origin
+fn triangle(gd: &mut GameData) -> Entity {
+    let vb = VertexBuffer::new_position(
+        vec![VertexGeo { position: [-1., 0., 0.] },
+             VertexGeo { position: [1., 0., 0.] },
+             VertexGeo { position: [0., 1., 0.] }],
+        vec![0, 1, 2]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    gd.new_geometry(Geometry::triangles(vb, 0, 3))
+}
+
+fn place(gd: &mut GameData, scene: Scene, geo: Entity, pos: Vector3<f32>) -> Entity {
+    let mat = gd.new_material(Material::simple([1., 1., 1.]));
+    let oid = gd.new_object(Some(scene.to_entity()));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, pos);
+    gd.set_draw(oid, geo, mat);
+    oid
+}
+
+fn camera(gd: &GameData) -> Camera {
+    Camera::new(100, 100, gd.position(gd.camera().unwrap()))
+}
+
+#[test]
+fn geometry_bounds() {
+    let (mut gd, _) = new_scene_game();
+    let geo = triangle(&mut gd);
+    let bounds = Bounds::from_geometry(&gd, geo).unwrap();
+    assert_eq!(bounds.center, Point3::new(0., 0.5, 0.));
+    assert!((bounds.radius * bounds.radius - 1.25).abs() < 0.0001);
+    assert!(Bounds::from_geometry(&gd, 12345).is_none());
+}
+
+#[test]
+fn bounds_transform() {
+    let bounds = Bounds { center: Point3::new(1., 0., 0.), radius: 1. };
+    let mut mat = Matrix4::from_translation(&Vector3::new(0., 0., -5.));
+    mat.x.x = 2.;
+    mat.y.y = 3.;
+    let moved = bounds.transform(&mat);
+    assert_eq!(moved.center, Point3::new(2., 0., -5.));
+    assert_eq!(moved.radius, 3.);
+}
+
+#[test]
+fn frustum_planes() {
+    let frustum = Frustum::new(&Camera::new(100, 100, Matrix4::identity()));
+    let at = |x: f32, y: f32, z: f32, r: f32| {
+        frustum.intersects(&Bounds { center: Point3::new(x, y, z), radius: r })
+    };
+    assert!(at(0., 0., -10., 1.));
+    // behind the camera
+    assert!(!at(0., 0., 10., 1.));
+    // far off to the side, but big enough to reach into the view
+    assert!(!at(100., 0., -10., 1.));
+    assert!(at(100., 0., -10., 100.));
+    // past the far plane
+    assert!(!at(0., 0., -20000., 1.));
+}
+
+#[test]
+fn lod_levels() {
+    let mut lod = Lod::new();
+    lod.add_level(10., 2);
+    lod.add_level(5., 1);
+    lod.add_level(20., 3);
+    assert_eq!(lod.levels(), &[(5., 1), (10., 2), (20., 3)][..]);
+    assert_eq!(lod.select(0.), Some(1));
+    assert_eq!(lod.select(5.), Some(2));
+    assert_eq!(lod.select(19.), Some(3));
+    assert_eq!(lod.select(20.), None);
+}
+
+#[test]
+fn invisible_drawables_are_culled() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = triangle(&mut gd);
+    let front = place(&mut gd, scene, geo, Vector3::new(0., 0., -5.));
+    place(&mut gd, scene, geo, Vector3::new(0., 0., 5.));
+    place(&mut gd, scene, geo, Vector3::new(50., 0., -5.));
+
+    let visible = Culler::new().visible(&gd, scene, &camera(&gd));
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].0, front);
+}
+
+#[test]
+fn changed_vertex_buffers_are_bounded_again() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = triangle(&mut gd);
+    let oid = place(&mut gd, scene, geo, Vector3::new(50., 0., -5.));
+
+    let mut culler = Culler::new();
+    assert_eq!(culler.visible(&gd, scene, &camera(&gd)).len(), 0);
+
+    // grow the triangle until it reaches back into the view
+    let vb = gd.geometry(geo).unwrap().vb;
+    gd.set_vertex_buffer(vb, VertexBuffer::new_position(
+        vec![VertexGeo { position: [-60., 0., 0.] },
+             VertexGeo { position: [1., 0., 0.] },
+             VertexGeo { position: [0., 1., 0.] }],
+        vec![0, 1, 2]
+    ));
+    let visible = culler.visible(&gd, scene, &camera(&gd));
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].0, oid);
+}
+
+#[test]
+fn casters_are_culled_by_the_shadow_views() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = triangle(&mut gd);
+    let front = place(&mut gd, scene, geo, Vector3::new(0., 0., -5.));
+    let aside = place(&mut gd, scene, geo, Vector3::new(50., 0., -5.));
+    let far = place(&mut gd, scene, geo, Vector3::new(500., 0., -5.));
+    let opted_out = place(&mut gd, scene, geo, Vector3::new(45., 0., -5.));
+    gd.set_cast_shadow(opted_out, false);
+
+    // a map looking down on everything between x = -10 and x = 60
+    let view = ShadowView {
+        projection: ortho(-35., 35., -35., 35., 0., 100.),
+        view: Matrix4::look_at(&Point3::new(25., 50., 0.),
+                               &Point3::new(25., 0., 0.),
+                               &Vector3::new(0., 0., -1.)),
+        resolution: 1024,
+        bias: 0.005,
+        split: 100.
+    };
+
+    let mut culler = Culler::new();
+    assert_eq!(culler.casters(&gd, scene, &camera(&gd), &[]).len(), 0);
+    let casters: Vec<Entity> = culler.casters(&gd, scene, &camera(&gd), &[view])
+        .into_iter().map(|(id, _)| id).collect();
+    assert!(casters.contains(&front));
+    assert!(casters.contains(&aside));
+    assert!(!casters.contains(&far));
+    assert!(!casters.contains(&opted_out));
+}
+
+#[test]
+fn billboards_are_culled_and_sorted() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = triangle(&mut gd);
+    let mut glass = Material::simple([1., 1., 1.]);
+    glass.set_blend(BlendMode::Alpha);
+    let glass = gd.new_material(glass);
+    let emitter = gd.new_object(Some(scene.to_entity()));
+    let at = |z: f32| Billboard { position: [0., 0., z], color: [1., 0., 0., 0.5], size: 1. };
+    gd.set_billboard_set(emitter, BillboardSet::new(geo, glass, vec![at(-5.), at(5.), at(-10.)]));
+
+    let camera = camera(&gd);
+    let billboards = Culler::new().billboards(&gd, scene, &camera);
+    assert_eq!(billboards.len(), 2);
+    assert!(billboards.iter().all(|&(id, draw, _)| id == emitter && draw.no_shadow));
+
+    // blended billboards are drawn back to front and keep their tint
+    let queues = RenderQueues::with_billboards(&gd, &camera, Vec::new(), billboards);
+    let depths: Vec<f32> = queues.transparent.iter().map(|i| i.depth).collect();
+    assert_eq!(depths, vec![10., 5.]);
+    assert_eq!(queues.transparent[0].tint(), [1., 0., 0., 0.5]);
+    assert_eq!(queues.transparent[0].model(&gd, &camera).w.z, -10.);
+}
+
+#[test]
+fn lod_replaces_geometry() {
+    let (mut gd, scene) = new_scene_game();
+    let near = triangle(&mut gd);
+    let far = triangle(&mut gd);
+    let mut lod = Lod::new();
+    lod.add_level(10., near);
+    lod.add_level(100., far);
+
+    let a = place(&mut gd, scene, near, Vector3::new(0., 0., -5.));
+    let b = place(&mut gd, scene, near, Vector3::new(0., 0., -50.));
+    let c = place(&mut gd, scene, near, Vector3::new(0., 0., -500.));
+    for &oid in [a, b, c].iter() {
+        gd.set_lod(oid, lod.clone());
+    }
+
+    let visible = Culler::new().visible(&gd, scene, &camera(&gd));
+    assert_eq!(visible.len(), 2);
+    assert_eq!(visible[0].0, a);
+    assert_eq!(visible[0].1.geometry, near);
+    assert_eq!(visible[1].0, b);
+    assert_eq!(visible[1].1.geometry, far);
+}
+
+#[test]
+fn queues_sort_by_depth() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = triangle(&mut gd);
+    let mut glass = Material::simple([1., 1., 1.]);
+    glass.set_d(0.5);
+    glass.set_blend(BlendMode::Alpha);
+    let glass = gd.new_material(glass);
+
+    let o_far = place(&mut gd, scene, geo, Vector3::new(0., 0., -9.));
+    let o_near = place(&mut gd, scene, geo, Vector3::new(0., 0., -3.));
+    let t_near = place(&mut gd, scene, geo, Vector3::new(0., 0., -2.));
+    let t_far = place(&mut gd, scene, geo, Vector3::new(0., 0., -8.));
+    for &oid in [t_near, t_far].iter() {
+        gd.set_draw(oid, geo, glass);
+    }
+
+    let camera = camera(&gd);
+    let visible = Culler::new().visible(&gd, scene, &camera);
+    let queues = RenderQueues::new(&gd, &camera, visible);
+
+    let ids = |items: &[render::DrawItem]| items.iter().map(|i| i.id).collect::<Vec<Entity>>();
+    assert_eq!(ids(&queues.opaque[..]), vec![o_near, o_far]);
+    assert_eq!(ids(&queues.transparent[..]), vec![t_far, t_near]);
+    assert!(queues.transparent.iter().all(|i| i.blend == BlendMode::Alpha));
+    assert_eq!(queues.opaque[0].depth, 3.);
+    assert_eq!(queues.iter().count(), 4);
+}
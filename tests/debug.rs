@@ -0,0 +1,204 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Entity, Scene};
+use snowmew::game::Game;
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material};
+use graphics::geometry::{VertexGeo, VertexGeoTexNorm};
+use render::{Renderable, DebugDraw, DebugDrawReset};
+use render::font::{glyph, glyph_pixel, text_size, text_rects};
+use soft::SoftRender;
+use cgmath::{Matrix4, Vector, Vector3, EuclideanVector};
+use common::{GameData, new_scene_game};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const GREEN: [f32; 4] = [0., 1., 0., 1.];
+const WHITE: [f32; 4] = [1., 1., 1., 1.];
+
+fn place(gd: &mut GameData, scene: Scene, geo: Entity, z: f32) -> Entity {
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    let oid = gd.new_object(Some(scene.to_entity()));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 0., z));
+    gd.set_draw(oid, geo, red);
+    oid
+}
+
+/// a 2x2 quad facing +z
+fn quad(gd: &mut GameData) -> Entity {
+    let v = |x: f32, y: f32| {
+        VertexGeoTexNorm {
+            position: [x, y, 0.],
+            texture: [0., 0.],
+            normal: [0., 0., 1.]
+        }
+    };
+    let vb = VertexBuffer::new_position_texture_normal(
+        vec![v(-1., -1.), v(1., -1.), v(1., 1.), v(-1., 1.)],
+        vec![0, 1, 2, 0, 2, 3]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    gd.new_geometry(Geometry::triangles(vb, 0, 6))
+}
+
+fn render(gd: &GameData) -> SoftRender {
+    let mut r = SoftRender::new(0, 0);
+    r.set_clear_color([0., 0., 0., 1.]);
+    r.render(gd);
+    r
+}
+
+#[test]
+fn font_glyphs() {
+    assert!(glyph_pixel('I', 2, 0));
+    assert!(glyph_pixel('I', 2, 6));
+    assert!(!glyph_pixel('I', 0, 0));
+    assert!(!glyph_pixel('I', 2, 7));
+    assert!(!glyph_pixel(' ', 2, 3));
+    assert_eq!(glyph('\u{e9}'), glyph('?'));
+
+    assert_eq!(text_size("", 1), (0, 7));
+    assert_eq!(text_size("ab", 1), (11, 7));
+    assert_eq!(text_size("ab\nc", 2), (22, 32));
+
+    // the bar of an I and its serifs
+    let rects = text_rects("I", 10., 20., 2);
+    assert_eq!(rects.len(), 11);
+    assert!(rects.contains(&[14., 20., 2., 2.]));
+    assert!(rects.iter().all(|r| r[0] >= 10. && r[1] >= 20.));
+}
+
+#[test]
+fn shapes_are_broken_into_lines() {
+    let mut debug = DebugDraw::new();
+    assert!(debug.is_empty());
+
+    debug.aabb(Vector3::new(0., 0., 0.), Vector3::new(1., 2., 3.), GREEN);
+    assert_eq!(debug.lines().len(), 12);
+    // every edge runs along one axis
+    for l in debug.lines().iter() {
+        let d = l.to.sub_v(&l.from);
+        let axes = [d.x, d.y, d.z].iter().filter(|&&v| v != 0.).count();
+        assert_eq!(axes, 1);
+    }
+
+    debug.clear();
+    let center = Vector3::new(1., 1., 1.);
+    debug.sphere(center, 2., GREEN);
+    assert_eq!(debug.lines().len(), 3 * render::debug::SPHERE_SEGMENTS);
+    for l in debug.lines().iter() {
+        assert!((l.from.sub_v(&center).length() - 2.).abs() < 1e-4);
+    }
+
+    debug.clear();
+    debug.axes(&Matrix4::from_translation(&Vector3::new(1., 0., 0.)), 2.);
+    assert_eq!(debug.lines().len(), 3);
+    assert_eq!(debug.lines()[0].from, Vector3::new(1., 0., 0.));
+    assert_eq!(debug.lines()[0].to, Vector3::new(3., 0., 0.));
+    assert_eq!(debug.lines()[0].color, [1., 0., 0., 1.]);
+    assert_eq!(debug.lines()[2].to, Vector3::new(1., 0., 2.));
+
+    debug.text_at(0., 0., "hi", WHITE);
+    assert_eq!(debug.text().len(), 1);
+    assert_eq!(debug.text_rects().len(), text_rects("hi", 0., 0., 1).len());
+}
+
+struct Marker;
+
+impl Game<GameData, ()> for Marker {
+    fn step(&mut self, _: (), mut gd: GameData) -> GameData {
+        gd.debug_draw_mut().line(Vector3::new(0., 0., 0.), Vector3::new(1., 0., 0.), GREEN);
+        gd
+    }
+}
+
+#[test]
+fn debug_draw_is_cleared_each_step() {
+    let (gd, _) = new_scene_game();
+    let mut game = DebugDrawReset::new(Marker);
+    let gd = game.step((), gd);
+    let gd = game.step((), gd);
+    assert_eq!(gd.debug_draw().lines().len(), 1);
+}
+
+#[test]
+fn debug_lines_are_drawn_on_top() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    place(&mut gd, scene, geo, -2.);
+    // behind the quad
+    gd.debug_draw_mut().line(Vector3::new(-1., 0., -5.), Vector3::new(1., 0., -5.), GREEN);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [0, 255, 0, 255]);
+    assert_eq!(r.frame().pixel(16, 12), [51, 0, 0, 255]);
+    // the depth of the quad is kept
+    assert!(r.frame().depth_at(16, 16) < 1.);
+}
+
+#[test]
+fn lines_through_the_near_plane() {
+    let (mut gd, _) = new_scene_game();
+    // clipped close to w=0, the far end is a long way off screen
+    gd.debug_draw_mut().line(Vector3::new(0., 0., -5.), Vector3::new(10000., 0., 10.), GREEN);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(24, 16), [0, 255, 0, 255]);
+    assert_eq!(r.frame().pixel(31, 16), [0, 255, 0, 255]);
+    assert_eq!(r.frame().pixel(8, 16), BLACK);
+}
+
+#[test]
+fn debug_text() {
+    let (mut gd, _) = new_scene_game();
+    gd.debug_draw_mut().text_at(1., 2., "I", WHITE);
+
+    let r = render(&gd);
+    let white = [255, 255, 255, 255];
+    assert_eq!(r.frame().pixel(3, 2), white);
+    assert_eq!(r.frame().pixel(3, 8), white);
+    assert_eq!(r.frame().pixel(2, 2), white);
+    assert_eq!(r.frame().pixel(2, 4), BLACK);
+    assert_eq!(r.frame().pixel(3, 9), BLACK);
+}
+
+#[test]
+fn line_geometry() {
+    let (mut gd, scene) = new_scene_game();
+    let vb = VertexBuffer::new_position(
+        vec![VertexGeo { position: [-1., 0., 0.] }, VertexGeo { position: [1., 0., 0.] }],
+        vec![0, 1]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    let geo = gd.new_geometry(Geometry::lines(vb, 0, 2));
+    place(&mut gd, scene, geo, -2.);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [51, 0, 0, 255]);
+    assert!(r.frame().depth_at(16, 16) < 1.);
+    assert_eq!(r.frame().pixel(16, 12), BLACK);
+    assert_eq!(r.frame().pixel(2, 16), BLACK);
+}
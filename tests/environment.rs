@@ -0,0 +1,264 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![feature(core, collections)]
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-loader" as loader;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use std::num::Float;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material, PbrMaterial, Texture};
+use graphics::{rgbe, from_rgbe};
+use graphics::geometry::VertexGeoTexNorm;
+use render::{Renderable, Environment, Sky, Ambient, ShProbe};
+use render::environment::sample_sky;
+use soft::SoftRender;
+use cgmath::Vector3;
+use common::{GameData, new_scene_game};
+
+/// a 2x2 quad facing +z, 2 units in front of the camera
+fn place_quad(gd: &mut GameData, scene: Scene, mat: Entity) {
+    let v = |x: f32, y: f32| {
+        VertexGeoTexNorm {
+            position: [x, y, 0.],
+            texture: [0., 0.],
+            normal: [0., 0., 1.]
+        }
+    };
+    let vb = VertexBuffer::new_position_texture_normal(
+        vec![v(-1., -1.), v(1., -1.), v(1., 1.), v(-1., 1.)],
+        vec![0, 1, 2, 0, 2, 3]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    let geo = gd.new_geometry(Geometry::triangles(vb, 0, 6));
+    let oid = gd.new_object(Some(scene.to_entity()));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 0., -2.));
+    gd.set_draw(oid, geo, mat);
+}
+
+fn render(gd: &GameData) -> SoftRender {
+    let mut r = SoftRender::new(0, 0);
+    r.set_clear_color([0., 0., 0., 1.]);
+    r.render(gd);
+    r
+}
+
+fn close(a: f32, b: f32, tolerance: f32) -> bool {
+    (a - b).abs() <= tolerance
+}
+
+fn close_v(a: Vector3<f32>, b: Vector3<f32>, tolerance: f32) -> bool {
+    close(a.x, b.x, tolerance) && close(a.y, b.y, tolerance) && close(a.z, b.z, tolerance)
+}
+
+#[test]
+fn rgbe_round_trip() {
+    for &c in [[0.5, 0.25, 0.125], [1., 1., 1.], [12., 3., 0.5], [0.01, 0., 0.02]].iter() {
+        let back = from_rgbe(rgbe(c));
+        for i in 0..3 {
+            // the mantissa has 8 bits shared by the largest channel
+            assert!(close(back[i], c[i], c[0].max(c[1]).max(c[2]) / 128.));
+        }
+    }
+    assert_eq!(rgbe([0., 0., 0.]), [0, 0, 0, 0]);
+    assert_eq!(from_rgbe([0, 0, 0, 0]), [0., 0., 0.]);
+}
+
+#[test]
+fn hdr_texels_are_decoded() {
+    let text = Texture::new_hdr(2, 1, &[4., 2., 1., 0., 0.5, 0.]);
+    assert!(text.is_hdr());
+    assert_eq!(text.depth(), 4);
+    let a = text.texel(0, 0);
+    let b = text.texel(1, 0);
+    assert!(close(a[0], 4., 0.05) && close(a[1], 2., 0.05) && close(a[2], 1., 0.05));
+    assert!(close(b[1], 0.5, 0.01) && b[0] == 0. && b[3] == 1.);
+
+    let ldr = Texture::new(1, 1, 4, vec![255, 0, 51, 255]);
+    assert!(!ldr.is_hdr());
+    assert_eq!(ldr.texel(0, 0), [1., 0., 0.2, 1.]);
+}
+
+fn hdr_file(resolution: &str, pixels: &[u8]) -> Vec<u8> {
+    let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n".to_vec();
+    data.push_all(resolution.as_bytes());
+    data.push(b'\n');
+    data.push_all(pixels);
+    data
+}
+
+#[test]
+fn decode_flat_hdr() {
+    let mut pixels = Vec::new();
+    pixels.push_all(&rgbe([1., 0., 0.]));
+    pixels.push_all(&rgbe([0., 2., 0.]));
+    let text = loader::hdr::decode(&hdr_file("-Y 1 +X 2", &pixels)).ok().unwrap();
+    assert_eq!((text.width(), text.height()), (2, 1));
+    assert!(text.is_hdr());
+    assert!(close(text.texel(0, 0)[0], 1., 0.01));
+    assert!(close(text.texel(1, 0)[1], 2., 0.01));
+}
+
+#[test]
+fn decode_run_length_encoded_hdr() {
+    let texel = rgbe([0.5, 0.25, 1.]);
+    // one run per channel, then a literal run of two and a run of six
+    let mut pixels = vec![2, 2, 0, 8];
+    for c in 0..3 {
+        pixels.push_all(&[128 + 8, texel[c]]);
+    }
+    pixels.push_all(&[2, texel[3], texel[3], 128 + 6, texel[3]]);
+    let text = loader::hdr::decode(&hdr_file("-Y 1 +X 8", &pixels)).ok().unwrap();
+    assert_eq!((text.width(), text.height()), (8, 1));
+    for x in 0..8 {
+        let t = text.texel(x, 0);
+        assert!(close(t[0], 0.5, 0.01) && close(t[1], 0.25, 0.01) && close(t[2], 1., 0.01));
+    }
+}
+
+#[test]
+fn decode_rejects_bad_hdr() {
+    let pixels = [0u8; 8];
+    assert!(loader::hdr::decode(&hdr_file("+Y 1 +X 2", &pixels)).is_err());
+    assert!(loader::hdr::decode(&hdr_file("-Y 2 +X 2", &pixels)).is_err());
+    assert!(loader::hdr::decode(b"P6\n1 1\n255\n").is_err());
+}
+
+#[test]
+fn environment_is_per_scene() {
+    let (mut gd, scene) = new_scene_game();
+    let other = gd.new_scene();
+    assert_eq!(gd.environment(scene), Environment::new());
+
+    let mut env = Environment::new();
+    env.sky = Some(Sky::Color(Vector3::new(0., 0., 1.)));
+    gd.set_environment(scene, env);
+    assert_eq!(gd.environment(scene), env);
+    assert_eq!(gd.environment(other), Environment::new());
+
+    assert!(gd.remove_environment(scene));
+    assert_eq!(gd.environment(scene), Environment::new());
+}
+
+#[test]
+fn constant_probe_matches_its_color() {
+    let c = Vector3::new(0.2, 0.4, 0.8);
+    let probe = ShProbe::constant(c);
+    assert!(close_v(probe.irradiance(Vector3::new(0., 1., 0.)), c, 1e-5));
+    assert!(close_v(probe.irradiance(Vector3::new(1., 0., 0.)), c, 1e-5));
+    assert!(close_v(Ambient::Color(c).probe().irradiance(Vector3::new(0., 0., -1.)), c, 1e-5));
+
+    let (gd, _) = new_scene_game();
+    let probe = ShProbe::from_sky(&gd, &Sky::Color(c));
+    assert!(close_v(probe.irradiance(Vector3::new(0., 1., 0.)), c, 0.01));
+    assert!(close_v(probe.irradiance(Vector3::new(0., 0., 1.)), c, 0.01));
+}
+
+#[test]
+fn probe_of_a_sky_lit_from_above() {
+    let (mut gd, _) = new_scene_game();
+    // the top row is the upper half of the sky
+    let sky = gd.new_texture(Texture::new_hdr(4, 2, &[1., 1., 1., 1., 1., 1.,
+                                                      1., 1., 1., 1., 1., 1.,
+                                                      0., 0., 0., 0., 0., 0.,
+                                                      0., 0., 0., 0., 0., 0.]));
+    let probe = ShProbe::from_sky(&gd, &Sky::Equirectangular(sky));
+    let one = Vector3::new(1., 1., 1.);
+    let half = Vector3::new(0.5, 0.5, 0.5);
+    assert!(close_v(probe.irradiance(Vector3::new(0., 1., 0.)), one, 0.02));
+    assert!(close_v(probe.irradiance(Vector3::new(0., -1., 0.)), Vector3::new(0., 0., 0.), 0.02));
+    assert!(close_v(probe.irradiance(Vector3::new(1., 0., 0.)), half, 0.02));
+}
+
+#[test]
+fn cube_faces_are_looked_up_by_direction() {
+    let (mut gd, _) = new_scene_game();
+    let mut faces = [0; 6];
+    for i in 0..6 {
+        faces[i] = gd.new_texture(Texture::new(1, 1, 4, vec![(i * 40) as u8, 0, 0, 255]));
+    }
+    let sky = Sky::Cube(faces);
+    let dirs = [Vector3::new(1., 0., 0.), Vector3::new(-1., 0., 0.),
+                Vector3::new(0., 1., 0.), Vector3::new(0., -1., 0.),
+                Vector3::new(0., 0., 1.), Vector3::new(0., 0., -1.)];
+    for (i, d) in dirs.iter().enumerate() {
+        let c = sample_sky(&gd, &sky, *d);
+        assert!(close(c.x, (i * 40) as f32 / 255., 1e-5));
+    }
+}
+
+#[test]
+fn sky_is_drawn_behind_the_scene() {
+    let (mut gd, scene) = new_scene_game();
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place_quad(&mut gd, scene, red);
+    let mut env = Environment::new();
+    env.sky = Some(Sky::Color(Vector3::new(0., 0., 1.)));
+    gd.set_environment(scene, env);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(0, 0), [0, 0, 255, 255]);
+    assert_eq!(r.frame().pixel(16, 16), [51, 0, 0, 255]);
+    // the sky does not write depth
+    assert_eq!(r.frame().depth_at(0, 0), 1.);
+}
+
+#[test]
+fn ambient_comes_from_the_environment() {
+    let (mut gd, scene) = new_scene_game();
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place_quad(&mut gd, scene, red);
+    let mut env = Environment::new();
+    env.ambient = Ambient::Color(Vector3::new(0.5, 0.5, 0.5));
+    gd.set_environment(scene, env);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [128, 0, 0, 255]);
+    // no sky, so the clear colour is left
+    assert_eq!(r.frame().pixel(0, 0), [0, 0, 0, 255]);
+}
+
+#[test]
+fn smooth_metal_reflects_the_sky() {
+    let (mut gd, scene) = new_scene_game();
+    let chrome = gd.new_pbr_material(PbrMaterial::simple([1., 1., 1.], 1., 0.));
+    place_quad(&mut gd, scene, chrome);
+    let mut env = Environment::new();
+    env.sky = Some(Sky::Color(Vector3::new(0., 1., 0.)));
+    env.ambient = Ambient::Color(Vector3::new(0., 0., 0.));
+    gd.set_environment(scene, env);
+
+    let r = render(&gd);
+    let p = r.frame().pixel(16, 16);
+    assert!(p[0] == 0 && p[1] >= 254 && p[2] == 0);
+
+    // without reflections only the black ambient is left
+    env.reflection = 0.;
+    gd.set_environment(scene, env);
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [0, 0, 0, 255]);
+}
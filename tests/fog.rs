@@ -0,0 +1,170 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![feature(core)]
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use std::num::Float;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material};
+use graphics::geometry::VertexGeoTexNorm;
+use render::{Renderable, Fog, FogMode};
+use soft::SoftRender;
+use cgmath::Vector3;
+use common::{GameData, new_scene_game};
+
+/// a 2x2 quad facing +z, 2 units in front of the camera
+fn place_quad(gd: &mut GameData, scene: Scene, mat: Entity) {
+    let v = |x: f32, y: f32| {
+        VertexGeoTexNorm {
+            position: [x, y, 0.],
+            texture: [0., 0.],
+            normal: [0., 0., 1.]
+        }
+    };
+    let vb = VertexBuffer::new_position_texture_normal(
+        vec![v(-1., -1.), v(1., -1.), v(1., 1.), v(-1., 1.)],
+        vec![0, 1, 2, 0, 2, 3]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    let geo = gd.new_geometry(Geometry::triangles(vb, 0, 6));
+    let oid = gd.new_object(Some(scene.to_entity()));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 0., -2.));
+    gd.set_draw(oid, geo, mat);
+}
+
+fn render(gd: &GameData) -> SoftRender {
+    let mut r = SoftRender::new(0, 0);
+    r.set_clear_color([0., 0., 0., 1.]);
+    r.render(gd);
+    r
+}
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-4
+}
+
+fn eye() -> Vector3<f32> {
+    Vector3::new(0., 0., 0.)
+}
+
+#[test]
+fn linear_fog() {
+    let fog = Fog::linear(Vector3::new(1., 1., 1.), 2., 6.);
+    assert_eq!(fog.amount(eye(), Vector3::new(0., 0., -1.)), 0.);
+    assert!(close(fog.amount(eye(), Vector3::new(0., 0., -4.)), 0.5));
+    assert!(close(fog.amount(eye(), Vector3::new(4., 0., 0.)), 0.5));
+    assert_eq!(fog.amount(eye(), Vector3::new(0., 0., -10.)), 1.);
+}
+
+#[test]
+fn exponential_fog() {
+    let fog = Fog::exponential(Vector3::new(1., 1., 1.), 0.5);
+    assert_eq!(fog.amount(eye(), eye()), 0.);
+    assert!(close(fog.amount(eye(), Vector3::new(0., 0., -2.)), 1. - (-1f32).exp()));
+    assert!(fog.amount(eye(), Vector3::new(0., 0., -100.)) > 0.999);
+}
+
+#[test]
+fn height_fog_thins_out_above_its_base() {
+    let flat = Fog::exponential(Vector3::new(1., 1., 1.), 0.5);
+    let fog = flat.with_height(0., 1.);
+    assert_eq!(fog.height.unwrap().base, 0.);
+
+    // level with the base the fog is as thick as without a height
+    let level = Vector3::new(0., 0., -2.);
+    assert!(close(fog.amount(eye(), level), flat.amount(eye(), level)));
+
+    // looking up through thinner fog hides less
+    let up = Vector3::new(0., 2., 0.);
+    assert!(fog.amount(eye(), up) < flat.amount(eye(), up));
+    // the average density from 0 to 2 is (1 - e^-2) / 2
+    let expected = 1. - (-0.5 * 2. * (1. - (-2f32).exp()) / 2.).exp();
+    assert!(close(fog.amount(eye(), up), expected));
+
+    // a raised eye looking level sees thinner fog
+    let high = Vector3::new(0., 3., 0.);
+    assert!(fog.amount(high, Vector3::new(0., 3., -2.)) < flat.amount(eye(), level));
+}
+
+#[test]
+fn fog_mixes_in_its_color() {
+    let fog = Fog::linear(Vector3::new(0., 0., 1.), 0., 4.);
+    let c = fog.apply(Vector3::new(1., 0., 0.), eye(), Vector3::new(0., 0., -1.));
+    assert!(close(c.x, 0.75) && close(c.y, 0.) && close(c.z, 0.25));
+    match fog.mode {
+        FogMode::Linear { start, end } => assert_eq!((start, end), (0., 4.)),
+        _ => panic!("expected linear fog")
+    }
+}
+
+#[test]
+fn fog_is_per_scene() {
+    let (mut gd, scene) = new_scene_game();
+    let other = gd.new_scene();
+    assert_eq!(gd.fog(scene), None);
+
+    let fog = Fog::exponential(Vector3::new(0.5, 0.5, 0.5), 0.1);
+    gd.set_fog(scene, fog);
+    assert_eq!(gd.fog(scene), Some(fog));
+    assert_eq!(gd.fog(other), None);
+
+    assert!(gd.remove_fog(scene));
+    assert_eq!(gd.fog(scene), None);
+}
+
+#[test]
+fn soft_render_draws_fog() {
+    let (mut gd, scene) = new_scene_game();
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place_quad(&mut gd, scene, red);
+    gd.set_fog(scene, Fog::linear(Vector3::new(0., 0., 1.), 0., 8.));
+
+    let r = render(&gd);
+    // 20% ambient red, a quarter of the way into the fog
+    assert_eq!(r.frame().pixel(16, 16), [38, 0, 64, 255]);
+    // the clear colour is not fogged
+    assert_eq!(r.frame().pixel(0, 0), [0, 0, 0, 255]);
+}
+
+#[test]
+fn thick_fog_hides_the_scene() {
+    let (mut gd, scene) = new_scene_game();
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place_quad(&mut gd, scene, red);
+    gd.set_fog(scene, Fog::exponential(Vector3::new(0., 1., 0.), 100.));
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [0, 255, 0, 255]);
+
+    // the fog of another scene is ignored
+    let other = gd.new_scene();
+    gd.remove_fog(scene);
+    gd.set_fog(other, Fog::exponential(Vector3::new(0., 1., 0.), 100.));
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [51, 0, 0, 255]);
+}
@@ -0,0 +1,208 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+//! Renders each of the examples with the software renderer after
+//! playing a fixed script of input, and compares the result against
+//! the reference images in `tests/golden`. A missing reference image
+//! fails the test, set `SNOWMEW_BLESS=1` to write the references for
+//! the first time or to replace them after an intended change. When
+//! an image does not match the rendered image and a diff are written
+//! to `target/golden`.
+
+#![feature(old_io, old_path, env, core)]
+
+extern crate cgmath;
+extern crate image;
+extern crate snowmew;
+extern crate "rustc-serialize" as rustc_serialize;
+
+pub use snowmew::{
+    core,
+    render,
+    loader,
+    graphics,
+    position,
+    input,
+    config,
+    debug
+};
+
+use std::env;
+use std::old_io::fs;
+use std::old_io::USER_RWX;
+
+use cgmath::Point3;
+use image::GenericImage;
+
+use core::Game;
+use graphics::Texture;
+use input::{Event, Button, GetIoState, integrator};
+use render::{Renderable, save_png};
+use render::soft::SoftRender;
+
+#[path = "../examples/cubes/game.rs"]
+mod cubes;
+#[path = "../examples/gears/game.rs"]
+mod gears;
+#[path = "../examples/noclip/game.rs"]
+mod noclip;
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+/// the seconds between each cadance pulse, this matches the default config
+const CADANCE: f64 = 0.015;
+
+/// the largest difference in any channel of a pixel that is not
+/// counted as a change
+const TOLERANCE: u8 = 8;
+
+/// the fraction of the pixels that are allowed to change
+const MAX_CHANGED: f64 = 0.002;
+
+fn steps(count: usize) -> Vec<Event> {
+    (0..count).map(|_| Event::Cadance(CADANCE)).collect()
+}
+
+/// hold `button` down for `count` cadance pulses
+fn hold(button: Button, count: usize) -> Vec<Event> {
+    let mut events = vec![Event::ButtonDown(button)];
+    events.extend(steps(count).into_iter());
+    events.push(Event::ButtonUp(button));
+    events
+}
+
+fn play<GD, G: Game<GD, Event>>(game: &mut G, mut gd: GD, script: Vec<Event>) -> GD {
+    for evt in script.into_iter() {
+        gd = game.step(evt, gd);
+    }
+    gd
+}
+
+fn render<RD: Renderable+GetIoState+Clone>(gd: &RD) -> Texture {
+    let mut gd = gd.clone();
+    gd.get_io_state_mut().size = (WIDTH, HEIGHT);
+    let mut render = SoftRender::new(WIDTH, HEIGHT);
+    render.render(&gd);
+    render.frame().to_texture()
+}
+
+fn load_png(path: &Path) -> Option<Texture> {
+    image::open(path).ok().map(|img| {
+        let img = img.to_rgba();
+        let (w, h) = img.dimensions();
+        Texture::new(w, h, 4, img.into_raw())
+    })
+}
+
+fn save(texture: &Texture, path: &Path) {
+    fs::mkdir_recursive(&path.dir_path(), USER_RWX).unwrap();
+    save_png(texture, path).unwrap();
+}
+
+/// The image that is written when a comparison fails, changed pixels
+/// are red and everything else is a faded copy of the reference.
+fn diff_image(actual: &Texture, expected: &Texture) -> (Texture, usize) {
+    let mut data = Vec::with_capacity(actual.data().len());
+    let mut changed = 0;
+    for (a, e) in actual.data().chunks(4).zip(expected.data().chunks(4)) {
+        let worst = (0..4).map(|i| {
+            if a[i] > e[i] { a[i] - e[i] } else { e[i] - a[i] }
+        }).max().unwrap();
+
+        let pixel = if worst > TOLERANCE {
+            changed += 1;
+            [255, 0, 0, 255]
+        } else {
+            let grey = (e[0] / 3 + e[1] / 3 + e[2] / 3) / 2;
+            [grey, grey, grey, 255]
+        };
+        data.extend(pixel.iter().map(|&x| x));
+    }
+    (Texture::new(actual.width(), actual.height(), 4, data), changed)
+}
+
+fn check(name: &str, actual: &Texture) {
+    let reference = Path::new("tests/golden").join(format!("{}.png", name));
+    let output = Path::new("target/golden");
+
+    if env::var("SNOWMEW_BLESS").is_ok() {
+        save(actual, &reference);
+        return;
+    }
+    let expected = match load_png(&reference) {
+        Some(expected) => expected,
+        None => panic!("{}: there is no reference image at {}, run with SNOWMEW_BLESS=1 to write it",
+                       name, reference.display())
+    };
+
+    let actual_path = output.join(format!("{}.actual.png", name));
+    if (actual.width(), actual.height()) != (expected.width(), expected.height()) {
+        save(actual, &actual_path);
+        panic!("{}: rendered a {}x{} image, the reference is {}x{}. see {}",
+               name, actual.width(), actual.height(),
+               expected.width(), expected.height(), actual_path.display());
+    }
+
+    let (diff, changed) = diff_image(actual, &expected);
+    let pixels = (actual.width() * actual.height()) as f64;
+    if changed as f64 > pixels * MAX_CHANGED {
+        let diff_path = output.join(format!("{}.diff.png", name));
+        save(actual, &actual_path);
+        save(&diff, &diff_path);
+        panic!("{}: {} of {} pixels changed, see {} and {}",
+               name, changed, pixels, actual_path.display(), diff_path.display());
+    }
+}
+
+#[test]
+fn cubes() {
+    let (mut game, gd) = integrator(cubes::Cubes, cubes::setup(2));
+
+    let mut script = steps(5);
+    script.extend(hold(Button::KeyboardS, 40).into_iter());
+    script.push(Event::Move(30., 10.));
+    script.extend(steps(5).into_iter());
+    let gd = play(&mut game, gd, script);
+
+    check("cubes", &render(&gd.inner));
+}
+
+#[test]
+fn gears() {
+    let (game, gd) = gears::setup();
+    let (mut game, gd) = integrator(game, gd);
+
+    // let the gears turn, then pause them
+    let mut script = steps(60);
+    script.extend(hold(Button::KeyboardSpace, 1).into_iter());
+    script.extend(steps(10).into_iter());
+    let gd = play(&mut game, gd, script);
+    assert!(gd.inner.paused);
+
+    check("gears", &render(&gd.inner));
+}
+
+#[test]
+fn noclip() {
+    let gd = noclip::setup(&Path::new("assets/rust_logo.obj"), 1., Point3::new(0., 0., 20.));
+    let (mut game, gd) = integrator(noclip::Noclip, gd);
+
+    let mut script = steps(5);
+    script.extend(hold(Button::KeyboardW, 30).into_iter());
+    script.extend(hold(Button::KeyboardA, 10).into_iter());
+    let gd = play(&mut game, gd, script);
+
+    check("noclip", &render(&gd.inner));
+}
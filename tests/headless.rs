@@ -0,0 +1,115 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-input" as input;
+
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use snowmew::core::Game;
+use snowmew::render::{Render, Renderable, RenderFactory, RenderTarget};
+use snowmew::render::{BasicRenderData, DefaultRender, RenderStats, UnsupportedTarget};
+use snowmew::input::{Event, GetIoState};
+use snowmew::config::SnowmewConfig;
+use common::{GameData, new_game};
+
+struct Counter;
+
+impl Game<GameData, Event> for Counter {
+    fn step(&mut self, _: Event, mut gd: GameData) -> GameData {
+        gd.steps += 1;
+        gd.debug_draw_mut().text_at(0., 0., "step", [1., 1., 1., 1.]);
+        gd
+    }
+}
+
+/// counts the frames it is given and checks the target it was created with
+struct CountingRender(Arc<AtomicUsize>);
+
+impl Render<BasicRenderData> for CountingRender {
+    fn update(&mut self, db: BasicRenderData) {
+        assert_eq!(db.get_io_state().size, (64, 32));
+        // the debug draw is cleared between frames
+        assert_eq!(db.debug_draw().text().len(), 1);
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn stats(&self) -> RenderStats {
+        let mut stats = RenderStats::new();
+        stats.frame = self.0.load(Ordering::SeqCst) as u64;
+        stats
+    }
+}
+
+struct CountingFactory(Arc<AtomicUsize>);
+
+impl RenderFactory<BasicRenderData, CountingRender> for CountingFactory {
+    fn init(self: Box<CountingFactory>, target: RenderTarget) -> CountingRender {
+        assert!(!target.is_window());
+        assert_eq!(target.size(), (64, 32));
+        CountingRender(self.0.clone())
+    }
+}
+
+/// a factory that can only draw to windows
+struct WindowFactory;
+
+impl RenderFactory<BasicRenderData, CountingRender> for WindowFactory {
+    fn init(self: Box<WindowFactory>, _: RenderTarget) -> CountingRender {
+        panic!("init should not be called for an unsupported target")
+    }
+
+    fn supports(&self, target: &RenderTarget) -> bool {
+        target.is_window()
+    }
+}
+
+fn config() -> SnowmewConfig {
+    let mut sc = SnowmewConfig::new();
+    sc.headless = true;
+    sc.frame_limit = Some(5);
+    sc.cadance_ms = 1;
+    sc.display.resolution = Some((64, 32));
+    sc
+}
+
+#[test]
+fn runs_without_display() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let gd = config().start(Box::new(CountingFactory(count.clone())), Counter, new_game()).unwrap();
+    assert_eq!(gd.steps, 5);
+    assert_eq!(gd.get_io_state().size, (64, 32));
+    assert_eq!(count.load(Ordering::SeqCst), 5);
+    // the game is handed the stats of the last frame
+    assert_eq!(gd.get_io_state().render_stats.frame, 5);
+    assert!(gd.debug_draw().is_empty());
+}
+
+#[test]
+fn default_render_is_offscreen() {
+    let gd = config().start(Box::new(DefaultRender::new()), Counter, new_game()).unwrap();
+    assert_eq!(gd.steps, 5);
+}
+
+#[test]
+fn unsupported_target_fails_before_starting() {
+    let res = config().start(Box::new(WindowFactory), Counter, new_game());
+    assert_eq!(res.err(), Some(UnsupportedTarget { target: "offscreen" }));
+}
@@ -0,0 +1,141 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-input" as input;
+
+mod common;
+
+use snowmew::common::{Common, Duplicate, Delete};
+use graphics::{Graphics, Material, PbrMaterial, BlendMode, VertexBuffer};
+use graphics::geometry::{VertexGeo, VertexGeoTexNorm};
+use common::{GameData, new_game};
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 0.0001
+}
+
+#[test]
+fn from_mtl_diffuse() {
+    let mut mtl = Material::new();
+    mtl.set_kd([0.5, 0.25, 0.]);
+    mtl.set_ke([0., 0., 1.]);
+    mtl.set_ns(0.);
+    mtl.set_map_kd(3);
+    mtl.set_map_bump(4);
+
+    let pbr = PbrMaterial::from_mtl(&mtl);
+    assert_eq!(pbr.base_color(), [0.5, 0.25, 0.]);
+    assert_eq!(pbr.emissive(), [0., 0., 1.]);
+    assert_eq!(pbr.base_color_map(), Some(3));
+    assert_eq!(pbr.normal_map(), Some(4));
+    assert_eq!(pbr.metallic(), 0.);
+    // a specular exponent of zero is as rough as it gets
+    assert!(close(pbr.roughness(), 1.));
+}
+
+#[test]
+fn from_mtl_ambient_fallback() {
+    let mut mtl = Material::new();
+    mtl.set_ka([0., 1., 0.]);
+    mtl.set_kd([0., 0., 0.]);
+    mtl.set_map_ka(7);
+
+    let pbr = PbrMaterial::from_mtl(&mtl);
+    assert_eq!(pbr.base_color(), [0., 1., 0.]);
+    assert_eq!(pbr.base_color_map(), Some(7));
+}
+
+#[test]
+fn from_mtl_shiny_and_metallic() {
+    let mut dull = Material::new();
+    dull.set_ns(10.);
+    let mut shiny = Material::new();
+    shiny.set_ns(500.);
+    shiny.set_illum(3);
+
+    let (dull, shiny) = (PbrMaterial::from_mtl(&dull), PbrMaterial::from_mtl(&shiny));
+    assert!(shiny.roughness() < dull.roughness());
+    assert_eq!(dull.metallic(), 0.);
+    assert_eq!(shiny.metallic(), 1.);
+}
+
+#[test]
+fn opacity() {
+    let mut mtl = Material::new();
+    assert_eq!(mtl.opacity(), 1.);
+    assert_eq!(mtl.blend(), BlendMode::Opaque);
+    mtl.set_tr(0.25);
+    assert_eq!(mtl.opacity(), 0.75);
+    mtl.set_d(0.5);
+    assert_eq!(mtl.opacity(), 0.5);
+
+    mtl.set_blend(BlendMode::Additive);
+    let pbr = PbrMaterial::from_mtl(&mtl);
+    assert_eq!(pbr.opacity(), 0.5);
+    assert_eq!(pbr.blend(), BlendMode::Additive);
+}
+
+#[test]
+fn pbr_materials_are_components() {
+    let mut gd = new_game();
+    let mat = gd.new_pbr_material(PbrMaterial::simple([1., 0., 0.], 1., 0.5));
+    assert!(gd.material(mat).is_none());
+    assert_eq!(gd.pbr_material(mat).unwrap().metallic(), 1.);
+    assert_eq!(gd.pbr_material_iter().count(), 1);
+
+    let copy = gd.new_object(None);
+    gd.graphics.duplicate(mat, copy);
+    assert!(gd.pbr_material(copy) == gd.pbr_material(mat));
+    gd.graphics.delete(mat);
+    assert!(gd.pbr_material(mat).is_none());
+}
+
+#[test]
+fn tangents_follow_u() {
+    let v = |x: f32, y: f32, u: f32, t: f32| {
+        VertexGeoTexNorm {
+            position: [x, y, 0.],
+            texture: [u, t],
+            normal: [0., 0., 1.]
+        }
+    };
+    // u runs along -y
+    let vb = VertexBuffer::new_position_texture_normal(
+        vec![v(0., 0., 0., 0.), v(0., -1., 1., 0.), v(1., -1., 1., 1.)],
+        vec![0, 1, 2]
+    );
+    for vert in vb.tangent_vertices().iter() {
+        assert!(close(vert.tangent[0], 0.));
+        assert!(close(vert.tangent[1], -1.));
+        assert!(close(vert.tangent[2], 0.));
+    }
+}
+
+#[test]
+fn tangents_without_texture_are_zero() {
+    let vb = VertexBuffer::new_position(
+        vec![VertexGeo { position: [0., 0., 0.] },
+             VertexGeo { position: [1., 0., 0.] },
+             VertexGeo { position: [0., 1., 0.] }],
+        vec![0, 1, 2]
+    );
+    let verts = vb.tangent_vertices();
+    assert_eq!(verts.len(), 3);
+    assert!(verts.iter().all(|v| v.tangent == [0.; 3] && v.normal == [0.; 3]));
+    assert_eq!(verts[1].position, [1., 0., 0.]);
+}
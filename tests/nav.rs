@@ -0,0 +1,169 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-nav" as nav;
+extern crate cgmath;
+
+use snowmew::common::{Common, CommonData, Entity};
+use position::{Positions, PositionData};
+use graphics::{Graphics, GraphicsData, Geometry, VertexBuffer, Material};
+use graphics::geometry::VertexGeo;
+use nav::{Navigation, NavData};
+use cgmath::{Vector, Vector3, EuclideanVector};
+
+#[derive(Clone)]
+struct GameData {
+    common: CommonData,
+    position: PositionData,
+    graphics: GraphicsData,
+    nav: NavData
+}
+
+impl Common for GameData {
+    fn get_common<'a>(&'a self) -> &'a CommonData { &self.common }
+    fn get_common_mut<'a>(&'a mut self) -> &'a mut CommonData { &mut self.common }
+}
+
+impl Positions for GameData {
+    fn get_position<'a>(&'a self) -> &'a PositionData { &self.position }
+    fn get_position_mut<'a>(&'a mut self) -> &'a mut PositionData { &mut self.position }
+}
+
+impl Graphics for GameData {
+    fn get_graphics<'a>(&'a self) -> &'a GraphicsData { &self.graphics }
+    fn get_graphics_mut<'a>(&'a mut self) -> &'a mut GraphicsData { &mut self.graphics }
+}
+
+impl Navigation for GameData {
+    fn get_nav<'a>(&'a self) -> &'a NavData { &self.nav }
+    fn get_nav_mut<'a>(&'a mut self) -> &'a mut NavData { &mut self.nav }
+}
+
+fn quad(vert: &mut Vec<VertexGeo>, idx: &mut Vec<u32>, corners: [[f32; 3]; 4]) {
+    let base = vert.len() as u32;
+    for &c in corners.iter() {
+        vert.push(VertexGeo { position: c });
+    }
+    for &i in [0, 1, 2, 2, 1, 3].iter() {
+        idx.push(base + i);
+    }
+}
+
+/// create a drawable made of 1x1 floor tiles, plus a wall
+/// that should never be walkable
+fn tiles(gd: &mut GameData, tiles: &[(f32, f32)], disp: Vector3<f32>) -> Entity {
+    let mut vert = Vec::new();
+    let mut idx = Vec::new();
+    for &(x, z) in tiles.iter() {
+        quad(&mut vert, &mut idx, [[x, 0., z], [x, 0., z+1.], [x+1., 0., z], [x+1., 0., z+1.]]);
+    }
+    quad(&mut vert, &mut idx, [[0., 0., -1.], [0., 1., -1.], [1., 0., -1.], [1., 1., -1.]]);
+
+    let len = idx.len();
+    let vb = gd.new_vertex_buffer(VertexBuffer::new_position(vert, idx));
+    let geo = gd.new_geometry(Geometry::triangles(vb, 0, len));
+    let mat = gd.new_material(Material::simple([1., 1., 1.]));
+    let oid = gd.new_object(None);
+    gd.set_draw(oid, geo, mat);
+    gd.set_displacement(oid, disp);
+    oid
+}
+
+fn level() -> (GameData, Entity) {
+    let mut gd = GameData {
+        common: CommonData::new(),
+        position: PositionData::new(),
+        graphics: GraphicsData::new(),
+        nav: NavData::new()
+    };
+
+    // an L shaped corridor made from two drawables
+    let offset = Vector3::new(10., 0., 0.);
+    let a = tiles(&mut gd, &[(0., 0.), (1., 0.), (2., 0.)], offset);
+    let b = tiles(&mut gd, &[(2., 1.), (2., 2.)], offset);
+    // an island that is not connected to anything
+    let c = tiles(&mut gd, &[(5., 5.)], offset);
+
+    let mesh = gd.build_navmesh(&[a, b, c], 0.5, 0.001);
+    (gd, mesh)
+}
+
+fn close(a: &Vector3<f32>, b: &Vector3<f32>) -> bool {
+    a.sub_v(b).length() < 0.001
+}
+
+#[test]
+fn walls_are_not_walkable() {
+    let (gd, mesh) = level();
+    // 6 tiles of two triangles, the walls are dropped
+    assert_eq!(gd.navmesh(mesh).unwrap().triangles().len(), 12);
+}
+
+#[test]
+fn drawables_are_welded() {
+    let (gd, mesh) = level();
+    let mesh = gd.navmesh(mesh).unwrap();
+    // the two corners shared by the corridor drawables are merged
+    assert_eq!(mesh.vertices().len(), 16);
+    assert!(mesh.find_triangle(&Vector3::new(10.5, 0., 0.5)).is_some());
+    assert!(mesh.find_triangle(&Vector3::new(10.5, 0., 2.5)).is_none());
+}
+
+#[test]
+fn path_around_corner() {
+    let (gd, mesh) = level();
+    let start = Vector3::new(10.5, 0., 0.5);
+    let goal = Vector3::new(12.5, 0., 2.5);
+    let path = gd.navmesh(mesh).unwrap().find_path(&start, &goal).unwrap();
+
+    assert_eq!(path.len(), 3);
+    assert!(close(&path[0], &start));
+    assert!(close(&path[1], &Vector3::new(12., 0., 1.)));
+    assert!(close(&path[2], &goal));
+}
+
+#[test]
+fn straight_path() {
+    let (gd, mesh) = level();
+    let start = Vector3::new(10.5, 0., 0.5);
+    let goal = Vector3::new(12.5, 0., 0.5);
+    let path = gd.navmesh(mesh).unwrap().find_path(&start, &goal).unwrap();
+    assert_eq!(path.len(), 2);
+}
+
+#[test]
+fn unreachable() {
+    let (gd, mesh) = level();
+    let start = Vector3::new(10.5, 0., 0.5);
+    let goal = Vector3::new(15.5, 0., 5.5);
+    assert!(gd.navmesh(mesh).unwrap().find_path(&start, &goal).is_none());
+}
+
+#[test]
+fn follow_path() {
+    let (mut gd, mesh) = level();
+    let agent = gd.new_object(None);
+    gd.set_displacement(agent, Vector3::new(10.5, 0., 0.5));
+
+    let goal = Vector3::new(12.5, 0., 2.5);
+    assert!(gd.set_destination(agent, mesh, goal, 1.));
+    for _ in (0..50) {
+        gd.follow_paths(0.1);
+    }
+    assert!(gd.follower(agent).unwrap().finished());
+    assert!(close(gd.get_displacement(agent).unwrap(), &goal));
+}
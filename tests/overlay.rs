@@ -0,0 +1,163 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+
+mod common;
+
+use graphics::{Graphics, Texture};
+use render::{Renderable, Anchor, Overlay, QuadTexture};
+use render::font::{atlas, atlas_uv, glyph_pixel, GLYPH_WIDTH, GLYPH_HEIGHT};
+use soft::SoftRender;
+use common::{GameData, new_game};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const RED: [f32; 4] = [1., 0., 0., 1.];
+const WHITE: [f32; 4] = [1., 1., 1., 1.];
+
+fn render(gd: &GameData) -> SoftRender {
+    let mut r = SoftRender::new(0, 0);
+    r.set_clear_color([0., 0., 0., 1.]);
+    r.render(gd);
+    r
+}
+
+#[test]
+fn anchors() {
+    let item = Overlay::rect(10., 4., RED);
+    assert_eq!(item.screen_rect(100, 50), [0., 0., 10., 4.]);
+    assert_eq!(item.clone().anchored(Anchor::BottomRight, -2., -2.).screen_rect(100, 50),
+               [88., 44., 10., 4.]);
+    assert_eq!(item.clone().anchored(Anchor::Center, 0., 0.).screen_rect(100, 50),
+               [45., 23., 10., 4.]);
+    assert_eq!(item.clone().anchored(Anchor::Top, 0., 5.).screen_rect(100, 50),
+               [45., 5., 10., 4.]);
+    assert_eq!(item.anchored(Anchor::Left, 3., 0.).screen_rect(100, 50),
+               [3., 23., 10., 4.]);
+}
+
+#[test]
+fn text_is_sized_by_its_font() {
+    let text = Overlay::text("a b", 2, WHITE);
+    assert_eq!(text.size(), [34., 14.]);
+    assert_eq!(text.anchored(Anchor::BottomRight, 0., 0.).screen_rect(100, 50),
+               [66., 36., 34., 14.]);
+}
+
+#[test]
+fn text_quads() {
+    let mut gd = new_game();
+    gd.new_overlay(Overlay::text("a b\nc", 2, WHITE).anchored(Anchor::TopLeft, 4., 6.));
+
+    // the space has no quad
+    let quads = gd.overlay_quads(32, 32);
+    assert_eq!(quads.len(), 3);
+    assert_eq!(quads[0].rect, [4., 6., 10., 14.]);
+    assert_eq!(quads[1].rect, [28., 6., 10., 14.]);
+    assert_eq!(quads[2].rect, [4., 24., 10., 14.]);
+    assert_eq!(quads[0].uv, atlas_uv('a'));
+    assert_eq!(quads[2].uv, atlas_uv('c'));
+    for q in quads.iter() {
+        assert_eq!(q.texture, QuadTexture::Font);
+    }
+}
+
+#[test]
+fn quads_are_sorted_by_layer() {
+    let mut gd = new_game();
+    let top = gd.new_overlay(Overlay::rect(1., 1., RED).layered(1));
+    let bottom = gd.new_overlay(Overlay::rect(2., 2., RED).layered(-1));
+    let middle = gd.new_overlay(Overlay::rect(3., 3., RED));
+    let hidden = gd.new_overlay(Overlay::rect(4., 4., RED));
+    let mut item = gd.overlay(hidden).unwrap().clone();
+    item.visible = false;
+    gd.set_overlay(hidden, item);
+
+    let sizes: Vec<f32> = gd.overlay_quads(32, 32).iter().map(|q| q.rect[2]).collect();
+    assert_eq!(sizes, vec![2., 3., 1.]);
+
+    assert!(gd.remove_overlay(middle));
+    assert!(gd.overlay(top).is_some());
+    assert!(gd.overlay(bottom).is_some());
+    assert_eq!(gd.overlay_iter().count(), 3);
+}
+
+#[test]
+fn font_atlas() {
+    let texture = atlas();
+    assert_eq!((texture.width(), texture.height()), (96, 48));
+
+    let uv = atlas_uv('A');
+    let texel = |u: f32, size: f32| (u * size + 0.5) as u32;
+    let (x, y) = (texel(uv[0], 96.), texel(uv[1], 48.));
+    assert_eq!((texel(uv[2], 96.) - x, texel(uv[3], 48.) - y), (GLYPH_WIDTH, GLYPH_HEIGHT));
+    for gy in 0..GLYPH_HEIGHT {
+        for gx in 0..GLYPH_WIDTH {
+            let i = (((y + gy) * 96 + x + gx) * 4) as usize;
+            assert_eq!(texture.data()[i + 3] == 255, glyph_pixel('A', gx, gy));
+        }
+    }
+    assert_eq!(atlas_uv('\u{e9}'), atlas_uv('?'));
+}
+
+#[test]
+fn rects_follow_the_window_size() {
+    let mut gd = new_game();
+    gd.new_overlay(Overlay::rect(4., 2., RED).anchored(Anchor::BottomRight, 0., 0.));
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(31, 31), [255, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(28, 30), [255, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(27, 31), BLACK);
+    assert_eq!(r.frame().pixel(31, 29), BLACK);
+
+    gd.io.size = (16, 8);
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(15, 7), [255, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(11, 7), BLACK);
+}
+
+#[test]
+fn sprites_are_tinted() {
+    let mut gd = new_game();
+    let texture = gd.new_texture(Texture::new(2, 1, 4, vec![255, 0, 0, 255, 0, 0, 255, 255]));
+    let mut sprite = Overlay::sprite(texture, 4., 2.).anchored(Anchor::TopLeft, 2., 2.);
+    sprite.color = [1., 1., 1., 0.5];
+    gd.new_overlay(sprite);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(2, 2), [128, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(5, 3), [0, 0, 128, 255]);
+    assert_eq!(r.frame().pixel(6, 2), BLACK);
+    assert_eq!(r.frame().pixel(2, 4), BLACK);
+}
+
+#[test]
+fn text_is_drawn_from_the_atlas() {
+    let mut gd = new_game();
+    gd.new_overlay(Overlay::text("I", 2, WHITE).anchored(Anchor::TopLeft, 3., 5.));
+
+    let r = render(&gd);
+    for y in 0..GLYPH_HEIGHT * 2 {
+        for x in 0..GLYPH_WIDTH * 2 {
+            let expected = if glyph_pixel('I', x / 2, y / 2) { [255, 255, 255, 255] } else { BLACK };
+            assert_eq!(r.frame().pixel(3 + x, 5 + y), expected);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-random" as random;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-particle" as particle;
+extern crate cgmath;
+
+use std::num::Float;
+
+use snowmew::common::{Common, CommonData, Entity};
+use position::{Positions, PositionData};
+use random::{Random, RandomData};
+use graphics::{Graphics, GraphicsData};
+use particle::{Particles, ParticleData, ParticleSystem, Emitter};
+use cgmath::{Vector, Vector3, Matrix4, EuclideanVector};
+
+#[derive(Clone)]
+struct GameData {
+    common: CommonData,
+    position: PositionData,
+    random: RandomData,
+    graphics: GraphicsData,
+    particles: ParticleData
+}
+
+impl Common for GameData {
+    fn get_common<'a>(&'a self) -> &'a CommonData { &self.common }
+    fn get_common_mut<'a>(&'a mut self) -> &'a mut CommonData { &mut self.common }
+}
+
+impl Positions for GameData {
+    fn get_position<'a>(&'a self) -> &'a PositionData { &self.position }
+    fn get_position_mut<'a>(&'a mut self) -> &'a mut PositionData { &mut self.position }
+}
+
+impl Random for GameData {
+    fn rng(&mut self) -> &mut RandomData { &mut self.random }
+}
+
+impl Graphics for GameData {
+    fn get_graphics<'a>(&'a self) -> &'a GraphicsData { &self.graphics }
+    fn get_graphics_mut<'a>(&'a mut self) -> &'a mut GraphicsData { &mut self.graphics }
+}
+
+impl Particles for GameData {
+    fn get_particles<'a>(&'a self) -> &'a ParticleData { &self.particles }
+    fn get_particles_mut<'a>(&'a mut self) -> &'a mut ParticleData { &mut self.particles }
+}
+
+fn scene(emitter: Emitter) -> (GameData, Entity) {
+    let mut gd = GameData {
+        common: CommonData::new(),
+        position: PositionData::new(),
+        random: RandomData::new(),
+        graphics: GraphicsData::new(),
+        particles: ParticleData::new()
+    };
+    let oid = gd.new_object(None);
+    gd.set_displacement(oid, Vector3::new(1., 2., 3.));
+    gd.set_emitter(oid, emitter);
+    (gd, oid)
+}
+
+#[test]
+fn emits_at_rate() {
+    let mut emitter = Emitter::new();
+    emitter.rate = 20.;
+    emitter.lifetime = (10., 10.);
+
+    let mut system = ParticleSystem::new();
+    let mut rng = RandomData::new();
+    for _ in (0..10) {
+        particle::simulate(&emitter, &mut system, &Matrix4::identity(), 0.1, &mut rng);
+    }
+    assert_eq!(system.particles.len(), 20);
+}
+
+#[test]
+fn particles_expire() {
+    let mut emitter = Emitter::new();
+    emitter.rate = 10.;
+    emitter.lifetime = (0.5, 0.5);
+
+    let mut system = ParticleSystem::new();
+    let mut rng = RandomData::new();
+    particle::simulate(&emitter, &mut system, &Matrix4::identity(), 0.1, &mut rng);
+    assert_eq!(system.particles.len(), 1);
+
+    emitter.emitting = false;
+    for _ in (0..6) {
+        particle::simulate(&emitter, &mut system, &Matrix4::identity(), 0.1, &mut rng);
+    }
+    assert_eq!(system.particles.len(), 0);
+}
+
+#[test]
+fn max_particles() {
+    let mut emitter = Emitter::new();
+    emitter.rate = 1000.;
+    emitter.max_particles = 5;
+
+    let mut system = ParticleSystem::new();
+    let mut rng = RandomData::new();
+    particle::simulate(&emitter, &mut system, &Matrix4::identity(), 1., &mut rng);
+    assert_eq!(system.particles.len(), 5);
+}
+
+#[test]
+fn cone_stays_inside_spread() {
+    let mut rng = RandomData::new();
+    let axis = Vector3::new(0f32, 0., -1.);
+    for _ in (0..100) {
+        let v = particle::cone(&mut rng, &axis, 0.25);
+        assert!((v.length() - 1.).abs() < 0.0001);
+        assert!(v.dot(&axis) >= 0.25f32.cos() - 0.0001);
+    }
+}
+
+#[test]
+fn gravity_and_color_over_life() {
+    let mut emitter = Emitter::new();
+    emitter.rate = 1.;
+    emitter.speed = (0., 0.);
+    emitter.gravity = Vector3::new(0., -10., 0.);
+    emitter.start_color = [1., 0., 0., 1.];
+    emitter.end_color = [0., 0., 1., 0.];
+    emitter.start_size = 1.;
+    emitter.end_size = 3.;
+    emitter.lifetime = (2., 2.);
+
+    let (mut gd, oid) = scene(emitter);
+    gd.step_particles(1.);
+    gd.emitter_mut(oid).unwrap().emitting = false;
+    gd.step_particles(1.);
+
+    let p = gd.particle_system(oid).unwrap().particles[0];
+    assert!(p.position.y < 2.);
+    assert_eq!(p.position.x, 1.);
+
+    let b = gd.billboards(oid);
+    assert_eq!(b.len(), 1);
+    assert_eq!(b[0].color, [0.5, 0., 0.5, 0.5]);
+    assert_eq!(b[0].size, 2.);
+
+    // the colour and size are kept for the renders
+    particle::update_billboards(&mut gd, oid, 10, 11);
+    let set = gd.billboard_set(oid).unwrap();
+    assert_eq!((set.geometry, set.material), (10, 11));
+    assert_eq!(set.billboards, b);
+}
+
+#[test]
+fn deterministic() {
+    let run = || {
+        let mut emitter = Emitter::new();
+        emitter.spread = 1.;
+        emitter.speed = (1., 4.);
+        let (mut gd, oid) = scene(emitter);
+        gd.set_nonce(3);
+        for _ in (0..30) {
+            gd.step_particles(1. / 30.);
+        }
+        gd.billboards(oid)
+    };
+    assert_eq!(run(), run());
+}
@@ -0,0 +1,132 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-render" as render;
+
+use render::{PostChain, PostEffect, PostImage, ToneMap};
+
+fn flat(width: u32, height: u32, c: [f32; 4]) -> PostImage {
+    PostImage::new(width, height, (0..width * height).map(|_| c).collect())
+}
+
+fn near(a: [f32; 4], b: [f32; 4]) -> bool {
+    (0..4).all(|i| (a[i] - b[i]).abs() < 1e-4)
+}
+
+#[test]
+fn tone_map() {
+    let img = flat(1, 1, [1., 3., 0., 1.]);
+    let reinhard = PostEffect::ToneMap { operator: ToneMap::Reinhard, exposure: 1. };
+    assert!(near(reinhard.apply(&img).pixels[0], [0.5, 0.75, 0., 1.]));
+
+    let exposed = PostEffect::ToneMap { operator: ToneMap::Reinhard, exposure: 3. };
+    assert!(near(exposed.apply(&img).pixels[0], [0.75, 0.9, 0., 1.]));
+
+    // the filmic curve saturates instead of approaching 1
+    let aces = PostEffect::ToneMap { operator: ToneMap::Aces, exposure: 1. };
+    let c = aces.apply(&flat(1, 1, [100., 0., 0.5, 1.])).pixels[0];
+    assert_eq!(c[0], 1.);
+    assert_eq!(c[1], 0.);
+    assert!(c[2] > 0.5 && c[2] < 0.7);
+}
+
+#[test]
+fn gamma() {
+    let img = flat(1, 1, [0.25, 1., 0., 0.25]);
+    let c = PostEffect::Gamma(2.).apply(&img).pixels[0];
+    // the alpha is left alone
+    assert!(near(c, [0.5, 1., 0., 0.25]));
+}
+
+#[test]
+fn fxaa_smooths_edges() {
+    // a white column on black
+    let b = [0., 0., 0., 1.];
+    let w = [1., 1., 1., 1.];
+    let img = PostImage::new(3, 3, vec![b, w, b, b, w, b, b, w, b]);
+    let fxaa = PostEffect::Fxaa { threshold: 0.1 };
+    let out = fxaa.apply(&img);
+    assert!(near(out.get(1, 1), [0.5, 0.5, 0.5, 1.]));
+    assert!(near(out.get(0, 1), [0.25, 0.25, 0.25, 1.]));
+
+    // flat areas are left alone
+    let flat = flat(3, 3, w);
+    assert_eq!(fxaa.apply(&flat), flat);
+}
+
+#[test]
+fn bloom_spreads_bright_pixels() {
+    let mut img = flat(5, 5, [0.5, 0.5, 0.5, 1.]);
+    img.pixels[12] = [9.5, 0.5, 0.5, 1.];
+    let bloom = PostEffect::Bloom { threshold: 1., intensity: 1., radius: 1 };
+    let out = bloom.apply(&img);
+    // 8.5 over the threshold, spread over 9 taps
+    assert!(near(out.get(1, 1), [0.5 + 8.5 / 9., 0.5, 0.5, 1.]));
+    assert!(near(out.get(2, 2), [9.5 + 8.5 / 9., 0.5, 0.5, 1.]));
+    assert!(near(out.get(0, 0), [0.5, 0.5, 0.5, 1.]));
+}
+
+#[test]
+fn vignette_darkens_corners() {
+    let img = flat(9, 9, [1., 1., 1., 1.]);
+    let vignette = PostEffect::Vignette { strength: 1., radius: 0.5 };
+    let out = vignette.apply(&img);
+    assert_eq!(out.get(4, 4), [1., 1., 1., 1.]);
+    assert!(out.get(0, 0)[0] < 0.2);
+    assert!(out.get(0, 4)[0] > out.get(0, 0)[0]);
+    assert_eq!(out.get(0, 0)[3], 1.);
+}
+
+#[test]
+fn color_grade() {
+    let img = flat(1, 1, [0.5, 0.25, 1., 1.]);
+    let identity = PostEffect::ColorGrade {
+        tint: [1., 1., 1.], brightness: 0., contrast: 1., saturation: 1.
+    };
+    assert!(near(identity.apply(&img).pixels[0], [0.5, 0.25, 1., 1.]));
+
+    let grey = PostEffect::ColorGrade {
+        tint: [1., 1., 1.], brightness: 0., contrast: 1., saturation: 0.
+    };
+    let c = grey.apply(&img).pixels[0];
+    assert!(near(c, [c[0], c[0], c[0], 1.]));
+
+    let tinted = PostEffect::ColorGrade {
+        tint: [0., 1., 1.], brightness: 0.1, contrast: 2., saturation: 1.
+    };
+    assert!(near(tinted.apply(&img).pixels[0], [0., 0.1, 1.6, 1.]));
+}
+
+#[test]
+fn chain_runs_enabled_effects_in_order() {
+    let img = flat(1, 1, [1., 1., 1., 1.]);
+    let mut chain = PostChain::new();
+    assert!(chain.is_empty());
+    assert_eq!(chain.apply(&img), img);
+
+    let tone = chain.push(PostEffect::ToneMap { operator: ToneMap::Reinhard, exposure: 1. });
+    let gamma = chain.push(PostEffect::Gamma(0.5));
+    assert_eq!(chain.len(), 2);
+    assert!(!chain.is_empty());
+    // 1 / (1 + 1) squared
+    assert!(near(chain.apply(&img).pixels[0], [0.25, 0.25, 0.25, 1.]));
+
+    chain.set_enabled(tone, false);
+    assert_eq!(chain.effects(), vec![PostEffect::Gamma(0.5)]);
+    assert!(near(chain.apply(&img).pixels[0], [1., 1., 1., 1.]));
+
+    chain.set_enabled(gamma, false);
+    assert!(chain.is_empty());
+    assert_eq!(chain.stages().len(), 2);
+}
@@ -0,0 +1,138 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+
+mod common;
+
+use snowmew::common::{Common, Entity, Delete, Duplicate};
+use position::Positions;
+use graphics::{Graphics, VertexBuffer, Material, Texture};
+use graphics::geometry::VertexGeo;
+use render::{Render, Renderable, RenderView, Change, ResourceTracker, ResourceStats};
+use soft::SoftRender;
+use common::{GameData, new_game_sized};
+
+fn triangle() -> VertexBuffer {
+    let v = |x: f32, y: f32| VertexGeo { position: [x, y, 0.] };
+    VertexBuffer::new_position(vec![v(0., 0.), v(1., 0.), v(0., 1.)], vec![0, 1, 2])
+}
+
+/// what a render would see of the game's vertex buffers
+fn vertex_buffers(gd: &GameData) -> Vec<(Entity, u64)> {
+    gd.vertex_buffer_iter().map(|(oid, _)| (oid, gd.revision(oid))).collect()
+}
+
+#[test]
+fn setting_a_resource_changes_its_revision() {
+    let mut gd = new_game_sized(8, 8);
+    let texture = gd.new_texture(Texture::new(1, 1, 4, vec![0; 4]));
+    let vb = gd.new_vertex_buffer(triangle());
+    let mat = gd.new_material(Material::simple([1., 0., 0.]));
+    let first = gd.revision(texture);
+    assert!(first != 0);
+    assert!(gd.revision(vb) != first);
+
+    gd.set_texture(texture, Texture::new(2, 2, 4, vec![0; 16]));
+    assert!(gd.revision(texture) != first);
+    assert_eq!(gd.get_texture(texture).unwrap().width(), 2);
+
+    let index = gd.material_index(mat);
+    let before = gd.revision(mat);
+    gd.set_material(mat, Material::simple([0., 1., 0.]));
+    assert!(gd.revision(mat) != before);
+    assert_eq!(gd.material_index(mat), index);
+
+    let copy = gd.new_object(None);
+    gd.graphics.duplicate(vb, copy);
+    assert_eq!(gd.revision(copy), gd.revision(vb));
+
+    assert!(gd.graphics.delete(texture));
+    assert_eq!(gd.revision(texture), 0);
+    // an entity without a resource
+    let empty = gd.new_object(None);
+    assert_eq!(gd.revision(empty), 0);
+}
+
+#[test]
+fn tracker_reports_changes() {
+    let mut gd = new_game_sized(8, 8);
+    let mut tracker = ResourceTracker::new();
+    let a = gd.new_vertex_buffer(triangle());
+    let b = gd.new_vertex_buffer(triangle());
+
+    let changes = tracker.update(vertex_buffers(&gd).into_iter());
+    assert_eq!(changes, vec![Change::Added(a), Change::Added(b)]);
+    assert_eq!(tracker.len(), 2);
+    assert!(tracker.contains(a));
+
+    // nothing changed
+    assert_eq!(tracker.update(vertex_buffers(&gd).into_iter()), vec![]);
+
+    gd.set_vertex_buffer(b, triangle());
+    assert!(gd.graphics.delete(a));
+    let changes = tracker.update(vertex_buffers(&gd).into_iter());
+    assert_eq!(changes, vec![Change::Removed(a), Change::Modified(b)]);
+    assert_eq!(tracker.len(), 1);
+    assert!(!tracker.contains(a));
+}
+
+#[test]
+fn reused_entities_are_uploaded_again() {
+    let mut gd = new_game_sized(8, 8);
+    let mut tracker = ResourceTracker::new();
+    let a = gd.new_vertex_buffer(triangle());
+    tracker.update(vertex_buffers(&gd).into_iter());
+
+    // the entity is deleted and reused before the render sees it gone
+    gd.graphics.delete(a);
+    gd.common.delete(a);
+    let b = gd.new_vertex_buffer(triangle());
+    assert_eq!(a, b);
+    assert_eq!(tracker.update(vertex_buffers(&gd).into_iter()), vec![Change::Modified(b)]);
+}
+
+/// the soft render's stats, as seen by a game of `GameData`
+fn resources(r: &SoftRender) -> ResourceStats {
+    <SoftRender as Render<GameData>>::resources(r)
+}
+
+#[test]
+fn soft_render_frees_target_images() {
+    let mut gd = new_game_sized(8, 8);
+    let camera = gd.new_object(None);
+    gd.set_to_identity(camera);
+    let scene = gd.new_scene();
+    let target = gd.new_texture(Texture::new(4, 4, 4, vec![0; 64]));
+    let mut view = RenderView::new(camera, scene);
+    view.target = Some(target);
+    let view = gd.new_view(view);
+
+    let mut r = SoftRender::new(8, 8);
+    assert_eq!(resources(&r), ResourceStats::new());
+    r.update(gd.clone());
+    r.update(gd.clone());
+    let stats = resources(&r);
+    assert_eq!((stats.textures, stats.uploads, stats.frees), (1, 1, 0));
+
+    assert!(gd.remove_view(view));
+    r.update(gd.clone());
+    let stats = resources(&r);
+    assert_eq!((stats.textures, stats.uploads, stats.frees), (0, 1, 1));
+}
@@ -0,0 +1,191 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![feature(old_io, old_path)]
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-input" as input;
+extern crate "snowmew-input-integrator" as input_integrator;
+extern crate "snowmew-script" as script;
+extern crate cgmath;
+
+use std::old_io::{File, TempDir};
+use std::old_io::fs;
+
+use snowmew::common::{Common, CommonData, Entity};
+use snowmew::game::Game;
+use position::{Positions, PositionData};
+use graphics::{Graphics, GraphicsData};
+use input::{Event, Button};
+use input_integrator::{input_integrator, InputIntegratorState};
+use script::{Scripting, ScriptData, Script, ScriptRunner, Value, bindings};
+use cgmath::Vector3;
+
+#[derive(Clone)]
+struct GameData {
+    common: CommonData,
+    position: PositionData,
+    graphics: GraphicsData,
+    scripts: ScriptData
+}
+
+impl Common for GameData {
+    fn get_common<'a>(&'a self) -> &'a CommonData { &self.common }
+    fn get_common_mut<'a>(&'a mut self) -> &'a mut CommonData { &mut self.common }
+}
+
+impl Positions for GameData {
+    fn get_position<'a>(&'a self) -> &'a PositionData { &self.position }
+    fn get_position_mut<'a>(&'a mut self) -> &'a mut PositionData { &mut self.position }
+}
+
+impl Graphics for GameData {
+    fn get_graphics<'a>(&'a self) -> &'a GraphicsData { &self.graphics }
+    fn get_graphics_mut<'a>(&'a mut self) -> &'a mut GraphicsData { &mut self.graphics }
+}
+
+impl Scripting for GameData {
+    fn get_scripting<'a>(&'a self) -> &'a ScriptData { &self.scripts }
+    fn get_scripting_mut<'a>(&'a mut self) -> &'a mut ScriptData { &mut self.scripts }
+}
+
+struct Nothing;
+
+impl Game<GameData, InputIntegratorState> for Nothing {
+    fn step(&mut self, _: InputIntegratorState, gd: GameData) -> GameData { gd }
+}
+
+fn new_game() -> GameData {
+    GameData {
+        common: CommonData::new(),
+        position: PositionData::new(),
+        graphics: GraphicsData::new(),
+        scripts: ScriptData::new()
+    }
+}
+
+fn attach(gd: &mut GameData, src: &str) -> Entity {
+    let script = gd.new_script(Script::from_str(src).ok().expect("failed to parse"));
+    let oid = gd.new_object(None);
+    gd.set_to_identity(oid);
+    gd.attach_script(oid, script);
+    oid
+}
+
+fn run(gd: &mut GameData) {
+    let errors = gd.run_scripts(&bindings::standard(), None, 0.5);
+    assert_eq!(errors.len(), 0);
+}
+
+#[test]
+fn parse_error_has_line() {
+    let err = Script::from_str("let a = 1;\nlet b = ;\n").err().unwrap();
+    assert_eq!(err.line, 2);
+}
+
+#[test]
+fn precedence() {
+    let mut gd = new_game();
+    let oid = attach(&mut gd, "let x = 1 + 2 * 3 - 4 / 2; let y = !(x > 3 && x < 5) || false;");
+    run(&mut gd);
+    assert!(gd.script_var(oid, "x") == Some(Value::Num(5.)));
+    assert!(gd.script_var(oid, "y") == Some(Value::Bool(true)));
+}
+
+#[test]
+fn variables_persist() {
+    let mut gd = new_game();
+    let src = "
+        let count = 0;
+        count = count + 1;
+        if count > 2 {
+            let big = true;
+        } else {
+            let i = 0;
+            while i < 10 { i = i + 1; }
+        }
+    ";
+    let oid = attach(&mut gd, src);
+    run(&mut gd);
+    run(&mut gd);
+    run(&mut gd);
+    assert!(gd.script_var(oid, "count") == Some(Value::Num(3.)));
+    assert!(gd.script_var(oid, "i") == Some(Value::Num(10.)));
+    assert!(gd.script_var(oid, "big") == Some(Value::Bool(true)));
+}
+
+#[test]
+fn moves_entity() {
+    let mut gd = new_game();
+    let oid = attach(&mut gd, "translate(self(), vec3(2, 0, 0) * dt());");
+    run(&mut gd);
+    run(&mut gd);
+    assert!(gd.get_displacement(oid).map(|&d| d) == Some(Vector3::new(2., 0., 0.)));
+}
+
+#[test]
+fn runtime_error_has_line() {
+    let mut gd = new_game();
+    let oid = attach(&mut gd, "let a = 1;\n\nmissing(a);\nlet b = 2;");
+    let errors = gd.run_scripts(&bindings::standard(), None, 0.5);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, oid);
+    assert_eq!(errors[0].1.line, 3);
+    assert!(gd.script_var(oid, "a").is_some());
+    assert!(gd.script_var(oid, "b").is_none());
+}
+
+#[test]
+fn reads_input() {
+    let mut gd = new_game();
+    let oid = attach(&mut gd, "let held = 0; if button_down(\"W\") { held = held + dt(); }");
+    let (mut game, mut gd) = input_integrator(ScriptRunner::new(Nothing), gd);
+
+    gd = game.step(Event::Cadance(0.25), gd);
+    gd = game.step(Event::ButtonDown(Button::KeyboardW), gd);
+    gd = game.step(Event::Cadance(0.25), gd);
+    gd = game.step(Event::Cadance(0.25), gd);
+    assert!(gd.inner.script_var(oid, "held") == Some(Value::Num(0.5)));
+}
+
+#[test]
+fn hot_reload() {
+    let dir = TempDir::new("snowmew-script").unwrap();
+    let path = dir.path().join("test.script");
+    File::create(&path).write_str("let a = 1; let v = 1;").unwrap();
+
+    let mut gd = new_game();
+    let script = gd.new_script(Script::from_file(&path).ok().unwrap());
+    let oid = gd.new_object(None);
+    gd.attach_script(oid, script);
+    run(&mut gd);
+    assert_eq!(gd.reload_scripts().len(), 0);
+
+    File::create(&path).write_str("let b = 2; v = v + 1;").unwrap();
+    fs::change_file_times(&path, 0, 1000).unwrap();
+    assert_eq!(gd.reload_scripts().len(), 0);
+    run(&mut gd);
+    assert!(gd.script_var(oid, "a") == Some(Value::Num(1.)));
+    assert!(gd.script_var(oid, "b") == Some(Value::Num(2.)));
+    assert!(gd.script_var(oid, "v") == Some(Value::Num(2.)));
+
+    // a broken script keeps the old version
+    File::create(&path).write_str("let c = ;").unwrap();
+    fs::change_file_times(&path, 0, 2000).unwrap();
+    assert_eq!(gd.reload_scripts().len(), 1);
+    run(&mut gd);
+    assert!(gd.script_var(oid, "v") == Some(Value::Num(3.)));
+}
@@ -0,0 +1,165 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use snowmew::common::Common;
+use position::Positions;
+use graphics::{Graphics, Geometry, Material};
+use graphics::light::{Light, Directional, Spot, Shadow};
+use render::{Lights, ShadowPlan, MAX_SHADOW_MAPS};
+use render::shadow::cascade_splits;
+use render::camera::Camera;
+use cgmath::{Matrix, Matrix4, Vector3, Vector4};
+use common::{GameData, new_game};
+
+fn sun() -> Light {
+    Light::Directional(Directional::new(Vector3::new(0.5, 1., 0.), Vector3::new(1., 1., 1.), 1.))
+}
+
+fn camera() -> Camera {
+    Camera::new(100, 100, Matrix4::identity())
+}
+
+#[test]
+fn splits_cover_the_distance() {
+    let splits = cascade_splits(0.01, 100., 3);
+    assert_eq!(splits.len(), 3);
+    assert!(splits[0] > 0.01);
+    assert!(splits[0] < splits[1] && splits[1] < splits[2]);
+    assert!((splits[2] - 100.).abs() < 0.001);
+}
+
+#[test]
+fn frustum_corners() {
+    let corners = camera().frustum_corners(1., 10.);
+    for c in corners[..4].iter() {
+        assert!((c.z + 1.).abs() < 0.0001);
+    }
+    for c in corners[4..].iter() {
+        assert!((c.z + 10.).abs() < 0.0001);
+    }
+    // the far corners are ten times as far from the center
+    assert!((corners[4].x - corners[0].x * 10.).abs() < 0.0001);
+    assert!((corners[6].y - corners[2].y * 10.).abs() < 0.0001);
+}
+
+#[test]
+fn cascades_contain_their_slice() {
+    let mut gd = new_game();
+    gd.new_light(sun());
+    let lights = Lights::new(&gd);
+    let camera = camera();
+    let plan = ShadowPlan::new(&camera, &lights);
+
+    assert_eq!(plan.views.len(), 2);
+    assert_eq!(plan.directional, vec![(0, 2)]);
+
+    let mut near = camera.near();
+    for view in plan.views.iter() {
+        let mat = view.projection.mul_m(&view.view);
+        for c in camera.frustum_corners(near, view.split).iter() {
+            let p = mat.mul_v(&Vector4::new(c.x, c.y, c.z, 1.));
+            assert!(p.x.abs() <= 1. && p.y.abs() <= 1. && p.z.abs() <= 1.);
+        }
+        assert_eq!(view.resolution, 2048);
+        near = view.split;
+    }
+}
+
+#[test]
+fn light_shadow_settings() {
+    let mut gd = new_game();
+    let mut light = Directional::new(Vector3::new(0., 1., 0.), Vector3::new(1., 1., 1.), 1.);
+    let mut shadow = Shadow::new(512, 0.001);
+    shadow.cascades = 2;
+    light.set_shadow(Some(shadow));
+    gd.new_light(Light::Directional(light));
+
+    let plan = ShadowPlan::new(&camera(), &Lights::new(&gd));
+    assert_eq!(plan.views.len(), 2);
+    assert!(plan.views.iter().all(|v| v.resolution == 512 && v.bias == 0.001));
+}
+
+#[test]
+fn spot_lights_opt_in() {
+    let mut gd = new_game();
+    let spot = Spot::new(Vector3::new(0., -1., 0.), Vector3::new(1., 1., 1.), 1., 0.2, 0.4);
+    gd.new_light(Light::Spot(spot));
+    let mut shadowed = spot;
+    shadowed.set_shadow(Some(Shadow::new(1024, 0.001)));
+    let oid = gd.new_light(Light::Spot(shadowed));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 5., 0.));
+
+    let plan = ShadowPlan::new(&camera(), &Lights::new(&gd));
+    assert_eq!(plan.spot, vec![(0, 0), (0, 1)]);
+    assert_eq!(plan.views[0].resolution, 1024);
+
+    // the point under the light is in the middle of the map
+    let view = &plan.views[0];
+    let p = view.projection.mul_m(&view.view).mul_v(&Vector4::new(0., 0., 0., 1.));
+    assert!((p.x / p.w).abs() < 0.0001 && (p.y / p.w).abs() < 0.0001);
+}
+
+#[test]
+fn maps_are_limited() {
+    let mut gd = new_game();
+    for _ in 0..3 {
+        gd.new_light(sun());
+    }
+    let plan = ShadowPlan::new(&camera(), &Lights::new(&gd));
+    assert!(plan.views.len() <= MAX_SHADOW_MAPS);
+    assert_eq!(plan.directional, vec![(0, 2), (2, 2), (0, 0)]);
+}
+
+#[test]
+fn cascades_shrink_to_fit() {
+    let mut gd = new_game();
+    let mut light = Directional::new(Vector3::new(0., 1., 0.), Vector3::new(1., 1., 1.), 1.);
+    let mut shadow = Shadow::default();
+    shadow.cascades = 3;
+    light.set_shadow(Some(shadow));
+    gd.new_light(Light::Directional(light));
+    gd.new_light(Light::Directional(light));
+
+    let camera = camera();
+    let plan = ShadowPlan::new(&camera, &Lights::new(&gd));
+    assert_eq!(plan.views.len(), MAX_SHADOW_MAPS);
+    assert_eq!(plan.directional, vec![(0, 3), (3, 1)]);
+    // the single cascade still reaches as far as the light's three do
+    assert!((plan.views[3].split - plan.views[2].split).abs() < 0.001);
+}
+
+#[test]
+fn drawables_opt_out_of_shadows() {
+    let mut gd = new_game();
+    let geo = gd.new_geometry(Geometry::triangles(0, 0, 0));
+    let mat = gd.new_material(Material::simple([1., 1., 1.]));
+    let oid = gd.new_object(None);
+    gd.set_draw(oid, geo, mat);
+    assert!(!gd.drawable(oid).unwrap().no_shadow);
+
+    gd.set_cast_shadow(oid, false);
+    assert!(gd.drawable(oid).unwrap().no_shadow);
+    gd.set_cast_shadow(oid, true);
+    assert!(!gd.drawable(oid).unwrap().no_shadow);
+}
@@ -0,0 +1,450 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+#![feature(old_io, old_path)]
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material, PbrMaterial, BlendMode, Texture};
+use graphics::{Billboard, BillboardSet};
+use graphics::geometry::VertexGeoTexNorm;
+use graphics::light::{Light, Directional, Point, Spot, Attenuation};
+use render::{Renderable, Render, Recorder, PostEffect, save_png};
+use soft::SoftRender;
+use cgmath::Vector3;
+use std::old_io::TempDir;
+use std::old_io::fs::PathExtensions;
+use common::{GameData, new_scene_game};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// a 2x2 quad facing +z
+fn quad(gd: &mut GameData) -> Entity {
+    let v = |x: f32, y: f32, u: f32, t: f32| {
+        VertexGeoTexNorm {
+            position: [x, y, 0.],
+            texture: [u, t],
+            normal: [0., 0., 1.]
+        }
+    };
+    let vb = VertexBuffer::new_position_texture_normal(
+        vec![v(-1., -1., 0., 0.), v(1., -1., 1., 0.), v(1., 1., 1., 1.), v(-1., 1., 0., 1.)],
+        vec![0, 1, 2, 0, 2, 3]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    gd.new_geometry(Geometry::triangles(vb, 0, 6))
+}
+
+fn place(gd: &mut GameData, scene: Scene, geo: Entity, mat: Entity, z: f32) -> Entity {
+    let oid = gd.new_object(Some(scene.to_entity()));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 0., z));
+    gd.set_draw(oid, geo, mat);
+    oid
+}
+
+fn render(gd: &GameData) -> SoftRender {
+    let mut r = SoftRender::new(0, 0);
+    r.set_clear_color([0., 0., 0., 1.]);
+    r.render(gd);
+    r
+}
+
+#[test]
+fn empty_scene_is_cleared() {
+    let (gd, _) = new_scene_game();
+    let r = render(&gd);
+    assert_eq!(r.frame().width(), 32);
+    assert_eq!(r.frame().height(), 32);
+    assert!(r.frame().color().chunks(4).all(|p| p == &BLACK[..]));
+    assert!(r.frame().depth().iter().all(|&d| d == 1.));
+}
+
+#[test]
+fn ambient_only() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+
+    let r = render(&gd);
+    // without a light only 20% of ka is visible
+    assert_eq!(r.frame().pixel(16, 16), [51, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(0, 0), BLACK);
+    assert!(r.frame().depth_at(16, 16) < 1.);
+}
+
+#[test]
+fn directional_light() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([0.5, 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+    let sun = Directional::new(Vector3::new(0., 0., 1.), Vector3::new(1., 1., 1.), 1.);
+    gd.new_light(Light::Directional(sun));
+
+    let r = render(&gd);
+    // 0.5 * 0.2 + 0.5 * 1.0
+    assert_eq!(r.frame().pixel(16, 16), [153, 0, 0, 255]);
+}
+
+#[test]
+fn directional_lights_accumulate() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([0.5, 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+    for _ in 0..2 {
+        let sun = Directional::new(Vector3::new(0., 0., 1.), Vector3::new(1., 1., 1.), 0.5);
+        gd.new_light(Light::Directional(sun));
+    }
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [153, 0, 0, 255]);
+}
+
+/// a light one unit in front of the quad
+fn light_at(gd: &mut GameData, light: Light) -> Entity {
+    let oid = gd.new_light(light);
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 0., -1.));
+    oid
+}
+
+#[test]
+fn point_light() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([0.5, 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+    light_at(&mut gd, Light::Point(Point::new(Vector3::new(1., 1., 1.), 1.)));
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [153, 0, 0, 255]);
+}
+
+#[test]
+fn point_light_attenuation() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+    let mut point = Point::new(Vector3::new(1., 1., 1.), 1.);
+    point.set_attenuation(Attenuation::new(0., 0., 1.));
+    light_at(&mut gd, Light::Point(point));
+
+    let r = render(&gd);
+    let center = r.frame().pixel(16, 16);
+    let edge = r.frame().pixel(8, 16);
+    // 0.2 + 1 / 1
+    assert_eq!(center, [255, 0, 0, 255]);
+    assert!(edge[0] > 51 && edge[0] < center[0]);
+}
+
+#[test]
+fn spot_light_cone() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+    let spot = Spot::new(Vector3::new(0., 0., -1.), Vector3::new(1., 1., 1.), 1., 0.2, 0.3);
+    light_at(&mut gd, Light::Spot(spot));
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [255, 0, 0, 255]);
+    // outside of the cone only the ambient light is left
+    assert_eq!(r.frame().pixel(8, 16), [51, 0, 0, 255]);
+}
+
+#[test]
+fn pbr_emissive_without_lights() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let mut glow = PbrMaterial::simple([0., 0., 0.], 0., 1.);
+    glow.set_emissive([0., 1., 0.]);
+    let glow = gd.new_pbr_material(glow);
+    place(&mut gd, scene, geo, glow, -2.);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [0, 255, 0, 255]);
+}
+
+#[test]
+fn pbr_rough_dielectric() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_pbr_material(PbrMaterial::simple([0.5, 0., 0.], 0., 1.));
+    place(&mut gd, scene, geo, red, -2.);
+    let sun = Directional::new(Vector3::new(0., 0., 1.), Vector3::new(1., 1., 1.), 1.);
+    gd.new_light(Light::Directional(sun));
+
+    let r = render(&gd);
+    // 0.5 * 0.2 + 0.5 * 0.96 diffuse + 0.01 specular
+    let p = r.frame().pixel(16, 16);
+    assert!(p[0] >= 149 && p[0] <= 152);
+    assert!(p[1] >= 2 && p[1] <= 3 && p[1] == p[2]);
+}
+
+#[test]
+fn pbr_metal_has_no_diffuse() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let metal = gd.new_pbr_material(PbrMaterial::simple([0.5, 0., 0.], 1., 1.));
+    place(&mut gd, scene, geo, metal, -2.);
+    let sun = Directional::new(Vector3::new(0., 0., 1.), Vector3::new(1., 1., 1.), 1.);
+    gd.new_light(Light::Directional(sun));
+
+    let r = render(&gd);
+    // 0.5 * 0.2 + a quarter of the base colour reflected
+    let p = r.frame().pixel(16, 16);
+    assert!(p[0] >= 56 && p[0] <= 58);
+    assert_eq!(p[1], 0);
+}
+
+/// blending rounds each step, so allow the channels to be one off
+fn near(a: [u8; 4], b: [u8; 4]) -> bool {
+    a.iter().zip(b.iter()).all(|(&x, &y)| (x as i32 - y as i32).abs() <= 1)
+}
+
+fn glass(gd: &mut GameData, color: [f32; 3], blend: BlendMode) -> Entity {
+    let mut mat = Material::simple(color);
+    mat.set_d(0.5);
+    mat.set_blend(blend);
+    gd.new_material(mat)
+}
+
+#[test]
+fn alpha_blending() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    let blue = glass(&mut gd, [0., 0., 1.], BlendMode::Alpha);
+    // the blended quad is added first but is drawn after the opaque one
+    place(&mut gd, scene, geo, blue, -2.);
+    place(&mut gd, scene, geo, red, -4.);
+
+    let r = render(&gd);
+    // half of 20% red behind half of 20% blue
+    assert!(near(r.frame().pixel(16, 16), [26, 0, 26, 255]));
+}
+
+#[test]
+fn blended_fragments_do_not_write_depth() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let blue = glass(&mut gd, [0., 0., 1.], BlendMode::Additive);
+    place(&mut gd, scene, geo, blue, -2.);
+
+    let r = render(&gd);
+    assert!(near(r.frame().pixel(16, 16), [0, 0, 26, 255]));
+    assert_eq!(r.frame().depth_at(16, 16), 1.);
+}
+
+#[test]
+fn blended_back_to_front() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = glass(&mut gd, [1., 0., 0.], BlendMode::Alpha);
+    let blue = glass(&mut gd, [0., 0., 1.], BlendMode::Alpha);
+    place(&mut gd, scene, geo, red, -2.);
+    place(&mut gd, scene, geo, blue, -4.);
+
+    let r = render(&gd);
+    // the far blue quad is drawn first and the near red one is mixed over it
+    assert!(near(r.frame().pixel(16, 16), [26, 0, 13, 255]));
+}
+
+#[test]
+fn nearest_wins() {
+    for &flip in [false, true].iter() {
+        let (mut gd, scene) = new_scene_game();
+        let geo = quad(&mut gd);
+        let red = gd.new_material(Material::simple([1., 0., 0.]));
+        let blue = gd.new_material(Material::simple([0., 0., 1.]));
+        if flip {
+            place(&mut gd, scene, geo, blue, -4.);
+            place(&mut gd, scene, geo, red, -2.);
+        } else {
+            place(&mut gd, scene, geo, red, -2.);
+            place(&mut gd, scene, geo, blue, -4.);
+        }
+        let r = render(&gd);
+        assert_eq!(r.frame().pixel(16, 16), [51, 0, 0, 255]);
+    }
+}
+
+#[test]
+fn behind_camera_is_clipped() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place(&mut gd, scene, geo, red, 2.);
+
+    let r = render(&gd);
+    assert!(r.frame().color().chunks(4).all(|p| p == &BLACK[..]));
+}
+
+#[test]
+fn textured() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    // a red texel next to a blue texel
+    let tex = gd.new_texture(Texture::new(2, 1, 3, vec![255, 0, 0, 0, 0, 255]));
+    let mut mat = Material::simple([0., 0., 0.]);
+    mat.set_map_ka(tex);
+    let mat = gd.new_material(mat);
+    place(&mut gd, scene, geo, mat, -2.);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(12, 16), [51, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(20, 16), [0, 0, 51, 255]);
+}
+
+#[test]
+fn billboards_are_tinted_and_scaled() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let white = gd.new_material(Material::simple([1., 1., 1.]));
+    let emitter = gd.new_object(Some(scene.to_entity()));
+    gd.set_billboard_set(emitter, BillboardSet::new(geo, white, vec![
+        Billboard { position: [0., 0., -2.], color: [0., 1., 0., 1.], size: 0.25 }
+    ]));
+
+    let r = render(&gd);
+    // only the ambient light, 20% of the tint
+    assert_eq!(r.frame().pixel(16, 16), [0, 51, 0, 255]);
+    // a quarter of the size of a quad, which would cover the corners
+    assert_eq!(r.frame().pixel(4, 4), BLACK);
+}
+
+#[test]
+fn single_channel_textures_are_grey() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    // a white texel next to a black texel
+    let tex = gd.new_texture(Texture::new(2, 1, 1, vec![255, 0]));
+    let mut mat = Material::simple([0., 0., 0.]);
+    mat.set_map_ka(tex);
+    let mat = gd.new_material(mat);
+    place(&mut gd, scene, geo, mat, -2.);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(12, 16), [51, 51, 51, 255]);
+    assert_eq!(r.frame().pixel(20, 16), BLACK);
+}
+
+#[test]
+fn post_chain_runs_on_the_frame() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+    let gamma = gd.post_chain_mut().push(PostEffect::Gamma(2.));
+
+    let r = render(&gd);
+    // 51 is 0.2, which is 0.447 after the gamma
+    assert_eq!(r.frame().pixel(16, 16), [114, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(0, 0), BLACK);
+
+    gd.post_chain_mut().set_enabled(gamma, false);
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [51, 0, 0, 255]);
+}
+
+#[test]
+fn render_trait_follows_io_size() {
+    let (mut gd, _) = new_scene_game();
+    let mut r = SoftRender::new(8, 8);
+    gd.io.size = (16, 8);
+    r.update(gd.clone());
+    assert_eq!(r.frame().width(), 16);
+    assert_eq!(r.frame().height(), 8);
+    assert_eq!(r.frame().color().len(), 16 * 8 * 4);
+}
+
+#[test]
+fn capture_next_frame() {
+    let (mut gd, scene) = new_scene_game();
+    let geo = quad(&mut gd);
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    place(&mut gd, scene, geo, red, -2.);
+
+    let mut r = SoftRender::new(0, 0);
+    let capture = r.capture();
+    assert!(capture.try_wait().is_err());
+    r.update(gd.clone());
+    let texture = capture.wait().unwrap();
+    assert_eq!((texture.width(), texture.height(), texture.depth()), (32, 32, 4));
+    assert_eq!(texture.data(), r.frame().color());
+
+    // only the next frame is captured
+    let capture = r.capture();
+    r.update(gd.clone());
+    r.update(gd.clone());
+    assert!(capture.try_wait().is_ok());
+    assert!(capture.try_wait().is_err());
+}
+
+#[test]
+fn recorder_saves_numbered_frames() {
+    let (gd, _) = new_scene_game();
+    let dir = TempDir::new("snowmew-soft").unwrap();
+    {
+        let mut rec = Recorder::new(SoftRender::new(0, 0), dir.path().clone(), 2);
+        for _ in 0..3 {
+            rec.update(gd.clone());
+        }
+        assert!(rec.finished());
+    }
+    assert!(dir.path().join("00000.png").exists());
+    assert!(dir.path().join("00001.png").exists());
+    assert!(!dir.path().join("00002.png").exists());
+}
+
+/// a render that can not read back its frames
+struct Blind;
+
+impl Render<GameData> for Blind {
+    fn update(&mut self, _: GameData) {}
+}
+
+#[test]
+fn recorder_keeps_failed_captures() {
+    let (gd, _) = new_scene_game();
+    let dir = TempDir::new("snowmew-soft").unwrap();
+    let mut rec = Recorder::new(Blind, dir.path().clone(), 1);
+    rec.update(gd.clone());
+    rec.flush();
+    assert!(rec.finished());
+    assert_eq!(rec.errors().len(), 1);
+    assert!(!dir.path().join("00000.png").exists());
+}
+
+#[test]
+fn save_png_needs_color() {
+    let dir = TempDir::new("snowmew-soft").unwrap();
+    let depth = Texture::new(1, 1, 1, vec![0]);
+    assert!(save_png(&depth, &dir.path().join("depth.png")).is_err());
+}
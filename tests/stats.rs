@@ -0,0 +1,119 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material};
+use graphics::geometry::VertexGeo;
+use render::{Render, Renderable, RenderStats};
+use render::stats::STATS_LAYER;
+use soft::SoftRender;
+use cgmath::Vector3;
+use common::{GameData, new_game_sized};
+
+/// a scene with `count` 2x2 quads 2 units in front of the origin
+fn quad_scene(gd: &mut GameData, count: usize) -> Scene {
+    let v = |x: f32, y: f32| VertexGeo { position: [x, y, 0.] };
+    let vb = gd.new_vertex_buffer(VertexBuffer::new_position(
+        vec![v(-1., -1.), v(1., -1.), v(1., 1.), v(-1., 1.)],
+        vec![0, 1, 2, 0, 2, 3]
+    ));
+    let geo = gd.new_geometry(Geometry::triangles(vb, 0, 6));
+    let mat = gd.new_material(Material::simple([1., 0., 0.]));
+
+    let scene = gd.new_scene();
+    for _ in 0..count {
+        let oid = gd.new_object(Some(scene.to_entity()));
+        gd.set_to_identity(oid);
+        gd.set_displacement(oid, Vector3::new(0., 0., -2.));
+        gd.set_draw(oid, geo, mat);
+    }
+    scene
+}
+
+fn camera(gd: &mut GameData) -> Entity {
+    let camera = gd.new_object(None);
+    gd.set_to_identity(camera);
+    camera
+}
+
+/// the soft render's stats, as seen by a game of `GameData`
+fn stats(r: &SoftRender) -> RenderStats {
+    <SoftRender as Render<GameData>>::stats(r)
+}
+
+#[test]
+fn draws_are_counted() {
+    let mut stats = RenderStats::new();
+    stats.draw(10, 2);
+    stats.draw(1, 0);
+    assert_eq!((stats.draw_calls, stats.instances, stats.triangles), (2, 11, 20));
+    assert!(stats.text().contains("triangles 20"));
+    assert_eq!(render::stats::overlay(&stats).layer, STATS_LAYER);
+}
+
+#[test]
+fn soft_render_reports_each_frame() {
+    let mut gd = new_game_sized(64, 64);
+    let cam = camera(&mut gd);
+    let scene = quad_scene(&mut gd, 3);
+    gd.set_camera(cam);
+    gd.set_scene(scene);
+
+    let mut r = SoftRender::new(0, 0);
+    assert_eq!(stats(&r), RenderStats::new());
+    r.update(gd.clone());
+    let s = stats(&r);
+    assert_eq!(s.frame, 1);
+    assert_eq!((s.draw_calls, s.instances, s.triangles), (3, 3, 6));
+    assert!(s.draw_time >= 0.);
+
+    // the counts are for the last frame only
+    r.update(gd.clone());
+    let s = stats(&r);
+    assert_eq!(s.frame, 2);
+    assert_eq!(s.draw_calls, 3);
+}
+
+#[test]
+fn stats_are_drawn_when_asked_for() {
+    let mut gd = new_game_sized(64, 64);
+    let mut plain = SoftRender::new(0, 0);
+    plain.update(gd.clone());
+    plain.update(gd.clone());
+
+    gd.set_show_stats(true);
+    assert!(gd.show_stats());
+    let mut shown = SoftRender::new(0, 0);
+    shown.update(gd.clone());
+    shown.update(gd.clone());
+
+    let differ = (0..64).filter(|&y| {
+        (0..64).any(|x| plain.frame().pixel(x, y) != shown.frame().pixel(x, y))
+    }).count();
+    assert!(differ > 0);
+    // the text is in the top left corner
+    assert!(plain.frame().pixel(60, 60) == shown.frame().pixel(60, 60));
+}
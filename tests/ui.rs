@@ -0,0 +1,325 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-input" as input;
+extern crate "snowmew-input-integrator" as integrator;
+extern crate "snowmew-ui" as ui;
+
+mod common;
+
+use snowmew::game::Game;
+use render::Renderable;
+use input::{Button, Event};
+use integrator::{input_integrator, InputIntegratorState};
+use ui::{Ui, UiState, UiInput, UiOverlays, DrawCommand, DrawList, UI_LAYER, hit};
+use common::{GameData, new_game};
+
+fn press(x: f32, y: f32) -> UiInput {
+    let mut input = UiInput::new(x, y);
+    input.down = true;
+    input.pressed = true;
+    input
+}
+
+fn hold(x: f32, y: f32) -> UiInput {
+    let mut input = UiInput::new(x, y);
+    input.down = true;
+    input
+}
+
+fn release(x: f32, y: f32) -> UiInput {
+    let mut input = UiInput::new(x, y);
+    input.released = true;
+    input
+}
+
+/// run one frame of the ui
+fn frame<F: FnMut(&mut Ui)>(state: &mut UiState, input: UiInput, mut f: F) -> DrawList {
+    let mut ui = Ui::new(state, input);
+    f(&mut ui);
+    ui.finish()
+}
+
+/// a button outside of any window is at 2, 2 and is 160x11
+fn button(state: &mut UiState, input: UiInput) -> bool {
+    let mut clicked = false;
+    frame(state, input, |ui| clicked = ui.button("ok"));
+    clicked
+}
+
+#[test]
+fn hit_testing() {
+    let rect = [10., 20., 5., 4.];
+    assert!(hit(rect, [10., 20.]));
+    assert!(hit(rect, [14.9, 23.9]));
+    assert!(!hit(rect, [15., 22.]));
+    assert!(!hit(rect, [12., 24.]));
+    assert!(!hit(rect, [9.9, 22.]));
+}
+
+#[test]
+fn buttons_click_on_release() {
+    let mut state = UiState::new();
+    assert!(!button(&mut state, press(10., 5.)));
+    assert_eq!(state.active(), Some("ok"));
+    assert!(!button(&mut state, hold(10., 5.)));
+    assert!(button(&mut state, release(10., 5.)));
+    assert_eq!(state.active(), None);
+
+    // a press and release between two frames
+    let mut input = press(10., 5.);
+    input.down = false;
+    input.released = true;
+    assert!(button(&mut state, input));
+}
+
+#[test]
+fn buttons_need_the_press_and_release() {
+    let mut state = UiState::new();
+
+    // dragged off before the release
+    assert!(!button(&mut state, press(10., 5.)));
+    assert!(!button(&mut state, release(10., 50.)));
+
+    // pressed somewhere else and released over it
+    assert!(!button(&mut state, press(10., 50.)));
+    assert!(!button(&mut state, release(10., 5.)));
+    assert_eq!(state.active(), None);
+}
+
+#[test]
+fn checkboxes_toggle() {
+    let mut state = UiState::new();
+    let mut value = false;
+    for input in vec![press(3., 3.), release(3., 3.)].into_iter() {
+        frame(&mut state, input, |ui| { ui.checkbox("fog", &mut value); });
+    }
+    assert!(value);
+    let list = frame(&mut state, release(3., 3.), |ui| { ui.checkbox("fog", &mut value); });
+    assert!(value);
+    // the box, the tick and the label
+    assert_eq!(list.len(), 3);
+}
+
+#[test]
+fn sliders_drag_and_scroll() {
+    let mut state = UiState::new();
+    let mut value = 0.;
+    let mut changed = false;
+    frame(&mut state, press(2., 5.), |ui| changed = ui.slider("speed", &mut value, 0., 10.));
+    assert!(!changed);
+    frame(&mut state, hold(82., 5.), |ui| changed = ui.slider("speed", &mut value, 0., 10.));
+    assert!(changed);
+    assert_eq!(value, 5.);
+    // held past the end of the bar
+    frame(&mut state, hold(400., 5.), |ui| changed = ui.slider("speed", &mut value, 0., 10.));
+    assert_eq!(value, 10.);
+    frame(&mut state, release(400., 5.), |ui| changed = ui.slider("speed", &mut value, 0., 10.));
+
+    let mut input = UiInput::new(50., 5.);
+    input.scroll = [0., -2.];
+    frame(&mut state, input, |ui| changed = ui.slider("speed", &mut value, 0., 10.));
+    assert!(changed);
+    assert_eq!(value, 9.);
+}
+
+#[test]
+fn text_fields_are_typed_into_once_clicked() {
+    let mut state = UiState::new();
+    let mut name = String::new();
+    let typed = |state: &mut UiState, name: &mut String, input: UiInput| {
+        let mut changed = false;
+        frame(state, input, |ui| changed = ui.text_field("name", name));
+        changed
+    };
+
+    let mut input = UiInput::new(10., 5.);
+    input.text = "ab".to_string();
+    assert!(!typed(&mut state, &mut name, input.clone()));
+    assert_eq!(state.focus(), None);
+
+    typed(&mut state, &mut name, press(10., 5.));
+    typed(&mut state, &mut name, release(10., 5.));
+    assert_eq!(state.focus(), Some("name"));
+    assert!(typed(&mut state, &mut name, input.clone()));
+    assert_eq!(name, "ab");
+
+    let mut input = UiInput::new(10., 5.);
+    input.backspace = true;
+    input.enter = true;
+    assert!(typed(&mut state, &mut name, input));
+    assert_eq!(name, "a");
+    assert_eq!(state.focus(), None);
+
+    typed(&mut state, &mut name, press(10., 5.));
+    typed(&mut state, &mut name, release(10., 5.));
+    assert_eq!(state.focus(), Some("name"));
+    // clicking somewhere else stops the typing
+    typed(&mut state, &mut name, press(10., 100.));
+    assert_eq!(state.focus(), None);
+}
+
+fn windows(state: &mut UiState, input: UiInput) -> (bool, bool) {
+    let (mut a, mut b) = (false, false);
+    frame(state, input, |ui| {
+        ui.begin_window("a", 10., 10., 50.);
+        a = ui.button("go");
+        ui.end_window();
+        ui.begin_window("b", 20., 20., 50.);
+        b = ui.button("go");
+        ui.end_window();
+    });
+    (a, b)
+}
+
+#[test]
+fn windows_are_laid_out() {
+    let mut state = UiState::new();
+    let list = frame(&mut state, UiInput::new(0., 0.), |ui| {
+        ui.begin_window("a", 10., 10., 50.);
+        ui.label("x");
+        ui.end_window();
+    });
+    // a title bar, a row of padding, the label and the padding below
+    assert_eq!(state.window_rect("a"), Some([10., 10., 50., 22.]));
+    assert_eq!(list.commands()[0], DrawCommand::Rect {
+        rect: [10., 10., 50., 22.],
+        color: state.style.background
+    });
+    match list.commands()[3] {
+        DrawCommand::Text{x, y, ref text, ..} => assert_eq!((x, y, &text[..]), (12., 23., "x")),
+        ref c => panic!("expected the label, found {:?}", c)
+    }
+}
+
+#[test]
+fn windows_cover_what_is_below_them() {
+    let mut state = UiState::new();
+    windows(&mut state, UiInput::new(0., 0.));
+    assert_eq!(state.window_order(), vec!["a", "b"]);
+    assert_eq!(state.window_at([25., 25.]), Some("b"));
+    assert_eq!(state.window_at([12., 12.]), Some("a"));
+    assert_eq!(state.window_at([5., 5.]), None);
+
+    // the top window's button gets the click
+    windows(&mut state, press(30., 35.));
+    assert_eq!(windows(&mut state, release(30., 35.)), (false, true));
+
+    // widgets outside of the windows do not see the mouse over them
+    let mut state2 = state.clone();
+    let mut clicked = false;
+    for input in vec![press(12., 12.), release(12., 12.)].into_iter() {
+        let mut ui = Ui::new(&mut state2, input);
+        assert!(ui.wants_mouse());
+        clicked |= ui.button("under");
+        ui.begin_window("a", 10., 10., 50.);
+        ui.end_window();
+        ui.begin_window("b", 20., 20., 50.);
+        ui.end_window();
+        ui.finish();
+    }
+    assert!(!clicked);
+}
+
+#[test]
+fn windows_are_raised_and_dragged() {
+    let mut state = UiState::new();
+    windows(&mut state, UiInput::new(0., 0.));
+
+    // clicking the part of a that b does not cover raises it
+    windows(&mut state, press(12., 12.));
+    assert_eq!(state.window_order(), vec!["b", "a"]);
+
+    // drag a by its title bar, it was grabbed 2 pixels from its corner
+    windows(&mut state, hold(32., 42.));
+    windows(&mut state, release(32., 42.));
+    let rect = state.window_rect("a").unwrap();
+    assert_eq!((rect[0], rect[1]), (30., 40.));
+    assert_eq!(state.window_order(), vec!["b", "a"]);
+
+    // windows that are not drawn are forgotten
+    frame(&mut state, UiInput::new(0., 0.), |_| ());
+    assert_eq!(state.window_rect("a"), None);
+}
+
+#[test]
+fn draw_lists_are_shown_as_overlays() {
+    let mut gd = new_game();
+    let mut overlays = UiOverlays::new();
+    let mut state = UiState::new();
+
+    let list = frame(&mut state, UiInput::new(0., 0.), |ui| { ui.button("a"); ui.button("b"); });
+    assert_eq!(list.len(), 4);
+    overlays.show(&mut gd, &list);
+    let quads = gd.overlay_quads(200, 100);
+    // a rect and a quad for each character
+    assert_eq!(quads.len(), 4);
+    assert_eq!(quads[0].rect, [2., 2., 160., 11.]);
+    assert_eq!(quads[2].rect, [2., 15., 160., 11.]);
+    for &oid in overlays.entities().iter() {
+        assert_eq!(gd.overlay(oid).unwrap().layer, UI_LAYER);
+    }
+
+    // the entities are reused and the spare ones hidden
+    let first = overlays.entities().to_vec();
+    let list = frame(&mut state, UiInput::new(0., 0.), |ui| { ui.button("a"); });
+    overlays.show(&mut gd, &list);
+    assert_eq!(overlays.entities(), &first[..]);
+    assert_eq!(gd.overlay_quads(200, 100).len(), 2);
+}
+
+/// records what the ui sees each step
+struct Record;
+
+impl Game<Vec<UiInput>, InputIntegratorState> for Record {
+    fn step(&mut self, state: InputIntegratorState, mut gd: Vec<UiInput>) -> Vec<UiInput> {
+        gd.push(UiInput::from_integrator(&state));
+        gd
+    }
+}
+
+#[test]
+fn input_from_the_integrator() {
+    let (mut game, mut gd) = input_integrator(Record, Vec::new());
+    let events = [Event::Move(5., 6.),
+                  Event::ButtonDown(Button::MouseLeft),
+                  Event::ButtonDown(Button::KeyboardLeftShift),
+                  Event::ButtonDown(Button::KeyboardA),
+                  Event::Scroll(0., 1.),
+                  Event::Cadance(0.1),
+                  Event::ButtonUp(Button::MouseLeft),
+                  Event::ButtonUp(Button::KeyboardLeftShift),
+                  Event::ButtonDown(Button::KeyboardBackspace),
+                  Event::Cadance(0.1)];
+    for &e in events.iter() {
+        gd = game.step(e, gd);
+    }
+
+    let first = &gd.inner[0];
+    assert_eq!(first.mouse, [5., 6.]);
+    assert!(first.down && first.pressed && !first.released);
+    assert_eq!(first.scroll, [0., 1.]);
+    assert_eq!(first.text, "A");
+    assert!(!first.backspace);
+
+    let second = &gd.inner[1];
+    assert!(!second.down && !second.pressed && second.released);
+    assert_eq!(second.scroll, [0., 0.]);
+    assert_eq!(second.text, "");
+    assert!(second.backspace);
+}
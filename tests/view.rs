@@ -0,0 +1,262 @@
+//   Copyright 2015 Colin Sherratt
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+extern crate "snowmew-core" as snowmew;
+extern crate "snowmew-position" as position;
+extern crate "snowmew-graphics" as graphics;
+extern crate "snowmew-render" as render;
+extern crate "snowmew-render-soft" as soft;
+extern crate "snowmew-input" as input;
+extern crate cgmath;
+
+mod common;
+
+use snowmew::ToEntity;
+use snowmew::common::{Common, Delete, Entity, Scene};
+use position::Positions;
+use graphics::{Graphics, Geometry, VertexBuffer, Material, Texture};
+use graphics::geometry::VertexGeoTexNorm;
+use render::{Renderable, RenderView, Viewport, Clear, Projection, Environment};
+use soft::SoftRender;
+use cgmath::{Vector3, Vector4};
+use common::{GameData, new_game};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// a camera at the origin looking down -z
+fn camera(gd: &mut GameData) -> Entity {
+    let camera = gd.new_object(None);
+    gd.set_to_identity(camera);
+    camera
+}
+
+/// a scene with a 2x2 quad 2 units in front of the origin
+fn quad_scene(gd: &mut GameData, mat: Entity) -> Scene {
+    let v = |x: f32, y: f32, u: f32, t: f32| {
+        VertexGeoTexNorm {
+            position: [x, y, 0.],
+            texture: [u, t],
+            normal: [0., 0., 1.]
+        }
+    };
+    let vb = VertexBuffer::new_position_texture_normal(
+        vec![v(-1., -1., 0., 0.), v(1., -1., 1., 0.), v(1., 1., 1., 1.), v(-1., 1., 0., 1.)],
+        vec![0, 1, 2, 0, 2, 3]
+    );
+    let vb = gd.new_vertex_buffer(vb);
+    let geo = gd.new_geometry(Geometry::triangles(vb, 0, 6));
+
+    let scene = gd.new_scene();
+    let oid = gd.new_object(Some(scene.to_entity()));
+    gd.set_to_identity(oid);
+    gd.set_displacement(oid, Vector3::new(0., 0., -2.));
+    gd.set_draw(oid, geo, mat);
+    scene
+}
+
+fn render(gd: &GameData) -> SoftRender {
+    let mut r = SoftRender::new(0, 0);
+    r.set_clear_color([0., 0., 0., 1.]);
+    r.render(gd);
+    r
+}
+
+#[test]
+fn viewport_pixels() {
+    assert_eq!(Viewport::full().pixels(32, 16), (0, 0, 32, 16));
+    assert_eq!(Viewport::new(0.5, 0., 0.5, 1.).pixels(32, 16), (16, 0, 16, 16));
+    assert_eq!(Viewport::new(0.25, 0.5, 0.25, 0.25).pixels(32, 16), (8, 8, 8, 4));
+    // views are never empty or outside of the frame
+    assert_eq!(Viewport::new(0., 0., 0., 0.).pixels(32, 16), (0, 0, 1, 1));
+    assert_eq!(Viewport::new(0.5, 0., 1., 1.).pixels(32, 16), (16, 0, 16, 16));
+    // empty views on the right and bottom edges stay in the frame
+    assert_eq!(Viewport::new(0., 1., 1., 0.).pixels(32, 16), (0, 15, 32, 1));
+    assert_eq!(Viewport::new(1., 0., 0., 1.).pixels(32, 16), (31, 0, 1, 16));
+    assert_eq!(Viewport::new(1., 1., 0., 0.).pixels(32, 16), (31, 15, 1, 1));
+}
+
+#[test]
+fn camera_and_scene_are_the_default_view() {
+    let mut gd = new_game();
+    assert_eq!(gd.render_views(), Vec::new());
+
+    let cam = camera(&mut gd);
+    let scene = gd.new_scene();
+    gd.set_camera(cam);
+    gd.set_scene(scene);
+    assert_eq!(gd.render_views(), vec![RenderView::new(cam, scene)]);
+
+    // once there is a view the camera and scene are not drawn on their own
+    let other = gd.new_scene();
+    let view = gd.new_view(RenderView::new(cam, other));
+    assert_eq!(gd.render_views(), vec![RenderView::new(cam, other)]);
+    assert!(gd.remove_view(view));
+    assert_eq!(gd.render_views(), vec![RenderView::new(cam, scene)]);
+}
+
+#[test]
+fn targets_are_drawn_before_they_are_read() {
+    let mut gd = new_game();
+    let cam = camera(&mut gd);
+    let target = gd.new_texture(Texture::new(4, 4, 4, vec![0; 64]));
+    let mut mat = Material::simple([0., 0., 0.]);
+    mat.set_map_ka(target);
+    let mat = gd.new_material(mat);
+    let screen = quad_scene(&mut gd, mat);
+    let other = gd.new_scene();
+
+    let mut window = RenderView::new(cam, screen);
+    window.viewport = Viewport::new(0., 0., 0.5, 1.);
+    let mut mirror = RenderView::new(cam, other);
+    mirror.target = Some(target);
+    let hud = RenderView::new(cam, other);
+
+    gd.new_view(window);
+    gd.new_view(mirror);
+    gd.new_view(hud);
+    assert_eq!(gd.render_views(), vec![mirror, window, hud]);
+
+    // a view that reads its own target sees what it drew last frame
+    let mut feedback = RenderView::new(cam, screen);
+    feedback.target = Some(target);
+    let mut gd = new_game();
+    gd.new_view(feedback);
+    gd.new_view(window);
+    assert_eq!(gd.render_views(), vec![feedback, window]);
+}
+
+#[test]
+fn split_screen() {
+    let mut gd = new_game();
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    let blue = gd.new_material(Material::simple([0., 0., 1.]));
+    let left = quad_scene(&mut gd, red);
+    let right = quad_scene(&mut gd, blue);
+    let cam = camera(&mut gd);
+
+    let mut view = RenderView::new(cam, left);
+    view.viewport = Viewport::new(0., 0., 0.5, 1.);
+    gd.new_view(view);
+    view.scene = right;
+    view.viewport = Viewport::new(0.5, 0., 0.5, 1.);
+    gd.new_view(view);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(8, 16), [51, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(24, 16), [0, 0, 51, 255]);
+    assert_eq!(r.frame().pixel(8, 0), BLACK);
+    assert_eq!(r.frame().pixel(24, 0), BLACK);
+}
+
+#[test]
+fn views_clear_their_viewport() {
+    let mut gd = new_game();
+    let cam = camera(&mut gd);
+    let scene = gd.new_scene();
+    let mut view = RenderView::new(cam, scene);
+    view.viewport = Viewport::new(0., 0., 0.5, 0.5);
+    view.clear = Clear::color(Vector4::new(0., 1., 0., 1.));
+    gd.new_view(view);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(0, 0), [0, 255, 0, 255]);
+    assert_eq!(r.frame().pixel(15, 15), [0, 255, 0, 255]);
+    assert_eq!(r.frame().pixel(16, 16), BLACK);
+    assert_eq!(r.frame().pixel(0, 16), BLACK);
+}
+
+#[test]
+fn render_to_texture() {
+    let mut gd = new_game();
+    let cam = camera(&mut gd);
+    let target = gd.new_texture(Texture::new(4, 4, 4, vec![0; 64]));
+    let mut mat = Material::simple([0., 0., 0.]);
+    mat.set_map_ka(target);
+    let mat = gd.new_material(mat);
+    let screen = quad_scene(&mut gd, mat);
+
+    // the window is added first, but has to wait for the texture
+    gd.new_view(RenderView::new(cam, screen));
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), BLACK);
+
+    let empty = gd.new_scene();
+    let mut mirror = RenderView::new(cam, empty);
+    mirror.clear = Clear::color(Vector4::new(1., 1., 1., 1.));
+    mirror.target = Some(target);
+    gd.new_view(mirror);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(16, 16), [51, 51, 51, 255]);
+}
+
+#[test]
+fn cameras_have_a_projection() {
+    let mut gd = new_game();
+    let cam = camera(&mut gd);
+    assert_eq!(gd.projection(cam), Projection::default());
+
+    let ortho = Projection::orthographic(4., 0.1, 100.);
+    gd.set_projection(cam, ortho);
+    assert_eq!(gd.projection(cam), ortho);
+    assert_eq!(gd.view_camera(cam, 32, 32).projection(), ortho);
+    assert_eq!(gd.projection_iter().count(), 1);
+
+    assert!(gd.remove_projection(cam));
+    assert!(!gd.remove_projection(cam));
+    assert_eq!(gd.projection(cam), Projection::default());
+}
+
+#[test]
+fn delete_clears_render_data() {
+    let mut gd = new_game();
+    let cam = camera(&mut gd);
+    let scene = gd.new_scene();
+    gd.set_camera(cam);
+    gd.set_scene(scene);
+    gd.set_projection(cam, Projection::orthographic(4., 0.1, 100.));
+    gd.set_environment(scene, Environment::new());
+    let view = gd.new_view(RenderView::new(cam, scene));
+
+    assert!(gd.render.delete(view));
+    assert!(gd.view(view).is_none());
+
+    assert!(gd.render.delete(cam));
+    assert_eq!(gd.projection_iter().count(), 0);
+    assert_eq!(gd.camera(), None);
+
+    let Scene(oid) = scene;
+    assert!(gd.render.delete(oid));
+    assert_eq!(gd.environment_iter().count(), 0);
+    assert_eq!(gd.scene(), None);
+    assert!(!gd.render.delete(oid));
+}
+
+#[test]
+fn orthographic_view() {
+    let mut gd = new_game();
+    let red = gd.new_material(Material::simple([1., 0., 0.]));
+    let scene = quad_scene(&mut gd, red);
+    let cam = camera(&mut gd);
+    // the 2x2 quad covers the middle half of a view 4 units high
+    gd.set_projection(cam, Projection::orthographic(4., 0.1, 100.));
+    gd.set_camera(cam);
+    gd.set_scene(scene);
+
+    let r = render(&gd);
+    assert_eq!(r.frame().pixel(9, 9), [51, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(22, 22), [51, 0, 0, 255]);
+    assert_eq!(r.frame().pixel(6, 16), BLACK);
+    assert_eq!(r.frame().pixel(16, 25), BLACK);
+}